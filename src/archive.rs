@@ -0,0 +1,111 @@
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use time::Date;
+use url::Url;
+use crate::feed::{Feed, FeedError};
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("FeedError: {0}")]
+    FeedError(#[from] FeedError),
+}
+
+pub type Result<T> = std::result::Result<T, ArchiveError>;
+
+/// Managed archive root, captures are stored under `<root>/<year>/<month>/<title>`
+pub struct Archive {
+    root: PathBuf,
+}
+
+impl Archive {
+
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: expand_home(root.into()) }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Existing capture of `url`, matched against both the captured and the final URL
+    pub fn find(&self, url: &str) -> Result<Option<PathBuf>> {
+
+        if !self.root.is_dir() {
+            return Ok(None);
+        }
+
+        let mut captures = Vec::new();
+        Feed::find_captures(&self.root, &mut captures)?;
+
+        let url = normalize(url);
+        Ok(captures
+            .into_iter()
+            .find(|(_, info)| normalize(&info.url) == url)
+            .map(|(directory, _)| directory))
+    }
+
+    /// Fresh directory for a capture named `file_stem` made on `date`, its parents are created.
+    /// A `-2`, `-3`, ... suffix is added when the name is already taken that month
+    pub fn capture_directory(&self, file_stem: &str, date: Date) -> Result<PathBuf> {
+
+        let month = self.root
+            .join(format!("{:04}", date.year()))
+            .join(format!("{:02}", u8::from(date.month())));
+        std::fs::create_dir_all(&month)?;
+
+        let directory = (1..)
+            .map(|n| match n {
+                1 => month.join(file_stem),
+                n => month.join(format!("{file_stem}-{n}")),
+            })
+            .find(|directory| !directory.exists())
+            .unwrap();
+
+        Ok(directory)
+    }
+}
+
+/// URL without its fragment, so `page#section` is the same capture as `page`
+fn normalize(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(mut url) => {
+            url.set_fragment(None);
+            url.to_string()
+        }
+        Err(_) => url.to_string(),
+    }
+}
+
+/// `~/WebArchive` as written in configuration files
+fn expand_home(path: PathBuf) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::Month;
+
+    #[test]
+    fn organizes_by_month_and_finds_existing_urls() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = Archive::new(tmp.path());
+        let date = Date::from_calendar_date(2025, Month::March, 4).unwrap();
+
+        let directory = archive.capture_directory("Title", date).unwrap();
+        assert_eq!(directory, tmp.path().join("2025/03/Title"));
+
+        std::fs::create_dir(&directory).unwrap();
+        let info = r#"{"url":"https://example.com/post","title":"Title","date":"2025-03-04","nb_md_words":1,"nb_images":0}"#;
+        std::fs::write(directory.join("informations.json"), info).unwrap();
+
+        assert_eq!(archive.capture_directory("Title", date).unwrap(), tmp.path().join("2025/03/Title-2"));
+        assert_eq!(archive.find("https://example.com/post#comments").unwrap(), Some(directory));
+        assert_eq!(archive.find("https://example.com/other").unwrap(), None);
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::{HashMap, HashSet};
+use futures::future::join_all;
+use scraper::{Html, Node, Selector};
+use url::Url;
+use crate::css;
+use crate::filename;
+use crate::http::HttpClient;
+use crate::warnings::{Warning, WarningKind, Warnings};
+
+/// Directory of the capture holding the stylesheets, scripts and fonts
+pub const DIRECTORY: &str = "assets";
+
+/// `@import` chains are followed this deep at most
+const MAX_IMPORT_DEPTH: usize = 4;
+
+const FONT_EXTENSIONS: &[&str] = &["woff2", "woff", "ttf", "otf", "eot"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssetKind {
+    Stylesheet,
+    Script,
+    Font,
+}
+
+impl AssetKind {
+
+    /// Directory of `assets/` holding the files of this kind
+    pub fn directory(self) -> &'static str {
+        match self {
+            Self::Stylesheet => "css",
+            Self::Script => "js",
+            Self::Font => "fonts",
+        }
+    }
+
+    /// Extension given to the file names having none
+    fn extension(self) -> Option<&'static str> {
+        match self {
+            Self::Stylesheet => Some("css"),
+            Self::Script => Some("js"),
+            Self::Font => None,
+        }
+    }
+}
+
+/// Stylesheet, script or web font of the page, saved in `assets/<kind>/`
+#[derive(Debug, Clone)]
+pub struct Asset {
+    pub kind: AssetKind,
+    pub filename: String,
+    pub url: String,
+    /// Stylesheets reference the other assets by their local paths
+    pub bytes: Vec<u8>,
+}
+
+impl Asset {
+    /// Path relative to the capture directory
+    pub fn path(&self) -> String {
+        format!("{DIRECTORY}/{}/{}", self.kind.directory(), self.filename)
+    }
+}
+
+/// File names of the asset URLs, unique within each directory
+#[derive(Default)]
+struct Names {
+    by_url: HashMap<Url, (AssetKind, String)>,
+    taken: HashMap<AssetKind, HashSet<String>>,
+}
+
+impl Names {
+
+    /// Name `url` after its last path segment, `false` when it already has a name
+    fn assign(&mut self, url: &Url, kind: AssetKind) -> bool {
+        if self.by_url.contains_key(url) {
+            return false;
+        }
+        let segment = url.path_segments().and_then(|mut segments| segments.next_back()).filter(|segment| !segment.is_empty());
+        let mut name = filename::sanitize(segment.unwrap_or("index"));
+        if let Some(extension) = kind.extension().filter(|_| filename::split_extension(&name).1.is_empty()) {
+            name = format!("{name}.{extension}");
+        }
+        let name = filename::unique(&name, self.taken.entry(kind).or_default());
+        self.by_url.insert(url.clone(), (kind, name));
+        true
+    }
+
+    /// Keep `url` remote, it couldn't be downloaded
+    fn forget(&mut self, url: &Url) {
+        self.by_url.remove(url);
+    }
+
+    fn name(&self, url: &Url) -> Option<&str> {
+        self.by_url.get(url).map(|(_, name)| name.as_str())
+    }
+
+    /// Local path of `url` from the capture directory, or else from the directory of `from` assets
+    fn path(&self, url: &Url, from: Option<AssetKind>) -> Option<String> {
+        let (kind, name) = self.by_url.get(url)?;
+        let name = filename::to_url_path(name);
+        Some(match from {
+            None => format!("{DIRECTORY}/{}/{name}", kind.directory()),
+            Some(from) if from == *kind => name,
+            Some(_) => format!("../{}/{name}", kind.directory()),
+        })
+    }
+}
+
+fn is_font(url: &Url) -> bool {
+    let segment = url.path_segments().and_then(|mut segments| segments.next_back()).unwrap_or_default();
+    FONT_EXTENSIONS.contains(&filename::split_extension(segment).1.to_ascii_lowercase().as_str())
+}
+
+fn is_remote(url: &Url) -> bool {
+    matches!(url.scheme(), "http" | "https")
+}
+
+/// `css` pointing at the local copies of its imports, fonts and images. Paths are relative to the
+/// capture directory for inline styles, or to the directory of `from` assets, where the URLs left remote
+/// are made absolute since the stylesheet no longer sits next to them
+fn rewrite_css(css: &str, base_url: &Url, names: &Names, image_paths: &HashMap<String, String>, from: Option<AssetKind>) -> String {
+    let local = |src: &str| -> Option<String> {
+        if src.starts_with("data:") || src.starts_with('#') {
+            return None;
+        }
+        let url = base_url.join(src).ok()?;
+        if let Some(path) = names.path(&url, from) {
+            return Some(path);
+        }
+        if let Some(path) = image_paths.get(url.as_str()) {
+            return Some(match from {
+                Some(_) => format!("../../{path}"),
+                None => path.clone(),
+            });
+        }
+        from.map(|_| url.to_string())
+    };
+    css::replace_urls(&css::replace_imports(css, local), local)
+}
+
+/// Stylesheets, scripts and web fonts of a page, saved so the HTML can be browsed offline
+#[derive(Debug, Default)]
+pub struct Assets {
+    assets: Vec<Asset>,
+}
+
+impl Assets {
+
+    /// Download the stylesheets (and their `@import`s), scripts and web fonts of `html` and point
+    /// the page at the local copies. `image_paths` are the images already saved (URL to relative path)
+    /// for the stylesheets to reference. Assets that can't be downloaded stay remote, with a warning
+    pub async fn capture(html: &str, base_url: &str, client: &HttpClient, image_paths: &HashMap<String, String>, warnings: &Warnings) -> (String, Self) {
+
+        let Ok(base_url) = Url::parse(base_url) else {
+            return (html.to_string(), Self::default());
+        };
+
+        let mut document = Html::parse_document(html);
+        let stylesheet_selector = Selector::parse("link[rel~=stylesheet][href]").unwrap();
+        let script_selector = Selector::parse("script[src]").unwrap();
+        let style_selector = Selector::parse("style").unwrap();
+        let resolve = |src: Option<&str>| base_url.join(src?.trim()).ok().filter(is_remote);
+
+        let links = document.select(&stylesheet_selector).filter_map(|link| Some((link.id(), resolve(link.attr("href"))?))).collect::<Vec<_>>();
+        let scripts = document.select(&script_selector).filter_map(|script| Some((script.id(), resolve(script.attr("src"))?))).collect::<Vec<_>>();
+        let styles = document
+            .select(&style_selector)
+            .flat_map(|style| style.children())
+            .filter_map(|child| Some((child.id(), child.value().as_text()?.to_string())))
+            .collect::<Vec<_>>();
+
+        let mut names = Names::default();
+        let mut fonts = Vec::new();
+        let mut level = Vec::new();
+        let mut found = |css: &str, base: &Url, names: &mut Names, imports: &mut Vec<Url>| {
+            for url in css::imports(css).iter().filter_map(|src| base.join(src).ok()).filter(is_remote) {
+                if names.assign(&url, AssetKind::Stylesheet) {
+                    imports.push(url);
+                }
+            }
+            for url in css::urls(css).iter().filter_map(|src| base.join(src).ok()).filter(|url| is_remote(url) && is_font(url)) {
+                if names.assign(&url, AssetKind::Font) {
+                    fonts.push(url);
+                }
+            }
+        };
+
+        for (_, url) in &links {
+            if names.assign(url, AssetKind::Stylesheet) {
+                level.push(url.clone());
+            }
+        }
+        for (_, css) in &styles {
+            found(css, &base_url, &mut names, &mut level);
+        }
+
+        // stylesheets with the URL they were served from, their relative URLs resolve against it
+        let mut stylesheets = Vec::new();
+        for _ in 0..MAX_IMPORT_DEPTH {
+            let responses = join_all(level.iter().map(|url| client.get(url))).await;
+            let mut imports = Vec::new();
+            for (url, response) in std::mem::take(&mut level).into_iter().zip(responses) {
+                match response {
+                    Ok(response) => {
+                        let css = String::from_utf8_lossy(&response.body).into_owned();
+                        found(&css, &response.url, &mut names, &mut imports);
+                        stylesheets.push((url, response.url, css));
+                    }
+                    Err(e) => {
+                        warnings.push(Warning::with_url(WarningKind::SkippedAsset, url.as_str(), e.to_string()));
+                        names.forget(&url);
+                    }
+                }
+            }
+            level = imports;
+        }
+        // too deep to be followed
+        for url in level {
+            names.forget(&url);
+        }
+
+        let mut downloads = fonts.into_iter().map(|url| (url, AssetKind::Font)).collect::<Vec<_>>();
+        for (_, url) in &scripts {
+            if names.assign(url, AssetKind::Script) {
+                downloads.push((url.clone(), AssetKind::Script));
+            }
+        }
+        let responses = join_all(downloads.iter().map(|(url, _)| client.get(url))).await;
+
+        let mut assets = Vec::new();
+        for ((url, kind), response) in downloads.into_iter().zip(responses) {
+            match response {
+                Ok(response) => assets.push(Asset {
+                    kind, filename: names.name(&url).unwrap_or_default().to_string(), url: url.to_string(), bytes: response.body,
+                }),
+                Err(e) => {
+                    warnings.push(Warning::with_url(WarningKind::SkippedAsset, url.as_str(), e.to_string()));
+                    names.forget(&url);
+                }
+            }
+        }
+        for (url, served_from, css) in stylesheets {
+            let css = rewrite_css(&css, &served_from, &names, image_paths, Some(AssetKind::Stylesheet));
+            let filename = names.name(&url).unwrap_or_default().to_string();
+            assets.push(Asset { kind: AssetKind::Stylesheet, filename, url: url.to_string(), bytes: css.into_bytes() });
+        }
+        assets.sort_by(|a, b| (a.kind.directory(), &a.filename).cmp(&(b.kind.directory(), &b.filename)));
+
+        let rewrites = links
+            .iter()
+            .map(|(id, url)| (*id, "href", url))
+            .chain(scripts.iter().map(|(id, url)| (*id, "src", url)))
+            .filter_map(|(id, attribute, url)| Some((id, attribute, names.path(url, None)?)));
+        for (id, attribute, path) in rewrites.collect::<Vec<_>>() {
+            if let Some(mut node) = document.tree.get_mut(id)
+                && let Node::Element(element) = node.value() {
+                // the rewritten stylesheets no longer match their hash
+                element.attrs.retain(|(name, _)| &*name.local != "integrity");
+                if let Some((_, value)) = element.attrs.iter_mut().find(|(name, _)| &*name.local == attribute) {
+                    *value = path.as_str().into();
+                }
+            }
+        }
+        for (id, css) in styles {
+            let rewritten = rewrite_css(&css, &base_url, &names, image_paths, None);
+            if rewritten != css
+                && let Some(mut node) = document.tree.get_mut(id)
+                && let Node::Text(text) = node.value() {
+                text.text = rewritten.as_str().into();
+            }
+        }
+
+        (document.html(), Self { assets })
+    }
+
+    pub fn assets(&self) -> &[Asset] {
+        &self.assets
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    /// Write the assets in `assets/` of the capture `directory`
+    pub async fn write_to_disk(&self, directory: &std::path::Path) -> std::io::Result<()> {
+        for asset in &self.assets {
+            let path = directory.join(DIRECTORY).join(asset.kind.directory()).join(&asset.filename);
+            tokio::fs::create_dir_all(path.parent().expect("assets are in a directory")).await?;
+            tokio::fs::write(path, &asset.bytes).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::NetworkMode;
+    use crate::testing::FixtureServer;
+
+    #[tokio::test]
+    async fn captures_stylesheets_scripts_and_fonts() {
+        let server = FixtureServer::new().unwrap();
+        server.route("/css/site.css", "text/css", r#"@import "type.css"; body { background: url(../bg.png) } .logo { background: url(/logo.png) }"#);
+        server.route("/css/type.css", "text/css", "@font-face { font-family: Inter; src: url(/fonts/inter.woff2) format('woff2') }");
+        server.route("/fonts/inter.woff2", "font/woff2", "wOF2");
+        server.route("/fonts/mono.ttf", "font/ttf", "ttf");
+        server.route("/js/app", "text/javascript", "console.log(1)");
+        let html = r#"<html><head>
+            <link rel="stylesheet" href="/css/site.css" integrity="sha384-abc">
+            <link rel="stylesheet" href="/css/missing.css">
+            <style>@font-face { font-family: Mono; src: url("/fonts/mono.ttf") }</style>
+            <script src="/js/app"></script>
+        </head><body></body></html>"#;
+
+        let client = HttpClient::new(&NetworkMode::Live).unwrap();
+        let warnings = Warnings::default();
+        let image_paths = HashMap::from([(server.url("/bg.png"), "images/bg.png".to_string())]);
+        let (html, assets) = Assets::capture(html, &server.url("/post.html"), &client, &image_paths, &warnings).await;
+
+        let paths = assets.assets().iter().map(Asset::path).collect::<Vec<_>>();
+        assert_eq!(paths, ["assets/css/site.css", "assets/css/type.css", "assets/fonts/inter.woff2", "assets/fonts/mono.ttf", "assets/js/app.js"]);
+        assert!(html.contains(r#"<link href="assets/css/site.css" rel="stylesheet">"#));
+        assert!(html.contains(r#"<link href="/css/missing.css" rel="stylesheet">"#));
+        assert!(html.contains(r#"src: url("assets/fonts/mono.ttf")"#));
+        assert!(html.contains(r#"<script src="assets/js/app.js">"#));
+        assert_eq!(warnings.len(), 1);
+
+        let site = String::from_utf8(assets.assets()[0].bytes.clone()).unwrap();
+        assert_eq!(site, format!(r#"@import "type.css"; body {{ background: url("../../images/bg.png") }} .logo {{ background: url("{}") }}"#, server.url("/logo.png")));
+        let typography = String::from_utf8(assets.assets()[1].bytes.clone()).unwrap();
+        assert!(typography.contains(r#"src: url("../fonts/inter.woff2")"#));
+    }
+}
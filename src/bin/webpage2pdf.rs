@@ -1,4 +1,7 @@
 use webpage_scraper::browser;
+use webpage_scraper::options::ScrapeOptions;
+use webpage_scraper::filename::Transliteration;
+use webpage_scraper::pdf::{self, PrintOptions};
 use clap::Parser;
 
 /// Converts a webpage to a PDF using a headless browser
@@ -7,13 +10,44 @@ use clap::Parser;
 struct Args {
     /// URL of the website to convert to PDF
     url: String,
+
+    /// Transliteration of the title when naming the PDF file
+    #[arg(long, value_enum, default_value_t = Transliteration::Unicode)]
+    transliterate: Transliteration,
+
+    /// Print the PDF in landscape orientation
+    #[arg(long)]
+    landscape: bool,
+
+    /// PDF margin on every side: 0.5in, 1.5cm, 10mm, 48px, inches when unitless
+    #[arg(long, value_name = "LENGTH", value_parser = pdf::parse_length)]
+    margin: Option<f64>,
+
+    /// Zoom of the page in the PDF, from 0.1 to 2
+    #[arg(long, default_value_t = 1.0, value_parser = pdf::parse_scale)]
+    scale: f64,
+
+    /// Leave background colors and images out of the PDF
+    #[arg(long)]
+    no_background: bool,
+
+    /// Bookmark the headings of the page in the PDF, down to DEPTH (Chrome only)
+    #[arg(long, value_name = "DEPTH", num_args = 0..=1, default_missing_value = "3")]
+    pdf_outline: Option<usize>,
+
+    /// Convert the PDF to PDF/A-2b for archiving, with the URL and date in its XMP metadata
+    #[arg(long)]
+    pdfa: bool,
 }
 
 fn main() {
 
     let args = Args::parse();
 
-    let browser = browser::Browser::new().expect("Can't initiate browser");
+    let pdf = PrintOptions { landscape: args.landscape, margin: args.margin, scale: args.scale, background: !args.no_background, outline: args.pdf_outline, pdfa: args.pdfa };
+    let options = ScrapeOptions { transliteration: args.transliterate, pdf, ..Default::default() };
+
+    let browser = browser::Browser::with_options(options).expect("Can't initiate browser");
 
     browser.url_to_pdf(&args.url).expect("Can't convert webpage to PDF");
 
@@ -1,34 +1,1103 @@
 use webpage_scraper::browser;
-use clap::Parser;
-use tokio;
+use webpage_scraper::options::{ScrapeOptions, ContentGuard, LazyAttributes, ImageFilter, ImageBudget, Thumbnails, MediaLimits};
+use webpage_scraper::filename::Transliteration;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser};
+use clap::parser::ValueSource;
+use std::time::{Duration, Instant};
+use std::path::{Path, PathBuf};
+use webpage_scraper::feed::Feed;
+use webpage_scraper::webdriver::WebDriver;
+use webpage_scraper::renderer::{Backend, PageRenderer};
+use webpage_scraper::webpage::{OutputPolicy, Outputs, WebPage, WebPageError};
+use std::str::FromStr;
+use webpage_scraper::fetch::StaticFetcher;
+use webpage_scraper::http::{HttpClient, NetworkMode, RetryPolicy};
+use webpage_scraper::recording::{Recording, ReplayRenderer};
+use webpage_scraper::srcset::SrcsetStrategy;
+use webpage_scraper::convert::ImageFormat;
+use webpage_scraper::config::Config;
+use webpage_scraper::rules::Rules;
+use webpage_scraper::extract::Fields;
+use webpage_scraper::diff;
+use webpage_scraper::markdown::{MarkdownConverter, PandocOptions, Wrap};
+use webpage_scraper::archive::Archive;
+use webpage_scraper::git::GitRepo;
+use webpage_scraper::obsidian::Vault;
+use webpage_scraper::dom;
+use webpage_scraper::bundle::{self, BundleFormat};
+use webpage_scraper::layout::Layout;
+use webpage_scraper::pdf::{self, PrintOptions};
+use webpage_scraper::crawl::{self, CrawlLimits};
+use webpage_scraper::verify::{self, LocalStatus, OriginStatus};
+use webpage_scraper::url_list;
+use webpage_scraper::robots::Robots;
+use webpage_scraper::ratelimit::RateLimiter;
+use webpage_scraper::status::StatusBoard;
+use webpage_scraper::logging::{self, LogFormat, Logger};
+use webpage_scraper::report::{CaptureStatus, PageReport, RunReport};
+use std::io::IsTerminal;
+use std::sync::{Arc, Mutex};
+use std::collections::HashSet;
+use futures::StreamExt;
+use tokio::sync::Semaphore;
+
+/// Pages of a list scraped at once unless `--jobs` says otherwise
+const DEFAULT_JOBS: usize = 4;
 
 /// Scraps a website, HTML (and its pandoc Markdown conversion), 
 /// info JSON and images
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    /// URL of the webpage to be scraped
-    url: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// URLs of the webpages to be scraped, followed by the name of the output directory,
+    /// the title of the page if not given. With several URLs, each page is captured
+    /// in a directory named after its title, under the output directory if given
+    #[arg(required_unless_present = "input", value_name = "URL")]
+    targets: Vec<String>,
+
+    /// File listing URLs to scrape after those given as arguments, one per line, - for the
+    /// standard input. Blank lines and # comments are skipped
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
+
+    /// Pages of a list scraped at once, each in its own browser tab, their image downloads
+    /// sharing --image-concurrency [default: 4]
+    #[arg(long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Managed archive where captures go under <year>/<month>/,
+    /// URLs already archived are skipped
+    #[arg(long, value_name = "DIR")]
+    archive_root: Option<PathBuf>,
+
+    /// Don't show the pages being scraped and their images as they go,
+    /// only shown when the standard error is a terminal
+    #[arg(long)]
+    no_progress: bool,
+
+    /// Log what the scraper does to the standard error: -v for each page and failed
+    /// download, -vv for every request and image, -vvv for the libraries it uses too
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Only print errors, no progress, messages or warnings
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Format of the log records
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    log_format: LogFormat,
+
+    /// Print the result of the run as JSON to the standard output: the files written for each
+    /// page, its counts, warnings or error and the time it took. Messages go to the standard error.
+    /// With verify, the report of each capture
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Capture the URL even when it is already in the archive root
+    #[arg(long)]
+    recapture: bool,
+
+    /// Git repository (created if needed) where the page is captured in <DIR>/<title>/,
+    /// replacing its previous capture, then committed with its URL and date
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["archive_root", "crawl_depth"])]
+    git_repo: Option<PathBuf>,
+
+    /// Obsidian vault where the page is written as a note instead of a capture directory,
+    /// with front matter and its images embedded from the attachment folder of the vault
+    #[arg(long, value_name = "VAULT", conflicts_with_all = ["archive_root", "git_repo", "crawl_depth", "bundle", "merge", "append_timestamp", "only"])]
+    obsidian: Option<PathBuf>,
+
+    /// Folder of the vault the note goes in, its root by default
+    #[arg(long, value_name = "FOLDER", requires = "obsidian")]
+    obsidian_folder: Option<PathBuf>,
+
+    /// Tag of the note, on top of the keywords of the page. Repeat for several
+    #[arg(long = "obsidian-tag", value_name = "TAG", requires = "obsidian")]
+    obsidian_tags: Vec<String>,
+
+    /// Replace the output directory when it already exists
+    #[arg(long, conflicts_with_all = ["merge", "append_timestamp"])]
+    force: bool,
+
+    /// Write into the output directory when it already exists, replacing files of the same name
+    #[arg(long, conflicts_with = "append_timestamp")]
+    merge: bool,
+
+    /// Write next to an existing output directory, in <DIR>-<YYYYMMDD-HHMMSS>
+    #[arg(long)]
+    append_timestamp: bool,
+
+    /// Where the HTML, PDF, markdown, text and DOCX go in the output directory, e.g. {domain}/{date}/{slug}/{kind}.
+    /// Placeholders: {domain}, {date}, {slug}, {kind}, {ext}; images and linked files go next to them
+    #[arg(long, value_name = "TEMPLATE", value_parser = Layout::new)]
+    layout: Option<Layout>,
+
+    /// Write only these outputs, comma-separated: html, pdf, md, images, info, screenshot, text, docx.
+    /// Without images, none is downloaded. Defaults to all of them but the screenshot
+    #[arg(long, value_name = "OUTPUTS", value_parser = Outputs::from_str)]
+    only: Option<Outputs>,
+
+    /// Pack the capture into a single <DIR>.zip or <DIR>.tar.gz instead of a directory
+    #[arg(long, value_enum, value_name = "FORMAT", conflicts_with_all = ["archive_root", "git_repo", "merge"])]
+    bundle: Option<BundleFormat>,
+
+    /// Minimum number of markdown words expected,
+    /// pages below it are flagged as low content in informations.json
+    #[arg(long)]
+    min_words: Option<usize>,
+
+    /// Fail instead of flagging when the page is below --min-words
+    #[arg(long, requires = "min_words")]
+    fail_on_low_content: bool,
+
+    /// Transliteration of the title when naming output files and directories
+    #[arg(long, value_enum, default_value_t = Transliteration::Unicode)]
+    transliterate: Transliteration,
+
+    /// Minimum delay in milliseconds between two pages of the same host
+    #[arg(long, value_name = "MS", default_value_t = 0)]
+    domain_delay: u64,
+
+    /// Random extra delay in milliseconds, up to this value, added to --domain-delay
+    #[arg(long, default_value_t = 0)]
+    domain_jitter: u64,
+
+    /// Minimum delay in milliseconds between two downloads from the same host, the images
+    /// and other files of the pages the scraper fetches itself
+    #[arg(long, value_name = "MS", default_value_t = 0)]
+    delay: u64,
+
+    /// Most requests sent per second over all hosts, page loads and downloads alike
+    #[arg(long, value_name = "N", value_parser = parse_max_rps)]
+    max_rps: Option<f64>,
+
+    /// Archive root whose feed.json is regenerated after the capture
+    #[arg(long, value_name = "ARCHIVE_ROOT")]
+    feed: Option<PathBuf>,
+
+    /// Browser used to render the page
+    #[arg(long, value_enum, default_value_t = Backend::Chrome)]
+    backend: Backend,
+
+    /// Endpoint of the WebDriver server used by --backend webdriver
+    #[arg(long, default_value = WebDriver::DEFAULT_ENDPOINT)]
+    webdriver_url: String,
+
+    /// Fetch the HTML with a plain HTTP request instead of a browser,
+    /// much faster for static sites but without PDF output
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", action = clap::ArgAction::Set, default_value_t = false, hide_default_value = true, hide_possible_values = true, conflicts_with = "backend")]
+    no_browser: bool,
+
+    /// Keep every network response of the scrape in recording.json
+    #[arg(long)]
+    record: bool,
+
+    /// Re-run the extraction and conversion from a recording.json instead of the network
+    #[arg(long, value_name = "RECORDING", conflicts_with_all = ["record", "backend", "no_browser"])]
+    replay: Option<PathBuf>,
+
+    /// srcset candidate to download: largest, smallest or a target width in pixels
+    #[arg(long, value_name = "STRATEGY", default_value = "largest")]
+    srcset: SrcsetStrategy,
+
+    /// Probe the srcset candidates with HEAD requests and download the largest one under this many bytes
+    #[arg(long, value_name = "BYTES")]
+    srcset_max_bytes: Option<u64>,
+
+    /// TOML configuration file of defaults for the flags and per-domain overrides (wait, remove,
+    /// auth, rate limits) [default: webpage-scraper.toml in the current or user configuration directory].
+    /// The switches it turns on are turned off with =false, as in --docx=false
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Don't read webpage-scraper.toml
+    #[arg(long, conflicts_with = "config")]
+    no_config: bool,
+
+    /// Directory of per-domain extraction rules, <domain>.toml files giving the content selector,
+    /// the selectors to exclude, lazy image attributes and the selector to wait for
+    #[arg(long, value_name = "DIR")]
+    rules: Option<PathBuf>,
+
+    /// TOML file of named fields saved to extracted.json, each a CSS selector or an XPath expression:
+    /// price = ".price", author = "//meta[@name='author']/@content", tags = { css = ".tag", all = true }
+    #[arg(long, value_name = "FILE")]
+    extract: Option<PathBuf>,
+
+    /// Compare the capture with an earlier one of the page in DIR, saving the changes
+    /// to changes.diff and whether there are any to informations.json
+    #[arg(long, value_name = "DIR")]
+    diff: Option<PathBuf>,
+
+    /// Expand the comment thread of the page and save it to comments.md
+    #[arg(long)]
+    comments: bool,
+
+    /// Save the source of embedded GitHub Gists and code snippets to code/, linked from the markdown
+    #[arg(long)]
+    code_embeds: bool,
+
+    /// Save the parse errors, unclosed tags and encoding problems of the captured HTML to html_report.json
+    #[arg(long)]
+    html_report: bool,
+
+    /// Comma separated attributes holding the real URL of lazy loaded images
+    #[arg(long, value_delimiter = ',', default_values = LazyAttributes::DEFAULT)]
+    lazy_attributes: Vec<String>,
+
+    /// Don't store pages whose robots meta tag or X-Robots-Tag header says noarchive
+    #[arg(long)]
+    honor_noarchive: bool,
+
+    /// Fetch the pages and images robots.txt disallows, and ignore its Crawl-delay.
+    /// robots.txt is only followed with several URLs or --crawl-depth
+    #[arg(long)]
+    ignore_robots: bool,
+
+    /// Leave out images narrower than this many pixels
+    #[arg(long, value_name = "PX")]
+    min_image_width: Option<u32>,
+
+    /// Leave out images shorter than this many pixels
+    #[arg(long, value_name = "PX")]
+    min_image_height: Option<u32>,
+
+    /// Leave out images smaller than this many bytes
+    #[arg(long, value_name = "BYTES")]
+    min_image_bytes: Option<usize>,
+
+    /// Leave out images larger than this many bytes
+    #[arg(long, value_name = "BYTES")]
+    max_image_bytes: Option<usize>,
+
+    /// Most images downloaded for a page, the next ones are listed as skipped in images/manifest.json
+    #[arg(long, value_name = "N")]
+    max_images: Option<usize>,
+
+    /// Most image bytes downloaded for a page, the next images are listed as skipped in images/manifest.json
+    #[arg(long, value_name = "BYTES")]
+    max_images_bytes: Option<u64>,
+
+    /// Image downloads in flight at once, for all the pages --jobs scrapes together
+    #[arg(long, value_name = "N", default_value_t = HttpClient::DEFAULT_CONCURRENCY)]
+    image_concurrency: usize,
+
+    /// Retries of image downloads failing with a timeout, 429 or 5xx status, with exponential backoff
+    #[arg(long, value_name = "N", default_value_t = RetryPolicy::default().max_retries)]
+    retries: u32,
+
+    /// Seconds a page fetched without a browser, or an image download, may take before failing
+    /// with a timeout
+    #[arg(long, value_name = "SECS", default_value_t = RetryPolicy::default().timeout.as_secs())]
+    timeout: u64,
+
+    /// Convert WebP images, and AVIF ones when built with the avif feature, to png or jpeg
+    #[arg(long, value_name = "FORMAT")]
+    convert_images: Option<ImageFormat>,
+
+    /// Save thumbnails of the images larger than PX pixels to images/thumbs/
+    #[arg(long, value_name = "PX", num_args = 0..=1, default_missing_value = "512")]
+    thumbnails: Option<u32>,
+
+    /// Save only the thumbnails of the images having one, the originals are listed in images/manifest.json
+    #[arg(long, requires = "thumbnails")]
+    thumbnails_only: bool,
+
+    /// Remove EXIF (GPS position included), XMP and IPTC metadata from downloaded JPEG images,
+    /// photos relying on their EXIF orientation may then show rotated
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", action = clap::ArgAction::Set, default_value_t = false, hide_default_value = true, hide_possible_values = true)]
+    strip_metadata: bool,
+
+    /// Take image bytes from the responses Chrome received instead of downloading them again,
+    /// images it didn't load are still downloaded (Chrome backend only)
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", action = clap::ArgAction::Set, default_value_t = false, hide_default_value = true, hide_possible_values = true)]
+    browser_images: bool,
+
+    /// Save the stylesheets, scripts and web fonts to assets/ and point the HTML at them,
+    /// so the saved page looks the same offline
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", action = clap::ArgAction::Set, default_value_t = false, hide_default_value = true, hide_possible_values = true)]
+    assets: bool,
+
+    /// Also save the page as a Word document (.docx) made from the markdown, images included
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", action = clap::ArgAction::Set, default_value_t = false, hide_default_value = true, hide_possible_values = true)]
+    docx: bool,
+
+    /// Also save the text of the page without any markup to a .txt file, for NLP and LLM pipelines
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", action = clap::ArgAction::Set, default_value_t = false, hide_default_value = true, hide_possible_values = true)]
+    text: bool,
+
+    /// Also save the page and every response received while scraping it to a .warc.gz file,
+    /// replayable in pywb or ReplayWeb.page. Authorization and cookie headers are left out of
+    /// the recorded requests
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", action = clap::ArgAction::Set, default_value_t = false, hide_default_value = true, hide_possible_values = true)]
+    warc: bool,
+
+    /// Also save the page as a single .mhtml file, resources included, as Chrome snapshots it (Chrome backend only)
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", action = clap::ArgAction::Set, default_value_t = false, hide_default_value = true, hide_possible_values = true)]
+    mhtml: bool,
+
+    /// Also save the page as a single .single.html file with its images, stylesheets, scripts
+    /// and fonts inline, easy to email or store as one artifact
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", action = clap::ArgAction::Set, default_value_t = false, hide_default_value = true, hide_possible_values = true)]
+    single_file: bool,
+
+    /// Start the markdown with YAML front matter (title, URL, date, authors, tags, word count)
+    /// for Obsidian, Jekyll and other note or static site tools. Org, AsciiDoc and
+    /// reStructuredText get a header of their own, the other --pandoc-format none
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", action = clap::ArgAction::Set, default_value_t = false, hide_default_value = true, hide_possible_values = true)]
+    front_matter: bool,
+
+    /// Insert a linked table of contents of the headings down to DEPTH after the title of the markdown,
+    /// and bookmark them in the PDF unless --pdf-outline says otherwise
+    #[arg(long, value_name = "DEPTH", num_args = 0..=1, default_missing_value = "3")]
+    toc: Option<usize>,
+
+    /// Show a thumbnail WIDTH pixels wide of the top of the page after the title of the markdown,
+    /// saved as <title>.thumbnail.jpg (Chrome only)
+    #[arg(long, value_name = "WIDTH", num_args = 0..=1, default_missing_value = "320", value_parser = clap::value_parser!(u32).range(1..))]
+    screenshot_thumbnail: Option<u32>,
+
+    /// Save each data table of the page as a CSV file in tables/, linked from the markdown
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", action = clap::ArgAction::Set, default_value_t = false, hide_default_value = true, hide_possible_values = true)]
+    tables: bool,
+
+    /// What converts the HTML to markdown: pandoc when installed (auto), pandoc only,
+    /// or the built-in converter needing no dependency [default: auto, or the configuration file's]
+    #[arg(long, value_enum)]
+    markdown_converter: Option<MarkdownConverter>,
+
+    /// Pandoc writer of the conversion: gfm, commonmark, markdown, org, asciidoc, rst, ...
+    /// Org and AsciiDoc documents link to the downloaded images like the markdown, plain text is
+    /// written to `<name>.plain.txt`, apart from the one of --text
+    #[arg(long, default_value = "gfm")]
+    pandoc_format: String,
+
+    /// Extensions of the pandoc writer, as written after its name [default: -raw_html for markdown writers]
+    #[arg(long, allow_hyphen_values = true)]
+    pandoc_extensions: Option<String>,
+
+    /// How pandoc wraps lines
+    #[arg(long, value_enum)]
+    wrap: Option<Wrap>,
+
+    /// Write links as references at the end of the document instead of inline (pandoc only)
+    #[arg(long)]
+    reference_links: bool,
+
+    /// Extra pandoc argument, --name=value or --flag, can be repeated
+    #[arg(long = "pandoc-arg", value_name = "ARG", allow_hyphen_values = true)]
+    pandoc_args: Vec<String>,
+
+    /// Save the page as nested sections of paragraphs, lists, images, code and tables to structure.json
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", action = clap::ArgAction::Set, default_value_t = false, hide_default_value = true, hide_possible_values = true)]
+    structure: bool,
 
-    /// Name of the output_directory
-    /// if not given, will use the name of the website
-    output_directory: Option<String>
+    /// Save the N most frequent terms, heading and link counts and the words of each section to stats.json
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "20")]
+    stats: Option<usize>,
+
+    /// Print the PDF in landscape orientation
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", action = clap::ArgAction::Set, default_value_t = false, hide_default_value = true, hide_possible_values = true)]
+    landscape: bool,
+
+    /// PDF margin on every side: 0.5in, 1.5cm, 10mm, 48px, inches when unitless
+    #[arg(long, value_name = "LENGTH", value_parser = pdf::parse_length)]
+    margin: Option<f64>,
+
+    /// Zoom of the page in the PDF, from 0.1 to 2
+    #[arg(long, default_value_t = 1.0, value_parser = pdf::parse_scale)]
+    scale: f64,
+
+    /// Leave background colors and images out of the PDF
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", action = clap::ArgAction::Set, default_value_t = false, hide_default_value = true, hide_possible_values = true)]
+    no_background: bool,
+
+    /// Bookmark the headings of the page in the PDF, down to DEPTH (Chrome only)
+    #[arg(long, value_name = "DEPTH", num_args = 0..=1, default_missing_value = "3")]
+    pdf_outline: Option<usize>,
+
+    /// Convert the PDF to PDF/A-2b for archiving, with the URL and date in its XMP metadata
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", action = clap::ArgAction::Set, default_value_t = false, hide_default_value = true, hide_possible_values = true)]
+    pdfa: bool,
+
+    /// Download no image, for faster captures when only the text and PDF matter,
+    /// the HTML and markdown keep linking to the remote images
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", action = clap::ArgAction::Set, default_value_t = false, hide_default_value = true, hide_possible_values = true, conflicts_with_all = ["convert_images", "thumbnails", "browser_images"])]
+    no_images: bool,
+
+    /// CSS selectors of the elements to remove before the conversion and the image downloads,
+    /// comma-separated: "nav, footer, .cookie-banner". Can be repeated
+    #[arg(long, value_name = "SELECTORS")]
+    exclude: Vec<String>,
+
+    /// CSS selector of the part of the page to capture, e.g. "article.main": the markdown,
+    /// images, links and saved HTML come from the matching elements only
+    #[arg(long, value_name = "SELECTOR")]
+    select: Option<String>,
+
+    /// Strip scripts, frames, event handlers and trackers from the saved HTML,
+    /// so it can be opened locally without running third-party code
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", action = clap::ArgAction::Set, default_value_t = false, hide_default_value = true, hide_possible_values = true)]
+    sanitize: bool,
+
+    /// List the RSS, Atom and JSON feeds the page links to in informations.json
+    /// without downloading them to feeds/
+    #[arg(long)]
+    no_feeds: bool,
+
+    /// Save the video and audio files of <video> and <audio> elements to media/, streams are left out
+    #[arg(long)]
+    media: bool,
+
+    /// Leave out video and audio files larger than this many bytes
+    #[arg(long, value_name = "BYTES", default_value_t = MediaLimits::DEFAULT_MAX_FILE_BYTES, requires = "media")]
+    max_media_bytes: u64,
+
+    /// Most video and audio bytes downloaded for a page
+    #[arg(long, value_name = "BYTES", requires = "media")]
+    max_total_media_bytes: Option<u64>,
+
+    /// Crawl the pages of the same host up to this many links away, each page is
+    /// captured in the output directory along with the crawl graph (DOT and GraphML)
+    #[arg(long, value_name = "DEPTH")]
+    crawl_depth: Option<usize>,
+
+    /// Maximum number of pages captured by --crawl-depth
+    #[arg(long, default_value_t = 50, requires = "crawl_depth")]
+    max_pages: usize,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Check the images of existing captures against their manifest,
+    /// writing a freshness.json report in each capture
+    Verify {
+        /// Capture directory, or archive root holding several captures
+        archive: PathBuf,
+
+        /// Download the images again to tell changed and dead origins apart,
+        /// restoring damaged files from unchanged origins
+        #[arg(long)]
+        refetch: bool,
+    },
+}
+
+async fn verify(archive: &std::path::Path, refetch: bool, json: bool) {
+
+    let reports = verify::verify_archive(archive, refetch).await.expect("Can't verify archive");
+
+    if json {
+        println!("{}", serde_json::to_string(&reports).expect("Can't serialize the reports"));
+    }
+    for report in reports.iter().filter(|_| !json) {
+        let mut summary = format!(
+            "{}: {} intact, {} modified, {} missing",
+            report.capture.display(),
+            report.count_local(LocalStatus::Intact),
+            report.count_local(LocalStatus::Modified),
+            report.count_local(LocalStatus::Missing),
+        );
+        let omitted = report.count_local(LocalStatus::Omitted);
+        if omitted > 0 {
+            summary.push_str(&format!(", {omitted} omitted"));
+        }
+        if refetch {
+            summary.push_str(&format!(
+                ", origins: {} unchanged, {} changed, {} dead, {} restored",
+                report.count_origin(OriginStatus::Unchanged),
+                report.count_origin(OriginStatus::Changed),
+                report.count_origin(OriginStatus::Dead),
+                report.count_restored(),
+            ));
+        }
+        if !report.has_manifest {
+            summary.push_str(" (no image manifest)");
+        }
+        println!("{summary}");
+    }
+
+    if !reports.iter().all(verify::FreshnessReport::is_intact) {
+        std::process::exit(1);
+    }
+}
+
+/// URLs of the positional arguments, and the output directory when the last isn't a URL
+/// and follows others or an `--input` list
+fn split_targets(targets: &[String], input: bool) -> (Vec<String>, Option<String>) {
+    let is_url = |target: &str| url::Url::parse(target).is_ok_and(|url| matches!(url.scheme(), "http" | "https" | "file"));
+    match targets.split_last() {
+        Some((last, urls)) if (input || !urls.is_empty()) && !is_url(last) => (urls.to_vec(), Some(last.clone())),
+        _ => (targets.to_vec(), None),
+    }
+}
+
+/// Take the values of `config` for the flags left out of the command line
+fn apply_config(args: &mut Args, matches: &ArgMatches, config: &Config) {
+
+    // flags with a default value are always set
+    let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+    args.archive_root = args.archive_root.take().or(config.archive_root.clone());
+
+    let browser = &config.browser;
+    if !given("backend") && let Some(backend) = browser.backend {
+        args.backend = backend;
+    }
+    if !given("webdriver_url") && let Some(url) = &browser.webdriver_url {
+        args.webdriver_url = url.clone();
+    }
+    args.no_browser = args.no_browser || !given("no_browser") && browser.no_browser && !given("backend") && args.replay.is_none();
+
+    let output = &config.output;
+    args.only = args.only.or(output.only);
+    args.layout = args.layout.take().or(output.layout.clone());
+    args.bundle = args.bundle.or(output.bundle);
+    if !given("transliterate") && let Some(transliteration) = output.transliterate {
+        args.transliterate = transliteration;
+    }
+    args.front_matter = args.front_matter || !given("front_matter") && output.front_matter;
+    args.toc = args.toc.or(output.toc);
+    args.screenshot_thumbnail = args.screenshot_thumbnail.or(output.screenshot_thumbnail);
+    if args.screenshot_thumbnail == Some(0) {
+        Args::command().error(clap::error::ErrorKind::ValueValidation, "screenshot_thumbnail of the configuration: 0 isn't a width").exit();
+    }
+    args.assets = args.assets || !given("assets") && output.assets;
+    args.docx = args.docx || !given("docx") && output.docx;
+    args.text = args.text || !given("text") && output.text;
+    args.warc = args.warc || !given("warc") && output.warc;
+    args.mhtml = args.mhtml || !given("mhtml") && output.mhtml;
+    args.single_file = args.single_file || !given("single_file") && output.single_file;
+    args.tables = args.tables || !given("tables") && output.tables;
+    args.structure = args.structure || !given("structure") && output.structure;
+    args.stats = args.stats.or(output.stats);
+    args.sanitize = args.sanitize || !given("sanitize") && output.sanitize;
+
+    let pdf = &config.pdf;
+    args.landscape = args.landscape || !given("landscape") && pdf.landscape;
+    args.margin = args.margin.or(pdf.margin);
+    if !given("scale") && let Some(scale) = pdf.scale {
+        args.scale = scale;
+    }
+    args.no_background = args.no_background || !given("no_background") && pdf.background == Some(false);
+    args.pdf_outline = args.pdf_outline.or(pdf.outline);
+    args.pdfa = args.pdfa || !given("pdfa") && pdf.pdfa;
+
+    let images = &config.images;
+    args.no_images = args.no_images || !given("no_images") && images.skip;
+    args.min_image_width = args.min_image_width.or(images.min_width);
+    args.min_image_height = args.min_image_height.or(images.min_height);
+    args.min_image_bytes = args.min_image_bytes.or(images.min_bytes);
+    args.max_image_bytes = args.max_image_bytes.or(images.max_bytes);
+    args.max_images = args.max_images.or(images.max_count);
+    args.max_images_bytes = args.max_images_bytes.or(images.max_total_bytes);
+    if !given("srcset") && let Some(strategy) = images.srcset {
+        args.srcset = strategy;
+    }
+    args.srcset_max_bytes = args.srcset_max_bytes.or(images.srcset_max_bytes);
+    args.convert_images = args.convert_images.or(images.convert);
+    args.thumbnails = args.thumbnails.or(images.thumbnails);
+    args.strip_metadata = args.strip_metadata || !given("strip_metadata") && images.strip_metadata;
+    args.browser_images = args.browser_images || !given("browser_images") && images.browser_images;
+    if !given("lazy_attributes") && let Some(attributes) = &images.lazy_attributes {
+        args.lazy_attributes = attributes.clone();
+    }
+
+    let selectors = &config.selectors;
+    args.select = args.select.take().or(selectors.select.clone());
+    args.exclude.splice(0..0, selectors.exclude.iter().cloned());
+
+    let concurrency = &config.concurrency;
+    args.jobs = args.jobs.or(concurrency.jobs);
+    args.max_rps = args.max_rps.or(concurrency.max_rps);
+    if let Some(max_rps) = args.max_rps && let Err(e) = parse_max_rps(&max_rps.to_string()) {
+        Args::command().error(clap::error::ErrorKind::ValueValidation, format!("max_rps of the configuration: {e}")).exit();
+    }
+    if !given("delay") && let Some(ms) = concurrency.delay_ms {
+        args.delay = ms;
+    }
+    if !given("image_concurrency") && let Some(concurrency) = concurrency.images {
+        args.image_concurrency = concurrency;
+    }
+    if !given("retries") && let Some(retries) = concurrency.retries {
+        args.retries = retries;
+    }
+    if !given("timeout") && let Some(secs) = concurrency.timeout_secs {
+        args.timeout = secs;
+    }
+    if !given("domain_delay") && let Some(ms) = concurrency.domain_delay_ms {
+        args.domain_delay = ms;
+    }
+    if !given("domain_jitter") && let Some(ms) = concurrency.domain_jitter_ms {
+        args.domain_jitter = ms;
+    }
+}
+
+/// Requests per second of `--max-rps`, a positive number
+fn parse_max_rps(value: &str) -> Result<f64, String> {
+    let max_rps = value.parse::<f64>().map_err(|e| e.to_string())?;
+    RateLimiter::interval(max_rps).map(|_| max_rps).ok_or_else(|| format!("{value} isn't a positive number of requests per second"))
+}
+
+/// Exit with a usage error when `flag` is set along with `other`
+fn check_conflict(conflicting: bool, flag: &str, other: &str) {
+    if conflicting {
+        Args::command().error(clap::error::ErrorKind::ArgumentConflict, format!("{flag} can't be used with {other}")).exit();
+    }
+}
+
+/// What the pages captured in one run share
+struct Session {
+    args: Args,
+    options: ScrapeOptions,
+    config: Config,
+    rules: Option<Rules>,
+    archive: Option<Archive>,
+    git_repo: Option<GitRepo>,
+    /// Pages being scraped, shown on the terminal
+    status: Option<Arc<StatusBoard>>,
+    /// Capture directories of the pages of the run, so two pages of the same title don't share one
+    taken: Mutex<HashSet<PathBuf>>,
+    today: time::Date,
+}
+
+impl Session {
+
+    /// Options of the run, with the configuration and rules of the domain of `url`
+    fn options_for(&self, url: &str) -> ScrapeOptions {
+        let mut options = self.config.options_for(url, &self.options);
+        if let Some(rules) = &self.rules {
+            options = rules.options_for(url, &options);
+        }
+        options.markdown_converter = self.args.markdown_converter.or(self.config.markdown_converter).unwrap_or_default();
+        options
+    }
+
+    fn already_archived(&self, url: &str) -> Option<PathBuf> {
+        match &self.archive {
+            Some(archive) if !self.args.recapture => archive.find(url).expect("Can't read the archive"),
+            _ => None,
+        }
+    }
+
+    /// Print `message` to the standard output, or the standard error with --json, below the status of the pages
+    fn say(&self, message: impl std::fmt::Display) {
+        if self.args.quiet {
+            return;
+        }
+        if self.args.json {
+            return self.error(message);
+        }
+        match &self.status {
+            Some(status) => status.suspend(|| println!("{message}")),
+            None => println!("{message}"),
+        }
+    }
+
+    /// Print `message` to the standard error, below the status of the pages
+    fn warn(&self, message: impl std::fmt::Display) {
+        if self.args.quiet {
+            return;
+        }
+        self.error(message);
+    }
+
+    /// Print `message` to the standard error, below the status of the pages, even with --quiet
+    fn error(&self, message: impl std::fmt::Display) {
+        match &self.status {
+            Some(status) => status.suspend(|| eprintln!("{message}")),
+            None => eprintln!("{message}"),
+        }
+    }
+
+    /// Print `header` and the `items` below it to the standard error, together
+    fn warn_list(&self, header: String, items: &[impl std::fmt::Display]) {
+        self.warn(std::iter::once(header).chain(items.iter().map(|item| format!("  - {item}"))).collect::<Vec<_>>().join("\n"));
+    }
+
+    /// Capture `url` with `renderer` in `output_directory`, or in a directory named after the page
+    /// under it when `under` is set
+    async fn capture(&self, renderer: &dyn PageRenderer, url: &str, output_directory: Option<&str>, under: bool) -> PageReport {
+        let started = Instant::now();
+        let mut options = self.options_for(url);
+        options.progress = self.status.as_ref().map(|status| status.start(url));
+        let report = self.capture_page(renderer, url, &options, output_directory, under).await
+            .unwrap_or_else(|e| PageReport::failed(url, e));
+        if let Some(status) = &self.status {
+            status.finish(url);
+        }
+        report.with_elapsed(started.elapsed())
+    }
+
+    async fn capture_page(&self, renderer: &dyn PageRenderer, url: &str, options: &ScrapeOptions, output_directory: Option<&str>, under: bool) -> Result<PageReport, String> {
+
+        let args = &self.args;
+        let today = self.today;
+        if let Some(robots) = &options.robots
+            && let Ok(parsed) = url::Url::parse(url)
+            && !robots.admit(&parsed).await {
+            self.say(format!("{url} is disallowed by robots.txt, skipped"));
+            return Ok(PageReport::skipped(url, "disallowed by robots.txt"));
+        }
+        let page = renderer.render(url, options).await.map_err(|e| format!("Can't render webpage: {e}"))?;
+        let mut webpage = match WebPage::from_page(page, options).await {
+            Err(WebPageError::NoArchive { url: final_url }) => {
+                self.say(format!("{final_url} asks not to be archived, nothing stored"));
+                return Ok(PageReport::skipped(url, "asks not to be archived"));
+            }
+            webpage => webpage.map_err(|e| format!("Can't scrape webpage: {e}"))?,
+        };
+
+        // redirections may lead to an archived URL
+        if let Some(existing) = self.already_archived(&webpage.url) {
+            self.say(format!("{} is already archived in {}, use --recapture to capture it again", webpage.url, existing.display()));
+            return Ok(PageReport::skipped(url, format!("already archived in {}", existing.display())));
+        }
+
+        if let Some(previous) = &args.diff {
+            let final_url = webpage.url.clone();
+            match webpage.diff(previous).map_err(|e| format!("Can't compare with the earlier capture: {e}"))? {
+                Some(_) => self.say(format!("{final_url} changed since {}, see {}", previous.display(), diff::FILENAME)),
+                None => self.say(format!("{final_url} didn't change since {}", previous.display())),
+            }
+        }
+
+        if let Some(root) = &args.obsidian {
+            let vault = Vault::open(root).map_err(|e| format!("Can't open the Obsidian vault: {e}"))?
+                .with_folder(args.obsidian_folder.clone().unwrap_or_default())
+                .with_tags(args.obsidian_tags.clone());
+            let note = webpage.export_to_obsidian(&vault, args.force).map_err(|e| format!("Can't write the note, use --force to replace it: {e}"))?;
+            self.say(format!("Saved {} as {}", webpage.url, note.display()));
+            let warnings = webpage.warnings();
+            if !warnings.is_empty() {
+                self.warn_list(format!("{} warning(s):", warnings.len()), &warnings);
+            }
+            return PageReport::captured(url, &webpage, &note).map_err(|e| format!("Can't list the files of the capture: {e}"));
+        }
+
+        let output_directory = match (output_directory, &self.archive) {
+            _ if let Some(repo) = &self.git_repo => {
+                let directory = crawl::unique_directory(repo.root(), &webpage.file_stem, &mut self.taken.lock().unwrap(), true);
+                // only a capture of an earlier run is replaced, it lives on in the history
+                if directory.is_dir() {
+                    std::fs::remove_dir_all(&directory).map_err(|e| format!("Can't replace the previous capture: {e}"))?;
+                }
+                directory.to_string_lossy().to_string()
+            }
+            (Some(root), _) if under => {
+                let mut taken = self.taken.lock().unwrap();
+                crawl::unique_directory(Path::new(root), &webpage.file_stem, &mut taken, true).to_string_lossy().to_string()
+            }
+            (Some(e), _) => e.to_string(),
+            (None, Some(archive)) => {
+                let directory = archive.capture_directory(&webpage.file_stem, today).map_err(|e| format!("Can't create archive directory: {e}"))?;
+                directory.to_string_lossy().to_string()
+            }
+            (None, None) => webpage.file_stem.clone()
+        };
+
+        let policy = match (args.force, args.merge, args.append_timestamp) {
+            (true, _, _) => OutputPolicy::Overwrite,
+            (_, true, _) => OutputPolicy::Merge,
+            (_, _, true) => OutputPolicy::AppendTimestamp,
+            _ => OutputPolicy::Error,
+        };
+        if let Some(format) = args.bundle && policy == OutputPolicy::Error {
+            let bundle = bundle::path(Path::new(&output_directory), format);
+            if bundle.exists() {
+                return Err(format!("{} already exists, use --force to replace it", bundle.display()));
+            }
+        }
+        let output_directory = webpage.write_outputs(&output_directory, policy, args.only.unwrap_or_default()).await
+            .map_err(|e| format!("Can't write scraped data to disk, use --force, --merge or --append-timestamp if it already exists: {e}"))?
+            .to_string_lossy()
+            .to_string();
+        // where warnings.json is to be found
+        let (saved_to, output_location) = match args.bundle {
+            Some(format) => {
+                let bundle = bundle::pack(Path::new(&output_directory), format).map_err(|e| format!("Can't pack the capture: {e}"))?;
+                self.say(format!("Capture packed in {}", bundle.display()));
+                let location = format!("{}:{}", bundle.display(), Path::new(&output_directory).file_name().unwrap_or_default().to_string_lossy());
+                (bundle, location)
+            }
+            None => (PathBuf::from(&output_directory), output_directory.clone()),
+        };
+
+        let (failed, skipped) = (webpage.image_failures().len(), webpage.nb_skipped_images());
+        if failed + skipped > 0 {
+            self.warn(format!("{} image(s) missing from the capture: {failed} failed to download, {skipped} over the image budget", failed + skipped));
+        }
+
+        let warnings = webpage.warnings();
+        if !warnings.is_empty() {
+            let header = match args.only.unwrap_or_default().contains(Outputs::INFO) {
+                true => format!("{} warning(s), see {}/warnings.json:", warnings.len(), output_location),
+                false => format!("{} warning(s):", warnings.len()),
+            };
+            self.warn_list(header, &warnings);
+        }
+
+        if let Some(repo) = &self.git_repo {
+            match repo.commit_capture(Path::new(&output_directory), &webpage.url, &today.to_string()).map_err(|e| format!("Can't commit the capture: {e}"))? {
+                Some(commit) => self.say(format!("Committed {} as {commit}", webpage.url)),
+                None => self.say(format!("{} didn't change since its last capture", webpage.url)),
+            }
+        }
+        PageReport::captured(url, &webpage, &saved_to).map_err(|e| format!("Can't list the files of the capture: {e}"))
+    }
+}
+
+/// Print the result of the run for --json
+fn print_report(pages: Vec<PageReport>, started: Instant) {
+    println!("{}", serde_json::to_string(&RunReport::new(pages, started.elapsed())).expect("Can't serialize the report"));
 }
 
 #[tokio::main]
 async fn main() {
 
-    let args = Args::parse();
+    let started = Instant::now();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    Logger::new(logging::level(args.verbose, args.quiet), args.log_format).install();
+
+    if let Some(Command::Verify { archive, refetch }) = &args.command {
+        return verify(archive, *refetch, args.json).await;
+    }
+    let config_path = match &args.config {
+        Some(path) => Some(path.clone()),
+        None if args.no_config => None,
+        None => Config::find(),
+    };
+    let config = match &config_path {
+        Some(path) => Config::load(path).unwrap_or_else(|e| panic!("Can't load configuration {}: {e}", path.display())),
+        None => Config::default(),
+    };
+    apply_config(&mut args, &matches, &config);
 
-    let browser = browser::Browser::new().expect("Can't initiate browser");
+    let (mut urls, output_directory) = split_targets(&args.targets, args.input.is_some());
+    if let Some(input) = &args.input {
+        urls.extend(url_list::read(input).expect("Can't read the URL list"));
+    }
+    if urls.is_empty() {
+        match args.json {
+            true => print_report(Vec::new(), started),
+            false => println!("No URL to scrape"),
+        }
+        return;
+    }
+    // pages of a list get a directory each, however many it holds
+    let batch = urls.len() > 1 || args.input.is_some();
+    if output_directory.is_some() {
+        let archive_root = match matches.value_source("archive_root") {
+            Some(ValueSource::CommandLine) => "--archive-root",
+            _ => "the archive root of the configuration",
+        };
+        check_conflict(args.archive_root.is_some(), "the output directory", archive_root);
+        check_conflict(args.git_repo.is_some(), "the output directory", "--git-repo");
+        check_conflict(args.obsidian.is_some(), "the output directory", "--obsidian");
+    }
+    if batch {
+        check_conflict(args.crawl_depth.is_some(), "--crawl-depth", "several URLs");
+        check_conflict(args.diff.is_some(), "--diff", "several URLs");
+    }
+    // packing removes the directory, with --merge an earlier capture
+    check_conflict(args.merge && args.bundle.is_some(), "--merge", "the bundle of the configuration");
+    let jobs = match batch {
+        true => args.jobs.unwrap_or(DEFAULT_JOBS).max(1),
+        false => 1,
+    };
 
-    let webpage = browser.open_tab(&args.url).await.unwrap();
+    let recording = args.replay.as_deref().map(|path| Arc::new(Recording::load(path).expect("Can't load recording")));
 
-    let output_directory = match args.output_directory {
-        Some(e) => e,
-        None => webpage.title.clone()
+    let network = match &recording {
+        Some(recording) => NetworkMode::Replay(recording.clone()),
+        None if args.record => NetworkMode::Record,
+        None => NetworkMode::Live,
     };
 
-    webpage.write_to_disk(&output_directory).await.expect("Can't write scraped data to disk");
+    let rate_limit = Arc::new(RateLimiter::new().with_max_rps(args.max_rps.unwrap_or_default()));
+    // asking for a single page isn't crawling
+    let robots = ((batch || args.crawl_depth.is_some()) && !args.ignore_robots).then(|| {
+        let robots = Robots::new(&network).expect("Can't initiate HTTP client");
+        Arc::new(robots.with_rate_limit(rate_limit.clone(), Duration::from_millis(args.delay)).with_auth(config.site_auth()))
+    });
+
+    let options = ScrapeOptions {
+        min_words: args.min_words,
+        content_guard: if args.fail_on_low_content { ContentGuard::Fail } else { ContentGuard::Flag },
+        transliteration: args.transliterate,
+        domain_delay: Duration::from_millis(args.domain_delay),
+        domain_jitter: Duration::from_millis(args.domain_jitter),
+        request_delay: Duration::from_millis(args.delay),
+        rate_limit,
+        network,
+        srcset_strategy: args.srcset,
+        srcset_max_bytes: args.srcset_max_bytes,
+        comments: args.comments,
+        code_embeds: args.code_embeds,
+        html_report: args.html_report,
+        lazy_attributes: LazyAttributes(args.lazy_attributes.clone()),
+        honor_noarchive: args.honor_noarchive,
+        robots,
+        image_filter: ImageFilter {
+            min_width: args.min_image_width,
+            min_height: args.min_image_height,
+            min_bytes: args.min_image_bytes,
+            max_bytes: args.max_image_bytes,
+        },
+        image_budget: ImageBudget { max_count: args.max_images, max_bytes: args.max_images_bytes },
+        image_concurrency: Some(args.image_concurrency),
+        download_permits: (jobs > 1).then(|| Arc::new(Semaphore::new(args.image_concurrency.max(1)))),
+        retry: RetryPolicy { max_retries: args.retries, timeout: Duration::from_secs(args.timeout), ..Default::default() },
+        convert_images: args.convert_images,
+        thumbnails: args.thumbnails.map(|max_size| Thumbnails { max_size, keep_originals: !args.thumbnails_only }),
+        strip_metadata: args.strip_metadata,
+        browser_images: args.browser_images,
+        assets: args.assets,
+        docx: args.docx,
+        plain_text: args.text,
+        warc: args.warc,
+        mhtml: args.mhtml,
+        single_file: args.single_file,
+        front_matter: args.front_matter,
+        toc: args.toc,
+        screenshot_thumbnail: args.screenshot_thumbnail,
+        tables: args.tables,
+        structure: args.structure,
+        stats: args.stats,
+        pdf: PrintOptions { landscape: args.landscape, margin: args.margin, scale: args.scale, background: !args.no_background, outline: args.pdf_outline.or(args.toc), pdfa: args.pdfa },
+        layout: args.layout.clone().unwrap_or_default(),
+        pandoc: PandocOptions {
+            format: args.pandoc_format.clone(),
+            extensions: args.pandoc_extensions.clone(),
+            wrap: args.wrap,
+            reference_links: args.reference_links,
+            extra_args: args.pandoc_args.clone(),
+        },
+        remove_selectors: args.exclude.iter().flat_map(|list| dom::split_selector_list(list)).collect(),
+        select: args.select.clone(),
+        sanitize: args.sanitize,
+        no_feeds: args.no_feeds,
+        extract: args.extract.as_ref().map(|path| Fields::load(path).expect("Can't load the extracted fields")).unwrap_or_default(),
+        no_images: args.no_images || args.only.is_some_and(|only| !only.contains(Outputs::IMAGES)),
+        media: args.media.then_some(MediaLimits { max_file_bytes: args.max_media_bytes, max_total_bytes: args.max_total_media_bytes }),
+        wait_for: config.selectors.wait_for.clone(),
+        wait_after_load: config.selectors.wait_ms.map(Duration::from_millis).unwrap_or(ScrapeOptions::default().wait_after_load),
+        ..Default::default()
+    };
+
+    let rules = args.rules.as_ref().or(config.rules.as_ref()).map(|directory| Rules::load(directory).expect("Can't load the extraction rules"));
+    let archive = args.archive_root.clone().map(Archive::new);
+    let today = time::OffsetDateTime::now_local().unwrap_or_else(|_| time::OffsetDateTime::now_utc()).date();
+    let mut session = Session { args, options, config, rules, archive, git_repo: None, status: None, taken: Mutex::default(), today };
+
+    let mut pages = Vec::new();
+    let urls = urls.into_iter().filter(|url| match session.already_archived(url) {
+        Some(existing) => {
+            session.say(format!("{url} is already archived in {}, use --recapture to capture it again", existing.display()));
+            pages.push(PageReport::skipped(url, format!("already archived in {}", existing.display())));
+            false
+        }
+        None => true,
+    }).collect::<Vec<_>>();
+    let Some(url) = urls.first().cloned() else {
+        if session.args.json {
+            print_report(pages, started);
+        }
+        return;
+    };
+
+    // set up for the first page, the others share the browser
+    let options = session.options_for(&url);
+    let args = &session.args;
+    let renderer: Box<dyn PageRenderer> = match args.backend {
+        _ if let Some(recording) = &recording => Box::new(ReplayRenderer::new(recording.clone())),
+        _ if args.no_browser => Box::new(StaticFetcher::with_options(options.clone()).expect("Can't initiate HTTP client")),
+        Backend::Chrome => Box::new(browser::Browser::with_options(options.clone()).expect("Can't initiate browser")),
+        Backend::Webdriver => Box::new(WebDriver::with_options(&args.webdriver_url, options.clone()).expect("Can't initiate WebDriver")),
+    };
+
+    if let Some(max_depth) = args.crawl_depth {
+        let host = url::Url::parse(&url).ok().and_then(|url| url.host_str().map(str::to_string)).unwrap_or("crawl".to_string());
+        let output_directory = match (&output_directory, &session.archive) {
+            (Some(directory), _) => PathBuf::from(directory),
+            (None, Some(archive)) => archive.capture_directory(&host, session.today).expect("Can't create archive directory"),
+            (None, None) => PathBuf::from(&host),
+        };
+        let limits = CrawlLimits { max_depth, max_pages: args.max_pages };
+        let graph = match crawl::crawl(renderer.as_ref(), &url, &options, limits, &output_directory).await {
+            Ok(graph) => graph,
+            Err(e) => {
+                session.error(format!("Can't crawl website: {e}"));
+                if args.json {
+                    pages.push(PageReport::failed(&url, format!("Can't crawl website: {e}")));
+                    print_report(pages, started);
+                }
+                std::process::exit(1);
+            }
+        };
+
+        match args.json {
+            true => {
+                for node in &graph.nodes {
+                    match PageReport::crawled(node, &output_directory) {
+                        Ok(report) => pages.extend(report),
+                        Err(e) => pages.push(PageReport::failed(&node.url, format!("Can't list the files of the capture: {e}"))),
+                    }
+                }
+                print_report(pages, started);
+            }
+            false => {
+                let captured = graph.nodes.iter().filter(|node| node.directory.is_some()).count();
+                println!("Captured {captured} page(s) into {}", output_directory.display());
+            }
+        }
+        for node in graph.nodes.iter().filter(|node| node.error.is_some()) {
+            eprintln!("  - {}: {}", node.url, node.error.as_deref().unwrap_or_default());
+        }
+        if let Some(archive_root) = &args.feed {
+            Feed::update(archive_root).expect("Can't update the archive feed");
+        }
+        return;
+    }
+
+    session.git_repo = session.args.git_repo.as_deref().map(|root| GitRepo::open_or_init(root).expect("Can't open the git repository"));
+
+    if batch && let Some(root) = &output_directory {
+        std::fs::create_dir_all(root).expect("Can't create the output directory");
+    }
+    if !session.args.no_progress && !session.args.quiet && std::io::stderr().is_terminal() {
+        session.status = Some(Arc::new(StatusBoard::new(urls.len())));
+    }
+    let (session, renderer, output_directory) = (&session, renderer.as_ref(), output_directory.as_deref());
+    let mut captures = futures::stream::iter(&urls)
+        .map(|url| session.capture(renderer, url, output_directory, batch))
+        .buffer_unordered(jobs);
+    while let Some(report) = captures.next().await {
+        if let Some(error) = &report.error {
+            session.error(format!("{}: {error}", report.url));
+        }
+        pages.push(report);
+    }
+    if let Some(status) = &session.status {
+        status.clear();
+    }
+    let count = |status| pages.iter().filter(|page| page.status == status).count();
+    let (captured, failures) = (count(CaptureStatus::Captured), count(CaptureStatus::Failed));
+    if batch {
+        session.say(format!("Captured {captured} of {} page(s), {failures} failed", urls.len()));
+    }
 
+    if let Some(archive_root) = &session.args.feed {
+        Feed::update(archive_root).expect("Can't update the archive feed");
+    }
+    if session.args.json {
+        print_report(pages, started);
+    }
+    if failures > 0 {
+        std::process::exit(1);
+    }
 }
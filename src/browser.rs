@@ -3,73 +3,469 @@ use anyhow;
 use url::{Url, ParseError};
 use thiserror::Error;
 use crate::webpage::{WebPage, WebPageError};
+use crate::options::ScrapeOptions;
+use crate::ratelimit::RequestKind;
+use crate::slug;
+use crate::pdf::{PdfError, PrintOptions};
+use crate::pdfa;
+use crate::renderer::{PageRenderer, RenderedPage};
+use crate::timings::{self, Timings};
+use crate::chrome;
+use crate::comments;
+use crate::warnings::{Warning, WarningKind};
+use crate::snapshot::Snapshot;
+use crate::http::{Cookie, Response};
+use base64::Engine;
+use headless_chrome::protocol::cdp::Network::{CookieParam, ResourceType};
+use headless_chrome::protocol::cdp::Page;
+use futures::future::{BoxFuture, FutureExt};
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
 #[derive(Error, Debug)]
 pub enum BrowserError {
     #[error("ChromeError: {0}")]
     ChromeError(#[from] anyhow::Error),
+    #[error("Chrome not found, install Chrome or point the CHROME environment variable to it. Searched: {}", searched.join(", "))]
+    ChromeNotFound { searched: Vec<String> },
     #[error("UrlError, can't parse given URL: {0}")]
     UrlError(#[from] ParseError),
     #[error("WebPageError: {0}")]
     WebPageError(#[from] WebPageError),
     #[error("IO error: {0}")]
-    IoError(#[from] std::io::Error)
+    IoError(#[from] std::io::Error),
+    #[error("WebDriverError: {0}")]
+    WebDriverError(String),
+    #[error("ReqwestError: {0}")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("JSON conversion error: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Base64Error: {0}")]
+    Base64Error(#[from] base64::DecodeError),
+    #[error("{0} is not the URL of the recording")]
+    NotRecorded(String),
+    #[error("PdfError: {0}")]
+    PdfError(#[from] PdfError),
 }
 pub type Result<T> = std::result::Result<T, BrowserError>;
 
-pub struct Browser (headless_chrome::Browser);
+/// Clones share the same Chrome, tabs and rate limits
+#[derive(Clone)]
+pub struct Browser {
+    browser: headless_chrome::Browser,
+    options: ScrapeOptions,
+    /// Tabs of dropped pages, navigated again instead of opening new ones
+    idle_tabs: IdleTabs,
+}
+
+type IdleTabs = Arc<Mutex<Vec<Arc<headless_chrome::Tab>>>>;
+
+type ImageResponses = Arc<Mutex<Vec<Response>>>;
+
+/// Name and value of the response headers
+type Headers = Vec<(String, String)>;
+
+/// A loaded tab along with what went wrong while waiting for it,
+/// the tab goes back to the idle tabs of its browser once dropped
+struct ChromePage {
+    tab: Arc<headless_chrome::Tab>,
+    warnings: Vec<Warning>,
+    /// Status and headers of the main document response
+    status: Option<u16>,
+    headers: Vec<(String, String)>,
+    requested_url: String,
+    timings: Timings,
+    /// Image responses received by the tab, when `ScrapeOptions::browser_images` is set
+    images: ImageResponses,
+    idle_tabs: IdleTabs,
+}
+
+impl ChromePage {
+    const IMAGES_HANDLER: &str = "image-responses";
+}
+
+impl Drop for ChromePage {
+    fn drop(&mut self) {
+        let _ = self.tab.deregister_response_handling(Self::IMAGES_HANDLER);
+        let mut idle_tabs = self.idle_tabs.lock().unwrap();
+        if idle_tabs.len() < Browser::MAX_IDLE_TABS {
+            idle_tabs.push(self.tab.clone());
+        } else {
+            let _ = self.tab.close(false);
+        }
+    }
+}
 
 impl Browser {
     
+    /// Idle tabs kept for later pages, the others are closed
+    const MAX_IDLE_TABS: usize = 4;
+
+    /// Time without any page after which Chrome gets closed, long enough for the shared browser
+    const SHARED_IDLE_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
     pub fn new() -> Result<Self> {
-        Ok(Self(headless_chrome::Browser::default()?))
+        Self::with_options(ScrapeOptions::default())
+    }
+
+    pub fn with_options(options: ScrapeOptions) -> Result<Self> {
+        Self::launch(options, None)
+    }
+
+    /// Browser shared by the whole process, launched on first use. Pages can be
+    /// rendered concurrently, each on its own tab, with `open_tab_with_options`
+    /// for options other than the default ones
+    pub fn shared() -> Result<&'static Self> {
+        static SHARED: OnceLock<Browser> = OnceLock::new();
+        static LAUNCH: Mutex<()> = Mutex::new(());
+
+        if let Some(browser) = SHARED.get() {
+            return Ok(browser);
+        }
+        // only one Chrome is launched when several threads get here first
+        let _launching = LAUNCH.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if SHARED.get().is_none() {
+            let _ = SHARED.set(Self::launch(ScrapeOptions::default(), Some(Self::SHARED_IDLE_TIMEOUT))?);
+        }
+        Ok(SHARED.get().expect("shared browser was just set"))
+    }
+
+    fn launch(options: ScrapeOptions, idle_timeout: Option<Duration>) -> Result<Self> {
+        let path = match chrome::find_executable() {
+            Ok(path) => path,
+            #[cfg(feature = "download-chromium")]
+            Err(_) => chrome::download::fetch()?,
+            #[cfg(not(feature = "download-chromium"))]
+            Err(searched) => return Err(BrowserError::ChromeNotFound { searched }),
+        };
+        let mut launch_options = headless_chrome::LaunchOptions::default_builder();
+        launch_options.path(Some(path));
+        if let Some(idle_timeout) = idle_timeout {
+            launch_options.idle_browser_timeout(idle_timeout);
+        }
+        let launch_options = launch_options.build().map_err(|e| anyhow::anyhow!(e))?;
+        tracing::debug!(path = ?launch_options.path, "launching Chrome");
+        let browser = headless_chrome::Browser::new(launch_options)?;
+        Ok(Self { browser, options, idle_tabs: IdleTabs::default() })
+    }
+
+    /// An idle tab if any is left, or else a new one
+    fn tab(&self) -> Result<Arc<headless_chrome::Tab>> {
+        let idle = self.idle_tabs.lock().unwrap().pop();
+        match idle {
+            // authentication of the previous page stays with the tab otherwise
+            Some(tab) if tab.disable_fetch().is_ok() => Ok(tab),
+            _ => Ok(self.browser.new_tab()?),
+        }
+    }
+
+    /// Time to wait before navigating to `url` so its host isn't hammered, `options` giving its delay
+    fn politeness_delay(&self, url: &str, options: &ScrapeOptions) -> Result<Duration> {
+        let url = Url::parse(url)?;
+        let host = url.host_str().unwrap_or_default();
+        Ok(self.options.rate_limit.reserve(RequestKind::Page, host, options.domain_delay, options.domain_jitter))
     }
 
-    fn url_to_tab(&self, url: &str) -> Result<Arc<headless_chrome::Tab>> {
+    fn url_to_tab(&self, url: &str, options: &ScrapeOptions) -> Result<ChromePage> {
         
-        Url::parse(url)?;
-        let tab = self.0.new_tab()?;
+        let started = Instant::now();
+        let parsed_url = Url::parse(url)?;
+        let tab = self.tab()?;
+        let mut warnings = Vec::new();
+
+        if let Some(auth) = options.auth.as_ref().filter(|auth| auth.applies_to(&parsed_url)) {
+            if let Some(username) = &auth.credentials.username {
+                tab.enable_fetch(None, Some(true))?;
+                tab.authenticate(Some(username.clone()), auth.credentials.password.clone())?;
+            }
+            let domain = format!(".{}", auth.domain.trim_start_matches("*.").trim_start_matches('.'));
+            let cookies = auth
+                .cookies()
+                .into_iter()
+                .map(|(name, value)| serde_json::from_value::<CookieParam>(
+                    serde_json::json!({ "name": name, "value": value, "domain": domain, "path": "/" })
+                ))
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            if !cookies.is_empty() {
+                tab.set_cookies(cookies)?;
+            }
+        }
+
+        // the first document response is the page itself, later ones are frames
+        let document_response: Arc<OnceLock<(u16, Headers)>> = Arc::default();
+        let captured = document_response.clone();
+        tab.register_response_handling("document-headers", Box::new(move |params, _| {
+            if params.Type == ResourceType::Document {
+                let headers = params.response.headers.0.as_ref().and_then(|headers| headers.as_object());
+                let _ = captured.set((params.response.status as u16, headers.into_iter().flatten().map(|(name, value)| {
+                    (name.clone(), value.as_str().unwrap_or_default().to_string())
+                }).collect()));
+            }
+        }))?;
 
+        // kept until the page is dropped, lazy images load after navigation
+        let images = ImageResponses::default();
+        if options.browser_images {
+            let captured = images.clone();
+            tab.register_response_handling(ChromePage::IMAGES_HANDLER, Box::new(move |params, fetch_body| {
+                if params.Type != ResourceType::Image || params.response.status != 200 {
+                    return;
+                }
+                let (Ok(url), Ok(body)) = (Url::parse(&params.response.url), fetch_body()) else { return };
+                let body = match body.base_64_encoded {
+                    true => base64::engine::general_purpose::STANDARD.decode(&body.body).unwrap_or_default(),
+                    false => body.body.into_bytes(),
+                };
+                if !body.is_empty() {
+                    let content_type = Some(params.response.mime_type.clone());
+                    captured.lock().unwrap().push(Response { url, status: 200, content_type, body });
+                }
+            }))?;
+        }
+
+        tracing::debug!(url, "navigating");
         tab.navigate_to(url)?.wait_until_navigated()?;
+        tab.deregister_response_handling("document-headers")?;
+        let (status, headers) = match document_response.get().cloned() {
+            Some((status, headers)) => (Some(status), headers),
+            None => (None, Vec::new()),
+        };
+        tracing::debug!(url, status, elapsed_ms = started.elapsed().as_millis() as u64, "navigated");
+
+        if let Some(selector) = &options.wait_for
+            && tab.wait_for_element(selector).is_err() {
+            warnings.push(Warning::with_url(WarningKind::SelectorNotFound, url, format!("{selector:?} never appeared, captured anyway")));
+        }
+        std::thread::sleep(options.wait_after_load);
+
+        if options.comments && tab.evaluate(comments::EXPAND_SCRIPT, true).is_err() {
+            warnings.push(Warning::with_url(WarningKind::SelectorNotFound, url, "comment threads couldn't be expanded"));
+        }
 
-        Ok(tab)
+        let timings = tab.evaluate(timings::SCRIPT, false).ok()
+            .and_then(|result| result.value)
+            .and_then(|value| value.as_str().and_then(Timings::from_script))
+            .unwrap_or_default();
+        let timings = Timings { render_ms: started.elapsed().as_millis() as u64, ..timings };
+        tracing::info!(url, render_ms = timings.render_ms, warnings = warnings.len(), "page loaded in Chrome");
+
+        Ok(ChromePage { tab, warnings, status, headers, requested_url: url.to_string(), timings, images, idle_tabs: self.idle_tabs.clone() })
 
     }
 
     pub async fn open_tab(&self, url: &str) -> Result<WebPage> {
+        self.open_tab_with_options(url, &self.options).await
+    }
+
+    /// `open_tab` with other options than those of the browser, the rate limit stays the browser's
+    pub async fn open_tab_with_options(&self, url: &str, options: &ScrapeOptions) -> Result<WebPage> {
     
-        let tab = self.url_to_tab(url)?;
+        tokio::time::sleep(self.politeness_delay(url, options)?).await;
+        let page: Arc<dyn RenderedPage> = Arc::new(self.url_to_tab(url, options)?);
 
-        let webpage = WebPage::from_tab(tab).await?;
+        let webpage = WebPage::from_page(page, options).await?;
 
         Ok(webpage)
     }
 
     pub fn url_to_pdf(&self, url: &str) -> Result<()> {
 
-        let tab = self.url_to_tab(url)?;
+        std::thread::sleep(self.politeness_delay(url, &self.options)?);
+        let page = self.url_to_tab(url, &self.options)?;
+        let tab = &page.tab;
         let title = tab.get_title()?;
-        let filename = format!("{}.pdf", title);
+        let filename = match slug::file_stem(&title, self.options.transliteration) {
+            stem if stem.is_empty() => "webpage.pdf".to_string(),
+            stem => format!("{stem}.pdf"),
+        };
         let output_path = Path::new(&filename);
-        let pdf = tab.print_to_pdf(None)?;
-        std::fs::write(&output_path, pdf)?;
+        let snapshot = Snapshot::detect(&tab.get_url(), &page.headers);
+        let pdf = self.options.pdf.post_process(tab.print_to_pdf(Some(self.options.pdf.to_chrome()))?);
+        let mut pdf = snapshot.tag_pdf(pdf);
+        if self.options.pdf.pdfa {
+            pdf = pdfa::convert(&pdf, &title, &tab.get_url(), time::OffsetDateTime::now_utc())?;
+        }
+        std::fs::write(output_path, pdf)?;
         Ok(())
     }
 }
 
+impl PageRenderer for Browser {
+
+    fn render<'a>(&'a self, url: &'a str, options: &'a ScrapeOptions) -> BoxFuture<'a, Result<Arc<dyn RenderedPage>>> {
+        async move {
+            tokio::time::sleep(self.politeness_delay(url, options)?).await;
+            // loading blocks, pages rendered at once load side by side on the blocking threads
+            let (browser, url, options) = (self.clone(), url.to_string(), options.clone());
+            let page = tokio::task::spawn_blocking(move || browser.url_to_tab(&url, &options))
+                .await
+                .map_err(anyhow::Error::from)??;
+            let page: Arc<dyn RenderedPage> = Arc::new(page);
+            Ok(page)
+        }.boxed()
+    }
+}
+
+impl RenderedPage for headless_chrome::Tab {
+
+    fn url(&self) -> String {
+        self.get_url()
+    }
+
+    fn title(&self) -> anyhow::Result<String> {
+        self.get_title()
+    }
+
+    fn html(&self) -> anyhow::Result<String> {
+        self.get_content()
+    }
+
+    fn print_to_pdf(&self, options: &PrintOptions) -> anyhow::Result<Vec<u8>> {
+        headless_chrome::Tab::print_to_pdf(self, Some(options.to_chrome()))
+    }
+
+    fn capture_screenshot(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(Some(headless_chrome::Tab::capture_screenshot(self, Page::CaptureScreenshotFormatOption::Png, None, None, true)?))
+    }
+
+    fn capture_thumbnail(&self, width: u32) -> anyhow::Result<Option<Vec<u8>>> {
+        let viewport = self.call_method(Page::GetLayoutMetrics(None))?.css_layout_viewport;
+        let (viewport_width, viewport_height) = (viewport.client_width as f64, viewport.client_height as f64);
+        if viewport_width == 0.0 {
+            return Ok(None);
+        }
+        let clip = Page::Viewport { x: 0.0, y: 0.0, width: viewport_width, height: viewport_height, scale: width as f64 / viewport_width };
+        Ok(Some(headless_chrome::Tab::capture_screenshot(self, Page::CaptureScreenshotFormatOption::Jpeg, Some(80), Some(clip), true)?))
+    }
+
+    fn capture_mhtml(&self) -> anyhow::Result<Option<String>> {
+        let snapshot = self.call_method(Page::CaptureSnapshot { format: Some(Page::CaptureSnapshotFormatOption::Mhtml) })?;
+        Ok(Some(snapshot.data))
+    }
+}
+
+impl RenderedPage for ChromePage {
+
+    fn url(&self) -> String {
+        self.tab.url()
+    }
+
+    fn requested_url(&self) -> Option<String> {
+        Some(self.requested_url.clone())
+    }
+
+    fn status(&self) -> Option<u16> {
+        self.status
+    }
+
+    fn title(&self) -> anyhow::Result<String> {
+        self.tab.title()
+    }
+
+    fn html(&self) -> anyhow::Result<String> {
+        self.tab.html()
+    }
+
+    fn print_to_pdf(&self, options: &PrintOptions) -> anyhow::Result<Vec<u8>> {
+        RenderedPage::print_to_pdf(self.tab.as_ref(), options)
+    }
+
+    fn capture_mhtml(&self) -> anyhow::Result<Option<String>> {
+        RenderedPage::capture_mhtml(self.tab.as_ref())
+    }
+
+    fn capture_screenshot(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        RenderedPage::capture_screenshot(self.tab.as_ref())
+    }
+
+    fn capture_thumbnail(&self, width: u32) -> anyhow::Result<Option<Vec<u8>>> {
+        RenderedPage::capture_thumbnail(self.tab.as_ref(), width)
+    }
+
+    fn timings(&self) -> Timings {
+        self.timings.clone()
+    }
+
+    fn warnings(&self) -> Vec<Warning> {
+        self.warnings.clone()
+    }
+
+    fn response_headers(&self) -> Vec<(String, String)> {
+        self.headers.clone()
+    }
+
+    fn image_responses(&self) -> Vec<Response> {
+        self.images.lock().unwrap().clone()
+    }
+
+    fn read_blob(&self, url: &str) -> Option<String> {
+        let script = format!(
+            "fetch({}).then(response => response.blob()).then(blob => new Promise((resolve, reject) => {{ \
+                const reader = new FileReader(); reader.onload = () => resolve(reader.result); reader.onerror = reject; reader.readAsDataURL(blob); }}))",
+            serde_json::to_string(url).ok()?
+        );
+        let result = self.tab.evaluate(&script, true).ok()?;
+        result.value?.as_str().filter(|data| data.starts_with("data:")).map(str::to_string)
+    }
+
+    fn cookies(&self) -> Vec<Cookie> {
+        self.tab.get_cookies().unwrap_or_default().into_iter().map(|cookie| Cookie {
+            name: cookie.name,
+            value: cookie.value,
+            // host-only cookies are the ones without a leading dot
+            host_only: !cookie.domain.starts_with('.'),
+            domain: cookie.domain,
+            path: cookie.path,
+            secure: cookie.secure,
+        }).collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use crate::testing::FixtureServer;
+
     #[tokio::test]
+    #[ignore = "needs Chrome and pandoc"]
     async fn test_complicated_website() {
+        let server = FixtureServer::with_article().unwrap();
+        let output = tempfile::tempdir().unwrap();
         let b = Browser::new().unwrap();
-        let link = "https://100-beste-plakate.de/plakate/";
-        //let link = "https://en.wikipedia.org/wiki/%C3%89cole_cantonale_d%27art_de_Lausanne";
-        let tab = b.open_tab(link).await.unwrap();
+        let tab = b.open_tab(&server.url("/article.html")).await.unwrap();
+
+        let output_path = output.path().join("article");
+        tab.write_to_disk(output_path.to_str().unwrap()).await.unwrap();
+        assert!(output_path.join("images/red.png").is_file());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs Chrome"]
+    async fn shared_browser_reuses_tabs() {
+        let server = FixtureServer::with_article().unwrap();
+        let browser = Browser::shared().unwrap();
+        assert!(std::ptr::eq(browser, Browser::shared().unwrap()));
+
+        let first = browser.render(&server.url("/article.html"), &ScrapeOptions::default()).await.unwrap();
+        drop(first);
+        assert_eq!(browser.idle_tabs.lock().unwrap().len(), 1);
+        let second = browser.render(&server.url("/article.html"), &ScrapeOptions::default()).await.unwrap();
+        assert!(browser.idle_tabs.lock().unwrap().is_empty());
+        assert_eq!(second.title().unwrap(), "Fixture article");
+    }
+
+    #[test]
+    #[ignore = "needs Chrome"]
+    fn keeps_image_responses_of_the_tab() {
+        let server = FixtureServer::with_article().unwrap();
+        let options = ScrapeOptions { browser_images: true, ..Default::default() };
+        let browser = Browser::with_options(options.clone()).unwrap();
+        let page = browser.url_to_tab(&server.url("/article.html"), &options).unwrap();
 
-        tab.write_to_disk("test/complicated_website").await.unwrap();
+        let red = server.url("/images/red.png");
+        let images = page.image_responses();
+        assert!(images.iter().any(|image| image.url.as_str() == red && image.body == crate::testing::RED_PNG));
     }
 }
@@ -0,0 +1,258 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::Compression;
+use time::OffsetDateTime;
+
+/// Single file a capture directory is packed into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum BundleFormat {
+    Zip,
+    TarGz,
+}
+
+impl BundleFormat {
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            BundleFormat::Zip => "zip",
+            BundleFormat::TarGz => "tar.gz",
+        }
+    }
+}
+
+/// Paths of the files of `directory`, relative to it, `/`-separated and sorted
+pub(crate) fn paths(directory: &Path) -> io::Result<Vec<String>> {
+    let mut paths = Vec::new();
+    let mut pending = vec![directory.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                let relative = path.strip_prefix(directory).expect("entries are within the directory");
+                paths.push(relative.components().map(|part| part.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"));
+            }
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Files of `directory` as `(relative path, bytes)`, `/`-separated and sorted
+pub(crate) fn files(directory: &Path) -> io::Result<Vec<(String, Vec<u8>)>> {
+    paths(directory)?.into_iter().map(|path| {
+        let bytes = std::fs::read(directory.join(&path))?;
+        Ok((path, bytes))
+    }).collect()
+}
+
+/// `<directory>.zip` or `<directory>.tar.gz`
+pub fn path(directory: &Path, format: BundleFormat) -> PathBuf {
+    let mut bundle = directory.as_os_str().to_owned();
+    bundle.push(format!(".{}", format.extension()));
+    PathBuf::from(bundle)
+}
+
+/// Pack `directory` into `<directory>.zip` or `<directory>.tar.gz`, its files under a top
+/// directory of the same name, then remove it. Returns the path of the bundle
+pub fn pack(directory: &Path, format: BundleFormat) -> io::Result<PathBuf> {
+
+    let name = directory.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| "capture".to_string());
+    let files = files(directory)?
+        .into_iter()
+        .map(|(path, bytes)| (format!("{name}/{path}"), bytes))
+        .collect::<Vec<_>>();
+    let bytes = match format {
+        BundleFormat::Zip => zip(&files, OffsetDateTime::now_utc())?,
+        BundleFormat::TarGz => tar_gz(&files, OffsetDateTime::now_utc())?,
+    };
+
+    let bundle = path(directory, format);
+    std::fs::write(&bundle, bytes)?;
+    std::fs::remove_dir_all(directory)?;
+    Ok(bundle)
+}
+
+/// MS-DOS time and date of zip headers
+fn dos_date_time(date: OffsetDateTime) -> (u16, u16) {
+    let time = ((date.hour() as u16) << 11) | ((date.minute() as u16) << 5) | (date.second() as u16 / 2);
+    let day = (((date.year() - 1980).clamp(0, 127) as u16) << 9) | ((u8::from(date.month()) as u16) << 5) | date.day() as u16;
+    (time, day)
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "capture too large for a zip file without zip64")
+}
+
+/// Zip archive of `files`, each deflated
+pub fn zip(files: &[(String, Vec<u8>)], date: OffsetDateTime) -> io::Result<Vec<u8>> {
+
+    let (time, day) = dos_date_time(date);
+    let mut archive = Vec::new();
+    let mut central = Vec::new();
+
+    for (name, bytes) in files {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes)?;
+        let compressed = encoder.finish()?;
+        let crc = crc32fast::hash(bytes);
+        let offset = u32::try_from(archive.len()).map_err(|_| too_large())?;
+        let size = u32::try_from(bytes.len()).map_err(|_| too_large())?;
+        let compressed_size = u32::try_from(compressed.len()).map_err(|_| too_large())?;
+        let name_len = u16::try_from(name.len()).map_err(|_| too_large())?;
+
+        // version 2.0, UTF-8 names, deflate
+        let common = [
+            &20u16.to_le_bytes()[..], &0x0800u16.to_le_bytes(), &8u16.to_le_bytes(), &time.to_le_bytes(), &day.to_le_bytes(),
+            &crc.to_le_bytes(), &compressed_size.to_le_bytes(), &size.to_le_bytes(), &name_len.to_le_bytes(), &0u16.to_le_bytes(),
+        ].concat();
+
+        archive.extend(0x04034b50u32.to_le_bytes());
+        archive.extend(&common);
+        archive.extend(name.as_bytes());
+        archive.extend(compressed);
+
+        central.extend(0x02014b50u32.to_le_bytes());
+        central.extend(20u16.to_le_bytes());
+        central.extend(&common);
+        // comment length, disk, internal and external attributes
+        central.extend([0u8; 10]);
+        central.extend(offset.to_le_bytes());
+        central.extend(name.as_bytes());
+    }
+
+    let count = u16::try_from(files.len()).map_err(|_| too_large())?;
+    let central_offset = u32::try_from(archive.len()).map_err(|_| too_large())?;
+    let central_size = u32::try_from(central.len()).map_err(|_| too_large())?;
+    archive.extend(central);
+    archive.extend(0x06054b50u32.to_le_bytes());
+    archive.extend([0u8; 4]);
+    archive.extend(count.to_le_bytes());
+    archive.extend(count.to_le_bytes());
+    archive.extend(central_size.to_le_bytes());
+    archive.extend(central_offset.to_le_bytes());
+    archive.extend([0u8; 2]);
+    Ok(archive)
+}
+
+/// Octal field of a tar header, NUL terminated. Values too large for its digits, files of
+/// 8 GiB or more, are written in the GNU base-256 encoding instead
+fn octal(field: &mut [u8], value: u64) {
+    let width = field.len() - 1;
+    if value < 8u64.pow(width as u32) {
+        let digits = format!("{value:0width$o}");
+        field[..digits.len()].copy_from_slice(digits.as_bytes());
+        return;
+    }
+    field.fill(0);
+    let start = field.len() - 8;
+    field[start..].copy_from_slice(&value.to_be_bytes());
+    field[0] |= 0x80;
+}
+
+fn tar_header(name: &str, size: usize, kind: u8, mtime: i64) -> [u8; 512] {
+    let mut header = [0u8; 512];
+    let name = name.as_bytes();
+    header[..name.len().min(100)].copy_from_slice(&name[..name.len().min(100)]);
+    octal(&mut header[100..108], 0o644);
+    octal(&mut header[108..116], 0);
+    octal(&mut header[116..124], 0);
+    octal(&mut header[124..136], size as u64);
+    octal(&mut header[136..148], mtime.max(0) as u64);
+    header[156] = kind;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    // the checksum is computed with its own field as spaces
+    header[148..156].copy_from_slice(b"        ");
+    let checksum = header.iter().map(|byte| *byte as u64).sum::<u64>();
+    octal(&mut header[148..155], checksum);
+    header
+}
+
+fn push_entry(tar: &mut Vec<u8>, header: [u8; 512], bytes: &[u8]) {
+    tar.extend(header);
+    tar.extend(bytes);
+    tar.resize(tar.len().next_multiple_of(512), 0);
+}
+
+/// Gzipped ustar archive of `files`, names longer than 100 bytes in PAX headers
+pub fn tar_gz(files: &[(String, Vec<u8>)], date: OffsetDateTime) -> io::Result<Vec<u8>> {
+
+    let mtime = date.unix_timestamp();
+    let mut tar = Vec::new();
+    for (name, bytes) in files {
+        if name.len() > 100 {
+            // a record is `<length> path=<name>\n`, its length counting its own digits
+            let record_len = |digits: usize| digits + " path=\n".len() + name.len();
+            let mut digits = 1;
+            while record_len(digits).to_string().len() != digits {
+                digits += 1;
+            }
+            let record = format!("{} path={name}\n", record_len(digits));
+            push_entry(&mut tar, tar_header("././@PaxHeader", record.len(), b'x', mtime), record.as_bytes());
+        }
+        push_entry(&mut tar, tar_header(name, bytes.len(), b'0', mtime), bytes);
+    }
+    tar.extend([0u8; 1024]);
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&tar)?;
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use flate2::read::{DeflateDecoder, GzDecoder};
+    use super::*;
+
+    fn sample() -> Vec<(String, Vec<u8>)> {
+        vec![
+            ("page/page.md".to_string(), b"# Page\n".to_vec()),
+            (format!("page/images/{}.png", "x".repeat(120)), vec![0, 1, 2]),
+        ]
+    }
+
+    #[test]
+    fn writes_zip_archives() {
+        let zip = zip(&sample(), OffsetDateTime::UNIX_EPOCH).unwrap();
+
+        assert_eq!(&zip[..4], b"PK\x03\x04");
+        assert_eq!(&zip[26..28], &12u16.to_le_bytes());
+        assert_eq!(&zip[30..42], b"page/page.md");
+        let compressed_size = u32::from_le_bytes(zip[18..22].try_into().unwrap()) as usize;
+        let mut content = String::new();
+        DeflateDecoder::new(&zip[42..42 + compressed_size]).read_to_string(&mut content).unwrap();
+        assert_eq!(content, "# Page\n");
+        // end of central directory, listing both files
+        let end = &zip[zip.len() - 22..];
+        assert_eq!(&end[..4], b"PK\x05\x06");
+        assert_eq!(&end[10..12], &2u16.to_le_bytes());
+    }
+
+    #[test]
+    fn writes_tar_gz_archives() {
+        let mut tar = Vec::new();
+        GzDecoder::new(&tar_gz(&sample(), OffsetDateTime::UNIX_EPOCH).unwrap()[..]).read_to_end(&mut tar).unwrap();
+
+        assert_eq!(&tar[..12], b"page/page.md");
+        assert_eq!(&tar[257..263], b"ustar\0");
+        assert_eq!(&tar[512..519], b"# Page\n");
+        // the long name comes in a PAX header
+        assert_eq!(tar[1024 + 156], b'x');
+        let record = String::from_utf8_lossy(&tar[1536..1536 + 150]);
+        assert!(record.starts_with(&format!("{} path=page/images/xxx", 7 + "page/images/".len() + 124 + 3)));
+        assert_eq!(tar.len() % 512, 0);
+    }
+
+    #[test]
+    fn writes_large_sizes_in_base_256() {
+        let mut field = [0u8; 12];
+        octal(&mut field, 0o77777777777);
+        assert_eq!(&field, b"77777777777\0");
+        octal(&mut field, 8 << 30);
+        assert_eq!(field, [0x80, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,64 @@
+use std::io;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::{bundle, hash};
+
+/// File at the root of the capture listing all the others
+pub const FILENAME: &str = "manifest.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChecksum {
+    /// Path relative to the capture directory, `/`-separated
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Size and SHA-256 of every file of a capture, to check it or sync it incrementally
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileManifest {
+    pub files: Vec<FileChecksum>,
+}
+
+impl FileManifest {
+
+    /// Files of `directory` but the manifest itself, sorted by path
+    pub fn of_directory(directory: &Path) -> io::Result<Self> {
+        let files = bundle::files(directory)?
+            .into_iter()
+            .filter(|(path, _)| path != FILENAME)
+            .map(|(path, bytes)| FileChecksum { path, size: bytes.len() as u64, sha256: hash::sha256_hex(&bytes) })
+            .collect();
+        Ok(Self { files })
+    }
+
+    /// Write the manifest of `directory` at its root
+    pub fn write(directory: &Path) -> io::Result<Self> {
+        let manifest = Self::of_directory(directory)?;
+        std::fs::write(directory.join(FILENAME), serde_json::to_string_pretty(&manifest)?)?;
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_every_file_with_its_checksum() {
+        let directory = tempfile::tempdir().unwrap();
+        std::fs::create_dir(directory.path().join("images")).unwrap();
+        std::fs::write(directory.path().join("page.md"), "abc").unwrap();
+        std::fs::write(directory.path().join("images/red.png"), [0u8; 4]).unwrap();
+
+        let manifest = FileManifest::write(directory.path()).unwrap();
+        assert_eq!(manifest.files.iter().map(|file| file.path.as_str()).collect::<Vec<_>>(), ["images/red.png", "page.md"]);
+        assert_eq!(manifest.files[1], FileChecksum {
+            path: "page.md".to_string(),
+            size: 3,
+            sha256: "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".to_string(),
+        });
+        // written last, the manifest doesn't list itself
+        assert_eq!(FileManifest::of_directory(directory.path()).unwrap(), manifest);
+    }
+}
@@ -0,0 +1,308 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Oldest Chrome major version known to work with the DevTools calls we make
+pub const MIN_CHROME_VERSION: u32 = 112;
+
+const EXECUTABLE_NAMES: &[&str] = &[
+    "google-chrome-stable",
+    "google-chrome",
+    "chromium",
+    "chromium-browser",
+    "chrome",
+    "chrome-headless-shell",
+    "microsoft-edge-stable",
+    "microsoft-edge",
+    "msedge",
+];
+
+#[cfg(target_os = "linux")]
+const EXECUTABLE_PATHS: &[&str] = &[
+    "/usr/bin/google-chrome",
+    "/usr/bin/chromium",
+    "/usr/bin/chromium-browser",
+    "/snap/bin/chromium",
+    "/opt/google/chrome/chrome",
+];
+
+#[cfg(target_os = "macos")]
+const EXECUTABLE_PATHS: &[&str] = &[
+    "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+    "/Applications/Chromium.app/Contents/MacOS/Chromium",
+    "/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+];
+
+#[cfg(target_os = "windows")]
+const EXECUTABLE_PATHS: &[&str] = &[
+    r"C:\Program Files\Google\Chrome\Application\chrome.exe",
+    r"C:\Program Files (x86)\Google\Chrome\Application\chrome.exe",
+    r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe",
+];
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+const EXECUTABLE_PATHS: &[&str] = &[];
+
+/// Find a usable Chrome binary: the `CHROME` environment variable, then well known
+/// names on the `PATH`, then well known install locations.
+/// On failure, returns every location tried along with why it was rejected
+pub fn find_executable() -> Result<PathBuf, Vec<String>> {
+
+    let mut searched = Vec::new();
+
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    if let Some(path) = std::env::var_os("CHROME") {
+        candidates.push(path.into());
+    }
+    #[cfg(feature = "download-chromium")]
+    candidates.push(download::executable_path());
+
+    for name in EXECUTABLE_NAMES {
+        match which::which(name) {
+            Ok(path) => candidates.push(path),
+            Err(_) => searched.push(format!("{name} (not on PATH)")),
+        }
+    }
+    candidates.extend(EXECUTABLE_PATHS.iter().map(PathBuf::from));
+
+    for candidate in candidates {
+        if !candidate.is_file() {
+            searched.push(format!("{} (missing)", candidate.display()));
+            continue;
+        }
+        match check_version(&candidate) {
+            Ok(()) => return Ok(candidate),
+            Err(reason) => searched.push(format!("{} ({})", candidate.display(), reason)),
+        }
+    }
+
+    Err(searched)
+}
+
+fn check_version(path: &Path) -> Result<(), String> {
+
+    // chrome.exe doesn't print its version on Windows
+    if cfg!(target_os = "windows") {
+        return Ok(());
+    }
+
+    let output = Command::new(path)
+        .arg("--version")
+        .output()
+        .map_err(|e| format!("can't run: {e}"))?;
+
+    let version = String::from_utf8_lossy(&output.stdout).to_string();
+    let major = version
+        .split_whitespace()
+        .find_map(|word| word.split('.').next()?.parse::<u32>().ok())
+        .ok_or_else(|| format!("unrecognized version {:?}", version.trim()))?;
+
+    if major < MIN_CHROME_VERSION {
+        return Err(format!("version {major} is older than {MIN_CHROME_VERSION}"));
+    }
+
+    Ok(())
+}
+
+/// Download of a pinned Chrome for Testing `chrome-headless-shell`, used when no Chrome is installed
+#[cfg(feature = "download-chromium")]
+pub mod download {
+    use std::io::Read;
+    use std::path::{Component, Path, PathBuf};
+
+    pub const PINNED_VERSION: &str = "131.0.6778.85";
+
+    #[cfg(target_os = "linux")]
+    const PLATFORM: &str = "linux64";
+    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+    const PLATFORM: &str = "mac-arm64";
+    #[cfg(all(target_os = "macos", not(target_arch = "aarch64")))]
+    const PLATFORM: &str = "mac-x64";
+    #[cfg(target_os = "windows")]
+    const PLATFORM: &str = "win64";
+
+    fn cache_directory() -> PathBuf {
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .unwrap_or_else(std::env::temp_dir);
+        base.join("webpage_scraper").join(format!("chrome-headless-shell-{PINNED_VERSION}"))
+    }
+
+    /// Where the pinned binary lives once downloaded
+    pub fn executable_path() -> PathBuf {
+        let executable = if cfg!(target_os = "windows") { "chrome-headless-shell.exe" } else { "chrome-headless-shell" };
+        cache_directory()
+            .join(format!("chrome-headless-shell-{PLATFORM}"))
+            .join(executable)
+    }
+
+    /// Download and unpack the pinned revision, returning the path of its executable
+    pub fn fetch() -> std::io::Result<PathBuf> {
+
+        let executable = executable_path();
+        if executable.is_file() {
+            return Ok(executable);
+        }
+
+        let url = format!(
+            "https://storage.googleapis.com/chrome-for-testing-public/{PINNED_VERSION}/{PLATFORM}/chrome-headless-shell-{PLATFORM}.zip"
+        );
+
+        // the blocking client can't run on a tokio worker, give it its own thread
+        let archive = std::thread::scope(|scope| {
+            scope.spawn(|| -> reqwest::Result<Vec<u8>> {
+                Ok(reqwest::blocking::get(&url)?.error_for_status()?.bytes()?.to_vec())
+            }).join()
+        })
+        .map_err(|_| std::io::Error::other("download thread panicked"))?
+        .map_err(std::io::Error::other)?;
+
+        unzip(&archive, &cache_directory())?;
+        Ok(executable)
+    }
+
+    fn u16_at(bytes: &[u8], offset: usize) -> Option<usize> {
+        Some(u16::from_le_bytes(bytes.get(offset..offset.checked_add(2)?)?.try_into().ok()?) as usize)
+    }
+
+    fn u32_at(bytes: &[u8], offset: usize) -> Option<usize> {
+        Some(u32::from_le_bytes(bytes.get(offset..offset.checked_add(4)?)?.try_into().ok()?) as usize)
+    }
+
+    /// Path of the entry `name` under the extraction directory, `None` unless it's relative
+    /// and stays within it: no `..`, root, drive or backslash
+    fn entry_path(name: &str) -> Option<PathBuf> {
+        let relative = name.strip_suffix('/').unwrap_or(name);
+        if relative.is_empty() || relative.contains(['\\', ':']) {
+            return None;
+        }
+        let path = PathBuf::from(relative);
+        path.components().all(|component| matches!(component, Component::Normal(_))).then_some(path)
+    }
+
+    /// Minimal zip extraction (stored and deflated entries, no zip64), checking the size and CRC-32 of each entry
+    fn unzip(archive: &[u8], destination: &Path) -> std::io::Result<()> {
+
+        let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid zip archive");
+
+        let end = (0..archive.len().saturating_sub(21))
+            .rev()
+            .find(|&i| archive[i..].starts_with(&[0x50, 0x4b, 0x05, 0x06]))
+            .ok_or_else(invalid)?;
+        let entries = u16_at(archive, end + 10).ok_or_else(invalid)?;
+        let mut offset = u32_at(archive, end + 16).ok_or_else(invalid)?;
+
+        for _ in 0..entries {
+            if !archive.get(offset..).is_some_and(|b| b.starts_with(&[0x50, 0x4b, 0x01, 0x02])) {
+                return Err(invalid());
+            }
+            let short = |at: usize| u16_at(archive, offset + at).ok_or_else(invalid);
+            let long = |at: usize| u32_at(archive, offset + at).ok_or_else(invalid);
+            let method = short(10)?;
+            let crc = long(16)? as u32;
+            let compressed_size = long(20)?;
+            let size = long(24)?;
+            let name_len = short(28)?;
+            let extra_len = short(30)?;
+            let comment_len = short(32)?;
+            let mode = (long(38)? >> 16) as u32;
+            let local_header = long(42)?;
+            let name = archive.get(offset + 46..offset + 46 + name_len).ok_or_else(invalid)?;
+            let name = String::from_utf8_lossy(name).to_string();
+            offset += 46 + name_len + extra_len + comment_len;
+
+            let path = destination.join(entry_path(&name).ok_or_else(invalid)?);
+            if name.ends_with('/') {
+                std::fs::create_dir_all(&path)?;
+                continue;
+            }
+
+            let local_name_len = u16_at(archive, local_header + 26).ok_or_else(invalid)?;
+            let local_extra_len = u16_at(archive, local_header + 28).ok_or_else(invalid)?;
+            let data_start = local_header + 30 + local_name_len + local_extra_len;
+            let data = archive.get(data_start..data_start + compressed_size).ok_or_else(invalid)?;
+            let contents = match method {
+                0 => data.to_vec(),
+                8 => {
+                    // one byte more than announced is enough to tell a lying entry
+                    let mut contents = Vec::new();
+                    flate2::read::DeflateDecoder::new(data).take(size as u64 + 1).read_to_end(&mut contents)?;
+                    contents
+                }
+                _ => return Err(invalid()),
+            };
+            if contents.len() != size || crc32fast::hash(&contents) != crc {
+                return Err(invalid());
+            }
+
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, contents)?;
+
+            #[cfg(unix)]
+            if mode != 0 {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode & 0o777))?;
+            }
+            #[cfg(not(unix))]
+            let _ = mode;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// Zip of stored entries
+        fn zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+            let (mut archive, mut directory) = (Vec::new(), Vec::new());
+            for (name, contents) in entries {
+                let crc = crc32fast::hash(contents).to_le_bytes();
+                let size = (contents.len() as u32).to_le_bytes();
+                let name_len = (name.len() as u16).to_le_bytes();
+                let local_header = (archive.len() as u32).to_le_bytes();
+                archive.extend([0x50, 0x4b, 0x03, 0x04, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+                archive.extend(crc.iter().chain(&size).chain(&size).chain(&name_len).chain(&[0, 0]));
+                archive.extend(name.as_bytes());
+                archive.extend(*contents);
+                directory.extend([0x50, 0x4b, 0x01, 0x02, 20, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+                directory.extend(crc.iter().chain(&size).chain(&size).chain(&name_len).chain(&[0; 12]).chain(&local_header));
+                directory.extend(name.as_bytes());
+            }
+            let directory_offset = (archive.len() as u32).to_le_bytes();
+            let directory_len = (directory.len() as u32).to_le_bytes();
+            let count = (entries.len() as u16).to_le_bytes();
+            archive.extend(directory);
+            archive.extend([0x50, 0x4b, 0x05, 0x06, 0, 0, 0, 0]);
+            archive.extend(count.iter().chain(&count).chain(&directory_len).chain(&directory_offset).chain(&[0, 0]));
+            archive
+        }
+
+        #[test]
+        fn unzips_stored_entries() {
+            let destination = tempfile::tempdir().unwrap();
+            unzip(&zip(&[("bin/", b""), ("bin/chrome", b"binary")]), destination.path()).unwrap();
+            assert_eq!(std::fs::read(destination.path().join("bin/chrome")).unwrap(), b"binary");
+        }
+
+        #[test]
+        fn rejects_broken_and_escaping_archives() {
+            let destination = tempfile::tempdir().unwrap();
+            let mut archive = zip(&[("chrome", b"binary")]);
+            for length in 0..archive.len() {
+                assert!(unzip(&archive[..length], destination.path()).is_err());
+            }
+            // a byte of the contents, after the local header and the name
+            archive[30 + "chrome".len()] ^= 1;
+            assert!(unzip(&archive, destination.path()).is_err());
+            for name in ["../chrome", "bin/../../chrome", "/tmp/chrome", "\\tmp\\chrome", "C:chrome", "./chrome"] {
+                assert!(unzip(&zip(&[(name, b"binary")]), destination.path()).is_err(), "{name}");
+            }
+            assert_eq!(std::fs::read_dir(destination.path()).unwrap().count(), 0);
+        }
+    }
+}
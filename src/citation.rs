@@ -0,0 +1,156 @@
+use serde_json::{json, Value};
+use crate::webpage::InfoJson;
+
+/// BibTeX entry of the capture, at its root
+pub const BIBTEX_FILENAME: &str = "citation.bib";
+/// CSL-JSON item of the capture, for Zotero, pandoc-citeproc and other CSL processors
+pub const CSL_FILENAME: &str = "citation.json";
+
+/// Year, month and day of a date written `2024-05-01...`, as many as are there
+fn date_parts(date: &str) -> Vec<u32> {
+    date.get(..10).unwrap_or(date)
+        .split('-')
+        .map_while(|part| part.parse().ok())
+        .take(3)
+        .collect()
+}
+
+/// Text with the characters special to BibTeX escaped
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c.is_control() => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Citation key: surname of the first author, or else the domain, then the year and the first word of the title
+fn key(info: &InfoJson) -> String {
+    let ascii_word = |text: &str| text.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_ascii_lowercase();
+    let domain = url::Url::parse(&info.url).ok()
+        .and_then(|url| url.host_str().map(|host| host.trim_start_matches("www.").split('.').next().unwrap_or_default().to_string()));
+    let who = info.metadata.authors.first()
+        .and_then(|author| author.split_whitespace().next_back())
+        .map(ascii_word)
+        .filter(|word| !word.is_empty())
+        .or(domain.map(|domain| ascii_word(&domain)))
+        .unwrap_or_else(|| "webpage".to_string());
+    let year = info.metadata.published.as_deref().map(date_parts).and_then(|parts| parts.first().copied())
+        .or_else(|| date_parts(&info.date).first().copied())
+        .map(|year| year.to_string())
+        .unwrap_or_default();
+    let word = info.title.split_whitespace().map(ascii_word).find(|word| word.len() > 3).unwrap_or_default();
+    format!("{who}{year}{word}")
+}
+
+/// `@misc` entry citing the page: title, authors, publication year, site, URL and access date
+pub fn bibtex(info: &InfoJson) -> String {
+
+    let mut fields = vec![("title", format!("{{{}}}", escape(&info.title)))];
+    if !info.metadata.authors.is_empty() {
+        let authors = info.metadata.authors.iter().map(|author| escape(author)).collect::<Vec<_>>();
+        fields.push(("author", authors.join(" and ")));
+    }
+    let published = info.metadata.published.as_deref().map(date_parts).unwrap_or_default();
+    if let Some(year) = published.first() {
+        fields.push(("year", year.to_string()));
+    }
+    if let Some(month) = published.get(1) {
+        fields.push(("month", month.to_string()));
+    }
+    if let Some(site_name) = &info.metadata.site_name {
+        fields.push(("howpublished", escape(site_name)));
+    }
+    fields.push(("url", info.url.clone()));
+    fields.push(("urldate", info.date.clone()));
+    fields.push(("note", format!("Accessed: {}", info.date)));
+
+    let mut entry = format!("@misc{{{},\n", key(info));
+    for (name, value) in fields {
+        entry.push_str(&format!("  {name} = {{{value}}},\n"));
+    }
+    entry.push_str("}\n");
+    entry
+}
+
+/// CSL-JSON array holding the `webpage` item of the capture
+pub fn csl_json(info: &InfoJson) -> Value {
+
+    let mut item = json!({
+        "id": key(info),
+        "type": "webpage",
+        "title": info.title,
+        "URL": info.url,
+        "accessed": { "date-parts": [date_parts(&info.date)] },
+    });
+    if !info.metadata.authors.is_empty() {
+        // names are kept whole, splitting them into given and family names is guesswork
+        item["author"] = info.metadata.authors.iter().map(|author| json!({ "literal": author })).collect();
+    }
+    if let Some(published) = info.metadata.published.as_deref().map(date_parts).filter(|parts| !parts.is_empty()) {
+        item["issued"] = json!({ "date-parts": [published] });
+    }
+    if let Some(site_name) = &info.metadata.site_name {
+        item["container-title"] = json!(site_name);
+    }
+    if let Some(language) = &info.language {
+        item["language"] = json!(language);
+    }
+    json!([item])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::Metadata;
+
+    fn info() -> InfoJson {
+        InfoJson {
+            url: "https://www.example.com/posts/rust".to_string(),
+            title: "Rust & {fearless} concurrency".to_string(),
+            date: "2024-05-01".to_string(),
+            metadata: Metadata {
+                authors: vec!["Ada Lovelace".to_string(), "Charles Babbage".to_string()],
+                published: Some("2023-11-20T08:00:00Z".to_string()),
+                site_name: Some("Example Blog".to_string()),
+                ..Default::default()
+            },
+            ..InfoJson::default()
+        }
+    }
+
+    #[test]
+    fn writes_bibtex_entries() {
+        assert_eq!(bibtex(&info()), "@misc{lovelace2023rust,\n  \
+            title = {{Rust \\& \\{fearless\\} concurrency}},\n  \
+            author = {Ada Lovelace and Charles Babbage},\n  \
+            year = {2023},\n  \
+            month = {11},\n  \
+            howpublished = {Example Blog},\n  \
+            url = {https://www.example.com/posts/rust},\n  \
+            urldate = {2024-05-01},\n  \
+            note = {Accessed: 2024-05-01},\n}\n");
+
+        let anonymous = InfoJson { metadata: Metadata::default(), ..info() };
+        assert!(bibtex(&anonymous).starts_with("@misc{example2024rust,\n"));
+    }
+
+    #[test]
+    fn writes_csl_items() {
+        let csl = csl_json(&info());
+        assert_eq!(csl[0]["type"], "webpage");
+        assert_eq!(csl[0]["issued"]["date-parts"], json!([[2023, 11, 20]]));
+        assert_eq!(csl[0]["accessed"]["date-parts"], json!([[2024, 5, 1]]));
+        assert_eq!(csl[0]["author"][1]["literal"], "Charles Babbage");
+    }
+}
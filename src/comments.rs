@@ -0,0 +1,73 @@
+use scraper::{Html, Selector};
+
+/// Containers of the comment systems rendered in the page itself, most specific first.
+/// Iframe based systems (Disqus, giscus, ...) are cross-origin and can't be read
+const COMMENT_SELECTORS: &[&str] = &[
+    "#comments .comment-list",
+    "ol.commentlist",
+    "#disqus_thread",
+    "#commento",
+    "#isso-thread",
+    ".comment-tree",
+    "shreddit-comment-tree",
+    ".topic-post",
+    "#comments",
+    ".comments",
+    "section[aria-label*=omment]",
+];
+
+/// Scroll to the bottom of the page and click "load more comments" / "show replies"
+/// buttons a few times, so lazily loaded threads are part of the capture.
+/// Evaluates to a promise, links that would navigate away are never clicked
+pub(crate) const EXPAND_SCRIPT: &str = r#"(async () => {
+    const sleep = ms => new Promise(resolve => setTimeout(resolve, ms));
+    const expander = /(load|show|view|more|expand|see).*(comment|repl)|(comment|repl).*(more|expand)/i;
+    const clicked = new Set();
+    for (let round = 0; round < 10; round++) {
+        window.scrollTo(0, document.body.scrollHeight);
+        await sleep(500);
+        const buttons = [...document.querySelectorAll('button, a, [role=button], summary')].filter(element => {
+            const href = element.tagName === 'A' ? element.getAttribute('href') : null;
+            return !clicked.has(element)
+                && element.offsetParent !== null
+                && expander.test(element.textContent || '')
+                && (href === null || href.startsWith('#') || href.startsWith('javascript:'));
+        });
+        if (buttons.length === 0) break;
+        for (const button of buttons) { clicked.add(button); button.click(); }
+        await sleep(1000);
+    }
+    window.scrollTo(0, 0);
+    return true;
+})()"#;
+
+/// HTML of the comment thread of the page, if a known comment container is found
+pub fn extract(html: &str) -> Option<String> {
+
+    let document = Html::parse_document(html);
+
+    COMMENT_SELECTORS.iter().find_map(|selector| {
+        let selector = Selector::parse(selector).unwrap();
+        let threads = document
+            .select(&selector)
+            .filter(|thread| !thread.text().collect::<String>().trim().is_empty())
+            .map(|thread| thread.html())
+            .collect::<Vec<_>>();
+        (!threads.is_empty()).then(|| threads.join("\n"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_known_comment_container() {
+        let html = r#"<article><p>Post</p></article>
+            <div id="comments"><h2>2 comments</h2><ol class="comment-list"><li>First!</li><li>Nice post</li></ol></div>"#;
+        let comments = extract(html).unwrap();
+        assert!(comments.starts_with(r#"<ol class="comment-list">"#));
+        assert!(comments.contains("Nice post"));
+        assert_eq!(extract("<p>No discussion here</p>"), None);
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use serde::{de, Deserialize, Deserializer};
+use thiserror::Error;
+use crate::bundle::BundleFormat;
+use crate::convert::ImageFormat;
+use crate::filename::Transliteration;
+use crate::layout::Layout;
+use crate::options::{ScrapeOptions, SiteAuth, Credentials};
+use crate::pdf;
+use crate::renderer::Backend;
+use crate::srcset::SrcsetStrategy;
+use crate::toml::{self, TomlError};
+use crate::markdown::MarkdownConverter;
+use crate::webpage::Outputs;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("{0}")]
+    TomlError(#[from] TomlError),
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, ConfigError>;
+
+/// Scraper configuration file. Values are written as the flags of the same name take them,
+/// the flags given on the command line winning over the file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Managed archive root, like `--archive-root`
+    pub archive_root: Option<PathBuf>,
+    /// `auto`, `pandoc` or `builtin`, like `--markdown-converter`
+    pub markdown_converter: Option<MarkdownConverter>,
+    /// Directory of per-domain extraction rules, like `--rules`
+    pub rules: Option<PathBuf>,
+    #[serde(default)]
+    pub browser: BrowserConfig,
+    #[serde(default)]
+    pub output: OutputConfig,
+    #[serde(default)]
+    pub pdf: PdfConfig,
+    #[serde(default)]
+    pub images: ImagesConfig,
+    #[serde(default)]
+    pub selectors: SelectorsConfig,
+    #[serde(default)]
+    pub concurrency: ConcurrencyConfig,
+    /// Overrides applied to the hosts matching each domain, subdomains included
+    #[serde(default)]
+    pub domains: BTreeMap<String, DomainConfig>,
+}
+
+/// `[browser]` section
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BrowserConfig {
+    /// `chrome` or `webdriver`, like `--backend`
+    #[serde(default, deserialize_with = "value_enum")]
+    pub backend: Option<Backend>,
+    pub webdriver_url: Option<String>,
+    /// Fetch the HTML with a plain HTTP request, like `--no-browser`
+    #[serde(default)]
+    pub no_browser: bool,
+}
+
+/// `[output]` section, what is written and where
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputConfig {
+    /// `"html, md, info"`, like `--only`
+    #[serde(default, deserialize_with = "parsed")]
+    pub only: Option<Outputs>,
+    #[serde(default, deserialize_with = "parsed")]
+    pub layout: Option<Layout>,
+    #[serde(default, deserialize_with = "value_enum")]
+    pub bundle: Option<BundleFormat>,
+    #[serde(default, deserialize_with = "value_enum")]
+    pub transliterate: Option<Transliteration>,
+    #[serde(default)]
+    pub front_matter: bool,
+    pub toc: Option<usize>,
+    pub screenshot_thumbnail: Option<u32>,
+    #[serde(default)]
+    pub assets: bool,
+    #[serde(default)]
+    pub docx: bool,
+    #[serde(default)]
+    pub text: bool,
+    #[serde(default)]
+    pub warc: bool,
+    #[serde(default)]
+    pub mhtml: bool,
+    #[serde(default)]
+    pub single_file: bool,
+    #[serde(default)]
+    pub tables: bool,
+    #[serde(default)]
+    pub structure: bool,
+    pub stats: Option<usize>,
+    #[serde(default)]
+    pub sanitize: bool,
+}
+
+/// `[pdf]` section
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PdfConfig {
+    #[serde(default)]
+    pub landscape: bool,
+    /// `"1.5cm"`, like `--margin`
+    #[serde(default, deserialize_with = "length")]
+    pub margin: Option<f64>,
+    pub scale: Option<f64>,
+    /// `false` like `--no-background`
+    pub background: Option<bool>,
+    /// Heading depth bookmarked, like `--pdf-outline`
+    pub outline: Option<usize>,
+    #[serde(default)]
+    pub pdfa: bool,
+}
+
+/// `[images]` section, which images are downloaded and how
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImagesConfig {
+    /// Download no image, like `--no-images`
+    #[serde(default)]
+    pub skip: bool,
+    pub min_width: Option<u32>,
+    pub min_height: Option<u32>,
+    pub min_bytes: Option<usize>,
+    pub max_bytes: Option<usize>,
+    /// Most images downloaded for a page, like `--max-images`
+    pub max_count: Option<usize>,
+    /// Most image bytes downloaded for a page, like `--max-images-bytes`
+    pub max_total_bytes: Option<u64>,
+    #[serde(default, deserialize_with = "parsed")]
+    pub srcset: Option<SrcsetStrategy>,
+    pub srcset_max_bytes: Option<u64>,
+    /// `png` or `jpeg`, like `--convert-images`
+    #[serde(default, deserialize_with = "parsed")]
+    pub convert: Option<ImageFormat>,
+    pub thumbnails: Option<u32>,
+    #[serde(default)]
+    pub strip_metadata: bool,
+    #[serde(default)]
+    pub browser_images: bool,
+    pub lazy_attributes: Option<Vec<String>>,
+}
+
+/// `[selectors]` section, applied to every page before the per-domain ones
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SelectorsConfig {
+    /// Part of the page captured, like `--select`
+    pub select: Option<String>,
+    /// Elements removed before the conversion, along with those of `--exclude`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Element waited for before capturing the page
+    pub wait_for: Option<String>,
+    /// Extra time given to the page once loaded, in milliseconds
+    pub wait_ms: Option<u64>,
+}
+
+/// `[concurrency]` section, how many requests at once and how far apart
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConcurrencyConfig {
+    /// Pages of a list scraped at once, like `--jobs`
+    pub jobs: Option<usize>,
+    /// Minimum delay between two downloads from the same host, like `--delay`
+    pub delay_ms: Option<u64>,
+    pub max_rps: Option<f64>,
+    /// Image downloads in flight at once, like `--image-concurrency`
+    pub images: Option<usize>,
+    pub retries: Option<u32>,
+    /// Seconds a request may take, like `--timeout`
+    pub timeout_secs: Option<u64>,
+    pub domain_delay_ms: Option<u64>,
+    pub domain_jitter_ms: Option<u64>,
+}
+
+/// `[domains."example.com"]` section
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DomainConfig {
+    /// CSS selector waited for before capturing the page
+    pub wait_for: Option<String>,
+    /// Extra time given to the page once loaded, in milliseconds
+    pub wait_ms: Option<u64>,
+    /// CSS selectors of elements removed before conversion (banners, paywalls, ...)
+    #[serde(default)]
+    pub remove: Vec<String>,
+    pub auth: Option<Credentials>,
+    pub domain_delay_ms: Option<u64>,
+    pub domain_jitter_ms: Option<u64>,
+}
+
+/// Whether `host` is `domain` or one of its subdomains, a leading `*.` or `.` is ignored
+pub fn matches_domain(host: &str, domain: &str) -> bool {
+    let domain = domain.trim_start_matches("*.").trim_start_matches('.');
+    let host = host.trim_end_matches('.');
+    host.eq_ignore_ascii_case(domain)
+        || host.len() > domain.len()
+            && host.as_bytes()[host.len() - domain.len() - 1] == b'.'
+            && host[host.len() - domain.len()..].eq_ignore_ascii_case(domain)
+}
+
+/// A value written as on the command line, parsed by its `FromStr`
+fn parsed<'de, D: Deserializer<'de>, T: FromStr<Err: Display>>(deserializer: D) -> std::result::Result<Option<T>, D::Error> {
+    Option::<String>::deserialize(deserializer)?.map(|text| text.parse().map_err(de::Error::custom)).transpose()
+}
+
+/// A possible value of a flag, the name it takes on the command line
+fn value_enum<'de, D: Deserializer<'de>, T: clap::ValueEnum>(deserializer: D) -> std::result::Result<Option<T>, D::Error> {
+    Option::<String>::deserialize(deserializer)?.map(|text| T::from_str(&text, true).map_err(de::Error::custom)).transpose()
+}
+
+fn length<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<f64>, D::Error> {
+    Option::<String>::deserialize(deserializer)?.map(|text| pdf::parse_length(&text).map_err(de::Error::custom)).transpose()
+}
+
+impl Config {
+
+    /// Looked for in the current directory, then in the user configuration directory
+    pub const FILENAME: &str = "webpage-scraper.toml";
+
+    /// The configuration file found in the current directory or the user configuration directory
+    /// (`$XDG_CONFIG_HOME`, `~/.config`), if any
+    pub fn find() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME").filter(|home| !home.is_empty()).map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+        std::iter::once(PathBuf::from(Self::FILENAME))
+            .chain(config_home.map(|home| home.join(Self::FILENAME)))
+            .find(|path| path.is_file())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        Ok(serde_json::from_value(toml::parse(text)?)?)
+    }
+
+    /// Sections matching `host`, least specific first
+    fn sections_for<'a>(&'a self, host: &'a str) -> impl Iterator<Item = (&'a String, &'a DomainConfig)> {
+        let mut sections = self.domains
+            .iter()
+            .filter(|(domain, _)| matches_domain(host, domain))
+            .collect::<Vec<_>>();
+        sections.sort_by_key(|(domain, _)| domain.trim_start_matches("*.").len());
+        sections.into_iter()
+    }
+
+    /// Credentials of every section giving some
+    pub fn site_auth(&self) -> Vec<SiteAuth> {
+        self.domains.iter()
+            .filter_map(|(domain, section)| section.auth.as_ref().map(|credentials| SiteAuth { domain: domain.clone(), credentials: credentials.clone() }))
+            .collect()
+    }
+
+    /// `options` with the overrides of every section matching the host of `url`,
+    /// more specific domains winning over their parents
+    pub fn options_for(&self, url: &str, options: &ScrapeOptions) -> ScrapeOptions {
+
+        let mut options = options.clone();
+        let Some(host) = url::Url::parse(url).ok().and_then(|url| url.host_str().map(str::to_string)) else {
+            return options;
+        };
+
+        for (domain, section) in self.sections_for(&host) {
+            if let Some(selector) = &section.wait_for {
+                options.wait_for = Some(selector.clone());
+            }
+            if let Some(ms) = section.wait_ms {
+                options.wait_after_load = Duration::from_millis(ms);
+            }
+            options.remove_selectors.extend(section.remove.iter().cloned());
+            if let Some(credentials) = &section.auth {
+                options.auth = Some(SiteAuth { domain: domain.clone(), credentials: credentials.clone() });
+            }
+            if let Some(ms) = section.domain_delay_ms {
+                options.domain_delay = Duration::from_millis(ms);
+            }
+            if let Some(ms) = section.domain_jitter_ms {
+                options.domain_jitter = Duration::from_millis(ms);
+            }
+        }
+
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_matching_sections_most_specific_last() {
+        let config = Config::parse(r##"
+            [domains."example.com"]
+            remove = [".cookie-banner"]
+            wait_ms = 500
+            domain_delay_ms = 2000
+
+            [domains."news.example.com"]
+            wait_for = "#article"
+            wait_ms = 1500
+            auth = { username = "reader", password = "hunter2" }
+        "##).unwrap();
+
+        let options = config.options_for("https://news.example.com/today", &ScrapeOptions::default());
+        assert_eq!(options.wait_for.as_deref(), Some("#article"));
+        assert_eq!(options.wait_after_load, Duration::from_millis(1500));
+        assert_eq!(options.domain_delay, Duration::from_secs(2));
+        assert_eq!(options.remove_selectors, vec![".cookie-banner"]);
+        assert_eq!(options.auth.unwrap().domain, "news.example.com");
+
+        let other = config.options_for("https://notexample.com/", &ScrapeOptions::default());
+        assert!(other.remove_selectors.is_empty());
+        assert!(Config::parse("[domains.\"a.com\"]\ntypo = 1\n").is_err());
+    }
+
+    #[test]
+    fn parses_values_as_the_flags_take_them() {
+        let config = Config::parse(r#"
+            [browser]
+            backend = "webdriver"
+
+            [output]
+            only = "html, md"
+            bundle = "tar-gz"
+            transliterate = "ascii"
+            front_matter = true
+
+            [pdf]
+            margin = "2.54cm"
+            background = false
+
+            [images]
+            srcset = "800px"
+            convert = "jpg"
+            max_count = 10
+
+            [selectors]
+            exclude = ["nav", ".ads"]
+
+            [concurrency]
+            images = 2
+        "#).unwrap();
+
+        assert_eq!(config.browser.backend, Some(Backend::Webdriver));
+        assert_eq!(config.output.only, Some(Outputs::HTML | Outputs::MARKDOWN));
+        assert_eq!(config.output.bundle, Some(BundleFormat::TarGz));
+        assert_eq!(config.output.transliterate, Some(Transliteration::Ascii));
+        assert!(config.output.front_matter && !config.output.docx);
+        assert_eq!(config.pdf.margin, Some(1.0));
+        assert_eq!(config.pdf.background, Some(false));
+        assert_eq!(config.images.srcset, Some(SrcsetStrategy::Closest(800)));
+        assert_eq!(config.images.convert, Some(ImageFormat::Jpeg));
+        assert_eq!(config.images.max_count, Some(10));
+        assert_eq!(config.selectors.exclude, ["nav", ".ads"]);
+        assert_eq!(config.concurrency.images, Some(2));
+
+        assert!(Config::parse("[output]\nonly = \"html, gif\"\n").is_err());
+        assert!(Config::parse("[pdf]\nmargin = \"2 furlongs\"\n").is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use std::io::Cursor;
+use std::str::FromStr;
+use image::codecs::jpeg::JpegEncoder;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ConvertError {
+    #[error("conversion failed: {0}")]
+    Image(#[from] image::ImageError),
+}
+
+pub type Result<T> = std::result::Result<T, ConvertError>;
+
+/// Format WebP and AVIF images are converted to, for tools and PDF engines not reading them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    /// Transparent areas become white
+    Jpeg,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    /// `png`, `jpeg` or `jpg`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "png" => Ok(Self::Png),
+            "jpeg" | "jpg" => Ok(Self::Jpeg),
+            other => Err(format!("unsupported image format {other:?}, expected png or jpeg")),
+        }
+    }
+}
+
+/// Whether `bytes` hold a WebP image, or an AVIF one with the `avif` feature, the formats worth converting
+pub fn needs_conversion(bytes: &[u8]) -> bool {
+    match bytes {
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => true,
+        [_, _, _, _, b'f', b't', b'y', b'p', brand @ ..] => cfg!(feature = "avif") && (brand.starts_with(b"avif") || brand.starts_with(b"avis")),
+        _ => false,
+    }
+}
+
+/// Convert the image in `bytes` to `format`
+pub fn convert(bytes: &[u8], format: ImageFormat) -> Result<Vec<u8>> {
+    let image = image::load_from_memory(bytes)?;
+    let mut output = Vec::new();
+    match format {
+        ImageFormat::Png => image.write_to(&mut Cursor::new(&mut output), image::ImageFormat::Png)?,
+        ImageFormat::Jpeg => {
+            let mut image = image.into_rgba8();
+            for pixel in image.pixels_mut() {
+                let [red, green, blue, alpha] = pixel.0.map(u32::from);
+                let over_white = |channel: u32| ((channel * alpha + 255 * (255 - alpha)) / 255) as u8;
+                pixel.0 = [over_white(red), over_white(green), over_white(blue), 255];
+            }
+            let image = image::DynamicImage::ImageRgba8(image).into_rgb8();
+            image.write_with_encoder(JpegEncoder::new_with_quality(&mut output, 90))?;
+        }
+    }
+    Ok(output)
+}
+
+/// Downscale the image in `bytes`, in its own format, to fit within `max_size` pixels
+pub fn thumbnail(bytes: &[u8], max_size: u32) -> Result<Vec<u8>> {
+    let format = image::guess_format(bytes)?;
+    let image = image::load_from_memory_with_format(bytes, format)?;
+    let mut output = Vec::new();
+    image.thumbnail(max_size, max_size).write_to(&mut Cursor::new(&mut output), format)?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::RED_PNG;
+
+    #[test]
+    fn detects_formats_to_convert() {
+        assert!(needs_conversion(b"RIFF\0\0\0\0WEBPVP8X"));
+        assert_eq!(needs_conversion(b"\0\0\0\x1cftypavif\0\0\0\0"), cfg!(feature = "avif"));
+        assert!(!needs_conversion(b"\0\0\0\x1cftypheic\0\0\0\0"));
+        assert!(!needs_conversion(RED_PNG));
+        assert_eq!("JPG".parse::<ImageFormat>(), Ok(ImageFormat::Jpeg));
+        assert!("gif".parse::<ImageFormat>().is_err());
+    }
+
+    #[test]
+    fn converts_webp_images() {
+        let mut webp = Vec::new();
+        image::load_from_memory(RED_PNG).unwrap().write_to(&mut Cursor::new(&mut webp), image::ImageFormat::WebP).unwrap();
+        assert!(needs_conversion(&webp));
+
+        let png = convert(&webp, ImageFormat::Png).unwrap();
+        assert_eq!(image::guess_format(&png).unwrap(), image::ImageFormat::Png);
+        let jpeg = convert(&webp, ImageFormat::Jpeg).unwrap();
+        assert_eq!(&jpeg[..2], &[0xFF, 0xD8]);
+    }
+
+    #[test]
+    fn makes_thumbnails_in_the_same_format() {
+        let thumbnail = super::thumbnail(RED_PNG, 8).unwrap();
+        assert_eq!(image::guess_format(&thumbnail).unwrap(), image::ImageFormat::Png);
+        assert_eq!(crate::dimensions::sniff(&thumbnail), Some((8, 8)));
+    }
+}
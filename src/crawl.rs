@@ -0,0 +1,361 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use scraper::{Html, Selector};
+use serde::Serialize;
+use thiserror::Error;
+use url::Url;
+use crate::browser::BrowserError;
+use crate::options::ScrapeOptions;
+use crate::renderer::PageRenderer;
+use crate::report::PageCounts;
+use crate::warnings::Warning;
+use crate::webpage::{WebPage, WebPageError};
+
+#[derive(Error, Debug)]
+pub enum CrawlError {
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("UrlError: {0}")]
+    UrlError(#[from] url::ParseError),
+    #[error("BrowserError: {0}")]
+    BrowserError(#[from] BrowserError),
+    #[error("WebPageError: {0}")]
+    WebPageError(#[from] WebPageError),
+}
+
+pub type Result<T> = std::result::Result<T, CrawlError>;
+
+/// How far a crawl goes from its start page
+#[derive(Debug, Clone, Copy)]
+pub struct CrawlLimits {
+    /// Link hops from the start page, 0 only captures the start page
+    pub max_depth: usize,
+    pub max_pages: usize,
+}
+
+/// What the crawl did with a page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeState {
+    /// Linked to, but not visited
+    Seen,
+    Captured,
+    /// Visited but not fetched, robots.txt disallowing it
+    Disallowed,
+    /// Failed to render, scrape or write, see `Node::error`
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Node {
+    pub url: String,
+    pub title: Option<String>,
+    /// Link hops from the start page, `None` for pages only seen
+    pub depth: Option<usize>,
+    pub state: NodeState,
+    /// Capture directory, relative to the crawl directory
+    pub directory: Option<String>,
+    pub error: Option<String>,
+    /// Time taken by the page, from loading to writing, for those loaded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elapsed_ms: Option<u64>,
+    /// Of the captured pages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub counts: Option<PageCounts>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    /// Whether the crawl followed the link, or only saw it
+    pub followed: bool,
+}
+
+/// Pages and links met during a crawl
+#[derive(Debug, Default, Serialize)]
+pub struct LinkGraph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    #[serde(skip)]
+    index: HashMap<String, usize>,
+}
+
+impl LinkGraph {
+
+    pub const DOT_FILENAME: &str = "crawl_graph.dot";
+    pub const GRAPHML_FILENAME: &str = "crawl_graph.graphml";
+
+    fn node(&mut self, url: &str) -> usize {
+        if let Some(&id) = self.index.get(url) {
+            return id;
+        }
+        self.nodes.push(Node { url: url.to_string(), title: None, depth: None, state: NodeState::Seen, directory: None, error: None, elapsed_ms: None, counts: None, warnings: Vec::new() });
+        self.index.insert(url.to_string(), self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    fn edge(&mut self, from: usize, to: usize, followed: bool) {
+        match self.edges.iter_mut().find(|edge| edge.from == from && edge.to == to) {
+            Some(edge) => edge.followed |= followed,
+            None => self.edges.push(Edge { from, to, followed }),
+        }
+    }
+
+    /// Graphviz rendering, captured pages are boxes, failed ones octagons, those disallowed by
+    /// robots.txt diamonds and those only seen ellipses. Links only seen are dashed
+    pub fn to_dot(&self) -> String {
+        let escape = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut dot = String::from("digraph crawl {\n    node [shape=ellipse];\n");
+        for (id, node) in self.nodes.iter().enumerate() {
+            let label = node.title.as_deref().unwrap_or(&node.url);
+            let shape = match node.state {
+                NodeState::Seen => "ellipse",
+                NodeState::Captured => "box",
+                NodeState::Disallowed => "diamond",
+                NodeState::Failed => "octagon",
+            };
+            let _ = writeln!(dot, "    n{id} [label=\"{}\", URL=\"{}\", shape={shape}];", escape(label), escape(&node.url));
+        }
+        for edge in &self.edges {
+            let style = if edge.followed { "solid" } else { "dashed" };
+            let _ = writeln!(dot, "    n{} -> n{} [style={style}];", edge.from, edge.to);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_graphml(&self) -> String {
+        let escape = |text: &str| text
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;");
+        let mut xml = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"url\" for=\"node\" attr.name=\"url\" attr.type=\"string\"/>\n",
+            "  <key id=\"title\" for=\"node\" attr.name=\"title\" attr.type=\"string\"/>\n",
+            "  <key id=\"depth\" for=\"node\" attr.name=\"depth\" attr.type=\"int\"/>\n",
+            "  <key id=\"state\" for=\"node\" attr.name=\"state\" attr.type=\"string\"/>\n",
+            "  <key id=\"directory\" for=\"node\" attr.name=\"directory\" attr.type=\"string\"/>\n",
+            "  <key id=\"followed\" for=\"edge\" attr.name=\"followed\" attr.type=\"boolean\"/>\n",
+            "  <graph id=\"crawl\" edgedefault=\"directed\">\n",
+        ));
+        for (id, node) in self.nodes.iter().enumerate() {
+            let _ = writeln!(xml, "    <node id=\"n{id}\">");
+            let _ = writeln!(xml, "      <data key=\"url\">{}</data>", escape(&node.url));
+            if let Some(title) = &node.title {
+                let _ = writeln!(xml, "      <data key=\"title\">{}</data>", escape(title));
+            }
+            if let Some(depth) = node.depth {
+                let _ = writeln!(xml, "      <data key=\"depth\">{depth}</data>");
+            }
+            let state = serde_json::to_value(node.state).ok().and_then(|state| state.as_str().map(str::to_string)).unwrap_or_default();
+            let _ = writeln!(xml, "      <data key=\"state\">{state}</data>");
+            if let Some(directory) = &node.directory {
+                let _ = writeln!(xml, "      <data key=\"directory\">{}</data>", escape(directory));
+            }
+            xml.push_str("    </node>\n");
+        }
+        for (id, edge) in self.edges.iter().enumerate() {
+            let _ = writeln!(
+                xml,
+                "    <edge id=\"e{id}\" source=\"n{}\" target=\"n{}\"><data key=\"followed\">{}</data></edge>",
+                edge.from, edge.to, edge.followed
+            );
+        }
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+
+    pub fn write(&self, directory: &Path) -> Result<()> {
+        std::fs::write(directory.join(Self::DOT_FILENAME), self.to_dot())?;
+        std::fs::write(directory.join(Self::GRAPHML_FILENAME), self.to_graphml())?;
+        Ok(())
+    }
+}
+
+/// http(s) links of `html`, resolved against `base` and without fragments
+pub fn links(html: &str, base: &Url) -> Vec<Url> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("a[href]").unwrap();
+    let mut seen = HashSet::new();
+    document
+        .select(&selector)
+        .filter_map(|a| base.join(a.value().attr("href")?).ok())
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .map(|mut url| { url.set_fragment(None); url })
+        .filter(|url| seen.insert(url.to_string()))
+        .collect()
+}
+
+/// Free directory named `name` under `directory`, suffixed when `taken` by another page of the run
+/// or, unless `replace_existing`, already on disk
+pub fn unique_directory(directory: &Path, name: &str, taken: &mut HashSet<PathBuf>, replace_existing: bool) -> PathBuf {
+    let path = (1..)
+        .map(|n| match n {
+            1 => directory.join(name),
+            n => directory.join(format!("{name}-{n}")),
+        })
+        .find(|path| (replace_existing || !path.exists()) && !taken.contains(path))
+        .unwrap();
+    taken.insert(path.clone());
+    path
+}
+
+/// Breadth-first crawl of the pages of the start host, each page captured in its own
+/// directory under `output`, next to the link graph files
+pub async fn crawl(renderer: &dyn PageRenderer, start: &str, options: &ScrapeOptions, limits: CrawlLimits, output: &Path) -> Result<LinkGraph> {
+
+    let start = Url::parse(start)?;
+    let host = start.host_str().unwrap_or_default().to_string();
+    std::fs::create_dir_all(output)?;
+
+    let mut graph = LinkGraph::default();
+    let mut queue = VecDeque::from([(start.to_string(), 0)]);
+    let mut queued = HashSet::from([start.to_string()]);
+    let mut taken = HashSet::new();
+    let mut captured = 0;
+
+    while let Some((url, depth)) = queue.pop_front() {
+
+        let id = graph.node(&url);
+        graph.nodes[id].depth = Some(depth);
+        if let Some(robots) = &options.robots && !robots.admit(&Url::parse(&url)?).await {
+            graph.nodes[id].state = NodeState::Disallowed;
+            continue;
+        }
+
+        let started = Instant::now();
+        let webpage = match renderer.render(&url, options).await {
+            Ok(page) => WebPage::from_page(page, options).await.map_err(CrawlError::from),
+            Err(e) => Err(e.into()),
+        };
+        let webpage = match webpage {
+            Ok(webpage) => webpage,
+            Err(e) => {
+                graph.nodes[id].state = NodeState::Failed;
+                graph.nodes[id].error = Some(e.to_string());
+                graph.nodes[id].elapsed_ms = Some(started.elapsed().as_millis() as u64);
+                continue;
+            }
+        };
+
+        graph.nodes[id].title = Some(webpage.title.clone());
+        let directory = unique_directory(output, &webpage.file_stem, &mut taken, false);
+        // the pages already captured are kept, along with the graph
+        let written = webpage.write_to_disk(&directory.to_string_lossy()).await;
+        graph.nodes[id].elapsed_ms = Some(started.elapsed().as_millis() as u64);
+        if let Err(e) = written {
+            graph.nodes[id].state = NodeState::Failed;
+            graph.nodes[id].error = Some(e.to_string());
+            continue;
+        }
+        captured += 1;
+
+        graph.nodes[id].state = NodeState::Captured;
+        graph.nodes[id].directory = directory.file_name().map(|name| name.to_string_lossy().to_string());
+        graph.nodes[id].counts = Some(PageCounts::of(&webpage));
+        graph.nodes[id].warnings = webpage.warnings();
+
+        let base = Url::parse(&webpage.url).unwrap_or_else(|_| start.clone());
+        for link in links(webpage.html(), &base) {
+            let follow = depth < limits.max_depth
+                && link.host_str() == Some(host.as_str())
+                && captured + queue.len() < limits.max_pages
+                && !queued.contains(link.as_str());
+            let target = graph.node(link.as_str());
+            graph.edge(id, target, follow);
+            if follow {
+                queued.insert(link.to_string());
+                queue.push_back((link.to_string(), depth + 1));
+            }
+        }
+    }
+
+    graph.write(output)?;
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::StaticFetcher;
+    use crate::markdown::MarkdownConverter;
+    use crate::testing::FixtureServer;
+
+    #[tokio::test]
+    async fn crawls_same_host_links() {
+        let server = FixtureServer::new().unwrap();
+        server.route("/", "text/html", r#"<title>Home</title><a href="/a">A</a><a href="https://example.com/">out</a>"#);
+        server.route("/a", "text/html", r#"<title>A</title><a href="/">home</a><a href="/b">B</a>"#);
+        server.route("/b", "text/html", r#"<title>B</title>"#);
+        let output = tempfile::tempdir().unwrap();
+
+        let limits = CrawlLimits { max_depth: 1, max_pages: 10 };
+        let fetcher = StaticFetcher::new().unwrap();
+        let options = ScrapeOptions { markdown_converter: MarkdownConverter::Builtin, ..Default::default() };
+        let graph = crawl(&fetcher, &server.url("/"), &options, limits, output.path()).await.unwrap();
+
+        // home and A are captured, B is beyond the depth and example.com on another host
+        assert_eq!(graph.nodes.iter().filter(|node| node.directory.is_some()).count(), 2);
+        assert_eq!(graph.nodes.len(), 4);
+        assert!(output.path().join(LinkGraph::GRAPHML_FILENAME).is_file());
+    }
+
+    #[tokio::test]
+    async fn skips_pages_disallowed_by_robots_txt() {
+        let server = FixtureServer::new().unwrap();
+        server.route("/robots.txt", "text/plain", "User-agent: *\nDisallow: /private\n");
+        server.route("/", "text/html", r#"<title>Home</title><a href="/private">Private</a><a href="/a">A</a>"#);
+        server.route("/a", "text/html", r#"<title>A</title>"#);
+        server.route("/private", "text/html", r#"<title>Private</title>"#);
+        let output = tempfile::tempdir().unwrap();
+
+        let limits = CrawlLimits { max_depth: 1, max_pages: 10 };
+        let fetcher = StaticFetcher::new().unwrap();
+        let robots = Some(std::sync::Arc::new(crate::robots::Robots::new(&crate::http::NetworkMode::Live).unwrap()));
+        let options = ScrapeOptions { markdown_converter: MarkdownConverter::Builtin, robots, ..Default::default() };
+        let graph = crawl(&fetcher, &server.url("/"), &options, limits, output.path()).await.unwrap();
+
+        let private = graph.nodes.iter().find(|node| node.url.ends_with("/private")).unwrap();
+        assert_eq!((private.state, private.directory.as_deref()), (NodeState::Disallowed, None));
+        assert!(graph.to_dot().contains(&format!(r#"URL="{}", shape=diamond"#, private.url)));
+        assert!(!server.requests().iter().any(|request| request.contains("GET /private ")));
+        assert_eq!(graph.nodes.iter().filter(|node| node.directory.is_some()).count(), 2);
+    }
+
+    #[test]
+    fn suffixes_directories_already_taken() {
+        let output = tempfile::tempdir().unwrap();
+        std::fs::create_dir(output.path().join("Page")).unwrap();
+        let mut taken = HashSet::new();
+
+        assert_eq!(unique_directory(output.path(), "Page", &mut taken, false), output.path().join("Page-2"));
+        assert_eq!(unique_directory(output.path(), "Page", &mut taken, false), output.path().join("Page-3"));
+        // a directory of an earlier run can be replaced, not one of this run
+        let mut taken = HashSet::new();
+        assert_eq!(unique_directory(output.path(), "Page", &mut taken, true), output.path().join("Page"));
+        assert_eq!(unique_directory(output.path(), "Page", &mut taken, true), output.path().join("Page-2"));
+    }
+
+    #[test]
+    fn renders_dot() {
+        let mut graph = LinkGraph::default();
+        let home = graph.node("https://example.com/");
+        let about = graph.node("https://example.com/about");
+        graph.nodes[home].depth = Some(0);
+        graph.nodes[home].state = NodeState::Captured;
+        graph.nodes[home].title = Some("Say \"hi\"".to_string());
+        graph.edge(home, about, false);
+
+        let dot = graph.to_dot();
+        assert!(dot.contains(r#"n0 [label="Say \"hi\"", URL="https://example.com/", shape=box];"#));
+        assert!(dot.contains("n0 -> n1 [style=dashed];"));
+    }
+}
@@ -0,0 +1,106 @@
+use std::sync::OnceLock;
+use regex::Regex;
+
+/// URLs referenced by `background` / `background-image` declarations of a stylesheet
+/// or of an inline `style` attribute
+pub fn background_image_urls(css: &str) -> Vec<String> {
+
+    static DECLARATION: OnceLock<Regex> = OnceLock::new();
+    let declaration = DECLARATION.get_or_init(|| {
+        Regex::new(r"(?i)background(?:-image)?\s*:([^;}]*)").unwrap()
+    });
+
+    declaration
+        .captures_iter(css)
+        .flat_map(|captures| urls(captures.get(1).map_or("", |m| m.as_str())))
+        .collect()
+}
+
+fn url_regex() -> &'static Regex {
+    static URL: OnceLock<Regex> = OnceLock::new();
+    URL.get_or_init(|| Regex::new(r#"url\(\s*['"]?([^'")]+?)['"]?\s*\)"#).unwrap())
+}
+
+/// Every `url(...)` of a CSS value
+pub fn urls(css: &str) -> Vec<String> {
+
+    url_regex()
+        .captures_iter(css)
+        .filter_map(|captures| captures.get(1))
+        .map(|m| m.as_str().trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect()
+}
+
+/// Replace each `url(...)` for which `replace` returns a new URL, the others are kept
+pub fn replace_urls(css: &str, replace: impl Fn(&str) -> Option<String>) -> String {
+
+    url_regex()
+        .replace_all(css, |captures: &regex::Captures| {
+            match replace(captures[1].trim()) {
+                Some(url) => format!("url(\"{url}\")"),
+                None => captures[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+fn import_regex() -> &'static Regex {
+    static IMPORT: OnceLock<Regex> = OnceLock::new();
+    IMPORT.get_or_init(|| Regex::new(r#"@import\s+(?:url\(\s*['"]?([^'")]+?)['"]?\s*\)|['"]([^'"]+)['"])"#).unwrap())
+}
+
+/// URLs of the `@import` rules of a stylesheet, in both the `url(...)` and the string form
+pub fn imports(css: &str) -> Vec<String> {
+    import_regex()
+        .captures_iter(css)
+        .filter_map(|captures| captures.get(1).or(captures.get(2)))
+        .map(|m| m.as_str().trim().to_string())
+        .collect()
+}
+
+/// Replace the URL of each `@import "..."` for which `replace` returns a new one,
+/// imports written `@import url(...)` are left to `replace_urls`
+pub fn replace_imports(css: &str, replace: impl Fn(&str) -> Option<String>) -> String {
+
+    import_regex()
+        .replace_all(css, |captures: &regex::Captures| {
+            match captures.get(2).and_then(|url| replace(url.as_str().trim())) {
+                Some(url) => format!("@import \"{url}\""),
+                None => captures[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_background_urls_only() {
+        let css = r#"
+            .hero { background-image: url("/img/hero.jpg"); }
+            .card { background: #fff url(card.png) no-repeat; color: red }
+            @font-face { src: url(font.woff2) }
+        "#;
+        assert_eq!(background_image_urls(css), vec!["/img/hero.jpg", "card.png"]);
+        assert_eq!(background_image_urls("background-image:url('a.webp'), url(b.webp)"), vec!["a.webp", "b.webp"]);
+    }
+
+    #[test]
+    fn replaces_known_urls() {
+        let css = "background: url('a.png'), url(b.png)";
+        let replaced = replace_urls(css, |url| (url == "a.png").then(|| "images/a.png".to_string()));
+        assert_eq!(replaced, r#"background: url("images/a.png"), url(b.png)"#);
+    }
+
+    #[test]
+    fn finds_and_replaces_imports() {
+        let css = r#"@import "base.css"; @import url('print.css') print; body { color: red }"#;
+        assert_eq!(imports(css), vec!["base.css", "print.css"]);
+        let local = |url: &str| Some(format!("assets/css/{url}"));
+        let replaced = replace_urls(&replace_imports(css, local), local);
+        assert_eq!(replaced, r#"@import "assets/css/base.css"; @import url("assets/css/print.css") print; body { color: red }"#);
+    }
+}
@@ -0,0 +1,247 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Unified diff of the capture against the earlier one it was compared with, at its root
+pub const FILENAME: &str = "changes.diff";
+
+/// Unchanged lines shown around each change
+const CONTEXT: usize = 3;
+
+/// Most edits searched for, the trace of the search growing with their square: past them the
+/// differing lines are all replaced
+const MAX_DISTANCE: isize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    /// Line `.0` of the old text is line `.1` of the new one
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Edit script turning `old` into `new`: the shortest one between their common first and last
+/// lines, or all of the lines between them replaced when it takes more than `MAX_DISTANCE` edits
+fn edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    let (old_middle, new_middle) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let mut edits = (0..prefix).map(|i| Edit::Equal(i, i)).collect::<Vec<_>>();
+    match shortest_edits(old_middle, new_middle, MAX_DISTANCE) {
+        Some(middle) => edits.extend(middle.into_iter().map(|edit| match edit {
+            Edit::Equal(i, j) => Edit::Equal(prefix + i, prefix + j),
+            Edit::Delete(i) => Edit::Delete(prefix + i),
+            Edit::Insert(j) => Edit::Insert(prefix + j),
+        })),
+        None => {
+            edits.extend((0..old_middle.len()).map(|i| Edit::Delete(prefix + i)));
+            edits.extend((0..new_middle.len()).map(|j| Edit::Insert(prefix + j)));
+        }
+    }
+    edits.extend((0..suffix).map(|i| Edit::Equal(old.len() - suffix + i, new.len() - suffix + i)));
+    edits
+}
+
+/// Shortest edit script turning `old` into `new`, with Myers' algorithm, `None` when it takes
+/// more than `max_distance` edits
+fn shortest_edits(old: &[&str], new: &[&str], max_distance: isize) -> Option<Vec<Edit>> {
+
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = n + m;
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * max as usize + 3];
+    // furthest reaching paths before each round, over the diagonals it can reach
+    let mut trace = Vec::new();
+    let mut found = false;
+    'search: for d in 0..=max.min(max_distance) {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let down = k == -d || (k != d && v[(offset + k - 1) as usize] < v[(offset + k + 1) as usize]);
+            let mut x = match down {
+                true => v[(offset + k + 1) as usize],
+                false => v[(offset + k - 1) as usize] + 1,
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(offset + k) as usize] = x;
+            if x >= n && y >= m {
+                found = true;
+                break 'search;
+            }
+        }
+    }
+    if !found {
+        return None;
+    }
+
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        if d == 0 {
+            while x > 0 && y > 0 {
+                x -= 1;
+                y -= 1;
+                edits.push(Edit::Equal(x as usize, y as usize));
+            }
+            break;
+        }
+        let furthest = |k: isize| v[(k + d) as usize];
+        let k = x - y;
+        let down = k == -d || (k != d && furthest(k - 1) < furthest(k + 1));
+        let previous_k = if down { k + 1 } else { k - 1 };
+        let previous_x = furthest(previous_k);
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Equal(x as usize, y as usize));
+        }
+        match down {
+            true => {
+                y -= 1;
+                edits.push(Edit::Insert(y as usize));
+            }
+            false => {
+                x -= 1;
+                edits.push(Edit::Delete(x as usize));
+            }
+        }
+    }
+    edits.reverse();
+    Some(edits)
+}
+
+/// `start,count` of a hunk header, `start` being the line before the hunk when it's empty
+fn range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{start},0"),
+        1 => format!("{}", start + 1),
+        count => format!("{},{count}", start + 1),
+    }
+}
+
+/// Unified diff of the lines of `old` and `new`, labelled `old_name` and `new_name`,
+/// `None` when they are the same
+pub fn unified(old: &str, new: &str, old_name: &str, new_name: &str) -> Option<String> {
+
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+    let edits = edits(&old, &new);
+    let changes = edits.iter().enumerate().filter(|(_, edit)| !matches!(edit, Edit::Equal(..))).map(|(i, _)| i).collect::<Vec<_>>();
+    if changes.is_empty() {
+        return None;
+    }
+
+    // line of the old and new texts each edit starts at
+    let mut positions = Vec::with_capacity(edits.len());
+    let (mut old_line, mut new_line) = (0, 0);
+    for edit in &edits {
+        positions.push((old_line, new_line));
+        match edit {
+            Edit::Equal(..) => {
+                old_line += 1;
+                new_line += 1;
+            }
+            Edit::Delete(_) => old_line += 1,
+            Edit::Insert(_) => new_line += 1,
+        }
+    }
+
+    // changes closer than twice the context share a hunk
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &change in &changes {
+        match hunks.last_mut() {
+            Some((_, last)) if change - *last <= 2 * CONTEXT => *last = change,
+            _ => hunks.push((change, change)),
+        }
+    }
+
+    let mut diff = format!("--- {old_name}\n+++ {new_name}\n");
+    for (first, last) in hunks {
+        let start = first.saturating_sub(CONTEXT);
+        let end = (last + CONTEXT + 1).min(edits.len());
+        let hunk = &edits[start..end];
+        let old_count = hunk.iter().filter(|edit| !matches!(edit, Edit::Insert(_))).count();
+        let new_count = hunk.iter().filter(|edit| !matches!(edit, Edit::Delete(_))).count();
+        let (old_start, new_start) = positions[start];
+        diff.push_str(&format!("@@ -{} +{} @@\n", range(old_start, old_count), range(new_start, new_count)));
+        for edit in hunk {
+            match edit {
+                Edit::Equal(i, _) => diff.push_str(&format!(" {}\n", old[*i])),
+                Edit::Delete(i) => diff.push_str(&format!("-{}\n", old[*i])),
+                Edit::Insert(j) => diff.push_str(&format!("+{}\n", new[*j])),
+            }
+        }
+    }
+    Some(diff)
+}
+
+/// File named `name` in `directory` or below it, the least deep first
+pub(crate) fn find(directory: &Path, name: &str) -> io::Result<Option<PathBuf>> {
+    let mut pending = vec![directory.to_path_buf()];
+    while !pending.is_empty() {
+        let mut subdirectories = Vec::new();
+        for current in pending {
+            let mut entries = std::fs::read_dir(&current)?.map(|entry| entry.map(|entry| entry.path())).collect::<io::Result<Vec<_>>>()?;
+            entries.sort();
+            for path in entries {
+                if path.is_dir() {
+                    subdirectories.push(path);
+                } else if path.file_name().is_some_and(|file_name| file_name == name) {
+                    return Ok(Some(path));
+                }
+            }
+        }
+        pending = subdirectories;
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_unified_diffs() {
+        let old = "# Title\n\nOne\nTwo\nThree\nFour\nFive\nSix\nSeven\nEight\nNine\nTen\n";
+        let new = "# New title\n\nOne\nTwo\nThree\nFour\nFive\nSix\nSeven\nEight\nNine\nTen\nEleven\n";
+
+        assert_eq!(unified(old, new, "a.md", "b.md").unwrap(), "--- a.md\n+++ b.md\n\
+            @@ -1,4 +1,4 @@\n-# Title\n+# New title\n \n One\n Two\n\
+            @@ -10,3 +10,4 @@\n Eight\n Nine\n Ten\n+Eleven\n");
+        assert_eq!(unified(old, old, "a.md", "b.md"), None);
+        assert_eq!(unified("", "a\n", "a", "b").unwrap(), "--- a\n+++ b\n@@ -0,0 +1 @@\n+a\n");
+    }
+
+    #[test]
+    fn finds_the_shortest_edit_script() {
+        let old = ["a", "b", "c", "a", "b", "b", "a"];
+        let new = ["c", "b", "a", "b", "a", "c"];
+        let edits = edits(&old, &new);
+        assert_eq!(edits.iter().filter(|edit| !matches!(edit, Edit::Equal(..))).count(), 5);
+        let kept = edits.iter().filter_map(|edit| match edit {
+            Edit::Equal(i, j) => Some((old[*i], new[*j])),
+            _ => None,
+        });
+        assert!(kept.into_iter().all(|(a, b)| a == b));
+    }
+
+    #[test]
+    fn replaces_everything_past_the_most_edits() {
+        let old = ["same", "a", "b", "c", "end"];
+        let new = ["same", "c", "b", "a", "end"];
+        assert_eq!(shortest_edits(&old[1..4], &new[1..4], 2), None);
+        assert_eq!(shortest_edits(&old[1..4], &new[1..4], 4).unwrap().len(), 5);
+
+        let old = (0..3000).map(|i| if i % 2 == 0 { "even" } else { "odd" }).collect::<Vec<_>>();
+        let new = (0..3000).map(|i| if i % 3 == 0 { "even" } else { "odd" }).collect::<Vec<_>>();
+        let edits = edits(&old, &new);
+        assert_eq!(edits.iter().filter(|edit| !matches!(edit, Edit::Insert(_))).count(), old.len());
+        assert_eq!(edits.iter().filter(|edit| !matches!(edit, Edit::Delete(_))).count(), new.len());
+    }
+}
@@ -0,0 +1,166 @@
+use std::sync::OnceLock;
+use regex::Regex;
+
+/// Width and height in pixels, read from the header of PNG, GIF, JPEG, WebP,
+/// BMP, ICO, AVIF/HEIF and SVG images without decoding them
+pub fn sniff(bytes: &[u8]) -> Option<(u32, u32)> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => Some((be32(bytes, 16)?, be32(bytes, 20)?)),
+        [b'G', b'I', b'F', b'8', ..] => Some((le16(bytes, 6)?.into(), le16(bytes, 8)?.into())),
+        [0xFF, 0xD8, ..] => jpeg(bytes),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => webp(bytes),
+        [b'B', b'M', ..] => {
+            let width = i32::from_le_bytes(bytes.get(18..22)?.try_into().ok()?);
+            let height = i32::from_le_bytes(bytes.get(22..26)?.try_into().ok()?);
+            Some((width.unsigned_abs(), height.unsigned_abs()))
+        }
+        [0, 0, 1, 0, ..] => {
+            // 0 stands for 256 pixels
+            let size = |byte: u8| if byte == 0 { 256 } else { u32::from(byte) };
+            Some((size(*bytes.get(6)?), size(*bytes.get(7)?)))
+        }
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => {
+            // first image spatial extent property
+            let ispe = bytes.windows(4).position(|window| window == b"ispe")?;
+            Some((be32(bytes, ispe + 8)?, be32(bytes, ispe + 12)?))
+        }
+        _ => svg(bytes),
+    }
+}
+
+fn be32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn le16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn le24(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 3)?;
+    Some(u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16)
+}
+
+/// Walk the JPEG segments up to the first start of frame
+fn jpeg(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut at = 2;
+    loop {
+        while *bytes.get(at)? != 0xFF {
+            at += 1;
+        }
+        while *bytes.get(at)? == 0xFF {
+            at += 1;
+        }
+        let marker = *bytes.get(at)?;
+        at += 1;
+        match marker {
+            // standalone markers, without length
+            0xD0..=0xD9 | 0x01 => continue,
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                return Some((be16(bytes, at + 5)?.into(), be16(bytes, at + 3)?.into()));
+            }
+            _ => at += usize::from(be16(bytes, at)?),
+        }
+    }
+}
+
+fn webp(bytes: &[u8]) -> Option<(u32, u32)> {
+    match bytes.get(12..16)? {
+        b"VP8 " => Some(((le16(bytes, 26)? & 0x3FFF).into(), (le16(bytes, 28)? & 0x3FFF).into())),
+        b"VP8L" => {
+            let bits = u32::from_le_bytes(bytes.get(21..25)?.try_into().ok()?);
+            Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        b"VP8X" => Some((le24(bytes, 24)? + 1, le24(bytes, 27)? + 1)),
+        _ => None,
+    }
+}
+
+/// Usual file extension of the image format of `bytes`, recognized from its header
+pub fn extension(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => Some("png"),
+        [b'G', b'I', b'F', b'8', ..] => Some("gif"),
+        [0xFF, 0xD8, ..] => Some("jpg"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("webp"),
+        [b'B', b'M', ..] => Some("bmp"),
+        [0, 0, 1, 0, ..] => Some("ico"),
+        [_, _, _, _, b'f', b't', b'y', b'p', b'a', b'v', b'i', b'f', ..] => Some("avif"),
+        [b'I', b'I', 0x2A, 0, ..] | [b'M', b'M', 0, 0x2A, ..] => Some("tif"),
+        [0xFF, 0x0A, ..] | [0, 0, 0, 0x0C, b'J', b'X', b'L', b' ', ..] => Some("jxl"),
+        _ if is_svg(bytes) => Some("svg"),
+        _ => None,
+    }
+}
+
+/// Whether `bytes` look like an SVG document rather than a raster image,
+/// or an HTML page showing an SVG icon
+pub fn is_svg(bytes: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(4096)]).to_ascii_lowercase();
+    text.contains("<svg") && !text.contains("<html") && !text.contains("<!doctype html")
+}
+
+/// `width` and `height` of the root `<svg>`, or else its `viewBox`
+fn svg(bytes: &[u8]) -> Option<(u32, u32)> {
+
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(4096)]);
+    let start = text.find("<svg")?;
+    let tag = &text[start..start + text[start..].find('>')?];
+
+    static ATTRIBUTE: OnceLock<Regex> = OnceLock::new();
+    let attribute = ATTRIBUTE.get_or_init(|| Regex::new(r#"\s(width|height|viewBox)\s*=\s*["']([^"']*)["']"#).unwrap());
+
+    let mut width = None;
+    let mut height = None;
+    let mut view_box = None;
+    for captures in attribute.captures_iter(tag) {
+        let value = captures[2].trim();
+        match &captures[1] {
+            "width" => width = pixels(value),
+            "height" => height = pixels(value),
+            _ => view_box = Some(value.split([' ', ',']).filter(|v| !v.is_empty()).filter_map(|v| v.parse::<f32>().ok()).collect::<Vec<_>>()),
+        }
+    }
+
+    match (width, height, view_box.as_deref()) {
+        (Some(width), Some(height), _) => Some((width, height)),
+        (_, _, Some([_, _, width, height])) => Some((width.round() as u32, height.round() as u32)),
+        _ => None,
+    }
+}
+
+/// Length in pixels, relative units (`%`, `em`) are unknown
+fn pixels(value: &str) -> Option<u32> {
+    let number = value.strip_suffix("px").unwrap_or(value);
+    number.parse::<f32>().ok().filter(|n| *n >= 0.0).map(|n| n.round() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::RED_PNG;
+
+    #[test]
+    fn reads_header_dimensions() {
+        assert_eq!(sniff(RED_PNG), Some((16, 16)));
+        assert_eq!(sniff(b"GIF89a\x20\x00\x10\x00"), Some((32, 16)));
+
+        let jpeg = [&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00][..], &[0xFF, 0xC0, 0x00, 0x11, 0x08, 0x01, 0xE0, 0x02, 0x80]].concat();
+        assert_eq!(sniff(&jpeg), Some((640, 480)));
+
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X".to_vec();
+        webp.extend([0; 8]);
+        webp.extend([0x7F, 0x07, 0x00, 0x37, 0x04, 0x00]);
+        assert_eq!(sniff(&webp), Some((1920, 1080)));
+
+        assert_eq!(sniff(br#"<svg xmlns="http://www.w3.org/2000/svg" width="24px" height="24">"#), Some((24, 24)));
+        assert_eq!(sniff(br#"<svg viewBox="0 0 100 50"><rect/></svg>"#), Some((100, 50)));
+        assert_eq!(sniff(b"not an image"), None);
+        assert_eq!((extension(RED_PNG), extension(&jpeg), extension(b"<html>")), (Some("png"), Some("jpg"), None));
+        assert_eq!(extension(b"<!DOCTYPE html><html><body><svg class=\"logo\"></svg>"), None);
+    }
+}
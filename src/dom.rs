@@ -0,0 +1,155 @@
+use ego_tree::{NodeId, NodeRef};
+use html5ever::{ns, QualName};
+use scraper::node::Attributes;
+use scraper::{Html, Node, Selector};
+
+/// Remove the elements matching `selectors` from `html`.
+/// Also returns the selectors that matched nothing or couldn't be parsed
+pub fn remove_elements(html: &str, selectors: &[String]) -> (String, Vec<String>) {
+
+    if selectors.is_empty() {
+        return (html.to_string(), Vec::new());
+    }
+
+    let mut document = Html::parse_document(html);
+    let mut unmatched = Vec::new();
+
+    for selector in selectors {
+        let Ok(parsed) = Selector::parse(selector) else {
+            unmatched.push(selector.clone());
+            continue;
+        };
+        let ids = document.select(&parsed).map(|element| element.id()).collect::<Vec<_>>();
+        if ids.is_empty() {
+            unmatched.push(selector.clone());
+        }
+        for id in ids {
+            if let Some(mut node) = document.tree.get_mut(id) {
+                node.detach();
+            }
+        }
+    }
+
+    (document.html(), unmatched)
+}
+
+/// `html` reduced to the elements matching `selector`, in document order, its head kept.
+/// `None` when the selector is invalid or matches nothing
+pub fn select_elements(html: &str, selector: &str) -> Option<String> {
+
+    let selector = Selector::parse(selector).ok()?;
+    let document = Html::parse_document(html);
+    let matched = document.select(&selector).collect::<Vec<_>>();
+    // the descendants of a match are already in it
+    let outermost = matched
+        .iter()
+        .filter(|element| !element.ancestors().any(|ancestor| matched.iter().any(|other| other.id() == ancestor.id())))
+        .map(|element| element.html())
+        .collect::<Vec<_>>();
+    if outermost.is_empty() {
+        return None;
+    }
+
+    let head = document.select(&Selector::parse("head").unwrap()).next().map(|head| head.html()).unwrap_or_default();
+    let lang = document.root_element().attr("lang").map(|lang| format!(" lang=\"{}\"", lang.replace('"', "&quot;"))).unwrap_or_default();
+    Some(format!("<!DOCTYPE html><html{lang}>{head}<body>{}</body></html>", outermost.join("\n")))
+}
+
+/// Selectors of a comma-separated list, `nav, footer` giving `nav` and `footer`.
+/// Commas within parentheses, brackets or quotes, as in `:is(h1, h2)`, don't split it
+pub fn split_selector_list(list: &str) -> Vec<String> {
+
+    let mut selectors = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    for c in list.chars() {
+        match (c, quote) {
+            (c, Some(open)) if c == open => quote = None,
+            (_, Some(_)) => {}
+            ('"' | '\'', None) => quote = Some(c),
+            ('(' | '[', None) => depth += 1,
+            (')' | ']', None) => depth = depth.saturating_sub(1),
+            (',', None) if depth == 0 => {
+                selectors.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    selectors.push(current);
+    selectors.into_iter().map(|selector| selector.trim().to_string()).filter(|selector| !selector.is_empty()).collect()
+}
+
+/// Put the nodes parsed from the `html` fragment in place of the node `id` of `document`
+pub fn replace_with_html(document: &mut Html, id: NodeId, html: &str) {
+
+    let fragment = Html::parse_fragment(html);
+    // fragments are parsed inside an <html> element
+    for child in fragment.root_element().children() {
+        let Some(mut target) = document.tree.get_mut(id) else { return };
+        let inserted = target.insert_before(child.value().clone()).id();
+        copy_children(document, child, inserted);
+    }
+    if let Some(mut target) = document.tree.get_mut(id) {
+        target.detach();
+    }
+}
+
+fn copy_children(document: &mut Html, source: NodeRef<Node>, parent: NodeId) {
+    for child in source.children() {
+        let Some(mut parent) = document.tree.get_mut(parent) else { return };
+        let copied = parent.append(child.value().clone()).id();
+        copy_children(document, child, copied);
+    }
+}
+
+/// Set the attribute `name` of an element, adding it when missing
+pub fn set_attribute(attributes: &mut Attributes, name: &str, value: &str) {
+    match attributes.iter_mut().find(|(qualified, _)| &*qualified.local == name) {
+        Some((_, existing)) => *existing = value.into(),
+        // HTML attributes all share the same (empty) namespace
+        None => attributes.push((QualName::new(None, ns!(), name.into()), value.into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_matching_elements() {
+        let html = r#"<html><body><div class="banner">Accept cookies</div><p>Text</p></body></html>"#;
+        let (cleaned, unmatched) = remove_elements(html, &[".banner".to_string(), "#paywall".to_string()]);
+        assert!(!cleaned.contains("Accept cookies"));
+        assert!(cleaned.contains("<p>Text</p>"));
+        assert_eq!(unmatched, vec!["#paywall"]);
+    }
+
+    #[test]
+    fn selects_elements() {
+        let html = r#"<html lang="en"><head><title>Portal</title></head><body>
+            <nav>Menu</nav><div class="main"><p>First</p><div class="main">Nested</div></div><aside>Ads</aside><div class="main">Second</div>
+        </body></html>"#;
+        let selected = select_elements(html, "div.main").unwrap();
+        assert!(selected.starts_with(r#"<!DOCTYPE html><html lang="en"><head><title>Portal</title></head><body><div class="main"><p>First</p>"#));
+        assert_eq!(selected.matches("Nested").count(), 1);
+        assert!(selected.contains("Second") && !selected.contains("Menu") && !selected.contains("Ads"));
+        assert_eq!(select_elements(html, "article"), None);
+    }
+
+    #[test]
+    fn splits_selector_lists() {
+        assert_eq!(split_selector_list("nav, footer,.cookie-banner , #comments"), ["nav", "footer", ".cookie-banner", "#comments"]);
+        assert_eq!(split_selector_list(r#":is(h1, h2) a[title="a, b"],"#), [r#":is(h1, h2) a[title="a, b"]"#]);
+    }
+
+    #[test]
+    fn replaces_element_with_fragment() {
+        let mut document = Html::parse_document("<p>Before</p><script src=\"embed.js\"></script><p>After</p>");
+        let id = document.select(&Selector::parse("script").unwrap()).next().unwrap().id();
+        replace_with_html(&mut document, id, r#"<p>See <a href="code/main.rs">main.rs</a></p>"#);
+        assert!(document.html().contains(r#"<p>Before</p><p>See <a href="code/main.rs">main.rs</a></p><p>After</p>"#));
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashSet;
+use futures::future::join_all;
+use scraper::{ElementRef, Html, Selector};
+use url::Url;
+use crate::dom;
+use crate::filename;
+use crate::http::HttpClient;
+use crate::warnings::{Warning, WarningKind, Warnings};
+
+/// Directory of the capture holding the sources of embedded code
+pub const DIRECTORY: &str = "code";
+
+/// Source of an embedded snippet, saved in `code/`
+#[derive(Debug, Clone)]
+pub struct CodeFile {
+    pub filename: String,
+    /// Where the raw source was downloaded from
+    pub url: String,
+    pub source: Vec<u8>,
+}
+
+/// Where an embed keeps its raw source, with the file name when the URL doesn't end with it
+#[derive(Debug, PartialEq)]
+struct RawSource {
+    url: Url,
+    filename: Option<String>,
+}
+
+/// Raw sources behind the `src` of an embedding `<script>` or `<iframe>`:
+/// GitHub Gists, GitHub files embedded through emgithub and CodePen pens
+fn raw_sources(embed: &Url) -> Vec<RawSource> {
+
+    let segments = embed.path_segments().map(|s| s.filter(|s| !s.is_empty()).collect::<Vec<_>>()).unwrap_or_default();
+    let raw = |url: &str, filename: Option<String>| Url::parse(url).ok().map(|url| RawSource { url, filename });
+
+    match (embed.host_str().unwrap_or_default(), segments.as_slice()) {
+        // gist.github.com/<user>/<id>.js?file=<name>, or the iframe of gist.github.com/<user>/<id>
+        ("gist.github.com", [user, id]) => {
+            let id = id.trim_end_matches(".js").trim_end_matches(".pibb");
+            let file = embed.query_pairs().find(|(key, _)| key == "file").map(|(_, file)| file.to_string());
+            let path = match &file {
+                Some(file) => format!("https://gist.github.com/{user}/{id}/raw/{file}"),
+                // redirects to the raw URL of the first file, named after it
+                None => format!("https://gist.github.com/{user}/{id}/raw"),
+            };
+            raw(&path, None).into_iter().collect()
+        }
+        // emgithub.com/embed.js?target=https://github.com/<user>/<repo>/blob/<ref>/<path>
+        ("emgithub.com", _) => {
+            let target = embed.query_pairs().find(|(key, _)| key == "target").and_then(|(_, target)| Url::parse(&target).ok());
+            let Some(target) = target.filter(|target| target.host_str() == Some("github.com")) else {
+                return Vec::new();
+            };
+            let path = target.path_segments().map(|s| s.collect::<Vec<_>>()).unwrap_or_default();
+            match path.as_slice() {
+                [user, repo, "blob", reference, file @ ..] if !file.is_empty() => {
+                    raw(&format!("https://raw.githubusercontent.com/{user}/{repo}/{reference}/{}", file.join("/")), None).into_iter().collect()
+                }
+                _ => Vec::new(),
+            }
+        }
+        // codepen.io/<user>/embed/[preview/]<id>
+        ("codepen.io", [user, "embed", .., id]) => ["html", "css", "js"]
+            .iter()
+            .filter_map(|extension| raw(&format!("https://codepen.io/{user}/pen/{id}.{extension}"), Some(format!("{id}.{extension}"))))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Fetch the sources of the code embeds of `html` and link to them in place of the embeds,
+/// so they survive in the HTML and markdown outputs. Embeds whose source can't be fetched
+/// are kept as they are, with a warning
+pub async fn extract(html: &str, base_url: &str, client: &HttpClient, warnings: &Warnings) -> (String, Vec<CodeFile>) {
+
+    let Ok(base_url) = Url::parse(base_url) else {
+        return (html.to_string(), Vec::new());
+    };
+
+    let mut document = Html::parse_document(html);
+    let selector = Selector::parse("script[src], iframe[src]").unwrap();
+
+    let embeds = document
+        .select(&selector)
+        .filter_map(|element| {
+            let url = base_url.join(element.attr("src")?).ok()?;
+            let sources = raw_sources(&url);
+            (!sources.is_empty()).then_some((element.id(), url, sources))
+        })
+        .collect::<Vec<_>>();
+
+    if embeds.is_empty() {
+        return (html.to_string(), Vec::new());
+    }
+
+    let downloads = embeds.iter().map(|(_, _, sources)| join_all(sources.iter().map(|source| client.get(&source.url))));
+    let responses = join_all(downloads).await;
+
+    let mut files = Vec::new();
+    let mut taken = HashSet::new();
+
+    for ((id, url, sources), responses) in embeds.into_iter().zip(responses) {
+
+        let mut links = Vec::new();
+        for (source, response) in sources.iter().zip(responses) {
+            let response = match response {
+                Ok(response) => response,
+                Err(e) => {
+                    warnings.push(Warning::with_url(WarningKind::SkippedEmbed, source.url.as_str(), e.to_string()));
+                    continue;
+                }
+            };
+            // CodePen answers with empty files for the languages a pen doesn't use
+            if response.body.is_empty() {
+                continue;
+            }
+            let name = source.filename.clone().or_else(|| {
+                response.url.path_segments().and_then(|mut s| s.next_back()).filter(|s| !s.is_empty()).map(str::to_string)
+            });
+            let filename = filename::unique(&filename::sanitize(name.as_deref().unwrap_or("snippet")), &mut taken);
+            links.push(format!(
+                r#"<a href="{DIRECTORY}/{}">{}</a>"#, escape(&filename::to_url_path(&filename)), escape(&filename)
+            ));
+            files.push(CodeFile { filename, url: response.url.to_string(), source: response.body });
+        }
+
+        if links.is_empty() {
+            continue;
+        }
+
+        // the markup a gist script wrote in a rendered page would be scrambled in the markdown
+        let rendered_gist = document
+            .tree
+            .get(id)
+            .and_then(|node| node.next_siblings().find_map(ElementRef::wrap))
+            .filter(|sibling| sibling.value().has_class("gist", scraper::CaseSensitivity::CaseSensitive))
+            .map(|sibling| sibling.id());
+        if let Some(mut gist) = rendered_gist.and_then(|gist| document.tree.get_mut(gist)) {
+            gist.detach();
+        }
+
+        let replacement = format!(
+            r#"<p>Embedded code: {} (<a href="{}">embed</a>)</p>"#, links.join(", "), escape(url.as_str())
+        );
+        dom::replace_with_html(&mut document, id, &replacement);
+    }
+
+    (document.html(), files)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('"', "&quot;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_urls(embed: &str) -> Vec<String> {
+        raw_sources(&Url::parse(embed).unwrap()).into_iter().map(|source| source.url.to_string()).collect()
+    }
+
+    #[test]
+    fn finds_raw_sources_of_embeds() {
+        assert_eq!(raw_urls("https://gist.github.com/octocat/6cad326836d38bd3a7ae.js"), vec!["https://gist.github.com/octocat/6cad326836d38bd3a7ae/raw"]);
+        assert_eq!(raw_urls("https://gist.github.com/octocat/6cad32.js?file=hello.rs"), vec!["https://gist.github.com/octocat/6cad32/raw/hello.rs"]);
+        assert_eq!(
+            raw_urls("https://emgithub.com/embed-v2.js?target=https%3A%2F%2Fgithub.com%2Frust-lang%2Frust%2Fblob%2Fmaster%2Fsrc%2Fmain.rs%23L1-L5"),
+            vec!["https://raw.githubusercontent.com/rust-lang/rust/master/src/main.rs"]
+        );
+        assert_eq!(raw_urls("https://codepen.io/chriscoyier/embed/preview/gfdDu").len(), 3);
+        assert!(raw_urls("https://www.youtube.com/embed/dQw4w9WgXcQ").is_empty());
+    }
+}
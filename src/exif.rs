@@ -0,0 +1,96 @@
+/// JPEG `bytes` without their EXIF (camera, GPS position, thumbnail), XMP, IPTC
+/// and comment segments. The JFIF header and ICC color profile are kept.
+/// Returns `None` when `bytes` aren't a JPEG or hold no such metadata
+pub fn strip_jpeg(bytes: &[u8]) -> Option<Vec<u8>> {
+
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let mut stripped = bytes[..2].to_vec();
+    let mut at = 2;
+    let mut removed = false;
+
+    loop {
+        if *bytes.get(at)? != 0xFF {
+            return None;
+        }
+        let marker = *bytes.get(at + 1)?;
+        match marker {
+            // fill byte
+            0xFF => {
+                at += 1;
+                continue;
+            }
+            // start of scan: the entropy-coded data and the rest of the file are copied as is
+            0xDA => {
+                stripped.extend(&bytes[at..]);
+                break;
+            }
+            0xD0..=0xD7 | 0x01 => {
+                stripped.extend(&bytes[at..at + 2]);
+                at += 2;
+                continue;
+            }
+            _ => {}
+        }
+
+        // the length counts its own two bytes, a shorter one is a broken file
+        let length = usize::from(u16::from_be_bytes(bytes.get(at + 2..at + 4)?.try_into().ok()?));
+        if length < 2 {
+            return None;
+        }
+        let segment = bytes.get(at..at + 2 + length)?;
+        let payload = segment.get(4..)?;
+
+        let metadata = match marker {
+            // APP1: EXIF or XMP
+            0xE1 => payload.starts_with(b"Exif\0") || payload.starts_with(b"http://ns.adobe.com/"),
+            // APP13: Photoshop IRB, holding IPTC
+            0xED => true,
+            // COM
+            0xFE => true,
+            _ => false,
+        };
+        if metadata {
+            removed = true;
+        } else {
+            stripped.extend(segment);
+        }
+        at += 2 + length;
+    }
+
+    removed.then_some(stripped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(marker: u8, payload: &[u8]) -> Vec<u8> {
+        [&[0xFF, marker][..], &((payload.len() + 2) as u16).to_be_bytes(), payload].concat()
+    }
+
+    #[test]
+    fn strips_jpeg_metadata() {
+        let jfif = segment(0xE0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+        let icc = segment(0xE2, b"ICC_PROFILE\0\x01\x01");
+        let frame = segment(0xC0, &[0x08, 0x00, 0x10, 0x00, 0x10, 0x01, 0x01, 0x11, 0x00]);
+        let scan = [&segment(0xDA, &[0x01, 0x01, 0x00, 0x00, 0x3F, 0x00])[..], &[0x12, 0x34, 0xFF, 0xD9]].concat();
+        let exif = segment(0xE1, b"Exif\0\0MM\0\x2A GPS 46.52N 6.56E");
+        let comment = segment(0xFE, b"Shot by me");
+
+        let jpeg = [&[0xFF, 0xD8][..], &jfif, &exif, &icc, &comment, &frame, &scan].concat();
+        let clean = [&[0xFF, 0xD8][..], &jfif, &icc, &frame, &scan].concat();
+
+        assert_eq!(strip_jpeg(&jpeg), Some(clean.clone()));
+        assert_eq!(strip_jpeg(&clean), None);
+        assert_eq!(strip_jpeg(b"\x89PNG"), None);
+    }
+
+    #[test]
+    fn leaves_broken_segment_lengths_alone() {
+        assert_eq!(strip_jpeg(&[0xFF, 0xD8, 0xFF, 0xE1, 0x00, 0x00, 0xFF, 0xDA]), None);
+        assert_eq!(strip_jpeg(&[0xFF, 0xD8, 0xFF, 0xE1, 0x00, 0x01, 0xFF, 0xDA]), None);
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use scraper::{Html, Selector};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use thiserror::Error;
+use crate::toml;
+use crate::xpath::{self, XPath, XPathError, XPathValue};
+
+/// Fields extracted from the page, at the root of the capture
+pub const FILENAME: &str = "extracted.json";
+
+#[derive(Error, Debug)]
+pub enum ExtractError {
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("invalid field file {file}: {message}")]
+    InvalidFields { file: PathBuf, message: String },
+    #[error("{0}")]
+    InvalidXPath(#[from] XPathError),
+    #[error("invalid CSS selector {0:?}")]
+    InvalidSelector(String),
+    #[error("a field needs either css, with an optional attribute, or xpath")]
+    AmbiguousField,
+}
+
+pub type Result<T> = std::result::Result<T, ExtractError>;
+
+/// Where the value of a field is found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expression {
+    /// Text of the elements matching the selector, or their `attribute`
+    Css { selector: String, attribute: Option<String> },
+    /// Strings of the nodes or attributes selected, or the string, number or boolean computed
+    XPath(String),
+}
+
+impl Expression {
+
+    /// XPath when `expression` starts like a location path or a function call, `/`, `(` or `name(`,
+    /// CSS otherwise
+    pub fn parse(expression: &str) -> Result<Self> {
+        let expression = expression.trim();
+        let function = expression.trim_start_matches(|c: char| c.is_ascii_alphabetic() || c == '-');
+        match expression.starts_with(['/', '(']) || (function.len() < expression.len() && function.starts_with('(')) {
+            true => Self::xpath(expression),
+            false => Self::css(expression, None),
+        }
+    }
+
+    pub fn css(selector: &str, attribute: Option<String>) -> Result<Self> {
+        Selector::parse(selector).map_err(|_| ExtractError::InvalidSelector(selector.to_string()))?;
+        Ok(Expression::Css { selector: selector.to_string(), attribute })
+    }
+
+    pub fn xpath(expression: &str) -> Result<Self> {
+        XPath::parse(expression)?;
+        Ok(Expression::XPath(expression.to_string()))
+    }
+
+    /// Values found in `document`, in document order
+    fn values(&self, document: &Html) -> Vec<Value> {
+        match self {
+            Expression::Css { selector, attribute } => {
+                let selector = Selector::parse(selector).expect("checked when parsed");
+                document.select(&selector)
+                    .filter_map(|element| match attribute {
+                        Some(attribute) => element.attr(attribute).map(str::trim).map(str::to_string),
+                        None => Some(xpath::normalize_space(&element.text().collect::<String>())),
+                    })
+                    .filter(|text| !text.is_empty())
+                    .map(Value::String)
+                    .collect()
+            }
+            Expression::XPath(expression) => match XPath::parse(expression).expect("checked when parsed").evaluate(document) {
+                XPathValue::Items(items) => items.iter()
+                    .map(|item| xpath::normalize_space(&item.string_value()))
+                    .filter(|text| !text.is_empty())
+                    .map(Value::String)
+                    .collect(),
+                XPathValue::Text(text) => Some(text.trim().to_string()).filter(|text| !text.is_empty()).map(Value::String).into_iter().collect(),
+                XPathValue::Number(number) if number.is_nan() => Vec::new(),
+                XPathValue::Number(number) if number.fract() == 0.0 && number.abs() < 1e15 => vec![Value::from(number as i64)],
+                XPathValue::Number(number) => vec![Value::from(number)],
+                XPathValue::Boolean(boolean) => vec![Value::Bool(boolean)],
+            },
+        }
+    }
+}
+
+/// Field as declared: a selector or XPath expression, or a table of them
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FieldDeclaration {
+    Expression(String),
+    Table(FieldTable),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FieldTable {
+    css: Option<String>,
+    xpath: Option<String>,
+    /// Attribute read from the elements matching `css`
+    attribute: Option<String>,
+    /// Every match instead of the first
+    #[serde(default)]
+    all: bool,
+}
+
+/// Named value extracted from the page
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "FieldDeclaration")]
+pub struct Field {
+    pub expression: Expression,
+    /// An array of every match rather than the first one
+    pub all: bool,
+}
+
+impl TryFrom<FieldDeclaration> for Field {
+    type Error = ExtractError;
+
+    fn try_from(declaration: FieldDeclaration) -> Result<Self> {
+        match declaration {
+            FieldDeclaration::Expression(expression) => Ok(Field { expression: Expression::parse(&expression)?, all: false }),
+            FieldDeclaration::Table(FieldTable { css: Some(selector), xpath: None, attribute, all }) => Ok(Field { expression: Expression::css(&selector, attribute)?, all }),
+            FieldDeclaration::Table(FieldTable { css: None, xpath: Some(expression), attribute: None, all }) => Ok(Field { expression: Expression::xpath(&expression)?, all }),
+            FieldDeclaration::Table(_) => Err(ExtractError::AmbiguousField),
+        }
+    }
+}
+
+/// Fields to extract, by name. Declared in TOML as `name = "<CSS selector or XPath>"`, or
+/// `name = { css = "...", attribute = "...", all = true }` and `name = { xpath = "...", all = true }`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Fields(pub BTreeMap<String, Field>);
+
+impl Fields {
+
+    /// Fields declared in the TOML file at `path`
+    pub fn load(path: &Path) -> Result<Self> {
+        let invalid = |message: String| ExtractError::InvalidFields { file: path.to_path_buf(), message };
+        let value = toml::parse(&std::fs::read_to_string(path)?).map_err(|e| invalid(e.to_string()))?;
+        serde_json::from_value(value).map_err(|e| invalid(e.to_string()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Adds the fields of `other`, replacing those of the same name
+    pub fn extend(&mut self, other: &Fields) {
+        self.0.extend(other.0.iter().map(|(name, field)| (name.clone(), field.clone())));
+    }
+
+    /// Value of each field in `html`, `null` or an empty array when not found, and the
+    /// names of the fields that weren't
+    pub fn extract(&self, html: &str) -> (Map<String, Value>, Vec<String>) {
+
+        let document = Html::parse_document(html);
+        let mut extracted = Map::new();
+        let mut missing = Vec::new();
+        for (name, field) in &self.0 {
+            let mut values = field.expression.values(&document);
+            if values.is_empty() {
+                missing.push(name.clone());
+            }
+            let value = match field.all {
+                true => Value::Array(values),
+                false if values.is_empty() => Value::Null,
+                false => values.swap_remove(0),
+            };
+            extracted.insert(name.clone(), value);
+        }
+        (extracted, missing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn extracts_declared_fields() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), r#"
+            title = "h1"
+            author = "//meta[@name='author']/@content"
+            price = { xpath = "substring-after(//*[@class='price'], '$')" }
+            image = { css = "img.photo", attribute = "src" }
+            tags = { css = ".tags a", all = true }
+            reviews = "count(//*[@class='review'])"
+            sku = ".sku"
+        "#).unwrap();
+        let fields = Fields::load(file.path()).unwrap();
+
+        let html = r#"<html><head><meta name="author" content="Ada"></head><body>
+            <h1> Desk
+                lamp </h1><p class="price">$24.50</p><img class="photo" src="/lamp.jpg">
+            <p class="tags"><a>home</a> <a>light</a></p>
+            <div class="review">Great</div><div class="review">Bright</div>
+        </body></html>"#;
+        let (extracted, missing) = fields.extract(html);
+        assert_eq!(Value::Object(extracted), json!({
+            "title": "Desk lamp", "author": "Ada", "price": "24.50", "image": "/lamp.jpg",
+            "tags": ["home", "light"], "reviews": 2, "sku": null,
+        }));
+        assert_eq!(missing, ["sku"]);
+    }
+
+    #[test]
+    fn rejects_invalid_fields() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        for declaration in ["a = \"//div[\"", "a = \"p >\"", "a = { css = \"p\", xpath = \"//p\" }"] {
+            std::fs::write(file.path(), declaration).unwrap();
+            assert!(matches!(Fields::load(file.path()), Err(ExtractError::InvalidFields { .. })), "{declaration}");
+        }
+    }
+}
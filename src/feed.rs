@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use thiserror::Error;
+use crate::webpage::InfoJson;
+
+#[derive(Error, Debug)]
+pub enum FeedError {
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("JSON conversion error: {0}")]
+    JsonError(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, FeedError>;
+
+/// JSON Feed (https://jsonfeed.org/version/1.1) listing the captures of an archive root
+#[derive(Serialize)]
+pub struct Feed {
+    version: &'static str,
+    title: String,
+    items: Vec<FeedItem>,
+}
+
+#[derive(Serialize)]
+struct FeedItem {
+    id: String,
+    url: String,
+    title: String,
+    summary: String,
+    content_text: String,
+    date_published: String,
+    /// Capture directory relative to the archive root
+    #[serde(rename = "_archive_path")]
+    archive_path: String,
+}
+
+impl Feed {
+
+    pub const FILENAME: &str = "feed.json";
+    const MAX_ITEMS: usize = 50;
+
+    /// Collect the most recent captures found under `root`
+    pub fn from_archive(root: &Path) -> Result<Self> {
+
+        let mut captures = Vec::new();
+        Self::find_captures(root, &mut captures)?;
+
+        let mut items = captures
+            .into_iter()
+            .map(|(directory, info)| {
+                let archive_path = directory
+                    .strip_prefix(root)
+                    .unwrap_or(&directory)
+                    .to_string_lossy()
+                    .to_string();
+                FeedItem {
+                    id: format!("{}#{}", info.url, info.date),
+                    date_published: format!("{}T00:00:00Z", info.date),
+                    content_text: info.summary.clone(),
+                    url: info.url,
+                    title: info.title,
+                    summary: info.summary,
+                    archive_path,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // most recent first, ISO dates sort lexicographically
+        items.sort_by(|a, b| b.date_published.cmp(&a.date_published));
+        items.truncate(Self::MAX_ITEMS);
+
+        let title = root
+            .file_name()
+            .map(|name| format!("{} captures", name.to_string_lossy()))
+            .unwrap_or_else(|| "Web archive captures".to_string());
+
+        Ok(Self { version: "https://jsonfeed.org/version/1.1", title, items })
+    }
+
+    /// Every capture directory (holding an `informations.json`) under `directory`
+    pub(crate) fn find_captures(directory: &Path, captures: &mut Vec<(PathBuf, InfoJson)>) -> Result<()> {
+
+        let info_path = directory.join("informations.json");
+        if info_path.is_file() {
+            let info: InfoJson = serde_json::from_str(&std::fs::read_to_string(&info_path)?)?;
+            captures.push((directory.to_path_buf(), info));
+            return Ok(());
+        }
+
+        for entry in std::fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                Self::find_captures(&path, captures)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Regenerate `feed.json` at the top of the archive root
+    pub fn update(root: &Path) -> Result<()> {
+        let feed = Self::from_archive(root)?;
+        std::fs::write(root.join(Self::FILENAME), serde_json::to_string_pretty(&feed)?)?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feed_lists_captures_newest_first() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        for (dir, date) in [("2024/old", "2024-01-02"), ("2025/new", "2025-03-04")] {
+            let capture = root.join(dir);
+            std::fs::create_dir_all(&capture).unwrap();
+            let info = format!(
+                r#"{{"url":"https://example.com/{dir}","title":"{dir}","date":"{date}","nb_md_words":1,"nb_images":0}}"#
+            );
+            std::fs::write(capture.join("informations.json"), info).unwrap();
+        }
+
+        let feed = Feed::from_archive(root).unwrap();
+
+        assert_eq!(feed.len(), 2);
+        assert_eq!(feed.items[0].title, "2025/new");
+        assert_eq!(feed.items[0].archive_path, "2025/new");
+    }
+}
@@ -0,0 +1,239 @@
+use std::sync::Arc;
+use std::time::Instant;
+use futures::future::{BoxFuture, FutureExt};
+use scraper::{Html, Selector};
+use url::Url;
+use crate::browser::Result;
+use crate::http::Cookie;
+use crate::images::Images;
+use crate::options::ScrapeOptions;
+use crate::ratelimit::RequestKind;
+use crate::pdf::PrintOptions;
+use crate::renderer::{PageRenderer, RenderedPage};
+use crate::timings::Timings;
+use crate::webpage::WebPage;
+use crate::warnings::{Warning, WarningKind};
+
+/// `PageRenderer` fetching the raw HTML with `reqwest`, without any browser.
+/// Much faster on static sites, but scripts don't run and no PDF can be printed
+pub struct StaticFetcher {
+    client: reqwest::Client,
+    options: ScrapeOptions,
+}
+
+struct StaticPage {
+    requested_url: String,
+    status: u16,
+    url: String,
+    title: String,
+    html: String,
+    headers: Vec<(String, String)>,
+    warnings: Vec<Warning>,
+    timings: Timings,
+}
+
+impl StaticFetcher {
+
+    pub fn new() -> Result<Self> {
+        Self::with_options(ScrapeOptions::default())
+    }
+
+    pub fn with_options(options: ScrapeOptions) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent(Images::USER_AGENT)
+            .build()?;
+        Ok(Self { client, options })
+    }
+
+    pub async fn open_tab(&self, url: &str) -> Result<WebPage> {
+        let page = self.render(url, &self.options).await?;
+        Ok(WebPage::from_page(page, &self.options).await?)
+    }
+
+    async fn fetch(&self, url: &str, options: &ScrapeOptions) -> Result<StaticPage> {
+
+        let host = Url::parse(url)?.host_str().unwrap_or_default().to_string();
+        tokio::time::sleep(self.options.rate_limit.reserve(RequestKind::Page, &host, options.domain_delay, options.domain_jitter)).await;
+
+        let started = Instant::now();
+        let mut request = self.client.get(url).timeout(options.retry.timeout);
+        if let Some(auth) = options.auth.as_ref().filter(|auth| Url::parse(url).is_ok_and(|url| auth.applies_to(&url))) {
+            for (name, value) in auth.headers() {
+                request = request.header(name, value);
+            }
+        }
+        let response = request.send().await?.error_for_status()?;
+        let status = response.status().as_u16();
+        let requested_url = url.to_string();
+        let url = response.url().to_string();
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let bytes = response.bytes().await?;
+        let elapsed = started.elapsed();
+        let timings = Timings {
+            navigation_ms: Some(elapsed.as_secs_f64() * 1000.0),
+            requests: 1,
+            transferred_bytes: bytes.len() as u64,
+            render_ms: elapsed.as_millis() as u64,
+            ..Default::default()
+        };
+
+        let (html, guessed) = decode(&bytes, content_type.as_deref());
+        let title = title(&html).unwrap_or_default();
+
+        let mut warnings = Vec::new();
+        if guessed {
+            warnings.push(Warning::with_url(WarningKind::GuessedEncoding, &url, "no charset declared, decoded as UTF-8"));
+        }
+
+        Ok(StaticPage { requested_url, status, url, title, html, headers, warnings, timings })
+    }
+}
+
+/// Decode the body with the charset of the Content-Type header,
+/// or else the one declared by a `<meta>` tag, defaulting to UTF-8.
+/// Also tells whether the encoding had to be guessed
+fn decode(bytes: &[u8], content_type: Option<&str>) -> (String, bool) {
+
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]).to_ascii_lowercase();
+
+    let label = content_type
+        .and_then(|content_type| charset_label(&content_type.to_ascii_lowercase()))
+        .or_else(|| charset_label(&head));
+
+    let encoding = label.and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()));
+
+    let decoded = encoding.unwrap_or(encoding_rs::UTF_8).decode(bytes).0.into_owned();
+    (decoded, encoding.is_none())
+}
+
+fn charset_label(text: &str) -> Option<String> {
+    let start = text.find("charset=")? + "charset=".len();
+    let label = text[start..]
+        .trim_start_matches(['"', '\''])
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .next()?;
+    (!label.is_empty()).then(|| label.to_string())
+}
+
+fn title(html: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("title").unwrap();
+    let title = document.select(&selector).next()?.text().collect::<String>();
+    Some(title.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+impl PageRenderer for StaticFetcher {
+
+    fn render<'a>(&'a self, url: &'a str, options: &'a ScrapeOptions) -> BoxFuture<'a, Result<Arc<dyn RenderedPage>>> {
+        async move {
+            let page: Arc<dyn RenderedPage> = Arc::new(self.fetch(url, options).await?);
+            Ok(page)
+        }.boxed()
+    }
+}
+
+impl RenderedPage for StaticPage {
+
+    fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    fn requested_url(&self) -> Option<String> {
+        Some(self.requested_url.clone())
+    }
+
+    fn status(&self) -> Option<u16> {
+        Some(self.status)
+    }
+
+    fn title(&self) -> anyhow::Result<String> {
+        Ok(self.title.clone())
+    }
+
+    fn html(&self) -> anyhow::Result<String> {
+        Ok(self.html.clone())
+    }
+
+    fn supports_pdf(&self) -> bool {
+        false
+    }
+
+    fn print_to_pdf(&self, _options: &PrintOptions) -> anyhow::Result<Vec<u8>> {
+        anyhow::bail!("PDF output needs a browser, the page was fetched without one")
+    }
+
+    fn timings(&self) -> Timings {
+        self.timings.clone()
+    }
+
+    fn warnings(&self) -> Vec<Warning> {
+        self.warnings.clone()
+    }
+
+    fn response_headers(&self) -> Vec<(String, String)> {
+        self.headers.clone()
+    }
+
+    fn cookies(&self) -> Vec<Cookie> {
+        let Ok(url) = Url::parse(&self.url) else {
+            return Vec::new();
+        };
+        self.headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("set-cookie"))
+            .filter_map(|(_, value)| Cookie::parse(value, &url))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::testing::FixtureServer;
+
+    #[tokio::test]
+    async fn fetches_page_without_browser() {
+        let server = FixtureServer::with_article().unwrap();
+        let page = StaticFetcher::new().unwrap().render(&server.url("/article.html"), &ScrapeOptions::default()).await.unwrap();
+        assert_eq!(page.title().unwrap(), "Fixture article");
+        assert!(!page.supports_pdf());
+    }
+
+    #[tokio::test]
+    async fn fetches_each_page_with_the_options_of_its_site() {
+        let server = FixtureServer::with_article().unwrap();
+        let config = Config::parse(r#"
+            [domains."127.0.0.1"]
+            auth = { cookie = "session=first" }
+
+            [domains."localhost"]
+            auth = { cookie = "session=second" }
+        "#).unwrap();
+        // built for the first site, as a batch does
+        let first = server.url("/article.html");
+        let fetcher = StaticFetcher::with_options(config.options_for(&first, &ScrapeOptions::default())).unwrap();
+
+        for url in [first.clone(), first.replace("127.0.0.1", "localhost")] {
+            fetcher.render(&url, &config.options_for(&url, &ScrapeOptions::default())).await.unwrap();
+        }
+        assert_eq!(server.request_header("/article.html", "cookie"), [Some("session=first".to_string()), Some("session=second".to_string())]);
+    }
+
+    #[test]
+    fn decodes_declared_charset() {
+        let latin1 = b"<meta charset=\"iso-8859-1\"><p>caf\xe9</p>";
+        assert_eq!(decode(latin1, None), ("<meta charset=\"iso-8859-1\"><p>café</p>".to_string(), false));
+        assert!(decode("café".as_bytes(), Some("text/html; charset=utf-8")).0.contains("café"));
+        assert!(decode("café".as_bytes(), None).1);
+    }
+}
@@ -0,0 +1,96 @@
+use scraper::{ElementRef, Html, Node, Selector};
+use crate::dom;
+
+/// Prepare `html` for the markdown conversion, which keeps little of figures:
+/// images without alt text take the caption of their figure, or their `title`/`aria-label`,
+/// figures become plain blocks and their captions paragraphs right after the images
+pub fn for_markdown(html: &str) -> String {
+
+    let mut document = Html::parse_document(html);
+    let figure_selector = Selector::parse("figure").unwrap();
+    let caption_selector = Selector::parse("figcaption").unwrap();
+    let img_selector = Selector::parse("img").unwrap();
+
+    let mut alts = Vec::new();
+    let mut captions = Vec::new();
+    let mut figures = Vec::new();
+    for figure in document.select(&figure_selector) {
+        let images = figure.select(&img_selector).collect::<Vec<_>>();
+        if images.is_empty() {
+            continue;
+        }
+        figures.push(figure.id());
+        let caption = figure.select(&caption_selector).next();
+        let text = caption.map(|caption| normalize(&caption.text().collect::<String>())).unwrap_or_default();
+        let untitled = images.iter().filter(|image| alt(image).is_none()).map(|image| image.id()).collect::<Vec<_>>();
+        if !text.is_empty() {
+            alts.extend(untitled.iter().map(|&id| (id, text.clone())));
+        }
+        if let Some(caption) = caption {
+            // a caption now carried by the only image of the figure would be repeated
+            let repeated = images.len() == 1 && untitled.len() == 1;
+            let replacement = match text.is_empty() || repeated {
+                true => String::new(),
+                false => format!("<p><em>{}</em></p>", escape(&text)),
+            };
+            captions.push((caption.id(), replacement));
+        }
+    }
+    for image in document.select(&img_selector) {
+        if alt(&image).is_none() && !alts.iter().any(|(id, _)| *id == image.id())
+            && let Some(label) = image.attr("title").or_else(|| image.attr("aria-label")).map(normalize).filter(|label| !label.is_empty()) {
+            alts.push((image.id(), label));
+        }
+    }
+
+    if figures.is_empty() && alts.is_empty() {
+        return html.to_string();
+    }
+    for (id, text) in alts {
+        if let Some(mut node) = document.tree.get_mut(id) && let Node::Element(element) = node.value() {
+            dom::set_attribute(&mut element.attrs, "alt", &text);
+        }
+    }
+    for (id, replacement) in captions {
+        dom::replace_with_html(&mut document, id, &replacement);
+    }
+    for id in figures {
+        if let Some(mut node) = document.tree.get_mut(id) && let Node::Element(element) = node.value() {
+            element.name.local = "div".into();
+        }
+    }
+    document.html()
+}
+
+fn alt<'a>(image: &ElementRef<'a>) -> Option<&'a str> {
+    image.attr("alt").filter(|alt| !alt.trim().is_empty())
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gives_images_their_captions() {
+        let html = r#"<html><body>
+            <figure><img src="/map.png"><figcaption>Map of the
+                valley &amp; rivers</figcaption></figure>
+            <figure><img src="/a.png" alt="Before"><img src="/b.png"><figcaption>Restoration</figcaption></figure>
+            <img src="/logo.png" title="Company logo">
+        </body></html>"#;
+        let prepared = for_markdown(html);
+
+        assert!(prepared.contains(r#"<div><img src="/map.png" alt="Map of the valley &amp; rivers"></div>"#));
+        assert!(prepared.contains(r#"<img alt="Before" src="/a.png"><img src="/b.png" alt="Restoration"><p><em>Restoration</em></p>"#));
+        assert!(prepared.contains(r#"<img src="/logo.png" title="Company logo" alt="Company logo">"#));
+        assert!(!prepared.contains("figure"));
+    }
+}
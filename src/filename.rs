@@ -0,0 +1,202 @@
+use std::collections::HashSet;
+use icu_normalizer::DecomposingNormalizerBorrowed;
+use crate::pinyin;
+
+/// How titles are transliterated when deriving directory and file names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Transliteration {
+    /// Keep the title as is, unicode included
+    #[default]
+    Unicode,
+    /// Fold accented latin, greek and cyrillic letters to ASCII, drop the rest
+    Ascii,
+    /// ASCII folding plus toneless pinyin for CJK ideographs
+    Pinyin,
+}
+
+/// Transliterate a page title according to `profile`
+pub fn transliterate(title: &str, profile: Transliteration) -> String {
+    match profile {
+        Transliteration::Unicode => title.to_string(),
+        Transliteration::Ascii => ascii_fold(title),
+        Transliteration::Pinyin => ascii_fold(&pinyin::romanize(title)),
+    }
+}
+
+fn ascii_fold(text: &str) -> String {
+
+    let decomposed = DecomposingNormalizerBorrowed::new_nfd().normalize(text);
+
+    let mut folded = String::with_capacity(decomposed.len());
+    for c in decomposed.chars() {
+        if c.is_ascii() {
+            folded.push(c);
+        } else if is_combining_mark(c) {
+            continue;
+        } else if let Some(ascii) = fold_char(c) {
+            folded.push_str(ascii);
+        } else if ('\u{FF01}'..='\u{FF5E}').contains(&c) {
+            // fullwidth forms mirror the ASCII block
+            folded.extend(char::from_u32(c as u32 - 0xFEE0));
+        } else if c.is_whitespace() {
+            folded.push(' ');
+        }
+    }
+
+    // dropped characters may leave runs of spaces behind
+    folded.split(' ').filter(|s| !s.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+fn is_combining_mark(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+fn fold_char(c: char) -> Option<&'static str> {
+    let ascii = match c {
+        // latin letters without a canonical decomposition
+        'ß' => "ss", 'Æ' => "AE", 'æ' => "ae", 'Œ' => "OE", 'œ' => "oe",
+        'Ø' => "O", 'ø' => "o", 'Đ' => "D", 'đ' => "d", 'Ł' => "L", 'ł' => "l",
+        'Þ' => "Th", 'þ' => "th", 'Ð' => "D", 'ð' => "d", 'ı' => "i", 'Ħ' => "H", 'ħ' => "h",
+        // punctuation
+        '‘' | '’' | '‚' | '′' => "'", '“' | '”' | '„' | '″' | '«' | '»' => "\"",
+        '–' | '—' | '‐' | '‑' | '−' => "-", '…' => "...", '·' | '•' => "-",
+        '\u{00A0}' | '\u{3000}' => " ",
+        '。' => ".", '、' => ",", '「' | '」' | '『' | '』' => "\"", '【' | '〔' => "[", '】' | '〕' => "]",
+        '《' | '〈' => "<", '》' | '〉' => ">",
+        // greek
+        'Α' => "A", 'α' => "a", 'Β' => "V", 'β' => "v", 'Γ' => "G", 'γ' => "g", 'Δ' => "D", 'δ' => "d",
+        'Ε' => "E", 'ε' => "e", 'Ζ' => "Z", 'ζ' => "z", 'Η' => "I", 'η' => "i", 'Θ' => "Th", 'θ' => "th",
+        'Ι' => "I", 'ι' => "i", 'Κ' => "K", 'κ' => "k", 'Λ' => "L", 'λ' => "l", 'Μ' => "M", 'μ' => "m",
+        'Ν' => "N", 'ν' => "n", 'Ξ' => "X", 'ξ' => "x", 'Ο' => "O", 'ο' => "o", 'Π' => "P", 'π' => "p",
+        'Ρ' => "R", 'ρ' => "r", 'Σ' => "S", 'σ' | 'ς' => "s", 'Τ' => "T", 'τ' => "t", 'Υ' => "Y", 'υ' => "y",
+        'Φ' => "F", 'φ' => "f", 'Χ' => "Ch", 'χ' => "ch", 'Ψ' => "Ps", 'ψ' => "ps", 'Ω' => "O", 'ω' => "o",
+        // cyrillic
+        'А' => "A", 'а' => "a", 'Б' => "B", 'б' => "b", 'В' => "V", 'в' => "v", 'Г' => "G", 'г' => "g",
+        'Д' => "D", 'д' => "d", 'Е' => "E", 'е' => "e", 'Ж' => "Zh", 'ж' => "zh", 'З' => "Z", 'з' => "z",
+        'И' => "I", 'и' => "i", 'К' => "K", 'к' => "k", 'Л' => "L", 'л' => "l", 'М' => "M", 'м' => "m",
+        'Н' => "N", 'н' => "n", 'О' => "O", 'о' => "o", 'П' => "P", 'п' => "p", 'Р' => "R", 'р' => "r",
+        'С' => "S", 'с' => "s", 'Т' => "T", 'т' => "t", 'У' => "U", 'у' => "u", 'Ф' => "F", 'ф' => "f",
+        'Х' => "Kh", 'х' => "kh", 'Ц' => "Ts", 'ц' => "ts", 'Ч' => "Ch", 'ч' => "ch", 'Ш' => "Sh", 'ш' => "sh",
+        'Щ' => "Shch", 'щ' => "shch", 'Ъ' | 'ъ' | 'Ь' | 'ь' => "", 'Ы' => "Y", 'ы' => "y", 'Э' => "E", 'э' => "e",
+        'Ю' => "Yu", 'ю' => "yu", 'Я' => "Ya", 'я' => "ya", 'Є' => "Ye", 'є' => "ye", 'І' => "I", 'і' => "i",
+        'Ґ' => "G", 'ґ' => "g",
+        _ => return None,
+    };
+    Some(ascii)
+}
+
+const MAX_FILENAME_LEN: usize = 120;
+
+/// Names Windows refuses for files, whatever their extension
+pub(crate) const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Make a URL path segment safe to use as a file name on every platform:
+/// percent-decoded, without query string, path separators or characters
+/// illegal on Windows, and not longer than `MAX_FILENAME_LEN` bytes
+pub fn sanitize(name: &str) -> String {
+
+    let name = name.split(['?', '#']).next().unwrap_or_default();
+    let decoded = percent_encoding::percent_decode_str(name).decode_utf8_lossy();
+
+    let mut sanitized = decoded
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim_matches(|c: char| c == '.' || c.is_whitespace())
+        .to_string();
+
+    let (stem, _) = split_extension(&sanitized);
+    if RESERVED_NAMES.iter().any(|reserved| stem.eq_ignore_ascii_case(reserved)) {
+        sanitized = format!("_{sanitized}");
+    }
+
+    if sanitized.len() > MAX_FILENAME_LEN {
+        let (stem, extension) = split_extension(&sanitized);
+        let mut end = MAX_FILENAME_LEN.saturating_sub(extension.len() + 1).min(stem.len());
+        while !stem.is_char_boundary(end) {
+            end -= 1;
+        }
+        sanitized = match extension {
+            "" => stem[..end].to_string(),
+            extension => format!("{}.{extension}", &stem[..end]),
+        };
+    }
+
+    if sanitized.is_empty() { "file".to_string() } else { sanitized }
+}
+
+/// Characters escaped when a file name is used in a relative URL
+const URL_PATH: &percent_encoding::AsciiSet = &percent_encoding::CONTROLS
+    .add(b' ').add(b'"').add(b'#').add(b'%').add(b'\'').add(b'(').add(b')').add(b'<').add(b'>').add(b'?').add(b'`');
+
+/// `name` percent-encoded for use as is in HTML and markdown links
+pub fn to_url_path(name: &str) -> String {
+    percent_encoding::utf8_percent_encode(name, URL_PATH).to_string()
+}
+
+/// `("photo", "jpg")` for `photo.jpg`, the extension is empty when there is none
+pub fn split_extension(name: &str) -> (&str, &str) {
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() && !extension.is_empty() && extension.len() <= 5 => (stem, extension),
+        _ => (name, ""),
+    }
+}
+
+/// `name`, or `name-2`, `name-3`... when already taken, compared case-insensitively
+pub fn unique(name: &str, taken: &mut HashSet<String>) -> String {
+    let (stem, extension) = split_extension(name);
+    let mut candidate = name.to_string();
+    let mut n = 2;
+    while !taken.insert(candidate.to_lowercase()) {
+        candidate = match extension {
+            "" => format!("{stem}-{n}"),
+            extension => format!("{stem}-{n}.{extension}"),
+        };
+        n += 1;
+    }
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transliteration_profiles() {
+        let title = "École cantonale d’art — Москва";
+        assert_eq!(transliterate(title, Transliteration::Unicode), title);
+        assert_eq!(transliterate(title, Transliteration::Ascii), "Ecole cantonale d'art - Moskva");
+        assert_eq!(transliterate("中国新闻网：首页", Transliteration::Pinyin), "zhong guo xin wen wang: shou ye");
+        assert_eq!(transliterate("中国", Transliteration::Ascii), "");
+    }
+
+    #[test]
+    fn sanitizes_file_names() {
+        assert_eq!(sanitize("photo%20of%20me.jpg?w=800"), "photo of me.jpg");
+        assert_eq!(sanitize("a:b*c|d.png"), "a_b_c_d.png");
+        assert_eq!(sanitize("con.png"), "_con.png");
+        assert_eq!(sanitize("..."), "file");
+        let long = sanitize(&format!("{}.webp", "é".repeat(100)));
+        assert!(long.len() <= MAX_FILENAME_LEN && long.ends_with("é.webp"));
+    }
+
+    #[test]
+    fn numbers_colliding_names() {
+        let mut taken = HashSet::new();
+        assert_eq!(unique("main.rs", &mut taken), "main.rs");
+        assert_eq!(unique("Main.rs", &mut taken), "Main-2.rs");
+    }
+}
@@ -0,0 +1,241 @@
+use crate::markdown;
+use crate::webpage::InfoJson;
+
+/// YAML double-quoted scalar, valid whatever the text holds
+fn quote(text: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// YAML front matter of the markdown of a capture, for Obsidian, Jekyll and other static site
+/// or note tools: title, URL, capture date, authors, tags and word count, and for
+/// captures of web archives, where it was taken from and when the archive captured the page
+pub fn render(info: &InfoJson) -> String {
+
+    let mut yaml = String::from("---\n");
+    let mut field = |name: &str, value: String| yaml.push_str(&format!("{name}: {value}\n"));
+
+    field("title", quote(&info.title));
+    field("url", quote(&info.url));
+    field("date", info.date.clone());
+    if let Some(published) = &info.metadata.published {
+        field("published", quote(published));
+    }
+    match &info.metadata.authors[..] {
+        [] => {}
+        [author] => field("author", quote(author)),
+        authors => field("author", format!("[{}]", authors.iter().map(|author| quote(author)).collect::<Vec<_>>().join(", "))),
+    }
+    if let Some(description) = &info.metadata.description {
+        field("description", quote(description));
+    }
+    field("tags", format!("[{}]", info.metadata.keywords.iter().map(|tag| quote(tag)).collect::<Vec<_>>().join(", ")));
+    field("word_count", info.nb_md_words.to_string());
+    if !info.capture_source.is_live() {
+        let source = serde_json::to_value(info.capture_source).ok().and_then(|source| source.as_str().map(str::to_string)).unwrap_or_default();
+        field("capture_source", source);
+        if let Some(date) = &info.original_capture_date {
+            field("original_capture_date", quote(date));
+        }
+    }
+
+    yaml.push_str("---\n\n");
+    yaml
+}
+
+/// Header of the document in the pandoc `format`: YAML front matter for markdown, keywords for
+/// org, a header for AsciiDoc, a field list for reStructuredText, none for the other formats
+pub fn render_for(info: &InfoJson, format: &str) -> String {
+    match format {
+        format if markdown::is_markdown(format) => render(info),
+        "org" => org(info),
+        "asciidoc" | "asciidoctor" => asciidoc(info),
+        "rst" => rst(info),
+        _ => String::new(),
+    }
+}
+
+/// `document` without the header `render_for` started it with in `format`, if it has one
+pub fn strip<'a>(document: &'a str, format: &str) -> &'a str {
+    let body = match format {
+        "org" if document.starts_with("#+") => {
+            let header = document.split_inclusive('\n').take_while(|line| line.starts_with("#+")).map(str::len).sum::<usize>();
+            Some(&document[header..])
+        }
+        "asciidoc" | "asciidoctor" if document.starts_with("= ") => document.split_once("\n\n").map(|(_, body)| body),
+        "rst" if document.starts_with(':') => document.split_once("\n\n").map(|(_, body)| body),
+        format if markdown::is_markdown(format) && document.starts_with("---\n") => document[3..].split_once("\n---\n").map(|(_, body)| body),
+        _ => None,
+    };
+    body.map_or(document, |body| body.strip_prefix('\n').unwrap_or(body))
+}
+
+/// Value on a single line, for the headers whose values end with the line
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `#+title:`, `#+author:`, `#+filetags:` ... keywords of an org document
+fn org(info: &InfoJson) -> String {
+
+    let mut org = String::new();
+    let mut keyword = |name: &str, value: &str| org.push_str(&format!("#+{name}: {}\n", single_line(value)));
+
+    keyword("title", &info.title);
+    if !info.metadata.authors.is_empty() {
+        keyword("author", &info.metadata.authors.join(", "));
+    }
+    keyword("date", &info.date);
+    keyword("url", &info.url);
+    if let Some(published) = &info.metadata.published {
+        keyword("published", published);
+    }
+    if let Some(description) = &info.metadata.description {
+        keyword("description", description);
+    }
+    // org tags are words, `:`-separated
+    let tags = info.metadata.keywords.iter()
+        .map(|tag| single_line(tag).replace(' ', "_").replace(':', ""))
+        .filter(|tag| !tag.is_empty())
+        .collect::<Vec<_>>();
+    if !tags.is_empty() {
+        keyword("filetags", &format!(":{}:", tags.join(":")));
+    }
+    keyword("word_count", &info.nb_md_words.to_string());
+
+    org.push('\n');
+    org
+}
+
+/// `= Title` line, author line and attributes of an AsciiDoc document
+fn asciidoc(info: &InfoJson) -> String {
+
+    let mut header = format!("= {}\n", single_line(&info.title));
+    if !info.metadata.authors.is_empty() {
+        header.push_str(&format!("{}\n", info.metadata.authors.iter().map(|author| single_line(author)).collect::<Vec<_>>().join("; ")));
+    }
+    let mut attribute = |name: &str, value: &str| header.push_str(&format!(":{name}: {}\n", single_line(value)));
+
+    attribute("revdate", &info.date);
+    attribute("url", &info.url);
+    if let Some(published) = &info.metadata.published {
+        attribute("published", published);
+    }
+    if let Some(description) = &info.metadata.description {
+        attribute("description", description);
+    }
+    if !info.metadata.keywords.is_empty() {
+        attribute("keywords", &info.metadata.keywords.join(", "));
+    }
+    attribute("word-count", &info.nb_md_words.to_string());
+
+    header.push('\n');
+    header
+}
+
+/// `:title:`, `:author:`, `:tags:` ... field list starting a reStructuredText document,
+/// which docutils reads as its bibliographic fields
+fn rst(info: &InfoJson) -> String {
+
+    let mut rst = String::new();
+    let mut field = |name: &str, value: &str| rst.push_str(&format!(":{name}: {}\n", single_line(value)));
+
+    field("title", &info.title);
+    if !info.metadata.authors.is_empty() {
+        field("authors", &info.metadata.authors.join("; "));
+    }
+    field("date", &info.date);
+    field("url", &info.url);
+    if let Some(published) = &info.metadata.published {
+        field("published", published);
+    }
+    if let Some(description) = &info.metadata.description {
+        field("description", description);
+    }
+    if !info.metadata.keywords.is_empty() {
+        field("tags", &info.metadata.keywords.join(", "));
+    }
+    field("word_count", &info.nb_md_words.to_string());
+
+    rst.push('\n');
+    rst
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::Metadata;
+    use crate::snapshot::CaptureSource;
+
+    #[test]
+    fn renders_yaml_front_matter() {
+        let info = InfoJson {
+            url: "https://example.com/post".to_string(),
+            title: "Rust: \"fearless\" concurrency".to_string(),
+            date: "2024-05-01".to_string(),
+            nb_md_words: 1200,
+            metadata: Metadata { authors: vec!["Ada Lovelace".to_string()], keywords: vec!["rust".to_string(), "c#".to_string()], ..Default::default() },
+            capture_source: CaptureSource::Wayback,
+            original_capture_date: Some("2020-01-31T12:34:56Z".to_string()),
+            ..InfoJson::default()
+        };
+        assert_eq!(render(&info), "---\n\
+            title: \"Rust: \\\"fearless\\\" concurrency\"\n\
+            url: \"https://example.com/post\"\n\
+            date: 2024-05-01\n\
+            author: \"Ada Lovelace\"\n\
+            tags: [\"rust\", \"c#\"]\n\
+            word_count: 1200\n\
+            capture_source: wayback\n\
+            original_capture_date: \"2020-01-31T12:34:56Z\"\n\
+            ---\n\n");
+    }
+
+    #[test]
+    fn renders_org_asciidoc_and_rst_headers() {
+        let info = InfoJson {
+            url: "https://example.com/post".to_string(),
+            title: "Rust\nconcurrency".to_string(),
+            date: "2024-05-01".to_string(),
+            nb_md_words: 1200,
+            metadata: Metadata { authors: vec!["Ada Lovelace".to_string(), "Alan Turing".to_string()], keywords: vec!["rust lang".to_string(), "c#".to_string()], ..Default::default() },
+            ..InfoJson::default()
+        };
+        assert_eq!(render_for(&info, "org"), "#+title: Rust concurrency\n\
+            #+author: Ada Lovelace, Alan Turing\n\
+            #+date: 2024-05-01\n\
+            #+url: https://example.com/post\n\
+            #+filetags: :rust_lang:c#:\n\
+            #+word_count: 1200\n\n");
+        assert_eq!(render_for(&info, "asciidoc"), "= Rust concurrency\n\
+            Ada Lovelace; Alan Turing\n\
+            :revdate: 2024-05-01\n\
+            :url: https://example.com/post\n\
+            :keywords: rust lang, c#\n\
+            :word-count: 1200\n\n");
+        assert_eq!(render_for(&info, "rst"), ":title: Rust concurrency\n\
+            :authors: Ada Lovelace; Alan Turing\n\
+            :date: 2024-05-01\n\
+            :url: https://example.com/post\n\
+            :tags: rust lang, c#\n\
+            :word_count: 1200\n\n");
+        assert!(render_for(&info, "gfm").starts_with("---\n"));
+        assert_eq!(render_for(&info, "plain"), "");
+        assert_eq!(strip("---\nText\n", "plain"), "---\nText\n");
+        for format in ["org", "asciidoc", "rst", "gfm"] {
+            assert_eq!(strip(&(render_for(&info, format) + "Text\n"), format), "Text\n");
+        }
+        assert_eq!(strip("Text\n", "gfm"), "Text\n");
+    }
+}
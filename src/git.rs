@@ -0,0 +1,113 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum GitError {
+    #[error("IO error, is git installed? {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("git {command} failed: {stderr}")]
+    CommandFailed { command: String, stderr: String },
+    #[error("{0} is not inside the repository")]
+    OutsideRepository(PathBuf),
+}
+
+pub type Result<T> = std::result::Result<T, GitError>;
+
+/// Git repository receiving one commit per capture, so the history of a page
+/// can be followed with `git log` and `git diff`
+pub struct GitRepo {
+    root: PathBuf,
+}
+
+impl GitRepo {
+
+    /// Author of the commits when git has no identity configured
+    const FALLBACK_NAME: &str = "webpage_scraper";
+    const FALLBACK_EMAIL: &str = "webpage_scraper@localhost";
+
+    /// Repository at `root`, initialized when it isn't one yet
+    pub fn open_or_init(root: &Path) -> Result<Self> {
+        std::fs::create_dir_all(root)?;
+        let root = root.canonicalize()?;
+        if !root.join(".git").exists() {
+            run(&root, &["init", "--quiet"])?;
+        }
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Stage everything under `directory` and commit it with the URL and date of the capture.
+    /// Returns the hash of the commit, `None` when the page didn't change since the last one
+    pub fn commit_capture(&self, directory: &Path, url: &str, date: &str) -> Result<Option<String>> {
+
+        let directory = directory.canonicalize()?;
+        let relative = directory
+            .strip_prefix(&self.root)
+            .map_err(|_| GitError::OutsideRepository(directory.clone()))?
+            .to_string_lossy()
+            .to_string();
+        let pathspec = if relative.is_empty() { ".".to_string() } else { relative };
+
+        run(&self.root, &["add", "--all", "--", &pathspec])?;
+
+        let staged = git(&self.root, &["diff", "--cached", "--quiet", "--", &pathspec])?;
+        if staged.status.success() {
+            return Ok(None);
+        }
+
+        let subject = format!("Capture {url}");
+        let body = format!("URL: {url}\nDate: {date}");
+        let mut commit = Vec::new();
+        if !git(&self.root, &["config", "user.email"])?.status.success() {
+            commit.extend(["-c".to_string(), format!("user.name={}", Self::FALLBACK_NAME)]);
+            commit.extend(["-c".to_string(), format!("user.email={}", Self::FALLBACK_EMAIL)]);
+        }
+        commit.extend(["commit", "--quiet", "-m", &subject, "-m", &body, "--", &pathspec].map(str::to_string));
+        run(&self.root, &commit.iter().map(String::as_str).collect::<Vec<_>>())?;
+
+        let head = run(&self.root, &["rev-parse", "HEAD"])?;
+        Ok(Some(String::from_utf8_lossy(&head.stdout).trim().to_string()))
+    }
+}
+
+fn git(root: &Path, args: &[&str]) -> Result<Output> {
+    Ok(Command::new("git").arg("-C").arg(root).args(args).output()?)
+}
+
+/// `git` erroring out on failure
+fn run(root: &Path, args: &[&str]) -> Result<Output> {
+    let output = git(root, args)?;
+    if !output.status.success() {
+        return Err(GitError::CommandFailed {
+            command: args.join(" "),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commits_only_changed_captures() {
+        let root = tempfile::tempdir().unwrap();
+        let repo = GitRepo::open_or_init(&root.path().join("archive")).unwrap();
+        let capture = repo.root().join("terms");
+        std::fs::create_dir(&capture).unwrap();
+
+        std::fs::write(capture.join("terms.md"), "# Terms\n").unwrap();
+        let first = repo.commit_capture(&capture, "https://example.com/terms", "2024-05-01").unwrap();
+        assert!(first.is_some());
+        assert_eq!(repo.commit_capture(&capture, "https://example.com/terms", "2024-05-02").unwrap(), None);
+
+        std::fs::write(capture.join("terms.md"), "# Terms\n\nNew clause.\n").unwrap();
+        let second = repo.commit_capture(&capture, "https://example.com/terms", "2024-05-03").unwrap();
+        assert!(second.is_some() && second != first);
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use html5ever::driver::{self, ParseOpts};
+use html5ever::tendril::TendrilSink;
+use html5ever::tokenizer::TokenizerOpts;
+use html5ever::tree_builder::{QuirksMode, TreeBuilderOpts};
+use regex::Regex;
+use scraper::{Html, HtmlTreeSink};
+use serde::{Deserialize, Serialize};
+
+/// Elements whose end tag may be left out, never reported as unclosed
+const OPTIONAL_END_TAGS: &[&str] = &[
+    "p", "li", "dt", "dd", "tr", "td", "th", "thead", "tbody", "tfoot", "option", "optgroup",
+    "rt", "rp", "colgroup", "caption", "html", "head", "body",
+];
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+];
+
+/// Most distinct parse errors listed, the others are only counted
+const MAX_PARSE_ERRORS: usize = 100;
+
+/// What the HTML parser had to recover from in the captured HTML, saved as `html_report.json`
+/// to explain markdown conversions coming out scrambled
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HtmlReport {
+    /// `no-quirks`, `limited-quirks` or `quirks`, the latter when the doctype is missing or outdated
+    pub quirks_mode: String,
+    pub nb_parse_errors: usize,
+    /// Parse errors by message, most frequent first
+    pub parse_errors: Vec<ParseError>,
+    /// Opened elements without a matching end tag, by element name
+    pub unclosed_tags: BTreeMap<String, usize>,
+    /// End tags without a matching opened element, by element name
+    pub stray_end_tags: BTreeMap<String, usize>,
+    pub encoding: EncodingReport,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseError {
+    pub message: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncodingReport {
+    /// Charset of the `Content-Type` response header
+    pub header_charset: Option<String>,
+    /// Charset of the `<meta charset>` or `<meta http-equiv="Content-Type">` tag
+    pub meta_charset: Option<String>,
+    /// U+FFFD characters, left by bytes invalid in the charset the page was decoded with
+    pub replacement_characters: usize,
+    /// Sequences like `Ã©` or `â€™`, UTF-8 decoded with a single byte charset
+    pub mojibake: usize,
+}
+
+impl HtmlReport {
+
+    pub const FILENAME: &str = "html_report.json";
+
+    /// Report on `html`, with the response `headers` of the page for its declared charset
+    pub fn new(html: &str, headers: &[(String, String)]) -> Self {
+
+        let opts = ParseOpts {
+            tokenizer: TokenizerOpts { exact_errors: true, ..Default::default() },
+            tree_builder: TreeBuilderOpts { exact_errors: true, ..Default::default() },
+        };
+        let document = driver::parse_document(HtmlTreeSink::new(Html::new_document()), opts).one(html);
+
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for error in &document.errors {
+            *counts.entry(error.to_string()).or_default() += 1;
+        }
+        let mut parse_errors = counts.into_iter().map(|(message, count)| ParseError { message, count }).collect::<Vec<_>>();
+        parse_errors.sort_by_key(|error| std::cmp::Reverse(error.count));
+        parse_errors.truncate(MAX_PARSE_ERRORS);
+
+        let quirks_mode = match document.quirks_mode {
+            QuirksMode::NoQuirks => "no-quirks",
+            QuirksMode::LimitedQuirks => "limited-quirks",
+            QuirksMode::Quirks => "quirks",
+        };
+
+        let (unclosed_tags, stray_end_tags) = tag_balance(html);
+
+        Self {
+            quirks_mode: quirks_mode.to_string(),
+            nb_parse_errors: document.errors.len(),
+            parse_errors,
+            unclosed_tags,
+            stray_end_tags,
+            encoding: EncodingReport::new(html, headers),
+        }
+    }
+
+    /// Whether the parser met no problem at all
+    pub fn is_clean(&self) -> bool {
+        self.nb_parse_errors == 0
+            && self.unclosed_tags.is_empty()
+            && self.stray_end_tags.is_empty()
+            && self.encoding.replacement_characters == 0
+            && self.encoding.mojibake == 0
+    }
+}
+
+impl EncodingReport {
+    fn new(html: &str, headers: &[(String, String)]) -> Self {
+
+        static CHARSET: OnceLock<Regex> = OnceLock::new();
+        static MOJIBAKE: OnceLock<Regex> = OnceLock::new();
+        let charset = CHARSET.get_or_init(|| Regex::new(r#"(?i)charset\s*=\s*["']?([\w.:-]+)"#).unwrap());
+        let mojibake = MOJIBAKE.get_or_init(|| Regex::new("Ã[\u{80}-\u{BF}]|â€[\u{80}-\u{BF}™œ“”˜¦]").unwrap());
+
+        let header_charset = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+            .and_then(|(_, value)| Some(charset.captures(value)?[1].to_ascii_lowercase()));
+
+        // the charset has to be declared within the first 1024 bytes
+        let head = &html[..html.floor_char_boundary(1024)];
+        let meta_charset = head
+            .match_indices("<meta")
+            .filter_map(|(start, _)| {
+                let tag = &head[start..start + head[start..].find('>')?];
+                Some(charset.captures(tag)?[1].to_ascii_lowercase())
+            })
+            .next();
+
+        Self {
+            header_charset,
+            meta_charset,
+            replacement_characters: html.matches('\u{FFFD}').count(),
+            mojibake: mojibake.find_iter(html).count(),
+        }
+    }
+}
+
+/// Count the start and end tags of each element outside of comments, scripts and styles
+fn tag_balance(html: &str) -> (BTreeMap<String, usize>, BTreeMap<String, usize>) {
+
+    static SKIPPED: OnceLock<Regex> = OnceLock::new();
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    let skipped = SKIPPED.get_or_init(|| {
+        Regex::new(r"(?is)<!--.*?-->|<script\b[^>]*>.*?</script\s*>|<style\b[^>]*>.*?</style\s*>|<template\b[^>]*>.*?</template\s*>").unwrap()
+    });
+    let tag = TAG.get_or_init(|| Regex::new(r"<(/?)([a-zA-Z][a-zA-Z0-9-]*)[^>]*?(/?)>").unwrap());
+
+    let html = skipped.replace_all(html, "");
+    let mut open: BTreeMap<String, usize> = BTreeMap::new();
+    let mut stray: BTreeMap<String, usize> = BTreeMap::new();
+
+    for captures in tag.captures_iter(&html) {
+        let name = captures[2].to_ascii_lowercase();
+        if VOID_ELEMENTS.contains(&name.as_str()) || OPTIONAL_END_TAGS.contains(&name.as_str()) {
+            continue;
+        }
+        match (&captures[1], &captures[3]) {
+            // self-closing, only valid on foreign elements but never left open
+            (_, "/") => {}
+            ("/", _) => {
+                let count = open.entry(name.clone()).or_default();
+                if *count > 0 {
+                    *count -= 1;
+                } else {
+                    *stray.entry(name).or_default() += 1;
+                }
+            }
+            _ => *open.entry(name).or_default() += 1,
+        }
+    }
+
+    let unclosed = open.into_iter().filter(|(_, count)| *count > 0).collect();
+    (unclosed, stray)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_parser_recoveries() {
+        let html = "<html><head><meta charset=\"ISO-8859-1\"></head><body><div><p>Caf\u{FFFD} dâ€™Ã©tÃ©<b>bold</div></span>\
+            <script>if (a < b) document.write('<div>')</script></body></html>";
+        let headers = [("Content-Type".to_string(), "text/html; charset=UTF-8".to_string())];
+        let report = HtmlReport::new(html, &headers);
+
+        assert_eq!(report.quirks_mode, "quirks");
+        assert!(report.nb_parse_errors > 0);
+        assert_eq!(report.unclosed_tags, BTreeMap::from([("b".to_string(), 1)]));
+        assert_eq!(report.stray_end_tags, BTreeMap::from([("span".to_string(), 1)]));
+        assert_eq!(report.encoding.header_charset.as_deref(), Some("utf-8"));
+        assert_eq!(report.encoding.meta_charset.as_deref(), Some("iso-8859-1"));
+        assert_eq!(report.encoding.replacement_characters, 1);
+        assert_eq!(report.encoding.mojibake, 3);
+
+        let clean = HtmlReport::new("<!DOCTYPE html><html><head><title>t</title></head><body><p>Hi</body></html>", &[]);
+        assert!(clean.is_clean(), "{clean:?}");
+    }
+}
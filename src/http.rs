@@ -0,0 +1,592 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::Semaphore;
+use url::Url;
+use crate::images::Images;
+use crate::recording::{Recording, RecordedResponse};
+use crate::options::SiteAuth;
+use crate::config::matches_domain;
+use crate::warc::{Exchange, Journal};
+use crate::robots::Robots;
+use crate::ratelimit::{RateLimiter, RequestKind};
+
+/// Where the HTTP requests made during a scrape (images, ...) go
+#[derive(Debug, Clone, Default)]
+pub enum NetworkMode {
+    /// Plain network requests
+    #[default]
+    Live,
+    /// Network requests whose responses are kept for a `Recording`
+    Record,
+    /// No network at all, responses come from a previous `Recording`
+    Replay(Arc<Recording>),
+}
+
+#[derive(Error, Debug)]
+pub enum HttpError {
+    #[error("ReqwestError: {0}")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("HTTP status {status} for {url}")]
+    StatusError { url: String, status: u16 },
+    #[error("{0} is not part of the recording")]
+    NotRecorded(String),
+    #[error("invalid recorded response for {url}: {reason}")]
+    InvalidRecording { url: String, reason: String },
+    #[error("{0} is disallowed by robots.txt")]
+    DisallowedByRobots(String),
+    #[error("{url} is over the limit of {max_bytes} bytes")]
+    TooLarge { url: String, max_bytes: u64 },
+}
+
+pub type Result<T> = std::result::Result<T, HttpError>;
+
+/// How often and how patiently failed requests are tried again. Only transient
+/// failures are retried: timeouts, connection errors, 429 and 5xx gateway statuses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt, 0 disables retrying
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each of the next ones
+    pub base_delay: Duration,
+    /// Upper bound of a delay, `Retry-After` included
+    pub max_delay: Duration,
+    /// Time an attempt may take, body included, before it fails as a timeout
+    pub timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_retries: 3, base_delay: Duration::from_millis(500), max_delay: Duration::from_secs(10), timeout: Duration::from_secs(30) }
+    }
+}
+
+impl RetryPolicy {
+
+    fn is_retryable(status: u16) -> bool {
+        matches!(status, 408 | 429 | 500 | 502 | 503 | 504)
+    }
+
+    /// Delay before retry number `attempt` (from 0): the exponential delay with up to
+    /// half of it taken off at random, so clients don't retry in lockstep.
+    /// A `Retry-After` asked by the server wins when longer
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        let exponential = self.base_delay.saturating_mul(2u32.saturating_pow(attempt)).min(self.max_delay);
+        let jitter = Duration::from_millis(fastrand::u64(0..=exponential.as_millis() as u64 / 2));
+        (exponential - jitter).max(retry_after.unwrap_or_default().min(self.max_delay))
+    }
+}
+
+/// Cookie of the browsing session, sent along with the asset requests it applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// Host the cookie was set for, also covering its subdomains unless `host_only`
+    pub domain: String,
+    /// Set without a `Domain`, only sent to that very host
+    pub host_only: bool,
+    pub path: String,
+    /// Only sent over HTTPS
+    pub secure: bool,
+}
+
+impl Cookie {
+
+    pub fn applies_to(&self, url: &Url) -> bool {
+        let domain = self.domain.trim_start_matches('.');
+        url.host_str().is_some_and(|host| match self.host_only {
+            true => host.trim_end_matches('.').eq_ignore_ascii_case(domain),
+            false => matches_domain(host, domain),
+        })
+            && Self::matches_path(url.path(), &self.path)
+            && (!self.secure || url.scheme() == "https")
+    }
+
+    /// Whether the cookie `path` covers `request_path`, as RFC 6265 path-matches:
+    /// `/admin` covers `/admin` and `/admin/users`, not `/administrator`
+    fn matches_path(request_path: &str, path: &str) -> bool {
+        request_path.strip_prefix(path).is_some_and(|rest| rest.is_empty() || path.ends_with('/') || rest.starts_with('/'))
+    }
+
+    /// Cookie set by a `Set-Cookie` header of the response to `url`
+    pub fn parse(header: &str, url: &Url) -> Option<Self> {
+        let mut attributes = header.split(';').map(str::trim);
+        let (name, value) = attributes.next()?.split_once('=')?;
+        let mut cookie = Self {
+            name: name.trim().to_string(),
+            value: value.trim().to_string(),
+            domain: url.host_str()?.to_string(),
+            host_only: true,
+            path: "/".to_string(),
+            secure: false,
+        };
+        for attribute in attributes {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            match key.to_ascii_lowercase().as_str() {
+                // only the host of the response or a parent domain of it, not a bare TLD
+                "domain" if !value.is_empty() => {
+                    let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                    if domain.contains('.') && matches_domain(&cookie.domain, &domain) {
+                        cookie.host_only = domain.eq_ignore_ascii_case(&cookie.domain);
+                        cookie.domain = domain;
+                    }
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_string(),
+                "secure" => cookie.secure = true,
+                _ => {}
+            }
+        }
+        (!cookie.name.is_empty()).then_some(cookie)
+    }
+}
+
+#[derive(Clone)]
+pub struct Response {
+    /// URL after redirections
+    pub url: Url,
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Clone)]
+enum Mode {
+    Live,
+    Record(Arc<Mutex<Vec<RecordedResponse>>>),
+    Replay(Arc<HashMap<String, RecordedResponse>>),
+}
+
+/// HTTP client shared by the asset downloaders, able to record and replay responses
+#[derive(Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    mode: Mode,
+    auth: Option<SiteAuth>,
+    /// Bounds the requests in flight across every clone of the client
+    permits: Arc<Semaphore>,
+    retry: RetryPolicy,
+    /// Responses already received elsewhere (by the browser), served instead of requesting them again
+    preloaded: Arc<HashMap<String, Response>>,
+    /// Page the assets belong to, sent as `Referer` for hotlink-protected hosts
+    referer: Option<Url>,
+    /// Cookies of the page's browsing session
+    cookies: Arc<Vec<Cookie>>,
+    /// Keeps the exchanges for a WARC file of the capture
+    journal: Option<Journal>,
+    /// Requests sent over the network by every clone of the client
+    traffic: Arc<Traffic>,
+    /// Rules the network requests have to follow
+    robots: Option<Arc<Robots>>,
+    /// Spaces out the network requests, along with those of other clients
+    rate_limit: Option<Arc<RateLimiter>>,
+    /// Between two requests to the same host, plus a random jitter up to the second one
+    host_delay: (Duration, Duration),
+}
+
+#[derive(Debug, Default)]
+struct Traffic {
+    requests: AtomicUsize,
+    bytes: AtomicU64,
+}
+
+impl HttpClient {
+
+    pub fn new(network: &NetworkMode) -> Result<Self> {
+
+        let client = reqwest::Client::builder()
+            .user_agent(Images::USER_AGENT)
+            .connect_timeout(Self::CONNECT_TIMEOUT)
+            .build()?;
+
+        let mode = match network {
+            NetworkMode::Live => Mode::Live,
+            NetworkMode::Record => Mode::Record(Arc::new(Mutex::new(Vec::new()))),
+            NetworkMode::Replay(recording) => Mode::Replay(Arc::new(
+                recording.responses.iter().map(|r| (r.url.clone(), r.clone())).collect()
+            )),
+        };
+
+        Ok(Self { client, mode, auth: None, permits: Arc::new(Semaphore::new(Self::DEFAULT_CONCURRENCY)), retry: RetryPolicy::default(), preloaded: Arc::default(), referer: None, cookies: Arc::default(), journal: None, traffic: Arc::default(), robots: None, rate_limit: None, host_delay: Default::default() })
+    }
+
+    /// Requests in flight at once unless `with_concurrency` says otherwise
+    pub const DEFAULT_CONCURRENCY: usize = 8;
+
+    /// Time to connect to a server, the whole request is bounded by `RetryPolicy::timeout`
+    const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Allow at most `concurrency` requests in flight at once, the others wait their turn
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.permits = Arc::new(Semaphore::new(concurrency.max(1)));
+        self
+    }
+
+    /// Take the requests in flight from `permits`, shared with other clients, instead
+    /// of its own
+    pub fn with_permits(mut self, permits: Option<Arc<Semaphore>>) -> Self {
+        if let Some(permits) = permits {
+            self.permits = permits;
+        }
+        self
+    }
+
+    /// Send `auth` credentials along with the requests to its domain
+    pub fn with_auth(mut self, auth: Option<SiteAuth>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Answer the requests to the URLs of `responses` with them, without any network request
+    pub fn with_preloaded(mut self, responses: Vec<Response>) -> Self {
+        self.preloaded = Arc::new(responses.into_iter().map(|response| (response.url.to_string(), response)).collect());
+        self
+    }
+
+    /// Send `page_url` as the `Referer` of the requests, as a browser would:
+    /// only its origin to other sites, and nothing from HTTPS to HTTP
+    pub fn with_referer(mut self, page_url: &str) -> Self {
+        self.referer = Url::parse(page_url).ok();
+        self
+    }
+
+    /// Send the `cookies` of the browsing session along with the requests they apply to
+    pub fn with_cookies(mut self, cookies: Vec<Cookie>) -> Self {
+        self.cookies = Arc::new(cookies);
+        self
+    }
+
+    /// Keep every request and its response in `journal`
+    pub fn with_journal(mut self, journal: Option<Journal>) -> Self {
+        self.journal = journal;
+        self
+    }
+
+    /// Only send the requests `robots` allows, the others fail with `HttpError::DisallowedByRobots`
+    pub fn with_robots(mut self, robots: Option<Arc<Robots>>) -> Self {
+        self.robots = robots;
+        self
+    }
+
+    /// Wait for a slot of `rate_limit` before each network request, `delay` plus a random
+    /// `jitter` after the previous download from the same host
+    pub fn with_rate_limit(mut self, rate_limit: Arc<RateLimiter>, delay: Duration, jitter: Duration) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self.host_delay = (delay, jitter);
+        self
+    }
+
+    /// Wait for the turn of `url` among the downloads of its host, spaced out by the
+    /// `Crawl-delay` of the site when longer than the delay of `with_rate_limit`
+    async fn wait_turn(&self, url: &Url) {
+        if let Some(rate_limit) = &self.rate_limit {
+            let (mut delay, jitter) = self.host_delay;
+            if let Some(crawl_delay) = self.robots.as_ref().and_then(|robots| robots.crawl_delay(url)) {
+                delay = delay.max(crawl_delay);
+            }
+            tokio::time::sleep(rate_limit.reserve(RequestKind::Download, url.host_str().unwrap_or_default(), delay, jitter)).await;
+        }
+    }
+
+    /// Credentials, cookies and referer sent along with a request to `url`
+    fn headers(&self, url: &Url) -> Vec<(&'static str, String)> {
+
+        let mut headers = Vec::new();
+        let mut cookies = self.cookies.iter().filter(|cookie| cookie.applies_to(url)).map(|cookie| (cookie.name.clone(), cookie.value.clone())).collect::<Vec<_>>();
+        if let Some(auth) = self.auth.as_ref().filter(|auth| auth.applies_to(url)) {
+            headers.extend(auth.headers().into_iter().filter(|(name, _)| *name != "Cookie"));
+            // already in the session when the browser was given them
+            for (name, value) in auth.cookies() {
+                if !cookies.iter().any(|(existing, _)| *existing == name) {
+                    cookies.push((name, value));
+                }
+            }
+        }
+        if !cookies.is_empty() {
+            headers.push(("Cookie", cookies.iter().map(|(name, value)| format!("{name}={value}")).collect::<Vec<_>>().join("; ")));
+        }
+
+        if let Some(referer) = &self.referer
+            && !(referer.scheme() == "https" && url.scheme() == "http") {
+            let referer = match referer.origin() == url.origin() {
+                true => {
+                    let mut referer = referer.clone();
+                    referer.set_fragment(None);
+                    referer.to_string()
+                }
+                false => format!("{}/", referer.origin().ascii_serialization()),
+            };
+            headers.push(("Referer", referer));
+        }
+        headers
+    }
+
+    /// GET `url`, retrying transient failures, error statuses (>= 400)
+    /// are turned into `HttpError::StatusError`
+    pub async fn get(&self, url: &Url) -> Result<Response> {
+        self.get_limited(url, None).await
+    }
+
+    /// GET `url` like `get`, failing with `HttpError::TooLarge` once the body is over `max_bytes`,
+    /// without receiving the rest of it
+    pub async fn get_limited(&self, url: &Url, max_bytes: Option<u64>) -> Result<Response> {
+
+        let response = match &self.mode {
+            _ if let Some(preloaded) = self.preloaded.get(url.as_str()) => preloaded.clone(),
+            Mode::Replay(responses) => {
+                let recorded = responses
+                    .get(url.as_str())
+                    .ok_or_else(|| HttpError::NotRecorded(url.to_string()))?;
+                recorded.to_response()?
+            }
+            Mode::Live | Mode::Record(_) => {
+                if let Some(robots) = &self.robots && !robots.allows(url).await {
+                    return Err(HttpError::DisallowedByRobots(url.to_string()));
+                }
+                let mut attempt = 0;
+                loop {
+                    let sent = self.send(url, max_bytes).await;
+                    // `Some` when the failure is worth another attempt
+                    let transient = match &sent {
+                        Ok((response, retry_after)) if RetryPolicy::is_retryable(response.status) => Some(*retry_after),
+                        Err(e) if e.is_timeout() || e.is_connect() => Some(None),
+                        _ => None,
+                    };
+                    match transient {
+                        Some(retry_after) if attempt < self.retry.max_retries => {
+                            tracing::debug!(url = %url, attempt = attempt + 1, "retrying");
+                            tokio::time::sleep(self.retry.delay(attempt, retry_after)).await;
+                            attempt += 1;
+                        }
+                        _ => break sent?.0,
+                    }
+                }
+            }
+        };
+
+        if let Some(max_bytes) = max_bytes && response.body.len() as u64 > max_bytes {
+            return Err(HttpError::TooLarge { url: url.to_string(), max_bytes });
+        }
+        if let Mode::Record(recorded) = &self.mode {
+            recorded.lock().unwrap().push(RecordedResponse::new(url.as_str(), &response));
+        }
+        if let Some(journal) = &self.journal {
+            let mut headers = vec![("User-Agent".to_string(), Images::USER_AGENT.to_string())];
+            headers.extend(self.headers(url).into_iter().map(|(name, value)| (name.to_string(), value)));
+            journal.push(Exchange::new(url, headers, &response));
+        }
+
+        if response.status >= 400 {
+            return Err(HttpError::StatusError { url: url.to_string(), status: response.status });
+        }
+
+        Ok(response)
+    }
+
+    /// One attempt at GET `url`, along with the `Retry-After` delay asked by the server.
+    /// The body stops being received one byte past `max_bytes`
+    async fn send(&self, url: &Url, max_bytes: Option<u64>) -> reqwest::Result<(Response, Option<Duration>)> {
+
+        self.wait_turn(url).await;
+        let _permit = self.permits.acquire().await.expect("the semaphore is never closed");
+        let mut request = self.client.get(url.clone()).timeout(self.retry.timeout);
+        for (name, value) in self.headers(url) {
+            request = request.header(name, value);
+        }
+        let mut response = request.send().await?;
+        let final_url = response.url().clone();
+        let status = response.status().as_u16();
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        // only the delay-seconds form, HTTP dates fall back to the backoff
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_secs);
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if let Some(max_bytes) = max_bytes && body.len() as u64 > max_bytes {
+                body.truncate(max_bytes as usize + 1);
+                break;
+            }
+        }
+        self.traffic.requests.fetch_add(1, Ordering::Relaxed);
+        self.traffic.bytes.fetch_add(body.len() as u64, Ordering::Relaxed);
+        Ok((Response { url: final_url, status, content_type, body }, retry_after))
+    }
+
+    /// Size announced by a HEAD request to `url`, `None` when the server doesn't tell.
+    /// Replays answer with the size of the recorded body
+    pub async fn content_length(&self, url: &Url) -> Result<Option<u64>> {
+
+        if let Some(preloaded) = self.preloaded.get(url.as_str()) {
+            return Ok(Some(preloaded.body.len() as u64));
+        }
+        if let Mode::Replay(responses) = &self.mode {
+            return responses.get(url.as_str()).map(|recorded| Ok(recorded.to_response()?.body.len() as u64)).transpose();
+        }
+        if let Some(robots) = &self.robots && !robots.allows(url).await {
+            return Err(HttpError::DisallowedByRobots(url.to_string()));
+        }
+
+        self.wait_turn(url).await;
+        let _permit = self.permits.acquire().await.expect("the semaphore is never closed");
+        let mut request = self.client.head(url.clone()).timeout(self.retry.timeout);
+        for (name, value) in self.headers(url) {
+            request = request.header(name, value);
+        }
+        let response = request.send().await?;
+        self.traffic.requests.fetch_add(1, Ordering::Relaxed);
+        if !response.status().is_success() {
+            return Ok(None);
+        }
+        Ok(response
+            .headers()
+            .get(reqwest::header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok()))
+    }
+
+    /// Requests sent over the network so far, preloaded and replayed responses left out,
+    /// and the bytes of their bodies
+    pub fn traffic(&self) -> (usize, u64) {
+        (self.traffic.requests.load(Ordering::Relaxed), self.traffic.bytes.load(Ordering::Relaxed))
+    }
+
+    /// Responses received so far, empty unless recording
+    pub fn recorded(&self) -> Vec<RecordedResponse> {
+        match &self.mode {
+            Mode::Record(recorded) => recorded.lock().unwrap().clone(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FixtureServer;
+
+    #[test]
+    fn sends_session_cookies_and_referer() {
+        let page = Url::parse("https://news.example.com/2024/story.html#top").unwrap();
+        let session = Cookie::parse("session=abc; Domain=.example.com; Path=/; Secure; HttpOnly", &page).unwrap();
+        let scoped = Cookie::parse("draft=1; Path=/admin", &page).unwrap();
+        let client = HttpClient::new(&NetworkMode::Live).unwrap().with_referer(page.as_str()).with_cookies(vec![session, scoped]);
+
+        let same_site = client.headers(&Url::parse("https://news.example.com/images/a.jpg").unwrap());
+        assert_eq!(same_site, [("Cookie", "session=abc".to_string()), ("Referer", "https://news.example.com/2024/story.html".to_string())]);
+
+        let cdn = client.headers(&Url::parse("https://cdn.example.com/a.jpg").unwrap());
+        assert_eq!(cdn, [("Cookie", "session=abc".to_string()), ("Referer", "https://news.example.com/".to_string())]);
+
+        assert!(client.headers(&Url::parse("http://tracker.net/pixel.gif").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn scopes_cookies_as_browsers_do() {
+        let page = Url::parse("https://news.example.com/admin/index.html").unwrap();
+        let url = |url: &str| Url::parse(url).unwrap();
+
+        let host_only = Cookie::parse("draft=1; Path=/admin", &page).unwrap();
+        assert!(host_only.applies_to(&url("https://news.example.com/admin")));
+        assert!(host_only.applies_to(&url("https://news.example.com/admin/users")));
+        assert!(!host_only.applies_to(&url("https://news.example.com/administrator")));
+        assert!(!host_only.applies_to(&url("https://www.news.example.com/admin/")));
+
+        for header in ["session=abc; Domain=bank.com", "session=abc; Domain=com", "session=abc; Domain=.com"] {
+            let cookie = Cookie::parse(header, &page).unwrap();
+            assert_eq!((cookie.domain.as_str(), cookie.host_only), ("news.example.com", true), "{header}");
+        }
+        let parent = Cookie::parse("session=abc; Domain=Example.com", &page).unwrap();
+        assert!(parent.applies_to(&url("https://cdn.example.com/")) && !parent.host_only);
+    }
+
+    #[test]
+    fn backoff_doubles_with_jitter() {
+        let policy = RetryPolicy { max_retries: 5, base_delay: Duration::from_millis(100), max_delay: Duration::from_secs(1), ..Default::default() };
+        for _ in 0..20 {
+            let third = policy.delay(2, None);
+            assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+        }
+        assert!(policy.delay(10, None) <= Duration::from_secs(1));
+        assert_eq!(policy.delay(0, Some(Duration::from_secs(60))), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn retries_transient_statuses_only() {
+        let server = FixtureServer::new().unwrap();
+        server.route_with_status("/busy.png", 503, "text/plain", "busy");
+        server.route_with_status("/gone.png", 404, "text/plain", "gone");
+
+        let retry = RetryPolicy { max_retries: 2, base_delay: Duration::from_millis(1), max_delay: Duration::from_millis(5), ..Default::default() };
+        let client = HttpClient::new(&NetworkMode::Live).unwrap().with_retry(retry);
+        for path in ["/busy.png", "/gone.png"] {
+            assert!(client.get(&Url::parse(&server.url(path)).unwrap()).await.is_err());
+        }
+
+        let count = |path: &str| server.requests().iter().filter(|request| request.contains(path)).count();
+        assert_eq!((count("/busy.png"), count("/gone.png")), (3, 1));
+    }
+
+    #[tokio::test]
+    async fn times_out_and_retries_a_server_that_never_answers() {
+        // accepts the connections and never answers them
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/hanging.png", listener.local_addr().unwrap())).unwrap();
+        let connections = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let accepted = connections.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                accepted.lock().unwrap().push(stream);
+            }
+        });
+
+        let retry = RetryPolicy { max_retries: 1, base_delay: Duration::from_millis(1), max_delay: Duration::from_millis(5), timeout: Duration::from_millis(200) };
+        let client = HttpClient::new(&NetworkMode::Live).unwrap().with_retry(retry);
+        let started = std::time::Instant::now();
+        assert!(matches!(client.get(&url).await, Err(HttpError::ReqwestError(e)) if e.is_timeout()));
+        assert!(started.elapsed() >= Duration::from_millis(400) && started.elapsed() < Duration::from_secs(5));
+        assert_eq!(connections.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn serves_preloaded_responses_without_requests() {
+        let server = FixtureServer::new().unwrap();
+        let url = Url::parse(&server.url("/images/seen.png")).unwrap();
+        let preloaded = Response { url: url.clone(), status: 200, content_type: Some("image/png".to_string()), body: b"displayed".to_vec() };
+        let client = HttpClient::new(&NetworkMode::Record).unwrap().with_preloaded(vec![preloaded]);
+
+        assert_eq!(client.get(&url).await.unwrap().body, b"displayed");
+        assert_eq!(client.content_length(&url).await.unwrap(), Some(9));
+        assert!(server.requests().is_empty());
+        assert_eq!(client.recorded().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn waits_for_the_shared_rate_limit() {
+        let server = FixtureServer::new().unwrap();
+        server.route("/a.png", "image/png", "a");
+        let rate_limit = Arc::new(RateLimiter::new().with_max_rps(10.0));
+        let client = HttpClient::new(&NetworkMode::Live).unwrap().with_rate_limit(rate_limit, Duration::ZERO, Duration::ZERO);
+
+        let started = std::time::Instant::now();
+        for _ in 0..3 {
+            client.get(&Url::parse(&server.url("/a.png")).unwrap()).await.unwrap();
+        }
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+}
@@ -3,12 +3,78 @@ use url::{Url, ParseError};
 use reqwest;
 use base64::Engine;
 use futures::future::join_all;
-use scraper::{Html, Selector};
+use futures::stream::{self, StreamExt};
+use scraper::{ElementRef, Html, Selector};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use serde::{Serialize, Deserialize};
+use crate::hash;
+use crate::dimensions;
+use crate::exif;
+use crate::filename;
+use crate::http::{HttpClient, HttpError, NetworkMode};
+use crate::css;
+use crate::convert::{self, ImageFormat};
+use crate::srcset::{self, Candidate, SrcsetStrategy};
+use crate::options::{ImageBudget, ImageFilter, ScrapeOptions, Thumbnails};
+use crate::progress::{DownloadEvent, Progress, ProgressEvent};
+use crate::warnings::{Warning, WarningKind, Warnings};
 
 pub struct Image {
     pub image_bytes: Vec<u8>,
     pub filename: String,
+    /// Where the image was downloaded from, `None` for inline `data:` images
+    pub url: Option<String>,
+    /// Hex SHA-256 of `image_bytes`
+    pub sha256: String,
+    /// Other occurrences of the same bytes in the page, not written to disk
+    pub aliases: Vec<Alias>,
+    /// `alt` text of the element the image comes from
+    pub alt: Option<String>,
+    /// Width and height in pixels, when the format is known
+    pub dimensions: Option<(u32, u32)>,
+    /// Downscaled copy saved in `images/thumbs/` under the same name
+    pub thumbnail: Option<Vec<u8>>,
+    /// EXIF, XMP and IPTC metadata were removed from the downloaded bytes
+    pub metadata_stripped: bool,
+    /// `data:` URLs this image was decoded from, pointed at the local copy like downloaded URLs
+    pub(crate) inline_srcs: Vec<String>,
+}
+
+/// Image of the page whose bytes duplicate an image kept on disk
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Alias {
+    pub filename: String,
+    pub url: Option<String>,
+}
+
+/// Entry of `images/manifest.json`, used to verify the archived images later on
+/// and to map them back to their context on the page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub filename: String,
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    pub sha256: String,
+    pub bytes: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<Alias>,
+    /// Path of the thumbnail, relative to `images/`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<String>,
+    /// Only the thumbnail was saved, the original can be restored from `url`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub omitted: bool,
+    /// `sha256` and `bytes` are those of the image once its metadata was removed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub metadata_stripped: bool,
 }
 
 #[derive(Error, Debug)]
@@ -17,6 +83,8 @@ pub enum ImagesError {
     UrlError(#[from] ParseError),
     #[error("ReqwestError: {0}")]
     ReqwestError(#[from] reqwest::Error),
+    #[error("HttpError: {0}")]
+    HttpError(#[from] HttpError),
     #[error("Base64Error when parsing image: {0}")]
     Base64Error(#[from] base64::DecodeError),
     #[error("Base64CommaError")]
@@ -24,17 +92,57 @@ pub enum ImagesError {
     #[error("I/O error: {0}")]
     IOError(#[from] std::io::Error),
     #[error("srcset error")]
-    SrcsetError
+    SrcsetError,
+    #[error("JSON conversion error: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("image budget of the page exhausted")]
+    BudgetExhausted,
+    #[error("image left out by the size filter")]
+    Filtered,
+    #[error("not an image: {0}")]
+    NotAnImage(String),
 }
 
 pub type Result<T> = std::result::Result<T, ImagesError>;
 
 impl Image {
 
-    async fn handle_image_src(src: &str, base_url: &Url, client: &reqwest::Client) -> Result<Self> {
+    pub fn new(image_bytes: Vec<u8>, filename: String, url: Option<String>) -> Self {
+        let sha256 = hash::sha256_hex(&image_bytes);
+        let dimensions = dimensions::sniff(&image_bytes);
+        Self { image_bytes, filename, url, sha256, aliases: Vec::new(), alt: None, dimensions, thumbnail: None, metadata_stripped: false, inline_srcs: Vec::new() }
+    }
+
+    fn with_alt(mut self, alt: Option<&str>) -> Self {
+        self.alt = alt.map(str::trim).filter(|alt| !alt.is_empty()).map(str::to_string);
+        self
+    }
+
+    /// Same image holding `bytes`, converted to `format`
+    fn converted(self, bytes: Vec<u8>, format: ImageFormat) -> Self {
+        let (stem, _) = filename::split_extension(&self.filename);
+        let filename = format!("{stem}.{}", format.extension());
+        Self { alt: self.alt, inline_srcs: self.inline_srcs, ..Self::new(bytes, filename, self.url) }
+    }
+
+    /// Same image without its JPEG metadata, as is when it has none
+    fn stripped(self) -> Self {
+        match exif::strip_jpeg(&self.image_bytes) {
+            Some(bytes) => Self { alt: self.alt, inline_srcs: self.inline_srcs, metadata_stripped: true, ..Self::new(bytes, self.filename, self.url) },
+            None => self,
+        }
+    }
+
+    fn alias(&self) -> Alias {
+        Alias { filename: self.filename.clone(), url: self.url.clone() }
+    }
+
+    async fn handle_image_src(src: &str, base_url: &Url, client: &HttpClient) -> Result<Self> {
         // Case 1: data:image/...;base64,...
         if src.starts_with("data:image") {
-            return Self::parse_data_url(src);
+            let mut image = Self::parse_data_url(src)?;
+            image.inline_srcs.push(base_url.join(src)?.to_string());
+            return Ok(image);
         }
 
         // Case 2: normal URL or relative URL
@@ -44,48 +152,53 @@ impl Image {
         Image::fetch_image(client, &img_url).await
     }
 
-    async fn handle_image_srcset(srcset: &str, client: &reqwest::Client) -> Result<Self> {
-        
-        let img_url = Image::extract_last_image_url(srcset).ok_or(ImagesError::SrcsetError)?;
-        let img_url = Url::parse(img_url)?;
+    async fn handle_image_srcset(srcset: &str, base_url: &Url, strategy: SrcsetStrategy, max_bytes: Option<u64>, client: &HttpClient) -> Result<Self> {
 
-        Image::fetch_image(client, &img_url).await
+        let candidates = srcset::parse(srcset);
+        let candidate = match max_bytes {
+            Some(max_bytes) => Self::probe_srcset(&candidates, base_url, strategy, max_bytes, client).await,
+            None => srcset::select(&candidates, strategy),
+        };
+        let candidate = candidate.ok_or(ImagesError::SrcsetError)?;
+
+        Image::handle_image_src(&candidate.url, base_url, client).await
     }
 
-    async fn fetch_image(client: &reqwest::Client, img_url: &Url) -> Result<Self> {
+    /// Largest candidate, up to the one `strategy` selects, announcing at most `max_bytes`
+    /// to a HEAD request. The smallest candidate when none does
+    async fn probe_srcset<'a>(candidates: &'a [Candidate], base_url: &Url, strategy: SrcsetStrategy, max_bytes: u64, client: &HttpClient) -> Option<&'a Candidate> {
 
-        let response = client
-            .get(img_url.clone())
-            .send()
-            .await?
-            .error_for_status()?;
+        let fallbacks = srcset::select_with_fallbacks(candidates, strategy);
+        for candidate in &fallbacks {
+            let Ok(url) = base_url.join(&candidate.url) else { continue };
+            let size = match url.scheme() {
+                "data" => Some(candidate.url.len() as u64),
+                _ => client.content_length(&url).await.ok().flatten(),
+            };
+            if size.is_some_and(|size| size <= max_bytes) {
+                return Some(candidate);
+            }
+        }
+        fallbacks.last().copied()
+    }
 
-        let bytes = response.bytes().await?.to_vec();
+    async fn fetch_image(client: &HttpClient, img_url: &Url) -> Result<Self> {
+
+        let response = client.get(img_url).await?;
+        // error and login pages answered with a 200 status
+        if dimensions::extension(&response.body).is_none() {
+            let content_type = response.content_type.as_deref().unwrap_or("no content type");
+            return Err(ImagesError::NotAnImage(format!("{content_type} response of {} bytes", response.body.len())));
+        }
 
         let filename = img_url
             .path_segments()
-            .and_then(|s| s.last())
+            .and_then(|mut s| s.next_back())
             .filter(|s| !s.is_empty())
             .unwrap_or("image");
+        let filename = with_image_extension(&filename::sanitize(filename), &response.body, response.content_type.as_deref());
 
-        Ok(Image {
-            image_bytes: bytes,
-            filename: filename.to_string(),
-        })
-    }
-
-    fn extract_last_image_url(srcset: &str) -> Option<&str> {
-        srcset
-            .split(',')
-            .map(|s| s.trim())
-            .filter_map(|entry| entry.split_whitespace().next())
-            .filter(|url| {
-                url.ends_with(".jpg")
-                    || url.ends_with(".jpeg")
-                    || url.ends_with(".png")
-                    || url.ends_with(".webp")
-            })
-            .last()
+        Ok(Image::new(response.body, filename, Some(img_url.to_string())))
     }
 
     fn parse_data_url(src: &str) -> Result<Self> {
@@ -99,102 +212,659 @@ impl Image {
             .split(';')
             .next()
             .and_then(|m| m.split('/').nth(1))
+            .map(|subtype| subtype.trim_end_matches("+xml"))
             .unwrap_or("img");
 
-        let bytes = base64::engine::general_purpose::STANDARD
-            .decode(data)?;
+        // SVGs are often inlined as percent-encoded text rather than base64
+        let bytes = match meta.ends_with(";base64") {
+            true => base64::engine::general_purpose::STANDARD.decode(data.trim())?,
+            false => percent_encoding::percent_decode_str(data).collect(),
+        };
 
-        Ok(Image {
-            image_bytes: bytes,
-            filename: format!("inline.{}", extension),
-        })
+        Ok(Image::new(bytes, filename::sanitize(&format!("inline.{}", extension)), None))
     }
 
-    async fn write_to_disk(&self, directory: &Path) -> Result<()> {
-        let output_path = directory.join(&self.filename);
-        std::fs::write(output_path, &self.image_bytes)?;
+    async fn write_to_disk(&self, directory: &Path, keep_original: bool) -> Result<()> {
+        if let Some(thumbnail) = &self.thumbnail {
+            let output_path = directory.join(Images::THUMBS_DIRECTORY).join(&self.filename);
+            tokio::fs::write(output_path, thumbnail).await?;
+        }
+        if keep_original || self.thumbnail.is_none() {
+            let output_path = directory.join(&self.filename);
+            tokio::fs::write(output_path, &self.image_bytes).await?;
+        }
         Ok(())
     }
 
 }
 
+/// Replace the `blob:` URLs of the images of `html` with `data:` URLs of their content, read
+/// by `read_blob`. Blobs only exist within the page that created them and can't be downloaded
+pub fn inline_blobs(html: &str, read_blob: impl Fn(&str) -> Option<String>, warnings: &Warnings) -> String {
+
+    let document = Html::parse_document(html);
+    let selector = Selector::parse(r#"img[src^="blob:"]"#).unwrap();
+    let blobs = document.select(&selector).filter_map(|img| img.attr("src")).collect::<HashSet<_>>();
+
+    let mut inlined = html.to_string();
+    for blob in blobs {
+        match read_blob(blob) {
+            // blob URLs embed a random UUID, they can't be mistaken for anything else
+            Some(data) => inlined = inlined.replace(blob, &data),
+            None => warnings.push(Warning::with_url(WarningKind::SkippedImage, blob, "blob: image that couldn't be read from the page")),
+        }
+    }
+    inlined
+}
+
+/// Extensions image files are saved with
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "avif", "svg", "bmp", "ico", "tif", "tiff", "apng", "jxl"];
 
-pub struct Images ( pub Vec<Image> );
+/// `filename` with the extension of the format of `bytes`, or else of the `content_type` header,
+/// when it has none of an image: `/photo?id=1` and `/image.php` are saved as `photo.jpg` and `image.jpg`
+fn with_image_extension(filename: &str, bytes: &[u8], content_type: Option<&str>) -> String {
+
+    let (stem, extension) = filename::split_extension(filename);
+    if IMAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()) {
+        return filename.to_string();
+    }
+
+    let mime = content_type
+        .and_then(|content_type| content_type.split(';').next())
+        .map(|mime| mime.trim().to_ascii_lowercase());
+    let from_mime = mime.as_deref().and_then(|mime| match mime.strip_prefix("image/")? {
+        "jpeg" | "pjpeg" => Some("jpg"),
+        "svg+xml" => Some("svg"),
+        "x-icon" | "vnd.microsoft.icon" => Some("ico"),
+        subtype => IMAGE_EXTENSIONS.iter().copied().find(|extension| *extension == subtype),
+    });
+
+    match dimensions::extension(bytes).or(from_mime) {
+        Some(detected) => format!("{stem}.{detected}"),
+        None => filename.to_string(),
+    }
+}
+
+#[derive(Default)]
+pub struct Images {
+    images: Vec<Image>,
+    /// Images left out once the download budget ran out
+    skipped: Vec<SkippedImage>,
+    /// Downloads whose bytes aren't an image
+    rejected: Vec<SkippedImage>,
+    /// Images that couldn't be downloaded, rejected ones included
+    failures: Vec<SkippedImage>,
+    /// Originals having a thumbnail aren't written to disk
+    thumbnails_only: bool,
+}
+
+/// Image of the page that wasn't downloaded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedImage {
+    /// URL, `srcset` or shortened `data:` URL of the image
+    pub src: String,
+    pub reason: String,
+}
+
+/// Content of `images/manifest.json`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub images: Vec<ManifestEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<SkippedImage>,
+    /// Downloads discarded because their bytes aren't an image
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rejected: Vec<SkippedImage>,
+}
+
+impl Manifest {
+
+    /// Read a manifest, also accepting the bare list of images written by earlier versions
+    pub fn load(path: &Path) -> Result<Self> {
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Stored {
+            Manifest(Manifest),
+            Images(Vec<ManifestEntry>),
+        }
+
+        Ok(match serde_json::from_str(&std::fs::read_to_string(path)?)? {
+            Stored::Manifest(manifest) => manifest,
+            Stored::Images(images) => Manifest { images, ..Default::default() },
+        })
+    }
+}
+
+/// Images and bytes downloaded so far for a page, against the limits of an `ImageBudget`
+struct Budget {
+    limits: ImageBudget,
+    count: AtomicUsize,
+    bytes: AtomicU64,
+    exhausted: AtomicBool,
+}
+
+impl Budget {
+
+    fn new(limits: ImageBudget) -> Self {
+        Self { limits, count: AtomicUsize::new(0), bytes: AtomicU64::new(0), exhausted: AtomicBool::new(false) }
+    }
+
+    /// Book one more image, refused for good once a limit is reached
+    fn reserve(&self) -> bool {
+        if self.exhausted.load(Ordering::SeqCst) {
+            return false;
+        }
+        let count = self.count.fetch_add(1, Ordering::SeqCst) + 1;
+        if self.limits.max_count.is_some_and(|max| count > max) {
+            self.release();
+            self.exhausted.store(true, Ordering::SeqCst);
+            return false;
+        }
+        true
+    }
+
+    /// Give back a booked image that won't be kept
+    fn release(&self) {
+        self.count.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// Count the bytes of a booked image, refused (and the image released) when they don't fit
+    fn spend(&self, bytes: u64) -> bool {
+        let total = self.bytes.fetch_add(bytes, Ordering::SeqCst) + bytes;
+        if self.limits.max_bytes.is_some_and(|max| total > max) {
+            self.bytes.fetch_sub(bytes, Ordering::SeqCst);
+            self.release();
+            self.exhausted.store(true, Ordering::SeqCst);
+            return false;
+        }
+        true
+    }
+
+    /// Run `download` if the budget allows one more image, keeping the image
+    /// only if it passes `filter` and its bytes fit in what is left of the budget
+    async fn admit(&self, download: impl Future<Output = Result<Image>>, filter: &ImageFilter) -> Result<Image> {
+        if !self.reserve() {
+            return Err(ImagesError::BudgetExhausted);
+        }
+        let image = match download.await {
+            Ok(image) if filter.accepts(image.image_bytes.len(), image.dimensions) => image,
+            result => {
+                self.release();
+                return result.and(Err(ImagesError::Filtered));
+            }
+        };
+        match self.spend(image.image_bytes.len() as u64) {
+            true => Ok(image),
+            false => Err(ImagesError::BudgetExhausted),
+        }
+    }
+}
 
 impl Images {
     
     pub async fn from(html: &str, base_url: &str) -> Result<Self> {
+        let client = HttpClient::new(&NetworkMode::Live)?;
+        Self::from_with_client(html, base_url, &client, &ScrapeOptions::default(), &Warnings::default()).await
+    }
+
+    /// Download the images of `html`, retried as `client` says. Each image that can't be fetched is skipped with a warning.
+    /// Images rejected by `options.image_filter` are left out silently, those beyond `options.image_budget`
+    /// are listed as skipped in the manifest
+    pub async fn from_with_client(html: &str, base_url: &str, client: &HttpClient, options: &ScrapeOptions, warnings: &Warnings) -> Result<Self> {
 
         let base_url = Url::parse(base_url)?;
 
         let document = Html::parse_document(html);
         let img_selector = Selector::parse("img").unwrap();
-        //let client = Client::new();
-        let client = Self::init_client()?;
 
+        let budget = Budget::new(options.image_budget);
         let mut tasks_src = Vec::new();
         let mut tasks_srcset = Vec::new();
+        let base = &base_url;
+        let strategy = options.srcset_strategy;
+        let max_bytes = options.srcset_max_bytes;
+        let budget = &budget;
+        let filter = &options.image_filter;
+        let progress = options.progress.as_ref();
+        let srcset_task = |srcset, alt| async move {
+            let download = Image::handle_image_srcset(srcset, base, strategy, max_bytes, client);
+            (srcset, Self::tracked(srcset, progress, budget.admit(download, filter)).await.map(|image| image.with_alt(alt)))
+        };
+
+        // <picture>: the first <source> matching the viewport and of a known type wins over the fallback <img>
+        let picture_selector = Selector::parse("picture").unwrap();
+        let viewport_width = match options.srcset_strategy {
+            SrcsetStrategy::Closest(width) => width,
+            _ => srcset::DEFAULT_VIEWPORT_WIDTH,
+        };
+        let mut replaced_imgs = HashSet::new();
+        // pages repeat the same images (avatars, icons...), each URL is downloaded once
+        let mut requested = HashSet::new();
+        let mut first_request = |src: &str, base: &Url| {
+            requested.insert(base.join(src.trim()).map(String::from).unwrap_or_else(|_| src.to_string()))
+        };
+        let mut requested_srcsets = HashSet::new();
+
+        for picture in document.select(&picture_selector) {
+            let source = picture
+                .children()
+                .filter_map(ElementRef::wrap)
+                .filter(|child| child.value().name() == "source")
+                .filter_map(|source| Some((source, Self::source_srcset(&source, options)?)))
+                .find(|(source, _)| {
+                    srcset::media_matches(source.attr("media"), viewport_width)
+                        && srcset::is_supported_type(source.attr("type"))
+                });
+            if let Some((_, srcset)) = source {
+                if requested_srcsets.insert(srcset.trim()) {
+                    let alt = picture.select(&img_selector).find_map(|img| img.attr("alt"));
+                    tasks_srcset.push(srcset_task(srcset, alt));
+                }
+                replaced_imgs.extend(picture.select(&img_selector).map(|img| img.id()));
+            }
+        }
 
         for element in document.select(&img_selector) {
-            if let Some(src) = element.value().attr("src") {
+            if replaced_imgs.contains(&element.id()) {
+                continue;
+            }
+            let lazy_srcs = options.lazy_attributes.src().filter_map(|name| element.attr(name)).collect::<Vec<_>>();
+            let lazy_srcsets = options.lazy_attributes.srcset().filter_map(|name| element.attr(name)).collect::<Vec<_>>();
+
+            // with a lazy loader, an inline src is only a placeholder
+            let placeholder = !(lazy_srcs.is_empty() && lazy_srcsets.is_empty())
+                && element.attr("src").is_some_and(|src| src.starts_with("data:"));
+            let srcs = element.attr("src").filter(|_| !placeholder).into_iter().chain(lazy_srcs);
+            let alt = element.attr("alt");
+
+            // blobs that couldn't be inlined were already warned about
+            let srcs = srcs.filter(|src| !src.starts_with("blob:"));
+            for src in srcs.filter(|src| first_request(src, &base_url)) {
                 // Spawn async task per image
-                let task = Image::handle_image_src(src, &base_url, &client);
+                let task = async move {
+                    let download = budget.admit(Image::handle_image_src(src, base, client), filter);
+                    (src, Self::tracked(src, progress, download).await.map(|image| image.with_alt(alt)))
+                };
 
                 tasks_src.push(task);
             }
 
-            if let Some(srcset) = element.attr("data-srcset") {
-                let task = Image::handle_image_srcset(srcset, &client);
-                tasks_srcset.push(task);
+            let srcsets = element.attr("srcset").into_iter().chain(lazy_srcsets);
+            for srcset in srcsets.filter(|srcset| requested_srcsets.insert(srcset.trim())) {
+                tasks_srcset.push(srcset_task(srcset, alt));
             }
         }
 
+        // CSS background images, with the URL their path is relative to
+        let mut backgrounds = Self::css_backgrounds(&document, &base_url, client).await;
+        backgrounds.retain(|(src, base)| first_request(src, base));
+        let tasks_css = backgrounds
+            .iter()
+            .map(|(src, base)| async move {
+                (src.as_str(), Self::tracked(src, progress, budget.admit(Image::handle_image_src(src, base, client), filter)).await)
+            });
+
+        let count = tasks_src.len() + tasks_srcset.len() + backgrounds.len();
+        tracing::debug!(url = %base_url, count, "downloading images");
+        if let Some(progress) = progress {
+            progress.emit(ProgressEvent::ImagesFound { count });
+        }
         // Run all downloads concurrently
         let results_src = join_all(tasks_src).await;
         let results_srcset = join_all(tasks_srcset).await;
+        let results_css = join_all(tasks_css).await;
 
         // Collect successful images only
-        let images = results_src
+        let mut skipped = Vec::new();
+        let mut rejected = Vec::new();
+        let mut failures = Vec::new();
+        let images: Vec<Image> = results_src
             .into_iter()
-            .chain(results_srcset.into_iter())
-            .filter_map(Result::ok)
+            .chain(results_srcset)
+            .chain(results_css)
+            .filter_map(|(src, result)| match result {
+                Ok(image) => Some(image),
+                Err(ImagesError::Filtered) => None,
+                Err(e @ ImagesError::BudgetExhausted) => {
+                    skipped.push(SkippedImage { src: Self::shorten(src), reason: e.to_string() });
+                    None
+                }
+                Err(e) => {
+                    warnings.push(Warning::with_url(WarningKind::SkippedImage, Self::shorten(src), e.to_string()));
+                    failures.push(SkippedImage { src: Self::shorten(src), reason: e.to_string() });
+                    if let ImagesError::NotAnImage(reason) = e {
+                        rejected.push(SkippedImage { src: Self::shorten(src), reason });
+                    }
+                    None
+                }
+            })
+            .collect();
+
+        if !skipped.is_empty() {
+            warnings.push(Warning::new(WarningKind::SkippedImage, format!(
+                "{} image(s) left out once the image budget was exhausted, listed in images/{}", skipped.len(), Self::MANIFEST_FILENAME
+            )));
+        }
+
+        // converted before deduplication, which resolves the name collisions of the new extensions
+        let images = match options.convert_images {
+            Some(format) => Self::convert(images, format, warnings).await,
+            None => images,
+        };
+        // after conversion, which may write JPEGs keeping the metadata of the originals
+        let images = match options.strip_metadata {
+            true => images.into_iter().map(Image::stripped).collect(),
+            false => images,
+        };
+
+        let mut images = Self { skipped, rejected, failures, ..Self::deduplicate(images) };
+        if let Some(thumbnails) = options.thumbnails {
+            images.make_thumbnails(thumbnails, warnings).await;
+        }
+
+        Ok(images)
+    }
+
+    /// Run `download` of `src`, logging how it ends and telling `progress` when it starts and how it ends
+    async fn tracked(src: &str, progress: Option<&Progress>, download: impl Future<Output = Result<Image>>) -> Result<Image> {
+        let src = Self::shorten(src);
+        if let Some(progress) = progress {
+            progress.emit(DownloadEvent::Started { src: src.clone() });
+        }
+        let result = download.await;
+        let event = match &result {
+            Ok(image) => {
+                tracing::debug!(src, bytes = image.image_bytes.len(), "image downloaded");
+                DownloadEvent::Finished { src, bytes: image.image_bytes.len() }
+            }
+            Err(e @ (ImagesError::Filtered | ImagesError::BudgetExhausted)) => {
+                tracing::debug!(src, reason = %e, "image skipped");
+                DownloadEvent::Skipped { src, reason: e.to_string() }
+            }
+            Err(e) => {
+                tracing::info!(src, error = %e, "image download failed");
+                DownloadEvent::Failed { src, error: e.to_string() }
+            }
+        };
+        if let Some(progress) = progress {
+            progress.emit(event);
+        }
+        result
+    }
+
+    /// Convert the images `convert::needs_conversion` picks to `format`, images failing to convert are kept as they are
+    async fn convert(images: Vec<Image>, format: ImageFormat, warnings: &Warnings) -> Vec<Image> {
+
+        let tasks = images.into_iter().map(|image| {
+            async move {
+                if !convert::needs_conversion(&image.image_bytes) {
+                    return image;
+                }
+                let bytes = image.image_bytes.clone();
+                let converted = tokio::task::spawn_blocking(move || convert::convert(&bytes, format)).await;
+                match converted {
+                    Ok(Ok(bytes)) => image.converted(bytes, format),
+                    Ok(Err(e)) => {
+                        let src = image.url.as_deref().unwrap_or(&image.filename);
+                        warnings.push(Warning::with_url(WarningKind::SkippedImage, Self::shorten(src), format!("kept unconverted, {e}")));
+                        image
+                    }
+                    Err(_) => image,
+                }
+            }
+        });
+        join_all(tasks).await
+    }
+
+    /// Downscale the raster images larger than the thumbnail size
+    async fn make_thumbnails(&mut self, thumbnails: Thumbnails, warnings: &Warnings) {
+
+        let max_size = thumbnails.max_size;
+        let needs_thumbnail = |image: &Image| {
+            !dimensions::is_svg(&image.image_bytes)
+                && image.dimensions.is_some_and(|(width, height)| width.max(height) > max_size)
+        };
+        let tasks = self.images.iter_mut().filter(|image| needs_thumbnail(image)).map(|image| {
+            async move {
+                let bytes = image.image_bytes.clone();
+                match tokio::task::spawn_blocking(move || convert::thumbnail(&bytes, max_size)).await {
+                    Ok(Ok(thumbnail)) => image.thumbnail = Some(thumbnail),
+                    Ok(Err(e)) => {
+                        let src = image.url.as_deref().unwrap_or(&image.filename);
+                        warnings.push(Warning::with_url(WarningKind::SkippedImage, Self::shorten(src), format!("no thumbnail made, {e}")));
+                    }
+                    Err(_) => {}
+                }
+            }
+        });
+        join_all(tasks).await;
+
+        self.thumbnails_only = !thumbnails.keep_originals;
+    }
+
+    /// Keep one image per distinct content, the others become aliases of the first one.
+    /// Different images sharing a file name get the start of their hash appended,
+    /// names are compared case-insensitively for case-insensitive file systems
+    fn deduplicate(images: Vec<Image>) -> Self {
+
+        let mut unique: Vec<Image> = Vec::new();
+        for image in images {
+            match unique.iter_mut().find(|kept| kept.sha256 == image.sha256) {
+                Some(kept) => {
+                    if kept.alt.is_none() {
+                        kept.alt = image.alt.clone();
+                    }
+                    for src in &image.inline_srcs {
+                        if !kept.inline_srcs.contains(src) {
+                            kept.inline_srcs.push(src.clone());
+                        }
+                    }
+                    let alias = image.alias();
+                    if alias != kept.alias() && !kept.aliases.contains(&alias) {
+                        kept.aliases.push(alias);
+                    }
+                }
+                None => unique.push(image),
+            }
+        }
+
+        let mut taken = HashSet::new();
+        for image in &mut unique {
+            if !taken.insert(image.filename.to_lowercase()) {
+                let (stem, extension) = filename::split_extension(&image.filename);
+                let renamed = match extension {
+                    "" => format!("{stem}-{}", &image.sha256[..8]),
+                    extension => format!("{stem}-{}.{extension}", &image.sha256[..8]),
+                };
+                taken.insert(renamed.to_lowercase());
+                image.filename = renamed;
+            }
+        }
+
+        Self { images: unique, skipped: Vec::new(), rejected: Vec::new(), failures: Vec::new(), thumbnails_only: false }
+    }
+
+    /// srcset of a `<source>`, possibly held by a lazy loading attribute
+    fn source_srcset<'a>(source: &ElementRef<'a>, options: &ScrapeOptions) -> Option<&'a str> {
+        source.attr("srcset").or_else(|| options.lazy_attributes.srcset().find_map(|name| source.attr(name)))
+    }
+
+    /// `background-image` URLs of inline `style` attributes, `<style>` blocks and linked stylesheets
+    async fn css_backgrounds(document: &Html, base_url: &Url, client: &HttpClient) -> Vec<(String, Url)> {
+
+        let style_attr_selector = Selector::parse("[style]").unwrap();
+        let style_selector = Selector::parse("style").unwrap();
+        let stylesheet_selector = Selector::parse("link[rel~=stylesheet][href]").unwrap();
+
+        let mut backgrounds: Vec<(String, Url)> = Vec::new();
+
+        let inline_css = document
+            .select(&style_attr_selector)
+            .filter_map(|element| element.value().attr("style").map(str::to_string))
+            .chain(document.select(&style_selector).map(|element| element.text().collect::<String>()));
+        for css in inline_css {
+            backgrounds.extend(css::background_image_urls(&css).into_iter().map(|url| (url, base_url.clone())));
+        }
+
+        let stylesheets = document
+            .select(&stylesheet_selector)
+            .filter_map(|element| base_url.join(element.value().attr("href")?).ok());
+        let responses = join_all(stylesheets.map(|url| async move { client.get(&url).await })).await;
+        for response in responses.into_iter().filter_map(std::result::Result::ok) {
+            let css = String::from_utf8_lossy(&response.body);
+            // stylesheet paths are relative to the stylesheet itself
+            backgrounds.extend(css::background_image_urls(&css).into_iter().map(|url| (url, response.url.clone())));
+        }
+
+        backgrounds
+    }
+
+    /// Keep inline `data:` images from flooding warnings
+    fn shorten(src: &str) -> String {
+        match src.starts_with("data:") && src.len() > 64 {
+            true => format!("{}…", src.chars().take(64).collect::<String>()),
+            false => src.to_string(),
+        }
+    }
+
+    pub(crate) const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/115.0.0.0 Safari/537.36";
+
+    /// Directory of the capture holding the images
+    pub const DIRECTORY: &str = "images";
+
+    /// Directory of `images/` holding the thumbnails
+    pub const THUMBS_DIRECTORY: &str = "thumbs";
+
+    pub const MANIFEST_FILENAME: &str = "manifest.json";
+
+    pub const URL_MAP_FILENAME: &str = "url_map.json";
+
+    /// Hashes and origins of the images as written to disk, along with the skipped ones
+    pub fn manifest(&self) -> Manifest {
+        let images = self.images
+            .iter()
+            .map(|image| ManifestEntry {
+                filename: image.filename.clone(),
+                url: image.url.clone(),
+                alt: image.alt.clone(),
+                width: image.dimensions.map(|(width, _)| width),
+                height: image.dimensions.map(|(_, height)| height),
+                sha256: image.sha256.clone(),
+                bytes: image.image_bytes.len(),
+                aliases: image.aliases.clone(),
+                thumbnail: image.thumbnail.as_ref().map(|_| format!("{}/{}", Self::THUMBS_DIRECTORY, image.filename)),
+                omitted: self.is_omitted(image),
+                metadata_stripped: image.metadata_stripped,
+            })
             .collect();
+        Manifest { images, skipped: self.skipped.clone(), rejected: self.rejected.clone() }
+    }
 
-        Ok(Self(images))
+    pub fn images(&self) -> &[Image] {
+        &self.images
     }
 
-    const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/115.0.0.0 Safari/537.36";
+    /// Images left out once the download budget ran out
+    pub fn skipped(&self) -> &[SkippedImage] {
+        &self.skipped
+    }
 
-    fn init_client() -> std::result::Result<reqwest::Client, reqwest::Error> {
-        reqwest::Client::builder()
-            .user_agent(Self::USER_AGENT)
-            .build()
+    /// Images that couldn't be downloaded, with the error of each
+    pub fn failures(&self) -> &[SkippedImage] {
+        &self.failures
+    }
+
+    /// Downloads discarded because their bytes aren't an image
+    pub fn rejected(&self) -> &[SkippedImage] {
+        &self.rejected
+    }
+
+    /// Whether only the thumbnail of `image` gets written to disk
+    fn is_omitted(&self, image: &Image) -> bool {
+        self.thumbnails_only && image.thumbnail.is_some()
+    }
+
+    /// Directory of `image` on disk, relative to the capture directory
+    fn directory(&self, image: &Image) -> String {
+        if self.is_omitted(image) {
+            format!("{}/{}", Self::DIRECTORY, Self::THUMBS_DIRECTORY)
+        } else {
+            Self::DIRECTORY.to_string()
+        }
+    }
+
+    /// Each downloaded URL, aliases included, along with the image it was saved as
+    fn urls(&self) -> impl Iterator<Item = (&String, &Image)> {
+        self.images.iter().flat_map(|image| {
+            image.url.iter().chain(image.aliases.iter().filter_map(|alias| alias.url.as_ref())).map(move |url| (url, image))
+        })
+    }
+
+    /// Relative path of the local copy of each downloaded URL,
+    /// percent-encoded so it can be used as is in HTML and markdown
+    pub fn local_paths(&self) -> HashMap<String, String> {
+        let inline_srcs = self.images.iter().flat_map(|image| image.inline_srcs.iter().map(move |src| (src, image)));
+        self.urls()
+            .chain(inline_srcs)
+            .map(|(url, image)| (url.clone(), format!("{}/{}", self.directory(image), filename::to_url_path(&image.filename))))
+            .collect()
+    }
+
+    /// Content of each file written to disk, by its percent-encoded path as in `local_paths`
+    pub fn local_files(&self) -> HashMap<String, &[u8]> {
+        self.images
+            .iter()
+            .map(|image| {
+                let bytes = match (self.is_omitted(image), &image.thumbnail) {
+                    (true, Some(thumbnail)) => thumbnail.as_slice(),
+                    _ => image.image_bytes.as_slice(),
+                };
+                (format!("{}/{}", self.directory(image), filename::to_url_path(&image.filename)), bytes)
+            })
+            .collect()
+    }
+
+    /// Original URL to file path of the local copy, relative to the capture directory
+    pub fn url_map(&self) -> BTreeMap<String, String> {
+        self.urls()
+            .map(|(url, image)| (url.clone(), format!("{}/{}", self.directory(image), image.filename)))
+            .collect()
     }
 
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
     }
 
+    /// Write the images and their manifest in `images/`, a few files at a time
     pub async fn write_images_to_disk(&self, output_directory: &Path) -> Result<()> {
 
-        if self.len() == 0 {return Ok(());}
+        if self.is_empty() && self.skipped.is_empty() && self.rejected.is_empty() {return Ok(());}
         
-        let output_directory = output_directory.join("images");
-        std::fs::create_dir(&output_directory)?;
+        let output_directory = output_directory.join(Self::DIRECTORY);
+        std::fs::create_dir_all(&output_directory)?;
 
 
-        let mut tasks = Vec::new();
-        for image in self.0.iter() {
-            let task = image.write_to_disk(&output_directory);
-            tasks.push(task);
+        if self.images.iter().any(|image| image.thumbnail.is_some()) {
+            std::fs::create_dir_all(output_directory.join(Self::THUMBS_DIRECTORY))?;
         }
 
-        let results = join_all(tasks).await;
+        let results = stream::iter(self.images.iter().map(|image| image.write_to_disk(&output_directory, !self.is_omitted(image))))
+            .buffer_unordered(HttpClient::DEFAULT_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
 
         for res in results {
             res?
         }
 
+        let manifest = serde_json::to_string_pretty(&self.manifest())?;
+        std::fs::write(output_directory.join(Self::MANIFEST_FILENAME), manifest)?;
+
         Ok(())
 
     }
@@ -205,14 +875,243 @@ impl Images {
 mod tests {
     use super::*;
 
+    use crate::options::LazyAttributes;
+    use crate::testing::{FixtureServer, ARTICLE_HTML, BLUE_PNG, RED_PNG};
+
     #[tokio::test]
     async fn al_images_from_website() {
         
-        let html: String = std::fs::read_to_string("test/htmls/EPFL.html").unwrap();
-        
-        let base_url = "https://www.epfl.ch/en/";
-        let output_path = "test/test_images_epfl";
-        let images = Images::from(&html, base_url).await.unwrap();
-        images.write_images_to_disk(Path::new(output_path)).await.unwrap();
+        let server = FixtureServer::with_article().unwrap();
+        let output = tempfile::tempdir().unwrap();
+
+        let client = HttpClient::new(&NetworkMode::Live).unwrap();
+        let warnings = Warnings::default();
+        let images = Images::from_with_client(ARTICLE_HTML, &server.url("/article.html"), &client, &ScrapeOptions::default(), &warnings).await.unwrap();
+        // the third image answers 404 and is dropped, the backgrounds of the header
+        // and the stylesheet are the same two images again
+        assert_eq!(images.len(), 2);
+        assert_eq!(warnings.to_vec()[0].kind, WarningKind::SkippedImage);
+        assert_eq!(warnings.to_vec()[0].url.as_deref(), Some("/images/missing.png"));
+        assert_eq!(images.failures()[0].src, "/images/missing.png");
+
+        images.write_images_to_disk(output.path()).await.unwrap();
+        assert_eq!(std::fs::read(output.path().join("images/red.png")).unwrap(), RED_PNG);
+        assert!(output.path().join("images/blue.png").is_file());
+
+        let manifest = images.manifest().images;
+        assert_eq!(manifest[0].alt.as_deref(), Some("A red square"));
+        assert_eq!((manifest[0].width, manifest[0].height), (Some(16), Some(16)));
+    }
+
+    #[tokio::test]
+    async fn reports_download_progress() {
+        let server = FixtureServer::new().unwrap();
+        server.route("/red.png", "image/png", RED_PNG);
+        let html = r#"<img src="/red.png"><img src="/missing.png">"#;
+
+        let (progress, mut events) = Progress::event_channel();
+        let (downloads, mut download_events) = Progress::channel();
+        let client = HttpClient::new(&NetworkMode::Live).unwrap();
+        for progress in [progress, downloads] {
+            let options = ScrapeOptions { progress: Some(progress), ..Default::default() };
+            Images::from_with_client(html, &server.url("/post.html"), &client, &options, &Warnings::default()).await.unwrap();
+        }
+
+        let mut received = Vec::new();
+        while let Some(event) = events.recv().await {
+            match event {
+                ProgressEvent::ImagesFound { count } => assert_eq!(count, 2),
+                ProgressEvent::Download(event) => received.push(event),
+                event => panic!("unexpected {event:?}"),
+            }
+        }
+        assert_eq!(received.iter().filter(|event| matches!(event, DownloadEvent::Started { .. })).count(), 2);
+        assert!(received.contains(&DownloadEvent::Finished { src: "/red.png".to_string(), bytes: RED_PNG.len() }));
+        assert!(received.iter().any(|event| matches!(event, DownloadEvent::Failed { src, .. } if src == "/missing.png")));
+        let mut downloads = Vec::new();
+        while let Some(event) = download_events.recv().await {
+            downloads.push(event);
+        }
+        assert!(downloads.len() == received.len() && downloads.iter().all(|event| received.contains(event)));
+    }
+
+    #[tokio::test]
+    async fn downloads_each_url_once() {
+        let server = FixtureServer::new().unwrap();
+        server.route("/red.png", "image/png", RED_PNG);
+        let html = format!(
+            r#"<img src="/red.png"><img src="red.png" alt="Red"><img src="{}"><div style="background: url(/red.png)"></div>"#,
+            server.url("/red.png")
+        );
+
+        let client = HttpClient::new(&NetworkMode::Live).unwrap();
+        let images = Images::from_with_client(&html, &server.url("/post.html"), &client, &ScrapeOptions::default(), &Warnings::default()).await.unwrap();
+
+        assert_eq!(images.len(), 1);
+        assert_eq!(server.requests().iter().filter(|request| request.contains("/red.png")).count(), 1);
+    }
+
+    #[tokio::test]
+    async fn resolves_protocol_relative_and_blob_sources() {
+        let server = FixtureServer::new().unwrap();
+        server.route("/red.png", "image/png", RED_PNG);
+        let protocol_relative = server.url("/red.png").replacen("http:", "", 1);
+        let blob = "blob:https://example.com/0b1c2d3e-4f50-6172-8394-a5b6c7d8e9f0";
+        let html = format!(r#"<img src="{protocol_relative}"><img src="{blob}"><img src="blob:https://example.com/revoked">"#);
+
+        let warnings = Warnings::default();
+        let blue = format!("data:image/png;base64,{}", base64::engine::general_purpose::STANDARD.encode(BLUE_PNG));
+        let html = inline_blobs(&html, |url| (url == blob).then(|| blue.clone()), &warnings);
+        assert!(html.contains(&blue) && !html.contains(blob));
+        assert_eq!(warnings.len(), 1);
+
+        let client = HttpClient::new(&NetworkMode::Live).unwrap();
+        let images = Images::from_with_client(&html, &server.url("/post.html"), &client, &ScrapeOptions::default(), &warnings).await.unwrap();
+        assert_eq!(images.len(), 2);
+        assert_eq!(images.url_map()[&server.url("/red.png")], "images/red.png");
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn rejects_pages_served_as_images() {
+        let server = FixtureServer::new().unwrap();
+        server.route("/photo.jpg", "text/html", "<html><body>Please log in</body></html>");
+        server.route("/red.png", "image/png", RED_PNG);
+        let html = r#"<img src="/photo.jpg"><img src="/red.png">"#;
+
+        let client = HttpClient::new(&NetworkMode::Live).unwrap();
+        let images = Images::from_with_client(html, &server.url("/post.html"), &client, &ScrapeOptions::default(), &Warnings::default()).await.unwrap();
+
+        assert_eq!(images.len(), 1);
+        let rejected = images.manifest().rejected;
+        assert_eq!(rejected.len(), 1);
+        assert_eq!((rejected[0].src.as_str(), rejected[0].reason.as_str()), ("/photo.jpg", "text/html response of 39 bytes"));
+    }
+
+    #[test]
+    fn names_images_after_their_format() {
+        assert_eq!(with_image_extension("photo", RED_PNG, Some("application/octet-stream")), "photo.png");
+        assert_eq!(with_image_extension("image.php", b"", Some("image/jpeg; charset=binary")), "image.jpg");
+        assert_eq!(with_image_extension("logo.PNG", b"GIF89a", Some("image/gif")), "logo.PNG");
+        assert_eq!(with_image_extension("pixel", b"", None), "pixel");
+    }
+
+    #[test]
+    fn keeps_one_copy_per_content() {
+        let image = |filename: &str, bytes: &[u8]| Image::new(bytes.to_vec(), filename.to_string(), Some(format!("https://example.com/{filename}")));
+        let images = Images::deduplicate(vec![image("logo.png", b"logo"), image("red.png", RED_PNG), image("logo@2x.png", b"logo"), image("logo.png", b"logo")]);
+
+        assert_eq!(images.len(), 2);
+        let manifest = images.manifest().images;
+        assert_eq!(manifest[0].aliases, vec![Alias { filename: "logo@2x.png".to_string(), url: Some("https://example.com/logo@2x.png".to_string()) }]);
+        assert!(manifest[1].aliases.is_empty());
+        assert_eq!(images.url_map()["https://example.com/logo@2x.png"], "images/logo.png");
+        assert_eq!(images.url_map().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn thumbnails_only_links_to_thumbnails() {
+        let mut images = Images::deduplicate(vec![
+            Image::new(RED_PNG.to_vec(), "red.png".to_string(), Some("https://example.com/red.png".to_string())),
+            Image::new(b"logo".to_vec(), "logo.png".to_string(), Some("https://example.com/logo.png".to_string())),
+        ]);
+        images.images[0].thumbnail = Some(b"thumbnail".to_vec());
+        images.thumbnails_only = true;
+
+        assert_eq!(images.url_map()["https://example.com/red.png"], "images/thumbs/red.png");
+        assert_eq!(images.url_map()["https://example.com/logo.png"], "images/logo.png");
+        let manifest = images.manifest().images;
+        assert_eq!((manifest[0].thumbnail.as_deref(), manifest[0].omitted), (Some("thumbs/red.png"), true));
+
+        let directory = tempfile::tempdir().unwrap();
+        images.write_images_to_disk(directory.path()).await.unwrap();
+        assert!(directory.path().join("images/thumbs/red.png").is_file());
+        assert!(!directory.path().join("images/red.png").exists());
+        assert!(directory.path().join("images/logo.png").is_file());
+    }
+
+    #[test]
+    fn decodes_percent_encoded_svg_data_urls() {
+        let image = Image::parse_data_url("data:image/svg+xml,%3Csvg%20width='4'%20height='2'%3E%3C/svg%3E").unwrap();
+        assert_eq!((image.filename.as_str(), image.dimensions), ("inline.svg", Some((4, 2))));
+    }
+
+    #[test]
+    fn renames_colliding_file_names() {
+        let image = |filename: &str, bytes: &[u8]| Image::new(bytes.to_vec(), filename.to_string(), None);
+        let images = Images::deduplicate(vec![image("photo.jpg", b"first"), image("Photo.JPG", b"second"), image("photo.jpg", b"third")]);
+
+        let names = images.images().iter().map(|image| image.filename.clone()).collect::<Vec<_>>();
+        assert_eq!(names[0], "photo.jpg");
+        assert_eq!(names[1], format!("Photo-{}.JPG", &hash::sha256_hex(b"second")[..8]));
+        assert_eq!(names[2], format!("photo-{}.jpg", &hash::sha256_hex(b"third")[..8]));
+    }
+
+    #[tokio::test]
+    async fn srcset_probing_steps_down_to_size_cap() {
+
+        let server = FixtureServer::with_article().unwrap();
+        server.route("/images/hero.png", "image/png", vec![0; 20_000]);
+        let html = r#"<img srcset="/images/hero.png 1600w, /images/red.png 400w">"#;
+
+        let client = HttpClient::new(&NetworkMode::Live).unwrap();
+        let options = ScrapeOptions { srcset_max_bytes: Some(10_000), ..Default::default() };
+        let images = Images::from_with_client(html, &server.url("/"), &client, &options, &Warnings::default()).await.unwrap();
+
+        assert_eq!(images.images().iter().map(|image| image.filename.as_str()).collect::<Vec<_>>(), vec!["red.png"]);
+        assert!(server.requests().contains(&"HEAD /images/hero.png HTTP/1.1".to_string()));
+        assert!(!server.requests().contains(&"GET /images/hero.png HTTP/1.1".to_string()));
+    }
+
+    #[tokio::test]
+    async fn budget_skips_images_past_the_limit() {
+
+        let server = FixtureServer::with_article().unwrap();
+        let client = HttpClient::new(&NetworkMode::Live).unwrap();
+        let options = ScrapeOptions { image_budget: ImageBudget { max_count: Some(1), max_bytes: None }, ..Default::default() };
+        let images = Images::from_with_client(ARTICLE_HTML, &server.url("/article.html"), &client, &options, &Warnings::default()).await.unwrap();
+
+        assert_eq!(images.len(), 1);
+        assert!(!images.skipped().is_empty());
+        assert_eq!(images.manifest().skipped, images.skipped());
+
+        let output = tempfile::tempdir().unwrap();
+        std::fs::write(output.path().join("manifest.json"), r#"[{"filename": "red.png", "url": null, "sha256": "00", "bytes": 2}]"#).unwrap();
+        assert_eq!(Manifest::load(&output.path().join("manifest.json")).unwrap().images[0].filename, "red.png");
+    }
+
+    #[tokio::test]
+    async fn lazy_attributes_replace_placeholders() {
+
+        let server = FixtureServer::with_article().unwrap();
+        let html = r#"<img src="data:image/gif;base64,R0lGODlhAQABAAAAACw=" data-lazy-src="/images/red.png">
+            <img data-original="/images/blue.png" loading="lazy">"#;
+
+        let client = HttpClient::new(&NetworkMode::Live).unwrap();
+        let images = Images::from_with_client(html, &server.url("/"), &client, &ScrapeOptions::default(), &Warnings::default()).await.unwrap();
+        assert_eq!(images.images().iter().map(|image| image.filename.as_str()).collect::<Vec<_>>(), vec!["red.png", "blue.png"]);
+
+        let options = ScrapeOptions { lazy_attributes: LazyAttributes(Vec::new()), ..Default::default() };
+        let images = Images::from_with_client(html, &server.url("/"), &client, &options, &Warnings::default()).await.unwrap();
+        assert_eq!(images.images().iter().map(|image| image.filename.as_str()).collect::<Vec<_>>(), vec!["inline.gif"]);
+    }
+
+    #[tokio::test]
+    async fn picture_source_replaces_fallback_img() {
+
+        let server = FixtureServer::with_article().unwrap();
+        let html = r#"<picture>
+            <source media="(max-width: 600px)" srcset="/images/red.png">
+            <source type="image/jxl" srcset="/images/red.jxl">
+            <source srcset="/images/red.png 1x, /images/blue.png 2x">
+            <img src="/images/missing.png">
+        </picture>"#;
+
+        let client = HttpClient::new(&NetworkMode::Live).unwrap();
+        let warnings = Warnings::default();
+        let images = Images::from_with_client(html, &server.url("/"), &client, &ScrapeOptions::default(), &warnings).await.unwrap();
+
+        assert_eq!(images.images().iter().map(|image| image.filename.as_str()).collect::<Vec<_>>(), vec!["blue.png"]);
+        assert!(warnings.is_empty());
     }
 }
@@ -0,0 +1,103 @@
+use scraper::{Html, Selector};
+
+/// Frequent short words of languages written in the latin script
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("en", &["the", "and", "of", "to", "is", "in", "that", "it", "for", "with", "was", "on", "are", "this", "you"]),
+    ("fr", &["le", "la", "les", "et", "des", "est", "une", "dans", "que", "pour", "pas", "qui", "sur", "du", "au"]),
+    ("de", &["der", "die", "und", "das", "ist", "nicht", "mit", "ein", "eine", "den", "zu", "sich", "auf", "auch", "dem"]),
+    ("es", &["el", "los", "las", "y", "que", "es", "una", "por", "con", "para", "del", "se", "como", "pero", "su"]),
+    ("it", &["il", "che", "di", "e", "della", "per", "non", "sono", "gli", "una", "con", "del", "si", "anche", "come"]),
+    ("pt", &["o", "os", "que", "não", "uma", "com", "para", "do", "da", "em", "é", "se", "por", "mais", "como"]),
+    ("nl", &["de", "het", "een", "en", "van", "is", "niet", "dat", "op", "te", "zijn", "voor", "met", "ook", "maar"]),
+];
+
+/// Frequent short words of `language`, or of every language having some when unknown
+pub(crate) fn stopwords(language: Option<&str>) -> Vec<&'static str> {
+    match STOPWORDS.iter().find(|(code, _)| Some(*code) == language) {
+        Some((_, stopwords)) => stopwords.to_vec(),
+        None => STOPWORDS.iter().flat_map(|(_, stopwords)| stopwords.iter().copied()).collect(),
+    }
+}
+
+/// Scripts mostly used by a single language
+fn script_language(c: char) -> Option<&'static str> {
+    match c {
+        '\u{3040}'..='\u{30FF}' => Some("ja"),
+        '\u{4E00}'..='\u{9FFF}' => Some("zh"),
+        '\u{AC00}'..='\u{D7AF}' | '\u{1100}'..='\u{11FF}' => Some("ko"),
+        '\u{0400}'..='\u{04FF}' => Some("ru"),
+        '\u{0370}'..='\u{03FF}' => Some("el"),
+        '\u{0590}'..='\u{05FF}' => Some("he"),
+        '\u{0600}'..='\u{06FF}' => Some("ar"),
+        '\u{0900}'..='\u{097F}' => Some("hi"),
+        '\u{0E00}'..='\u{0E7F}' => Some("th"),
+        _ => None,
+    }
+}
+
+/// Primary language subtag of the `lang` attribute of the document, lowercased: `pt` for `pt-BR`
+pub fn declared(html: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("html[lang]").unwrap();
+    let lang = document.select(&selector).next()?.attr("lang")?;
+    let primary = lang.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+    (primary.len() >= 2 && primary.chars().all(|c| c.is_ascii_alphabetic())).then_some(primary)
+}
+
+/// ISO 639-1 code of the language `text` is most likely written in, from its script and,
+/// for the latin script, the frequent words of the main European languages
+pub fn detect(text: &str) -> Option<&'static str> {
+
+    let letters = text.chars().filter(|c| c.is_alphabetic()).collect::<Vec<_>>();
+    if letters.is_empty() {
+        return None;
+    }
+    let mut scripts: Vec<(&str, usize)> = Vec::new();
+    for language in letters.iter().filter_map(|c| script_language(*c)) {
+        match scripts.iter_mut().find(|(script, _)| *script == language) {
+            Some((_, count)) => *count += 1,
+            None => scripts.push((language, 1)),
+        }
+    }
+    let non_latin = scripts.iter().map(|(_, count)| count).sum::<usize>();
+    if non_latin * 2 > letters.len() {
+        // kana among ideographs is Japanese
+        if scripts.iter().any(|(script, count)| *script == "ja" && count * 20 > non_latin) {
+            return Some("ja");
+        }
+        return scripts.iter().max_by_key(|(_, count)| *count).map(|(script, _)| *script);
+    }
+
+    let words = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+    let (language, hits) = STOPWORDS
+        .iter()
+        .map(|(language, stopwords)| (*language, words.iter().filter(|word| stopwords.contains(&word.as_str())).count()))
+        .max_by_key(|(_, hits)| *hits)?;
+    // a handful of stopwords in a long text is too weak a signal
+    (hits >= 2 && hits * 20 >= words.len()).then_some(language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_languages() {
+        assert_eq!(detect("The quick brown fox jumps over the lazy dog, and it is fast."), Some("en"));
+        assert_eq!(detect("Le chat est sur la table et il dort dans le salon."), Some("fr"));
+        assert_eq!(detect("Der Hund ist nicht auf dem Sofa und die Katze auch nicht."), Some("de"));
+        assert_eq!(detect("東京は日本の首都です。"), Some("ja"));
+        assert_eq!(detect("Москва — столица России."), Some("ru"));
+        assert_eq!(detect("1234 !!"), None);
+    }
+
+    #[test]
+    fn reads_the_declared_language() {
+        assert_eq!(declared(r#"<html lang="pt-BR"><body></body></html>"#).as_deref(), Some("pt"));
+        assert_eq!(declared("<html><body></body></html>"), None);
+    }
+}
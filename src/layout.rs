@@ -0,0 +1,156 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use regex::{Captures, Regex};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum LayoutError {
+    #[error("unknown placeholder {{{0}}}, expected {{domain}}, {{date}}, {{slug}}, {{kind}} or {{ext}}")]
+    UnknownPlaceholder(String),
+    #[error("{{kind}} and {{ext}} can only be used in the file name, the last segment")]
+    FilePlaceholderInDirectory,
+    #[error("invalid segment {0:?}, segments can't be empty, `.` or `..`")]
+    InvalidSegment(String),
+}
+
+pub type Result<T> = std::result::Result<T, LayoutError>;
+
+/// Documents named by the layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Html,
+    Pdf,
+    Markdown,
+    Text,
+    Docx,
+}
+
+impl Kind {
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::Html => "html",
+            Kind::Pdf => "pdf",
+            Kind::Markdown => "markdown",
+            Kind::Text => "text",
+            Kind::Docx => "docx",
+        }
+    }
+}
+
+/// Values of the placeholders for a capture
+#[derive(Debug, Clone, Copy)]
+pub struct Fields<'a> {
+    /// Host of the page, without `www.`
+    pub domain: &'a str,
+    /// Capture date, `2024-05-01`
+    pub date: &'a str,
+    /// File stem derived from the title
+    pub slug: &'a str,
+}
+
+/// Where the HTML, PDF, markdown, text and DOCX of a capture go within its directory, from a template
+/// like `{domain}/{date}/{slug}/{kind}`. The last segment names the files, `.{ext}` added
+/// unless written, the others the directory they share with the images and everything else
+/// they link to. Metadata files stay at the root of the capture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    directories: Vec<String>,
+    file: String,
+}
+
+impl Default for Layout {
+    /// `<title>.<ext>` at the root of the capture
+    fn default() -> Self {
+        Self { directories: Vec::new(), file: "{slug}".to_string() }
+    }
+}
+
+fn placeholder() -> &'static Regex {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    PLACEHOLDER.get_or_init(|| Regex::new(r"\{([^{}]*)\}").unwrap())
+}
+
+impl std::str::FromStr for Layout {
+    type Err = LayoutError;
+
+    fn from_str(template: &str) -> Result<Self> {
+        Self::new(template)
+    }
+}
+
+impl Layout {
+
+    pub fn new(template: &str) -> Result<Self> {
+
+        let mut segments = template.split('/').map(str::to_string).collect::<Vec<_>>();
+        for segment in &segments {
+            if matches!(segment.trim(), "" | "." | "..") {
+                return Err(LayoutError::InvalidSegment(segment.clone()));
+            }
+            for captures in placeholder().captures_iter(segment) {
+                if !matches!(&captures[1], "domain" | "date" | "slug" | "kind" | "ext") {
+                    return Err(LayoutError::UnknownPlaceholder(captures[1].to_string()));
+                }
+            }
+        }
+        let file = segments.pop().unwrap_or_default();
+        if segments.iter().any(|segment| segment.contains("{kind}") || segment.contains("{ext}")) {
+            return Err(LayoutError::FilePlaceholderInDirectory);
+        }
+        Ok(Self { directories: segments, file })
+    }
+
+    fn render(segment: &str, fields: &Fields, document: Option<(Kind, &str)>) -> String {
+        placeholder().replace_all(segment, |captures: &Captures| match (&captures[1], document) {
+            ("domain", _) => fields.domain.to_string(),
+            ("date", _) => fields.date.to_string(),
+            ("slug", _) => fields.slug.to_string(),
+            ("kind", Some((kind, _))) => kind.name().to_string(),
+            ("ext", Some((_, extension))) => extension.to_string(),
+            _ => String::new(),
+        })
+        .replace(['/', '\\'], "_")
+    }
+
+    /// Directory of the documents, relative to the capture directory
+    pub fn directory(&self, fields: &Fields) -> PathBuf {
+        self.directories.iter().map(|segment| Self::render(segment, fields, None)).collect()
+    }
+
+    /// Path of the `kind` document, relative to the capture directory
+    pub fn file(&self, fields: &Fields, kind: Kind, extension: &str) -> PathBuf {
+        let mut name = Self::render(&self.file, fields, Some((kind, extension)));
+        if !self.file.contains("{ext}") {
+            name = format!("{name}.{extension}");
+        }
+        self.directory(fields).join(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_templates() {
+        let fields = Fields { domain: "example.com", date: "2024-05-01", slug: "Hello world" };
+
+        let layout = Layout::new("{domain}/{date}/{slug}/{kind}").unwrap();
+        assert_eq!(layout.directory(&fields), PathBuf::from("example.com/2024-05-01/Hello world"));
+        assert_eq!(layout.file(&fields, Kind::Markdown, "md"), PathBuf::from("example.com/2024-05-01/Hello world/markdown.md"));
+
+        let default = Layout::default();
+        assert_eq!(default.directory(&fields), PathBuf::new());
+        assert_eq!(default.file(&fields, Kind::Pdf, "pdf"), PathBuf::from("Hello world.pdf"));
+        assert_eq!(Layout::new("{date}-{slug}.{ext}").unwrap().file(&fields, Kind::Html, "html"), PathBuf::from("2024-05-01-Hello world.html"));
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        assert_eq!(Layout::new("{domain}/{title}"), Err(LayoutError::UnknownPlaceholder("title".to_string())));
+        assert_eq!(Layout::new("{kind}/{slug}"), Err(LayoutError::FilePlaceholderInDirectory));
+        assert_eq!(Layout::new("../{slug}"), Err(LayoutError::InvalidSegment("..".to_string())));
+        assert_eq!(Layout::new("/{slug}"), Err(LayoutError::InvalidSegment(String::new())));
+    }
+}
@@ -1,4 +1,73 @@
-
 pub mod webpage;
 pub mod browser;
 pub mod images;
+pub mod options;
+pub mod filename;
+pub mod slug;
+pub mod ratelimit;
+pub mod feed;
+pub mod renderer;
+pub mod webdriver;
+pub mod fetch;
+pub mod chrome;
+pub mod http;
+pub mod recording;
+pub mod css;
+pub mod warnings;
+pub mod srcset;
+mod toml;
+pub mod config;
+pub mod rules;
+pub mod dom;
+pub mod comments;
+pub mod hash;
+pub mod verify;
+pub mod archive;
+pub mod crawl;
+pub mod url_list;
+pub mod robots;
+pub mod localize;
+pub mod dimensions;
+pub mod git;
+pub mod embeds;
+pub mod convert;
+pub mod html_report;
+pub mod pdf;
+pub mod pdfa;
+pub mod snapshot;
+pub mod exif;
+pub mod preview;
+pub mod svg;
+pub mod figures;
+pub mod text;
+pub mod warc;
+pub mod single_file;
+pub mod metadata;
+pub mod front_matter;
+pub mod links;
+pub mod tables;
+pub mod markdown;
+pub mod media;
+pub mod assets;
+pub mod progress;
+pub mod status;
+pub mod logging;
+pub mod report;
+pub mod bundle;
+pub mod layout;
+pub mod language;
+pub mod structure;
+pub mod syndication;
+pub mod citation;
+pub mod obsidian;
+pub mod checksums;
+pub mod timings;
+pub mod sanitize;
+pub mod xpath;
+pub mod extract;
+pub mod diff;
+pub mod toc;
+pub mod stats;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+mod pinyin;
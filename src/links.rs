@@ -0,0 +1,107 @@
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// File of the capture listing the links of the page
+pub const FILENAME: &str = "links.json";
+
+/// Anchor of the page, for crawl tools and outbound link graphs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Link {
+    /// Absolute URL, without its fragment
+    pub url: String,
+    /// Text of the anchor, or the alt text of its images
+    pub text: String,
+    /// Points at the host of the page (`www.` aside)
+    pub internal: bool,
+    /// `rel="nofollow"`, or its `sponsored` and `ugc` refinements
+    pub nofollow: bool,
+    /// Every `rel` keyword of the anchor
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rel: Vec<String>,
+}
+
+fn host(url: &Url) -> Option<String> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    Some(host.strip_prefix("www.").map(str::to_string).unwrap_or(host))
+}
+
+fn anchor_text(anchor: &ElementRef) -> String {
+    let text = anchor.text().collect::<Vec<_>>().join(" ");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !text.is_empty() {
+        return text;
+    }
+    let img = Selector::parse("img[alt]").unwrap();
+    anchor.select(&img).filter_map(|img| img.attr("alt")).map(str::trim).find(|alt| !alt.is_empty()).unwrap_or_default().to_string()
+}
+
+/// HTTP(S) links of `html`, in document order. Links within the page itself (`#section`) are left out
+pub fn extract(html: &str, base_url: &str) -> Vec<Link> {
+
+    let Ok(base_url) = Url::parse(base_url) else {
+        return Vec::new();
+    };
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("a[href], area[href]").unwrap();
+
+    document
+        .select(&selector)
+        .filter_map(|anchor| {
+            let href = anchor.attr("href")?.trim();
+            if href.starts_with('#') {
+                return None;
+            }
+            let mut url = base_url.join(href).ok().filter(|url| matches!(url.scheme(), "http" | "https"))?;
+            url.set_fragment(None);
+            let rel = anchor.attr("rel").unwrap_or_default().split_whitespace().map(str::to_ascii_lowercase).collect::<Vec<_>>();
+            Some(Link {
+                internal: host(&url) == host(&base_url),
+                nofollow: rel.iter().any(|rel| matches!(rel.as_str(), "nofollow" | "sponsored" | "ugc")),
+                text: anchor_text(&anchor),
+                url: url.to_string(),
+                rel,
+            })
+        })
+        .collect()
+}
+
+/// Absolute URL of the `rel="canonical"` link of `html`
+pub fn canonical(html: &str, base_url: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("link[rel~=canonical][href]").unwrap();
+    let href = document.select(&selector).next()?.attr("href")?.trim();
+    let url = Url::parse(base_url).ok()?.join(href).ok()?;
+    matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_and_classifies_links() {
+        let html = r##"<html><body>
+            <a href="/about#team">About   <b>us</b></a>
+            <a href="#comments">Comments</a>
+            <a href="https://example.com/feed"><img src="rss.png" alt="RSS"></a>
+            <a href="https://ads.example.org/?id=3" rel="Sponsored noopener">Ad</a>
+            <a href="mailto:me@example.com">Mail</a>
+        </body></html>"##;
+        let links = extract(html, "https://www.example.com/post");
+
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[0], Link { url: "https://www.example.com/about".to_string(), text: "About us".to_string(), internal: true, nofollow: false, rel: Vec::new() });
+        assert!(links[1].internal);
+        assert_eq!(links[1].text, "RSS");
+        assert!(!links[2].internal && links[2].nofollow);
+        assert_eq!(links[2].rel, ["sponsored", "noopener"]);
+    }
+
+    #[test]
+    fn resolves_the_canonical_url() {
+        let html = r#"<html><head><link rel="alternate" href="/amp"><link rel="canonical" href="/post?id=1"></head></html>"#;
+        assert_eq!(canonical(html, "https://example.com/post/amp").as_deref(), Some("https://example.com/post?id=1"));
+        assert_eq!(canonical("<p>none</p>", "https://example.com/"), None);
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use url::Url;
+use crate::css;
+use crate::dom::set_attribute;
+use crate::options::LazyAttributes;
+use crate::srcset;
+
+/// Local path of `src` once resolved against `base_url`
+fn local<'a>(src: &str, base_url: &Url, paths: &'a HashMap<String, String>) -> Option<&'a str> {
+    let url = base_url.join(src.trim()).ok()?;
+    paths.get(url.as_str()).map(String::as_str)
+}
+
+/// Local path of the first downloaded URL among the `src`, `srcset` and lazy attributes of `element`
+fn element_path<'a>(element: &ElementRef, base_url: &Url, paths: &'a HashMap<String, String>, lazy: &LazyAttributes) -> Option<&'a str> {
+
+    let srcs = ["src"].into_iter().chain(lazy.src()).filter_map(|name| element.attr(name));
+    let srcsets = ["srcset"].into_iter().chain(lazy.srcset()).filter_map(|name| element.attr(name));
+
+    srcs.filter_map(|src| local(src, base_url, paths)).next().or_else(|| {
+        srcsets.flat_map(srcset::parse).find_map(|candidate| local(&candidate.url, base_url, paths))
+    })
+}
+
+/// Point the images of `html` at their local copies in `paths` (URL to relative path):
+/// `<img>` and `<source>` lose their remote `srcset` and lazy loading attributes,
+/// `<picture>` sources without a local copy are dropped, and `url(...)` of inline
+/// styles are rewritten. Images that weren't downloaded are left untouched
+pub fn html(html: &str, base_url: &str, paths: &HashMap<String, String>, lazy: &LazyAttributes) -> String {
+
+    let Ok(base_url) = Url::parse(base_url) else {
+        return html.to_string();
+    };
+    if paths.is_empty() {
+        return html.to_string();
+    }
+
+    let mut document = Html::parse_document(html);
+
+    let image_selector = Selector::parse("img, source").unwrap();
+    let picture_selector = Selector::parse("picture").unwrap();
+    let style_attr_selector = Selector::parse("[style]").unwrap();
+    let style_selector = Selector::parse("style").unwrap();
+
+    let mut rewrites: HashMap<_, String> = document
+        .select(&image_selector)
+        .filter_map(|element| Some((element.id(), element_path(&element, &base_url, paths, lazy)?.to_string())))
+        .collect();
+
+    // within a <picture>, the fallback <img> takes the local copy of the chosen source
+    let mut dropped = Vec::new();
+    for picture in document.select(&picture_selector) {
+        let children = picture.select(&image_selector).collect::<Vec<_>>();
+        let Some(path) = children.iter().find_map(|child| rewrites.get(&child.id())).cloned() else {
+            continue;
+        };
+        let missing = children.iter().filter(|child| !rewrites.contains_key(&child.id())).collect::<Vec<_>>();
+        for child in missing {
+            match child.value().name() {
+                "img" => { rewrites.insert(child.id(), path.clone()); }
+                _ => dropped.push(child.id()),
+            }
+        }
+    }
+
+    let styles = document
+        .select(&style_attr_selector)
+        .filter_map(|element| {
+            let style = element.attr("style")?;
+            let rewritten = css::replace_urls(style, |url| local(url, &base_url, paths).map(str::to_string));
+            (rewritten != style).then_some((element.id(), rewritten))
+        })
+        .collect::<Vec<_>>();
+
+    let stylesheets = document
+        .select(&style_selector)
+        .flat_map(|element| element.children())
+        .filter_map(|child| {
+            let text = child.value().as_text()?;
+            let rewritten = css::replace_urls(text, |url| local(url, &base_url, paths).map(str::to_string));
+            (rewritten != **text).then_some((child.id(), rewritten))
+        })
+        .collect::<Vec<_>>();
+
+    for (id, path) in rewrites {
+        if let Some(mut node) = document.tree.get_mut(id)
+            && let Node::Element(element) = node.value() {
+            let attribute = if element.name() == "img" { "src" } else { "srcset" };
+            element.attrs.retain(|(name, _)| {
+                let name = &*name.local;
+                name != "srcset" && !lazy.0.iter().any(|lazy| lazy == name)
+            });
+            set_attribute(&mut element.attrs, attribute, &path);
+        }
+    }
+
+    for (id, style) in styles {
+        if let Some(mut node) = document.tree.get_mut(id)
+            && let Node::Element(element) = node.value() {
+            set_attribute(&mut element.attrs, "style", &style);
+        }
+    }
+
+    for (id, css) in stylesheets {
+        if let Some(mut node) = document.tree.get_mut(id)
+            && let Node::Text(text) = node.value() {
+            text.text = css.as_str().into();
+        }
+    }
+
+    for id in dropped {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+
+    document.html()
+}
+
+/// Point the image links of `markdown` at their local copies in `paths` (URL to relative path)
+pub fn markdown(markdown: &str, base_url: &str, paths: &HashMap<String, String>) -> String {
+
+    let Ok(base_url) = Url::parse(base_url) else {
+        return markdown.to_string();
+    };
+
+    static IMAGE: OnceLock<Regex> = OnceLock::new();
+    let image = IMAGE.get_or_init(|| Regex::new(r"(!\[(?:\\.|[^\]\\])*\]\()(<[^>]*>|[^)\s]+)").unwrap());
+
+    image
+        .replace_all(markdown, |captures: &regex::Captures| {
+            let target = captures[2].trim_start_matches('<').trim_end_matches('>');
+            match local(target, &base_url, paths) {
+                Some(path) => format!("{}{path}", &captures[1]),
+                None => captures[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths() -> HashMap<String, String> {
+        HashMap::from([
+            ("https://example.com/images/red.png".to_string(), "images/red.png".to_string()),
+            ("https://example.com/images/big.webp".to_string(), "images/big.webp".to_string()),
+        ])
+    }
+
+    #[test]
+    fn rewrites_html_images() {
+        let html = r#"<html><body>
+            <img src="data:image/gif;base64,R0lG" data-src="/images/red.png">
+            <picture><source srcset="/images/big.webp 2x" type="image/webp"><source srcset="/images/big.avif"><img src="/images/fallback.jpg"></picture>
+            <img src="https://cdn.example.org/other.png">
+            <div style="background: url('/images/red.png')"></div>
+        </body></html>"#;
+        let localized = super::html(html, "https://example.com/post", &paths(), &LazyAttributes::default());
+
+        assert!(localized.contains(r#"<img src="images/red.png">"#));
+        assert!(localized.contains(r#"<source type="image/webp" srcset="images/big.webp">"#));
+        assert!(!localized.contains("big.avif"));
+        assert!(localized.contains(r#"<img src="images/big.webp">"#));
+        assert!(localized.contains("https://cdn.example.org/other.png"));
+        assert!(localized.contains(r#"url(&quot;images/red.png&quot;)"#));
+    }
+
+    #[test]
+    fn rewrites_markdown_images() {
+        let md = "![A red square](/images/red.png \"Red\")\n\n[![alt \\] text](https://example.com/images/big.webp)](/post)\n\n![remote](other.png)";
+        assert_eq!(
+            markdown(md, "https://example.com/post", &paths()),
+            "![A red square](images/red.png \"Red\")\n\n[![alt \\] text](images/big.webp)](/post)\n\n![remote](other.png)"
+        );
+    }
+}
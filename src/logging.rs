@@ -0,0 +1,198 @@
+use std::fmt::Debug;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use serde_json::{Map, Value};
+use time::{OffsetDateTime, PrimitiveDateTime};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+use crate::snapshot;
+
+/// How the log records are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// `<time> <LEVEL> <target>: <message> <field>=<value>...`
+    #[default]
+    Text,
+    /// A JSON object per line, the fields of the event next to `timestamp`, `level`, `target` and `message`
+    Json,
+}
+
+/// Level of the records logged for `verbose` `-v` flags, warnings without any and only errors when `quiet`
+pub fn level(verbose: u8, quiet: bool) -> Level {
+    match (quiet, verbose) {
+        (true, _) => Level::ERROR,
+        (false, 0) => Level::WARN,
+        (false, 1) => Level::INFO,
+        (false, 2) => Level::DEBUG,
+        _ => Level::TRACE,
+    }
+}
+
+/// Subscriber writing the events of the scraper up to `level` to the standard error, one per line.
+/// Those of its dependencies (HTTP client, Chrome driver, ...) are only written from warnings
+/// up, or all of them at the trace level
+pub struct Logger {
+    level: Level,
+    format: LogFormat,
+    next_span: AtomicU64,
+}
+
+impl Logger {
+
+    pub fn new(level: Level, format: LogFormat) -> Self {
+        Self { level, format, next_span: AtomicU64::new(1) }
+    }
+
+    /// Make the logger the subscriber of the whole process, `false` when there was one already
+    pub fn install(self) -> bool {
+        tracing::subscriber::set_global_default(self).is_ok()
+    }
+
+    /// Line of `event` that happened at `date`
+    fn format(&self, event: &Event<'_>, date: OffsetDateTime) -> String {
+
+        let metadata = event.metadata();
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let timestamp = snapshot::iso8601(PrimitiveDateTime::new(date.date(), date.time()));
+
+        match self.format {
+            LogFormat::Text => {
+                let mut line = format!("{timestamp} {:>5} {}: {}", metadata.level(), metadata.target(), fields.message);
+                for (name, value) in &fields.values {
+                    match value {
+                        Value::String(text) => line.push_str(&format!(" {name}={text:?}")),
+                        value => line.push_str(&format!(" {name}={value}")),
+                    }
+                }
+                line
+            }
+            LogFormat::Json => {
+                let mut record = Map::new();
+                record.insert("timestamp".to_string(), timestamp.into());
+                record.insert("level".to_string(), metadata.level().as_str().into());
+                record.insert("target".to_string(), metadata.target().into());
+                record.insert("message".to_string(), fields.message.into());
+                record.extend(fields.values);
+                Value::Object(record).to_string()
+            }
+        }
+    }
+}
+
+/// Message and other fields of an event
+#[derive(Default)]
+struct Fields {
+    message: String,
+    /// In the order of the event
+    values: Vec<(String, Value)>,
+}
+
+impl Fields {
+    fn insert(&mut self, field: &Field, value: Value) {
+        match (field.name(), value) {
+            ("message", Value::String(message)) => self.message = message,
+            (name, value) => self.values.push((name.to_string(), value)),
+        }
+    }
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into());
+    }
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, value.into());
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.insert(field, format!("{value:?}").into());
+    }
+}
+
+impl Subscriber for Logger {
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        let own = metadata.target().split("::").next() == Some(env!("CARGO_CRATE_NAME"));
+        *metadata.level() <= self.level && (own || self.level == Level::TRACE || *metadata.level() <= Level::WARN)
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(LevelFilter::from_level(self.level))
+    }
+
+    // events aren't told the spans they happen in
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        Id::from_u64(self.next_span.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let line = self.format(event, OffsetDateTime::now_utc());
+        let _ = writeln!(std::io::stderr().lock(), "{line}");
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Lines `logger` formats for the events of `log`
+    fn logged(logger: Logger, log: impl FnOnce()) -> Vec<String> {
+        struct Capture(Logger, Arc<Mutex<Vec<String>>>);
+        impl Subscriber for Capture {
+            fn enabled(&self, metadata: &Metadata<'_>) -> bool { self.0.enabled(metadata) }
+            fn new_span(&self, attributes: &Attributes<'_>) -> Id { self.0.new_span(attributes) }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, event: &Event<'_>) {
+                self.1.lock().unwrap().push(self.0.format(event, OffsetDateTime::from_unix_timestamp(1_709_296_200).unwrap()));
+            }
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        tracing::subscriber::with_default(Capture(logger, lines.clone()), log);
+        lines.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn writes_the_events_up_to_the_level() {
+        let lines = logged(Logger::new(Level::INFO, LogFormat::Text), || {
+            tracing::info!(url = "https://example.com/", images = 3, "page captured");
+            tracing::debug!("left out");
+            tracing::info!(target: "hyper::client", "left out too");
+            tracing::warn!(target: "hyper::client", "connection reset");
+        });
+        assert_eq!(lines, [
+            "2024-03-01T12:30:00Z  INFO webpage_scraper::logging::tests: page captured url=\"https://example.com/\" images=3",
+            "2024-03-01T12:30:00Z  WARN hyper::client: connection reset",
+        ]);
+
+        let lines = logged(Logger::new(Level::WARN, LogFormat::Json), || {
+            tracing::error!(status = 503, "image download failed");
+        });
+        assert_eq!(serde_json::from_str::<Value>(&lines[0]).unwrap(), serde_json::json!({
+            "timestamp": "2024-03-01T12:30:00Z", "level": "ERROR", "target": "webpage_scraper::logging::tests",
+            "message": "image download failed", "status": 503,
+        }));
+    }
+}
@@ -0,0 +1,379 @@
+use ego_tree::NodeRef;
+use scraper::{ElementRef, Html, Node};
+use serde::Deserialize;
+use crate::tables;
+
+/// What turns the HTML of a page into markdown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkdownConverter {
+    /// pandoc when it's installed, the built-in converter otherwise
+    #[default]
+    Auto,
+    /// pandoc only, failing when it isn't installed
+    Pandoc,
+    /// The built-in converter, simpler than pandoc but without any dependency
+    Builtin,
+}
+
+impl MarkdownConverter {
+    /// Whether pandoc does the conversion
+    pub fn uses_pandoc(self) -> bool {
+        match self {
+            Self::Auto => which::which("pandoc").is_ok(),
+            Self::Pandoc => true,
+            Self::Builtin => false,
+        }
+    }
+}
+
+/// How pandoc's writer wraps lines, `--wrap`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Wrap {
+    /// At the column width
+    Auto,
+    /// Never, paragraphs on a single line
+    None,
+    /// Where the HTML source wrapped
+    Preserve,
+}
+
+/// Writer pandoc converts the HTML with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PandocOptions {
+    /// `gfm`, `commonmark`, `markdown`, `org`, `asciidoc`, `rst`, ...
+    pub format: String,
+    /// Extensions of the writer, as pandoc writes them after the format: `-raw_html+smart`.
+    /// `-raw_html` for the markdown writers unless given, none for the others
+    pub extensions: Option<String>,
+    pub wrap: Option<Wrap>,
+    /// Links as references at the end of the document instead of inline
+    pub reference_links: bool,
+    /// Other pandoc arguments, `--name=value` or `--flag`
+    pub extra_args: Vec<String>,
+}
+
+impl Default for PandocOptions {
+    fn default() -> Self {
+        Self { format: "gfm".to_string(), extensions: None, wrap: None, reference_links: false, extra_args: Vec::new() }
+    }
+}
+
+impl PandocOptions {
+
+    /// Writer with its extensions, `gfm-raw_html`
+    pub fn writer(&self) -> String {
+        // other writers don't know `raw_html`, pandoc would reject it
+        let default = if is_markdown(&self.format) { "-raw_html" } else { "" };
+        format!("{}{}", self.format, self.extensions.as_deref().unwrap_or(default))
+    }
+
+    /// Add the writer and arguments to `pandoc`
+    pub fn apply(&self, pandoc: &mut pandoc::Pandoc) {
+        pandoc.set_output_format(pandoc::OutputFormat::Other(self.writer()), vec![]);
+        if let Some(wrap) = self.wrap {
+            pandoc.arg("wrap", &format!("{wrap:?}").to_ascii_lowercase());
+        }
+        if self.reference_links {
+            pandoc.add_option(pandoc::PandocOption::ReferenceLinks);
+        }
+        for arg in &self.extra_args {
+            // pandoc takes `--flag=true` for any `--flag`
+            let (name, value) = arg.trim_start_matches('-').split_once('=').unwrap_or((arg.trim_start_matches('-'), "true"));
+            pandoc.arg(name, value);
+        }
+    }
+}
+
+/// Whether the pandoc `format` is a flavor of markdown
+pub fn is_markdown(format: &str) -> bool {
+    matches!(format, "gfm" | "commonmark" | "commonmark_x" | "markdown" | "markdown_strict" | "markdown_mmd" | "markdown_phpextra" | "markdown_github")
+}
+
+/// Extension of the files written in the pandoc `format`
+pub fn file_extension(format: &str) -> &str {
+    match format {
+        format if is_markdown(format) => "md",
+        "asciidoc" | "asciidoctor" => "adoc",
+        "mediawiki" | "dokuwiki" => "wiki",
+        // `txt` being the one of the text output
+        "plain" => "plain.txt",
+        "textile" => "textile",
+        format => format,
+    }
+}
+
+/// Elements whose content isn't part of the document
+const SKIPPED: &[&str] = &["head", "script", "style", "noscript", "template", "svg", "canvas", "iframe", "object", "button", "select", "textarea", "input"];
+
+/// Elements containing blocks, whose blocks are kept as they are
+const CONTAINERS: &[&str] = &[
+    "html", "body", "div", "section", "article", "main", "header", "footer", "aside", "nav", "figure", "figcaption",
+    "details", "summary", "address", "form", "fieldset", "center", "dd",
+];
+
+/// GitHub flavored markdown of `html`: headings, paragraphs, emphasis, links, images, lists,
+/// quotes, code blocks and simple tables. A fallback for machines without pandoc
+pub fn from_html(html: &str) -> String {
+    let document = Html::parse_document(html);
+    let mut markdown = blocks(*document.root_element()).join("\n\n");
+    markdown.push('\n');
+    markdown
+}
+
+/// Markdown blocks of the children of `node`, text and inline elements between blocks making paragraphs
+fn blocks(node: NodeRef<Node>) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut paragraph = String::new();
+    for child in node.children() {
+        let block = match child.value() {
+            Node::Element(element) if SKIPPED.contains(&element.name()) => continue,
+            Node::Element(element) => block(child, element.name()),
+            _ => None,
+        };
+        match block {
+            Some(block) => {
+                blocks.extend(finish_paragraph(&std::mem::take(&mut paragraph)));
+                blocks.extend(block);
+            }
+            None => paragraph.push_str(&inline(child)),
+        }
+    }
+    blocks.extend(finish_paragraph(&paragraph));
+    blocks
+}
+
+/// Markdown blocks of a block element, `None` for inline elements
+fn block(node: NodeRef<Node>, name: &str) -> Option<Vec<String>> {
+    let element = ElementRef::wrap(node)?;
+    Some(match name {
+        "p" => finish_paragraph(&inline_children(node)).into_iter().collect(),
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let text = collapse(&inline_children(node).replace("\\\n", " "));
+            let level = name[1..].parse::<usize>().unwrap_or(1);
+            (!text.is_empty()).then(|| format!("{} {text}", "#".repeat(level))).into_iter().collect()
+        }
+        "ul" | "ol" => vec![list(element, name == "ol")].into_iter().filter(|list| !list.is_empty()).collect(),
+        "pre" => vec![code_block(element)],
+        "blockquote" => {
+            let quoted = blocks(node).join("\n\n");
+            (!quoted.is_empty()).then(|| prefix_lines(&quoted, "> ", ">")).into_iter().collect()
+        }
+        "table" => table(element).into_iter().collect(),
+        "hr" => vec!["* * *".to_string()],
+        "dl" => element.children().filter_map(ElementRef::wrap).flat_map(|child| match child.value().name() {
+            "dt" => finish_paragraph(&format!("**{}**", collapse(&inline_children(*child)))).into_iter().collect(),
+            _ => blocks(*child),
+        }).collect(),
+        name if CONTAINERS.contains(&name) => blocks(node),
+        _ => return None,
+    })
+}
+
+fn inline_children(node: NodeRef<Node>) -> String {
+    node.children().map(inline).collect()
+}
+
+/// Markdown of `node` within a paragraph
+fn inline(node: NodeRef<Node>) -> String {
+    let element = match node.value() {
+        Node::Text(text) => return escape(&text.replace(['\n', '\t', '\r'], " ")),
+        Node::Element(element) => element,
+        _ => return String::new(),
+    };
+    let wrap = |marker: &str| {
+        let content = inline_children(node);
+        match content.trim() {
+            "" => content,
+            trimmed => {
+                // emphasis markers can't be next to the spaces they enclose
+                let leading = &content[..content.len() - content.trim_start().len()];
+                let trailing = &content[content.trim_end().len()..];
+                format!("{leading}{marker}{trimmed}{marker}{trailing}")
+            }
+        }
+    };
+    match element.name() {
+        name if SKIPPED.contains(&name) => String::new(),
+        "br" => "\\\n".to_string(),
+        "em" | "i" | "cite" | "dfn" => wrap("*"),
+        "strong" | "b" => wrap("**"),
+        "del" | "s" | "strike" => wrap("~~"),
+        "code" | "kbd" | "samp" | "tt" => {
+            let code = node.descendants().filter_map(|node| node.value().as_text()).map(|text| &**text).collect::<String>();
+            let fence = "`".repeat(longest_run(&code, '`') + 1);
+            let padding = if code.starts_with('`') || code.ends_with('`') { " " } else { "" };
+            match code.is_empty() {
+                true => String::new(),
+                false => format!("{fence}{padding}{}{padding}{fence}", code.replace('\n', " ")),
+            }
+        }
+        "a" => {
+            let content = inline_children(node);
+            match element.attr("href").map(str::trim).filter(|href| !href.is_empty() && !href.starts_with("javascript:")) {
+                Some(href) if !content.trim().is_empty() => format!("[{}]({}{})", content.trim(), destination(href), title(element.attr("title"))),
+                _ => content,
+            }
+        }
+        "img" => match element.attr("src").map(str::trim).filter(|src| !src.is_empty()) {
+            Some(src) => format!("![{}]({}{})", escape(element.attr("alt").unwrap_or_default().trim()), destination(src), title(element.attr("title"))),
+            None => String::new(),
+        },
+        _ => inline_children(node),
+    }
+}
+
+/// Link destination, in angle brackets when it holds spaces or parentheses
+fn destination(url: &str) -> String {
+    match url.contains([' ', '(', ')']) {
+        true => format!("<{}>", url.replace('<', "%3C").replace('>', "%3E")),
+        false => url.to_string(),
+    }
+}
+
+fn title(title: Option<&str>) -> String {
+    match title.map(str::trim).filter(|title| !title.is_empty()) {
+        Some(title) => format!(" \"{}\"", title.replace('"', "\\\"")),
+        None => String::new(),
+    }
+}
+
+/// Text with the characters markdown would interpret escaped
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '[' | ']' | '`' | '<') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Whitespace collapsed to single spaces
+fn collapse(text: &str) -> String {
+    text.split(' ').filter(|word| !word.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+/// Paragraph of inline markdown, `None` when it's empty
+fn finish_paragraph(text: &str) -> Option<String> {
+    let lines = text.split('\n').map(collapse).collect::<Vec<_>>();
+    let paragraph = lines.join("\n").trim_end_matches("\\\n").trim_end_matches('\\').trim().to_string();
+    if paragraph.is_empty() {
+        return None;
+    }
+    // text starting like a heading, a quote or a list item isn't one
+    let first = paragraph.split_whitespace().next().unwrap_or_default();
+    let digits = first.len() - first.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if matches!(first, "-" | "+") || first.starts_with(['#', '>']) {
+        return Some(format!("\\{paragraph}"));
+    }
+    if digits > 0 && matches!(&first[digits..], "." | ")") {
+        return Some(format!("{}\\{}", &paragraph[..digits], &paragraph[digits..]));
+    }
+    Some(paragraph)
+}
+
+fn prefix_lines(text: &str, prefix: &str, empty_prefix: &str) -> String {
+    text.lines().map(|line| if line.is_empty() { empty_prefix.to_string() } else { format!("{prefix}{line}") }).collect::<Vec<_>>().join("\n")
+}
+
+fn list(element: ElementRef, ordered: bool) -> String {
+    let start = element.attr("start").and_then(|start| start.trim().parse::<usize>().ok()).unwrap_or(1);
+    element
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|child| child.value().name() == "li")
+        .enumerate()
+        .map(|(i, item)| {
+            let marker = if ordered { format!("{}. ", start + i) } else { "- ".to_string() };
+            let content = blocks(*item).join("\n");
+            let indent = " ".repeat(marker.len());
+            let mut lines = content.lines();
+            let first = format!("{marker}{}", lines.next().unwrap_or_default());
+            std::iter::once(first.trim_end().to_string())
+                .chain(lines.map(|line| if line.is_empty() { String::new() } else { format!("{indent}{line}") }))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    text.split(|other| other != c).map(str::len).max().unwrap_or_default()
+}
+
+fn code_block(pre: ElementRef) -> String {
+    let code = pre.text().collect::<String>();
+    let code = code.strip_prefix('\n').unwrap_or(&code).trim_end();
+    // `language-rust` or `lang-rust`, on the <pre> or its <code>
+    let language = std::iter::once(pre)
+        .chain(pre.children().filter_map(ElementRef::wrap).filter(|child| child.value().name() == "code"))
+        .flat_map(|element| element.value().classes().map(str::to_string).collect::<Vec<_>>())
+        .find_map(|class| class.strip_prefix("language-").or_else(|| class.strip_prefix("lang-")).map(str::to_string))
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+    format!("{fence}{language}\n{code}\n{fence}")
+}
+
+/// Pipe table, the first row as header
+fn table(table: ElementRef) -> Option<String> {
+    let grid = tables::grid(&table);
+    let width = grid.first()?.len();
+    if width == 0 {
+        return None;
+    }
+    let row = |cells: &[String]| format!("| {} |", cells.iter().map(|cell| escape(cell).replace('|', "\\|")).collect::<Vec<_>>().join(" | "));
+    let mut lines = vec![row(&grid[0]), format!("|{}", " --- |".repeat(width))];
+    lines.extend(grid[1..].iter().map(|cells| row(cells)));
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_extensions_to_the_writer() {
+        let options = |format: &str| PandocOptions { format: format.to_string(), ..Default::default() };
+        assert_eq!(options("gfm").writer(), "gfm-raw_html");
+        assert_eq!(options("org").writer(), "org");
+        assert_eq!(PandocOptions { extensions: Some("+smart".to_string()), ..options("asciidoc") }.writer(), "asciidoc+smart");
+    }
+
+    #[test]
+    fn names_files_after_the_format() {
+        assert_eq!(file_extension("commonmark"), "md");
+        assert_eq!(file_extension("asciidoc"), "adoc");
+        assert_eq!(file_extension("org"), "org");
+        assert_eq!(file_extension("plain"), "plain.txt");
+    }
+
+    #[test]
+    fn converts_common_elements() {
+        let html = r#"<html><head><title>Ignored</title></head><body><article>
+            <h1>Fixture <em>article</em></h1>
+            <p>Some <strong>bold</strong> and <i>italic </i>text, a <a href="/post (1)" title="First">link</a>
+               and <code>a `tick`</code>.<br>New line with 5*3_x.</p>
+            <p>1. not a list</p>
+            <ul><li>One</li><li>Two<ol start="3"><li>Three</li></ol></li></ul>
+            <blockquote><p>Quoted</p><p>twice</p></blockquote>
+            <pre><code class="language-rust">fn main() {
+    println!("hi");
+}</code></pre>
+            <figure><img src="red.png" alt="A red square"></figure>
+            <table><tr><th>Name</th><th>Size</th></tr><tr><td>a|b</td><td>3</td></tr></table>
+            <script>track()</script>
+        </article></body></html>"#;
+        assert_eq!(from_html(html), "# Fixture *article*\n\n\
+            Some **bold** and *italic* text, a [link](</post (1)> \"First\") and `` a `tick` ``.\\\nNew line with 5\\*3\\_x.\n\n\
+            1\\. not a list\n\n\
+            - One\n- Two\n  3. Three\n\n\
+            > Quoted\n>\n> twice\n\n\
+            ```rust\nfn main() {\n    println!(\"hi\");\n}\n```\n\n\
+            ![A red square](red.png)\n\n\
+            | Name | Size |\n| --- | --- |\n| a\\|b | 3 |\n");
+    }
+}
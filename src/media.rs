@@ -0,0 +1,202 @@
+use std::collections::{HashMap, HashSet};
+use futures::future::join_all;
+use scraper::{Html, Node, Selector};
+use url::Url;
+use crate::filename;
+use crate::http::HttpClient;
+use crate::options::MediaLimits;
+use crate::warnings::{Warning, WarningKind, Warnings};
+
+/// Directory of the capture holding the video and audio files
+pub const DIRECTORY: &str = "media";
+
+/// Extensions of the files players can play as they are, streaming manifests (`.m3u8`, `.mpd`) aren't
+const EXTENSIONS: &[&str] = &["mp4", "m4v", "webm", "ogv", "ogg", "oga", "mov", "mp3", "m4a", "aac", "opus", "wav", "flac"];
+
+/// Video or audio file of the page, saved in `media/`
+#[derive(Debug, Clone)]
+pub struct MediaFile {
+    pub filename: String,
+    pub url: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Whether `src`, of the MIME `kind` when given, is a file rather than a stream or a `blob:` of the page
+fn is_direct_file(src: &Url, kind: Option<&str>) -> bool {
+    if !matches!(src.scheme(), "http" | "https") {
+        return false;
+    }
+    let kind = kind.map(|kind| kind.split(';').next().unwrap_or_default().trim().to_ascii_lowercase());
+    if let Some(kind) = kind.filter(|kind| !kind.is_empty()) {
+        return (kind.starts_with("video/") || kind.starts_with("audio/")) && !kind.contains("mpegurl");
+    }
+    let name = src.path_segments().and_then(|mut segments| segments.next_back()).unwrap_or_default();
+    EXTENSIONS.contains(&filename::split_extension(name).1.to_ascii_lowercase().as_str())
+}
+
+/// Download the video and audio files of `html` within `limits` and point their elements
+/// at the local copies. Streams, and files over the limits, are left as they are with a warning
+pub async fn capture(html: &str, base_url: &str, client: &HttpClient, limits: &MediaLimits, warnings: &Warnings) -> (String, Vec<MediaFile>) {
+
+    let Ok(base_url) = Url::parse(base_url) else {
+        return (html.to_string(), Vec::new());
+    };
+
+    let mut document = Html::parse_document(html);
+    let media_selector = Selector::parse("video, audio").unwrap();
+    let source_selector = Selector::parse("source[src]").unwrap();
+
+    // the element whose `src` points at the file, for each player
+    let mut candidates = Vec::new();
+    for player in document.select(&media_selector) {
+        let own = player.attr("src").map(|src| (player, src, None));
+        let sources = player.select(&source_selector).map(|source| (source, source.attr("src").unwrap_or_default(), source.attr("type")));
+        let found = own.into_iter().chain(sources).find_map(|(element, src, kind)| {
+            let url = base_url.join(src.trim()).ok()?;
+            is_direct_file(&url, kind).then_some((element.id(), url))
+        });
+        match found {
+            Some(candidate) => candidates.push(candidate),
+            None if player.attr("src").is_some() || player.select(&source_selector).next().is_some() => {
+                warnings.push(Warning::with_url(WarningKind::SkippedMedia, base_url.as_str(), format!(
+                    "the <{}> of the page plays a stream, not a file", player.value().name()
+                )));
+            }
+            None => {}
+        }
+    }
+    let mut seen = HashSet::new();
+    let urls = candidates.iter().map(|(_, url)| url.clone()).filter(|url| seen.insert(url.clone())).collect::<Vec<_>>();
+
+    // sizes announced before downloading, so files over the limits aren't transferred
+    let sizes = join_all(urls.iter().map(|url| client.content_length(url))).await;
+    let mut remaining = limits.max_total_bytes;
+    let mut selected = Vec::new();
+    for (url, size) in urls.into_iter().zip(sizes) {
+        let size = size.ok().flatten();
+        if let Some(size) = size && let Err(reason) = limits.check(size, remaining) {
+            warnings.push(Warning::with_url(WarningKind::SkippedMedia, url.as_str(), reason));
+            continue;
+        }
+        if let (Some(remaining), Some(size)) = (remaining.as_mut(), size) {
+            *remaining -= size;
+        }
+        selected.push(url);
+    }
+
+    // bounded by the limits too when the server didn't announce the size
+    let max_bytes = limits.max_total_bytes.map_or(limits.max_file_bytes, |total| total.min(limits.max_file_bytes));
+    let responses = join_all(selected.iter().map(|url| client.get_limited(url, Some(max_bytes)))).await;
+    let mut remaining = limits.max_total_bytes;
+    let mut taken = HashSet::new();
+    let mut files = Vec::new();
+    let mut paths = HashMap::new();
+    for (url, response) in selected.into_iter().zip(responses) {
+        let bytes = match response {
+            Ok(response) => response.body,
+            Err(error) => {
+                warnings.push(Warning::with_url(WarningKind::SkippedMedia, url.as_str(), error.to_string()));
+                continue;
+            }
+        };
+        // servers not announcing the size, or announcing a wrong one
+        if let Err(reason) = limits.check(bytes.len() as u64, remaining) {
+            warnings.push(Warning::with_url(WarningKind::SkippedMedia, url.as_str(), reason));
+            continue;
+        }
+        if let Some(remaining) = remaining.as_mut() {
+            *remaining -= bytes.len() as u64;
+        }
+        let name = match url.path_segments().and_then(|mut segments| segments.next_back()).unwrap_or_default() {
+            "" => format!("media-{}", files.len() + 1),
+            name => filename::sanitize(name),
+        };
+        let filename = filename::unique(&name, &mut taken);
+        paths.insert(url.clone(), format!("{DIRECTORY}/{}", filename::to_url_path(&filename)));
+        files.push(MediaFile { filename, url: url.to_string(), bytes });
+    }
+
+    if paths.is_empty() {
+        return (html.to_string(), files);
+    }
+    for (id, url) in candidates {
+        if let Some(path) = paths.get(&url)
+            && let Some(mut node) = document.tree.get_mut(id)
+            && let Node::Element(element) = node.value()
+            && let Some((_, src)) = element.attrs.iter_mut().find(|(name, _)| &*name.local == "src") {
+            *src = path.as_str().into();
+        }
+    }
+    (document.html(), files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::NetworkMode;
+    use crate::testing::FixtureServer;
+
+    #[test]
+    fn recognizes_files() {
+        let url = |url: &str| Url::parse(url).unwrap();
+        assert!(is_direct_file(&url("https://example.com/clip.MP4?t=3"), None));
+        assert!(is_direct_file(&url("https://example.com/play?id=3"), Some("video/webm; codecs=vp9")));
+        assert!(!is_direct_file(&url("https://example.com/live.m3u8"), None));
+        assert!(!is_direct_file(&url("https://example.com/live"), Some("application/x-mpegURL")));
+        assert!(!is_direct_file(&url("blob:https://example.com/5c1e"), Some("video/mp4")));
+    }
+
+    #[tokio::test]
+    async fn downloads_media_within_limits() {
+        let server = FixtureServer::new().unwrap();
+        server.route("/clip.webm", "video/webm", vec![1; 100]);
+        server.route("/talk.mp3", "audio/mpeg", vec![2; 10]);
+        server.route("/movie.mp4", "video/mp4", vec![3; 1000]);
+        let html = r#"<html><body>
+            <video controls><source src="/stream.m3u8"><source src="/clip.webm" type="video/webm"></video>
+            <audio src="/talk.mp3"></audio>
+            <video src="/movie.mp4"></video>
+        </body></html>"#;
+
+        let client = HttpClient::new(&NetworkMode::Live).unwrap();
+        let warnings = Warnings::default();
+        let limits = MediaLimits { max_file_bytes: 500, max_total_bytes: None };
+        let (html, files) = capture(html, &server.url("/post.html"), &client, &limits, &warnings).await;
+
+        assert_eq!(files.iter().map(|file| file.filename.as_str()).collect::<Vec<_>>(), ["clip.webm", "talk.mp3"]);
+        assert!(html.contains(r#"<source src="media/clip.webm" type="video/webm">"#));
+        assert!(html.contains(r#"<audio src="media/talk.mp3">"#));
+        assert!(html.contains(r#"<video src="/movie.mp4">"#));
+        assert_eq!(warnings.len(), 1);
+        assert!(!server.requests().contains(&"GET /movie.mp4 HTTP/1.1".to_string()));
+    }
+
+    #[tokio::test]
+    async fn stops_downloads_of_unannounced_sizes_at_the_limit() {
+        // answers with a body of 100 kB and no Content-Length
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                use std::io::{Read, Write};
+                let _ = stream.read(&mut [0; 4096]);
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: video/mp4\r\nConnection: close\r\n\r\n");
+                let _ = stream.write_all(&[0; 100_000]);
+            }
+        });
+        let html = format!(r#"<video><source src="http://{address}/clips/" type="video/mp4"></video><video src="http://{address}/movie.mp4"></video>"#);
+
+        let client = HttpClient::new(&NetworkMode::Live).unwrap();
+        let warnings = Warnings::default();
+        let limits = MediaLimits { max_file_bytes: 1000, max_total_bytes: None };
+        let (_, files) = capture(&html, &format!("http://{address}/"), &client, &limits, &warnings).await;
+        assert!(files.is_empty());
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings.to_vec().iter().all(|warning| warning.message.ends_with("is over the limit of 1000 bytes")));
+
+        let limits = MediaLimits { max_file_bytes: 200_000, max_total_bytes: None };
+        let (html, files) = capture(&html, &format!("http://{address}/"), &client, &limits, &warnings).await;
+        assert_eq!(files.iter().map(|file| file.filename.as_str()).collect::<Vec<_>>(), ["media-1", "movie.mp4"]);
+        assert!(html.contains(r#"<source src="media/media-1" type="video/mp4">"#));
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// What a page says about itself in its `<meta>` tags (OpenGraph and Twitter cards included)
+/// and `application/ld+json` blocks. The fields take the first source giving a value:
+/// plain meta tags, then OpenGraph, then Twitter, then JSON-LD
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+    /// Publication date, as written by the page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site_name: Option<String>,
+    /// `article`, `website`, `NewsArticle`, ...
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// `og:` and `article:` properties, without their prefix for `og:`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub open_graph: BTreeMap<String, String>,
+    /// `twitter:` properties, without their prefix
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub twitter: BTreeMap<String, String>,
+    /// Parsed `application/ld+json` blocks
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub json_ld: Vec<Value>,
+}
+
+impl Metadata {
+
+    pub fn from_html(html: &str) -> Self {
+
+        let document = Html::parse_document(html);
+        let meta_selector = Selector::parse("meta[content]").unwrap();
+        let json_ld_selector = Selector::parse(r#"script[type="application/ld+json"]"#).unwrap();
+
+        // every value of each name or property, lowercased, in document order
+        let mut meta: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for element in document.select(&meta_selector) {
+            let Some(key) = element.attr("property").or_else(|| element.attr("name")).or_else(|| element.attr("itemprop")) else { continue };
+            let content = element.attr("content").unwrap_or_default().trim();
+            if !content.is_empty() {
+                meta.entry(key.trim().to_ascii_lowercase()).or_default().push(content.to_string());
+            }
+        }
+        let json_ld = document
+            .select(&json_ld_selector)
+            .filter_map(|script| serde_json::from_str::<Value>(script.text().collect::<String>().trim()).ok())
+            .collect::<Vec<_>>();
+        let objects = json_ld.iter().flat_map(objects).collect::<Vec<_>>();
+
+        let first = |keys: &[&str]| keys.iter().find_map(|key| meta.get(*key)?.first().cloned());
+        let all = |keys: &[&str]| keys.iter().flat_map(|key| meta.get(*key).into_iter().flatten().cloned()).collect::<Vec<_>>();
+        let from_json_ld = |key: &str| objects.iter().find_map(|object| text(object.get(key)?));
+
+        let mut authors = all(&["author", "article:author"]);
+        if authors.is_empty() {
+            authors = objects.iter().find_map(|object| object.get("author")).map(names).unwrap_or_default();
+        }
+        let mut keywords = all(&["keywords", "article:tag"]).iter()
+            .flat_map(|keywords| keywords.split(',').map(|keyword| keyword.trim().to_string()).collect::<Vec<_>>())
+            .filter(|keyword| !keyword.is_empty())
+            .collect::<Vec<_>>();
+        if keywords.is_empty() {
+            keywords = match objects.iter().find_map(|object| object.get("keywords")) {
+                Some(Value::Array(values)) => values.iter().filter_map(text).collect(),
+                Some(value) => text(value).map(|keywords| keywords.split(',').map(|keyword| keyword.trim().to_string()).collect()).unwrap_or_default(),
+                None => Vec::new(),
+            };
+        }
+        keywords.dedup();
+
+        let prefixed = |prefix: &str, strip: bool| meta.iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, values)| (if strip { key[prefix.len()..].to_string() } else { key.clone() }, values[0].clone()))
+            .collect::<BTreeMap<_, _>>();
+        let mut open_graph = prefixed("og:", true);
+        open_graph.extend(prefixed("article:", false));
+
+        Self {
+            description: first(&["description", "og:description", "twitter:description"]).or_else(|| from_json_ld("description")),
+            authors,
+            published: first(&["article:published_time", "datepublished", "date", "pubdate"]).or_else(|| from_json_ld("datePublished")),
+            modified: first(&["article:modified_time", "og:updated_time", "datemodified"]).or_else(|| from_json_ld("dateModified")),
+            site_name: first(&["og:site_name", "application-name"])
+                .or_else(|| objects.iter().find_map(|object| names(object.get("publisher")?).into_iter().next())),
+            kind: first(&["og:type"]).or_else(|| from_json_ld("@type")),
+            keywords,
+            open_graph,
+            twitter: prefixed("twitter:", true),
+            json_ld,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Objects of a JSON-LD block, those of its `@graph` included
+fn objects(value: &Value) -> Vec<&serde_json::Map<String, Value>> {
+    match value {
+        Value::Array(values) => values.iter().flat_map(objects).collect(),
+        Value::Object(object) => std::iter::once(object).chain(object.get("@graph").into_iter().flat_map(objects)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Text of a JSON-LD value, the first of a list, `@type` lists included
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.trim().to_string()).filter(|text| !text.is_empty()),
+        Value::Array(values) => values.iter().find_map(text),
+        _ => None,
+    }
+}
+
+/// Names of JSON-LD persons or organizations, written as text or as objects
+fn names(value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(values) => values.iter().flat_map(names).collect(),
+        Value::Object(object) => object.get("name").and_then(text).into_iter().collect(),
+        value => text(value).into_iter().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_meta_tags_and_json_ld() {
+        let html = r#"<html><head>
+            <meta property="og:description" content="From OpenGraph">
+            <meta property="og:type" content="article">
+            <meta property="article:tag" content="rust">
+            <meta property="article:tag" content="scraping">
+            <meta name="twitter:card" content="summary_large_image">
+            <script type="application/ld+json">{"@context": "https://schema.org", "@graph": [
+                {"@type": "WebSite", "name": "Example"},
+                {"@type": "NewsArticle", "datePublished": "2024-03-01T08:00:00Z", "description": "From JSON-LD",
+                 "author": [{"@type": "Person", "name": "Ada Lovelace"}, "Charles Babbage"],
+                 "publisher": {"@type": "Organization", "name": "Example News"}}
+            ]}</script>
+            <script type="application/ld+json">not json</script>
+        </head></html>"#;
+        let metadata = Metadata::from_html(html);
+
+        assert_eq!(metadata.description.as_deref(), Some("From OpenGraph"));
+        assert_eq!(metadata.authors, ["Ada Lovelace", "Charles Babbage"]);
+        assert_eq!(metadata.published.as_deref(), Some("2024-03-01T08:00:00Z"));
+        assert_eq!(metadata.site_name.as_deref(), Some("Example News"));
+        assert_eq!(metadata.kind.as_deref(), Some("article"));
+        assert_eq!(metadata.keywords, ["rust", "scraping"]);
+        assert_eq!(metadata.open_graph["type"], "article");
+        assert_eq!(metadata.twitter["card"], "summary_large_image");
+        assert_eq!(metadata.json_ld.len(), 1);
+        assert!(Metadata::from_html("<p>Nothing</p>").is_empty());
+    }
+}
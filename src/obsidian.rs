@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use regex::{Captures, Regex};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ObsidianError {
+    #[error("I/O Error: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("{0} isn't a directory, the vault has to exist")]
+    NotAVault(PathBuf),
+    #[error("the note {0} already exists")]
+    NoteExists(PathBuf),
+}
+
+pub type Result<T> = std::result::Result<T, ObsidianError>;
+
+/// Obsidian vault the captures are written into as notes, their images in the attachment
+/// folder the vault is configured with
+#[derive(Debug, Clone)]
+pub struct Vault {
+    root: PathBuf,
+    /// Folder of the notes within the vault, its root unless given
+    folder: PathBuf,
+    /// Added to the keywords of the page in the `tags` property
+    tags: Vec<String>,
+}
+
+impl Vault {
+
+    /// Settings of the vault, with the attachment folder
+    const APP_SETTINGS: &str = ".obsidian/app.json";
+
+    pub fn open(root: &Path) -> Result<Self> {
+        if !root.is_dir() {
+            return Err(ObsidianError::NotAVault(root.to_path_buf()));
+        }
+        Ok(Self { root: root.to_path_buf(), folder: PathBuf::new(), tags: Vec::new() })
+    }
+
+    pub fn with_folder(mut self, folder: impl Into<PathBuf>) -> Self {
+        self.folder = folder.into();
+        self
+    }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Path of the note named `stem`
+    pub fn note_path(&self, stem: &str) -> PathBuf {
+        self.root.join(&self.folder).join(format!("{stem}.md"))
+    }
+
+    /// Directory the attachments of the notes go in, following the
+    /// `attachmentFolderPath` setting: `/` for the vault root, the default, `./` for
+    /// the folder of the note, `./<name>` for a subfolder of it, `<name>` for a vault folder
+    pub fn attachment_directory(&self) -> PathBuf {
+        let setting = std::fs::read_to_string(self.root.join(Self::APP_SETTINGS)).ok()
+            .and_then(|settings| serde_json::from_str::<serde_json::Value>(&settings).ok())
+            .and_then(|settings| settings.get("attachmentFolderPath").and_then(|path| path.as_str()).map(str::to_string))
+            .unwrap_or_default();
+        match setting.trim() {
+            "" | "/" => self.root.clone(),
+            relative if relative == "." || relative.starts_with("./") => self.root.join(&self.folder).join(relative.trim_start_matches('.').trim_start_matches('/')),
+            folder => self.root.join(folder.trim_matches('/')),
+        }
+    }
+}
+
+/// Tag as Obsidian accepts them: no `#`, spaces or punctuation other than `_`, `-` and `/`,
+/// not only digits. `None` when nothing is left
+pub fn tag(text: &str) -> Option<String> {
+    let tag = text.trim().trim_start_matches('#')
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '/'))
+        .collect::<String>();
+    let tag = tag.trim_matches(['-', '/']);
+    (tag.chars().any(|c| !c.is_ascii_digit())).then(|| tag.to_string())
+}
+
+/// `markdown` with its images, `![alt](path)` with `path` a key of `attachments`, turned into
+/// `![[name|alt]]` embeds of the attachment names
+pub fn embed_images(markdown: &str, attachments: &HashMap<String, String>) -> String {
+
+    static IMAGE: OnceLock<Regex> = OnceLock::new();
+    let image = IMAGE.get_or_init(|| Regex::new(r#"!\[((?:\\.|[^\]\\])*)\]\((<[^>]*>|[^)\s]+)(?:\s+"(?:\\.|[^"\\])*")?\)"#).unwrap());
+
+    image
+        .replace_all(markdown, |captures: &Captures| {
+            let target = captures[2].trim_start_matches('<').trim_end_matches('>');
+            let Some(name) = attachments.get(target) else {
+                return captures[0].to_string();
+            };
+            // `|` and `]` would end the embed early
+            let alt = captures[1].replace('\\', "").replace(['|', ']', '['], " ").split_whitespace().collect::<Vec<_>>().join(" ");
+            match alt.is_empty() {
+                true => format!("![[{name}]]"),
+                false => format!("![[{name}|{alt}]]"),
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embeds_local_images() {
+        let attachments = HashMap::from([("images/a%20b.png".to_string(), "Post - a b.png".to_string())]);
+        let markdown = "Intro\n\n![A \\[diagram\\]](images/a%20b.png \"Title\")\n\n![](images/a%20b.png) ![remote](https://example.com/c.png)";
+        assert_eq!(
+            embed_images(markdown, &attachments),
+            "Intro\n\n![[Post - a b.png|A diagram]]\n\n![[Post - a b.png]] ![remote](https://example.com/c.png)"
+        );
+    }
+
+    #[test]
+    fn makes_obsidian_tags() {
+        assert_eq!(tag("#Machine Learning").as_deref(), Some("Machine-Learning"));
+        assert_eq!(tag("c++/rust").as_deref(), Some("c/rust"));
+        assert_eq!(tag("2024"), None);
+        assert_eq!(tag("  "), None);
+    }
+
+    #[test]
+    fn follows_the_attachment_folder_setting() {
+        let root = tempfile::tempdir().unwrap();
+        let vault = Vault::open(root.path()).unwrap().with_folder("Clippings");
+        assert_eq!(vault.attachment_directory(), root.path());
+        assert_eq!(vault.note_path("Post"), root.path().join("Clippings/Post.md"));
+
+        std::fs::create_dir(root.path().join(".obsidian")).unwrap();
+        std::fs::write(root.path().join(Vault::APP_SETTINGS), r#"{"attachmentFolderPath": "./assets"}"#).unwrap();
+        assert_eq!(vault.attachment_directory(), root.path().join("Clippings/assets"));
+        std::fs::write(root.path().join(Vault::APP_SETTINGS), r#"{"attachmentFolderPath": "Attachments"}"#).unwrap();
+        assert_eq!(vault.attachment_directory(), root.path().join("Attachments"));
+    }
+}
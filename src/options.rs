@@ -0,0 +1,323 @@
+use std::time::Duration;
+use base64::Engine;
+use serde::Deserialize;
+use url::Url;
+use crate::convert::ImageFormat;
+use crate::progress::Progress;
+use crate::filename::Transliteration;
+use crate::http::{NetworkMode, RetryPolicy};
+use crate::srcset::SrcsetStrategy;
+use crate::config::matches_domain;
+use crate::markdown::{MarkdownConverter, PandocOptions};
+use crate::layout::Layout;
+use crate::pdf::PrintOptions;
+use crate::extract::Fields;
+use crate::robots::Robots;
+use crate::ratelimit::RateLimiter;
+
+/// What to do with a capture whose markdown falls below `ScrapeOptions::min_words`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContentGuard {
+    /// Keep the capture but mark it as low content in `informations.json`
+    #[default]
+    Flag,
+    /// Refuse the capture with `WebPageError::InsufficientContent`
+    Fail,
+}
+
+/// Options applied when turning a browser tab into a `WebPage`
+#[derive(Debug, Clone, Default)]
+pub struct ScrapeOptions {
+    /// Minimum number of markdown words expected from a page,
+    /// fewer usually means a consent wall, login wall or JS error got captured
+    pub min_words: Option<usize>,
+    pub content_guard: ContentGuard,
+    /// Transliteration applied to titles when naming output files and directories
+    pub transliteration: Transliteration,
+    /// Minimum delay between two pages of the same host
+    pub domain_delay: Duration,
+    /// Random extra delay, up to this value, added to `domain_delay`
+    pub domain_jitter: Duration,
+    /// Minimum delay between two downloads from the same host: images, media, assets, ...
+    pub request_delay: Duration,
+    /// Slots of the requests, shared by the renderer and the pages scraped with clones of the
+    /// options. Also caps the requests over all hosts when made `with_max_rps`
+    pub rate_limit: std::sync::Arc<RateLimiter>,
+    /// Whether asset requests are live, recorded or replayed from a recording
+    pub network: NetworkMode,
+    /// Which candidate of `srcset` and `data-srcset` attributes gets downloaded
+    pub srcset_strategy: SrcsetStrategy,
+    /// Step down from the selected `srcset` candidate to smaller ones until
+    /// a HEAD request announces at most this many bytes
+    pub srcset_max_bytes: Option<u64>,
+    /// CSS selector waited for before the page is captured
+    pub wait_for: Option<String>,
+    /// Extra time given to the page once loaded
+    pub wait_after_load: Duration,
+    /// CSS selectors of elements removed from the HTML before conversion
+    pub remove_selectors: Vec<String>,
+    /// CSS selector of the elements the markdown, images, links and saved HTML are made of,
+    /// the whole page when it matches nothing
+    pub select: Option<String>,
+    pub auth: Option<SiteAuth>,
+    /// Expand the comment thread of the page and save it to `comments.md`
+    pub comments: bool,
+    /// Save the source of embedded Gists and code snippets to `code/`
+    pub code_embeds: bool,
+    /// Save what the HTML parser had to recover from to `html_report.json`
+    pub html_report: bool,
+    /// Attributes where lazy loaders keep the real image URL
+    pub lazy_attributes: LazyAttributes,
+    /// Refuse pages asking not to be archived (`noarchive` robots directive)
+    /// with `WebPageError::NoArchive` instead of storing them
+    pub honor_noarchive: bool,
+    /// Images left out of the capture for their size
+    pub image_filter: ImageFilter,
+    /// Limits of the images downloaded for a page
+    pub image_budget: ImageBudget,
+    /// Image and stylesheet downloads in flight at once,
+    /// `HttpClient::DEFAULT_CONCURRENCY` when unset
+    pub image_concurrency: Option<usize>,
+    /// Downloads in flight shared with the other pages scraped at the same time,
+    /// taking over `image_concurrency`
+    pub download_permits: Option<std::sync::Arc<tokio::sync::Semaphore>>,
+    /// Retries of image and stylesheet downloads failing transiently
+    pub retry: RetryPolicy,
+    /// Format WebP images, and AVIF ones with the `avif` feature, are converted to, kept as downloaded when unset
+    pub convert_images: Option<ImageFormat>,
+    /// Downscaled copies of the large images saved in `images/thumbs/`
+    pub thumbnails: Option<Thumbnails>,
+    /// Remove the EXIF (GPS position included), XMP and IPTC metadata of JPEG images
+    pub strip_metadata: bool,
+    /// Take image bytes from the responses the browser received, so they're those the page displayed.
+    /// Images the browser didn't load are downloaded
+    pub browser_images: bool,
+    /// Save the stylesheets, scripts and web fonts to `assets/`, for the HTML to be browsable offline
+    pub assets: bool,
+    /// Also save the page as a Word document, made by pandoc from the markdown and its images
+    pub docx: bool,
+    /// Save the sections, paragraphs, lists, images and tables of the page to `structure.json`
+    pub structure: bool,
+    /// Save the word frequencies (this many top terms), headings, links and section lengths to `stats.json`
+    pub stats: Option<usize>,
+    /// Where the HTML, PDF, markdown, text and DOCX go within the capture directory
+    pub layout: Layout,
+    /// Orientation, margins, scale and background of the PDF
+    pub pdf: PrintOptions,
+    /// Also save the text of the page, without any markup, to a `.txt` file
+    pub plain_text: bool,
+    /// Also save the page and every response received while scraping it to a WARC file
+    pub warc: bool,
+    /// Also save the single-file MHTML snapshot of the browser (Chrome backend only)
+    pub mhtml: bool,
+    /// Also save the page as one HTML file with its images, stylesheets, scripts and fonts inline.
+    /// The assets are downloaded for it, but only saved to `assets/` when `assets` is set
+    pub single_file: bool,
+    /// Start the markdown with YAML front matter (title, URL, date, authors, tags, word count),
+    /// the header of their own for org, AsciiDoc and reStructuredText, none for the other formats
+    pub front_matter: bool,
+    /// Insert a linked table of contents of the headings down to this level after the title of the markdown
+    pub toc: Option<usize>,
+    /// Save a JPEG thumbnail this many pixels wide of the top of the page, referenced after the
+    /// title of the markdown (Chrome only)
+    pub screenshot_thumbnail: Option<u32>,
+    /// Save the data tables of the page as CSV files in `tables/`, linked from the markdown
+    pub tables: bool,
+    /// pandoc, or the built-in converter for machines without it
+    pub markdown_converter: MarkdownConverter,
+    /// Writer and arguments of pandoc, when it converts the page
+    pub pandoc: PandocOptions,
+    /// Download no image at all (favicon and preview image included),
+    /// the saved HTML and markdown keep linking to the remote ones
+    pub no_images: bool,
+    /// Strip scripts, frames, event handlers and trackers from the saved HTML and single file,
+    /// so they can be opened without running third-party code
+    pub sanitize: bool,
+    /// List the RSS, Atom and JSON feeds the page links to without downloading them to `feeds/`
+    pub no_feeds: bool,
+    /// Named fields, CSS selectors or XPath expressions, extracted from the page to `extracted.json`
+    pub extract: Fields,
+    /// Save the video and audio files of the page to `media/`, within these limits
+    pub media: Option<MediaLimits>,
+    /// Told when each image download starts and ends
+    pub progress: Option<Progress>,
+    /// robots.txt rules of the sites, pages and images they disallow aren't fetched
+    pub robots: Option<std::sync::Arc<Robots>>,
+}
+
+/// Size limits keeping tracking pixels, spacers and icons out of the capture.
+/// Images whose dimensions can't be read from their header are only checked for bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImageFilter {
+    pub min_width: Option<u32>,
+    pub min_height: Option<u32>,
+    pub min_bytes: Option<usize>,
+    pub max_bytes: Option<usize>,
+}
+
+/// Most images, and most image bytes, downloaded for a single page.
+/// Images found once a limit is reached are skipped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImageBudget {
+    pub max_count: Option<usize>,
+    pub max_bytes: Option<u64>,
+}
+
+/// Size limits of the video and audio files downloaded for a page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MediaLimits {
+    pub max_file_bytes: u64,
+    pub max_total_bytes: Option<u64>,
+}
+
+/// Thumbnails of the images wider or taller than `max_size` pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Thumbnails {
+    pub max_size: u32,
+    /// Save the originals along with their thumbnails, otherwise they're only listed in the manifest
+    pub keep_originals: bool,
+}
+
+impl ImageFilter {
+
+    /// Whether an image of `bytes` bytes, and of `dimensions` when known, is kept
+    pub fn accepts(&self, bytes: usize, dimensions: Option<(u32, u32)>) -> bool {
+        let (width, height) = dimensions.unzip();
+        self.min_bytes.is_none_or(|min| bytes >= min)
+            && self.max_bytes.is_none_or(|max| bytes <= max)
+            && self.min_width.zip(width).is_none_or(|(min, width)| width >= min)
+            && self.min_height.zip(height).is_none_or(|(min, height)| height >= min)
+    }
+}
+
+impl MediaLimits {
+
+    pub const DEFAULT_MAX_FILE_BYTES: u64 = 50_000_000;
+
+    /// Whether a file of `bytes` bytes fits, with `remaining` bytes left of the total.
+    /// The error tells which limit it exceeds
+    pub fn check(&self, bytes: u64, remaining: Option<u64>) -> Result<(), String> {
+        if bytes > self.max_file_bytes {
+            return Err(format!("{bytes} bytes, over the limit of {} bytes per file", self.max_file_bytes));
+        }
+        if remaining.is_some_and(|remaining| bytes > remaining) {
+            return Err(format!("{bytes} bytes, over what's left of the limit of {} media bytes", self.max_total_bytes.unwrap_or_default()));
+        }
+        Ok(())
+    }
+}
+
+impl Default for MediaLimits {
+    fn default() -> Self {
+        Self { max_file_bytes: Self::DEFAULT_MAX_FILE_BYTES, max_total_bytes: None }
+    }
+}
+
+/// Image attributes checked besides `src` and `srcset`, those containing
+/// `srcset` are parsed as srcsets, the others as plain URLs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LazyAttributes(pub Vec<String>);
+
+impl LazyAttributes {
+
+    pub const DEFAULT: &[&str] = &[
+        "data-src", "data-original", "data-lazy-src", "data-lazy", "data-srcset", "data-lazy-srcset",
+    ];
+
+    /// Attributes holding a single URL
+    pub fn src(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str).filter(|name| !name.contains("srcset"))
+    }
+
+    /// Attributes holding a srcset
+    pub fn srcset(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str).filter(|name| name.contains("srcset"))
+    }
+}
+
+impl Default for LazyAttributes {
+    fn default() -> Self {
+        Self(Self::DEFAULT.iter().map(|name| name.to_string()).collect())
+    }
+}
+
+/// Credentials of a site, from the `auth` entry of a domain configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Credentials {
+    /// HTTP basic authentication
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// `Cookie` header value, `name=value; other=value`
+    pub cookie: Option<String>,
+}
+
+/// Credentials only ever sent to `domain` and its subdomains
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiteAuth {
+    pub domain: String,
+    pub credentials: Credentials,
+}
+
+impl SiteAuth {
+
+    pub fn applies_to(&self, url: &Url) -> bool {
+        url.host_str().is_some_and(|host| matches_domain(host, &self.domain))
+    }
+
+    /// `Authorization` and `Cookie` headers carrying the credentials
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = Vec::new();
+        if let Some(username) = &self.credentials.username {
+            let password = self.credentials.password.as_deref().unwrap_or_default();
+            let token = base64::engine::general_purpose::STANDARD.encode(format!("{username}:{password}"));
+            headers.push(("Authorization", format!("Basic {token}")));
+        }
+        if let Some(cookie) = &self.credentials.cookie {
+            headers.push(("Cookie", cookie.clone()));
+        }
+        headers
+    }
+
+    /// `(name, value)` pairs of the cookie credentials
+    pub fn cookies(&self) -> Vec<(String, String)> {
+        self.credentials.cookie
+            .iter()
+            .flat_map(|cookie| cookie.split(';'))
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .collect()
+    }
+}
+
+impl ScrapeOptions {
+
+    /// Whether `nb_words` is below the configured threshold
+    pub fn is_low_content(&self, nb_words: usize) -> bool {
+        self.min_words.is_some_and(|min| nb_words < min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn low_content_threshold() {
+        let options = ScrapeOptions { min_words: Some(50), ..Default::default() };
+        assert!(options.is_low_content(49));
+        assert!(!options.is_low_content(50));
+        assert!(!ScrapeOptions::default().is_low_content(0));
+    }
+
+    #[test]
+    fn image_filter_limits() {
+        let filter = ImageFilter { min_width: Some(2), min_height: Some(2), min_bytes: Some(100), max_bytes: Some(1000) };
+        assert!(filter.accepts(500, Some((64, 64))));
+        assert!(!filter.accepts(500, Some((1, 1))));
+        assert!(!filter.accepts(50, Some((64, 64))));
+        assert!(!filter.accepts(5000, None));
+        assert!(filter.accepts(500, None));
+        assert!(ImageFilter::default().accepts(0, Some((1, 1))));
+    }
+}
@@ -0,0 +1,427 @@
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+use regex::bytes::Regex;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PdfError {
+    #[error("no trailer found, cross-reference streams aren't supported")]
+    NoTrailer,
+    #[error("malformed trailer: {0}")]
+    MalformedTrailer(&'static str),
+    #[error("the catalog of the document can't be found")]
+    NoCatalog,
+    #[error("font {0} isn't embedded, as PDF/A requires")]
+    UnembeddedFont(String),
+    #[error("invalid length {0:?}, expected a number followed by in, cm, mm or px")]
+    InvalidLength(String),
+    #[error("{0} isn't a scale between 0.1 and 2")]
+    InvalidScale(String),
+}
+
+pub type Result<T> = std::result::Result<T, PdfError>;
+
+/// Page layout of the printed PDF
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrintOptions {
+    pub landscape: bool,
+    /// Margin on every side, in inches, the browser's default when `None`
+    pub margin: Option<f64>,
+    /// Zoom of the page, from 0.1 to 2
+    pub scale: f64,
+    /// Print background colors and images
+    pub background: bool,
+    /// Have the browser turn the headings down to this level into PDF bookmarks, Chrome only
+    pub outline: Option<usize>,
+    /// Convert the PDF to PDF/A-2b for long-term archiving
+    pub pdfa: bool,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self { landscape: false, margin: None, scale: 1.0, background: true, outline: None, pdfa: false }
+    }
+}
+
+impl PrintOptions {
+
+    pub fn to_chrome(&self) -> headless_chrome::types::PrintToPdfOptions {
+        headless_chrome::types::PrintToPdfOptions {
+            landscape: Some(self.landscape),
+            print_background: Some(self.background),
+            scale: Some(self.scale),
+            margin_top: self.margin,
+            margin_bottom: self.margin,
+            margin_left: self.margin,
+            margin_right: self.margin,
+            // the outline is made from the structure of tagged PDFs
+            generate_tagged_pdf: self.outline.map(|_| true),
+            generate_document_outline: self.outline.map(|_| true),
+            ..Default::default()
+        }
+    }
+
+    /// `pdf` as printed with these options, with the outline the browser made of every heading
+    /// level limited to `outline`. Kept as is when it can't be
+    pub fn post_process(&self, pdf: Vec<u8>) -> Vec<u8> {
+        match self.outline {
+            Some(depth) => limit_outline(&pdf, depth).ok().flatten().unwrap_or(pdf),
+            None => pdf,
+        }
+    }
+
+    /// Parameters of the WebDriver print command, whose margins are in centimeters
+    pub fn to_webdriver(&self) -> serde_json::Value {
+        let mut parameters = serde_json::json!({
+            "orientation": if self.landscape { "landscape" } else { "portrait" },
+            "scale": self.scale,
+            "background": self.background,
+        });
+        if let Some(margin) = self.margin {
+            let margin = margin * 2.54;
+            parameters["margin"] = serde_json::json!({ "top": margin, "bottom": margin, "left": margin, "right": margin });
+        }
+        parameters
+    }
+}
+
+/// Length in inches of `1.5cm`, `10mm`, `0.5in` or `96px`, a bare number being in inches
+pub fn parse_length(text: &str) -> Result<f64> {
+    let text = text.trim();
+    let split = text.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number = number.trim().parse::<f64>().ok().filter(|number| number.is_finite() && *number >= 0.0);
+    let per_inch = match unit.trim() {
+        "" | "in" => 1.0,
+        "cm" => 2.54,
+        "mm" => 25.4,
+        "px" => 96.0,
+        _ => return Err(PdfError::InvalidLength(text.to_string())),
+    };
+    number.map(|number| number / per_inch).ok_or_else(|| PdfError::InvalidLength(text.to_string()))
+}
+
+/// Scale of the page like `0.8`, between 0.1 and 2 as Chrome accepts
+pub fn parse_scale(text: &str) -> Result<f64> {
+    text.trim().parse::<f64>().ok().filter(|scale| (0.1..=2.0).contains(scale)).ok_or_else(|| PdfError::InvalidScale(text.to_string()))
+}
+
+/// Object number and generation of an indirect reference
+pub(crate) type Reference = (u64, u64);
+
+static REFERENCE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"/([^\s/\[\]<>(){}%]+)\s+(\d+)\s+(\d+)\s+R").unwrap());
+static OBJECT_HEADER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?:^|[^\d])(\d+)\s+(\d+)\s+obj\b").unwrap());
+static SIZE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"/Size\s+(\d+)").unwrap());
+static STARTXREF: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"startxref\s+(\d+)").unwrap());
+static ID: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"/ID\s*(\[[^\]]*\])").unwrap());
+static OUTLINE_CHILDREN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"/(?:First|Last)\s+\d+\s+\d+\s+R|/Count\s+-?\d+").unwrap());
+static COUNT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"/Count\s+(-?\d+)").unwrap());
+
+/// Decimal number of `bytes`
+fn number<T: std::str::FromStr>(bytes: &[u8]) -> Option<T> {
+    std::str::from_utf8(bytes).ok()?.parse().ok()
+}
+
+/// `/Key n g R` entry of a dictionary
+pub(crate) fn reference(dictionary: &[u8], key: &str) -> Option<Reference> {
+    REFERENCE.captures_iter(dictionary)
+        .find(|captures| &captures[1] == key.as_bytes())
+        .and_then(|captures| Some((number(&captures[2])?, number(&captures[3])?)))
+}
+
+/// Trailer of the last revision of a PDF with a classic cross-reference table
+pub(crate) struct Trailer {
+    pub size: u64,
+    pub previous_xref: u64,
+    pub root: Reference,
+    pub info: Option<Reference>,
+    pub id: Option<Vec<u8>>,
+}
+
+impl Trailer {
+
+    pub fn read(pdf: &[u8]) -> Result<Self> {
+
+        let trailer_start = rfind(pdf, b"trailer").ok_or(PdfError::NoTrailer)?;
+        let trailer = &pdf[trailer_start..];
+
+        let entry = |regex: &Regex| -> Option<u64> { number(&regex.captures(trailer)?[1]) };
+        Ok(Self {
+            size: entry(&SIZE).ok_or(PdfError::MalformedTrailer("no /Size"))?,
+            previous_xref: entry(&STARTXREF).ok_or(PdfError::MalformedTrailer("no startxref"))?,
+            root: reference(trailer, "Root").ok_or(PdfError::MalformedTrailer("no /Root"))?,
+            info: reference(trailer, "Info"),
+            id: ID.captures(trailer).map(|captures| captures[1].to_vec()),
+        })
+    }
+}
+
+/// Content of the dictionary of the last definition of each object, without `<<` and `>>`,
+/// read in one pass. Streams are left out
+pub(crate) fn dictionaries(pdf: &[u8]) -> BTreeMap<Reference, &[u8]> {
+    let mut dictionaries = BTreeMap::new();
+    for captures in OBJECT_HEADER.captures_iter(pdf) {
+        let body = &pdf[captures.get(0).unwrap().end()..];
+        let body = &body[..body.windows(6).position(|window| window == b"endobj").unwrap_or(body.len())];
+        let (Some(number), Some(generation)) = (number(&captures[1]), number(&captures[2])) else { continue };
+        let (Some(start), Some(end)) = (body.windows(2).position(|window| window == b"<<"), body.windows(2).rposition(|window| window == b">>")) else {
+            continue;
+        };
+        // redefined by a later revision
+        dictionaries.remove(&(number, generation));
+        if start < end && !body.windows(6).any(|window| window == b"stream") {
+            dictionaries.insert((number, generation), &body[start + 2..end]);
+        }
+    }
+    dictionaries
+}
+
+/// Object written by an incremental update
+pub(crate) struct Object {
+    pub reference: Reference,
+    /// Content of its dictionary, without `<<` and `>>`
+    pub dictionary: Vec<u8>,
+    pub stream: Option<Vec<u8>>,
+}
+
+impl Object {
+
+    pub fn new(reference: Reference, dictionary: Vec<u8>) -> Self {
+        Self { reference, dictionary, stream: None }
+    }
+
+    /// Stream object, its `/Length` is added to `dictionary`
+    pub fn stream(reference: Reference, dictionary: &str, stream: Vec<u8>) -> Self {
+        let dictionary = format!("{dictionary} /Length {}", stream.len()).into_bytes();
+        Self { reference, dictionary, stream: Some(stream) }
+    }
+}
+
+/// `pdf` followed by an incremental update (re)defining `objects`, `trailer` being that of the update
+pub(crate) fn append_objects(pdf: &[u8], trailer: &Trailer, objects: &[Object]) -> Vec<u8> {
+
+    let mut updated = pdf.to_vec();
+    if !updated.ends_with(b"\n") {
+        updated.push(b'\n');
+    }
+
+    let mut offsets = Vec::new();
+    for Object { reference: (number, generation), dictionary, stream } in objects {
+        offsets.push((*number, *generation, updated.len()));
+        updated.extend(format!("{number} {generation} obj\n<<").as_bytes());
+        updated.extend(dictionary);
+        updated.extend(b">>");
+        if let Some(stream) = stream {
+            updated.extend(b"\nstream\n");
+            updated.extend(stream);
+            updated.extend(b"\nendstream");
+        }
+        updated.extend(b"\nendobj\n");
+    }
+    offsets.sort();
+
+    let xref_offset = updated.len();
+    updated.extend(b"xref\n");
+    for (number, generation, offset) in offsets {
+        updated.extend(format!("{number} 1\n{offset:010} {generation:05} n \n").as_bytes());
+    }
+    updated.extend(format!("trailer\n<</Size {} /Root {} {} R", trailer.size, trailer.root.0, trailer.root.1).as_bytes());
+    if let Some((number, generation)) = trailer.info {
+        updated.extend(format!(" /Info {number} {generation} R").as_bytes());
+    }
+    updated.extend(format!(" /Prev {}", trailer.previous_xref).as_bytes());
+    if let Some(id) = &trailer.id {
+        updated.extend(b" /ID ");
+        updated.extend(id);
+    }
+    updated.extend(format!(">>\nstartxref\n{xref_offset}\n%%EOF\n").as_bytes());
+    updated
+}
+
+/// Add `entries` to the document information dictionary of `pdf`, overriding those
+/// of the same key. The original bytes are kept and followed by an incremental update,
+/// as written by Chrome the PDF must have a classic cross-reference table
+pub fn set_info(pdf: &[u8], entries: &[(&str, &str)]) -> Result<Vec<u8>> {
+
+    let trailer = Trailer::read(pdf)?;
+
+    // entries of the current information dictionary, without the overridden ones
+    let mut dictionary = trailer.info.and_then(|info| dictionaries(pdf).get(&info).map(|info| info.to_vec())).unwrap_or_default();
+    for (key, _) in entries {
+        let entry = Regex::new(&format!(r"/{}\s*(\([^)]*\)|<[^>]*>)", regex::escape(key))).unwrap();
+        dictionary = entry.replace_all(&dictionary, &b""[..]).to_vec();
+    }
+    for (key, value) in entries {
+        dictionary.extend(format!("/{key} {}\n", text_string(value)).as_bytes());
+    }
+
+    let info = (trailer.size, 0);
+    let trailer = Trailer { size: trailer.size + 1, info: Some(info), ..trailer };
+    Ok(append_objects(pdf, &trailer, &[Object::new(info, dictionary)]))
+}
+
+/// Item of the document outline
+struct OutlineItem {
+    reference: Reference,
+    dictionary: Vec<u8>,
+    children: Vec<usize>,
+}
+
+/// Limit the outline (bookmarks) of `pdf` to `depth` levels, as Chrome makes one of every
+/// heading level. `None` when the PDF has no outline
+pub fn limit_outline(pdf: &[u8], depth: usize) -> Result<Option<Vec<u8>>> {
+
+    let trailer = Trailer::read(pdf)?;
+    let objects = dictionaries(pdf);
+    let dictionary = |reference: Reference| objects.get(&reference).map(|dictionary| dictionary.to_vec());
+    let Some(root) = dictionary(trailer.root).and_then(|catalog| reference(&catalog, "Outlines")) else {
+        return Ok(None);
+    };
+    let Some(root_dictionary) = dictionary(root) else {
+        return Ok(None);
+    };
+
+    // the root is item 0, its children the first level
+    let mut items = vec![OutlineItem { reference: root, dictionary: root_dictionary, children: Vec::new() }];
+    let mut pending = vec![(0, 0)];
+    while let Some((parent, level)) = pending.pop() {
+        let mut child = reference(&items[parent].dictionary, "First");
+        // a malformed outline could loop
+        while let Some(reference) = child.filter(|_| items.len() < 100_000) {
+            let Some(dictionary) = dictionary(reference) else { break };
+            child = self::reference(&dictionary, "Next");
+            items.push(OutlineItem { reference, dictionary, children: Vec::new() });
+            let index = items.len() - 1;
+            items[parent].children.push(index);
+            if level + 1 < depth {
+                pending.push((index, level + 1));
+            }
+        }
+    }
+
+    let (children, count) = (&*OUTLINE_CHILDREN, &*COUNT);
+    let open = |item: &OutlineItem| count.captures(&item.dictionary)
+        .and_then(|captures| number::<i64>(&captures[1]))
+        .is_none_or(|count| count >= 0);
+
+    // visible descendants of each item, the deepest first
+    let mut visible = vec![0i64; items.len()];
+    for index in (0..items.len()).rev() {
+        visible[index] = items[index].children.iter()
+            .map(|&child| 1 + if open(&items[child]) { visible[child] } else { 0 })
+            .sum();
+    }
+
+    let mut objects = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let mut dictionary = item.dictionary.clone();
+        if item.children.is_empty() {
+            if !children.is_match(&dictionary) {
+                continue;
+            }
+            dictionary = children.replace_all(&dictionary, &b""[..]).to_vec();
+        } else {
+            let sign = if index == 0 || open(item) { 1 } else { -1 };
+            let updated = format!("/Count {}", sign * visible[index]);
+            if count.find(&dictionary).is_some_and(|found| found.as_bytes() == updated.as_bytes()) {
+                continue;
+            }
+            dictionary = count.replace_all(&dictionary, &b""[..]).to_vec();
+            dictionary.extend(format!(" {updated}").as_bytes());
+        }
+        objects.push(Object::new(item.reference, dictionary));
+    }
+
+    Ok(Some(match objects.is_empty() {
+        true => pdf.to_vec(),
+        false => append_objects(pdf, &trailer, &objects),
+    }))
+}
+
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).rposition(|window| window == needle)
+}
+
+/// `text` as the content of a PDF literal string
+fn escape_string(text: &str) -> String {
+    text.replace('\\', r"\\").replace('(', r"\(").replace(')', r"\)")
+}
+
+/// `text` as a PDF text string: a literal string when ASCII, UTF-16 with a byte order mark otherwise
+pub(crate) fn text_string(text: &str) -> String {
+    match text.is_ascii() {
+        true => format!("({})", escape_string(text)),
+        false => format!("<FEFF{}>", text.encode_utf16().map(|unit| format!("{unit:04X}")).collect::<String>()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_lengths() {
+        assert_eq!(parse_length("0.5").unwrap(), 0.5);
+        assert_eq!(parse_length("2.54cm").unwrap(), 1.0);
+        assert_eq!(parse_length("48px").unwrap(), 0.5);
+        assert!(parse_length("1ft").is_err());
+        assert!(parse_length("-1in").is_err());
+        assert_eq!(parse_scale("0.8").unwrap(), 0.8);
+        assert!(parse_scale("3").is_err());
+    }
+
+    #[test]
+    fn appends_information_dictionary() {
+        let pdf = b"%PDF-1.4\n1 0 obj\n<</Creator (Chromium)\n/Producer (Skia)>>\nendobj\n2 0 obj\n<</Type /Catalog>>\nendobj\n\
+            xref\n0 3\n0000000000 65535 f \n0000000009 00000 n \n0000000063 00000 n \n\
+            trailer\n<</Size 3\n/Root 2 0 R\n/Info 1 0 R\n/ID [<AB> <AB>]>>\nstartxref\n101\n%%EOF";
+
+        let updated = set_info(pdf, &[("Producer", "webpage_scraper"), ("Subject", "Snapshot (2020)")]).unwrap();
+        let text = String::from_utf8_lossy(&updated);
+
+        assert!(updated.starts_with(pdf));
+        assert!(text.contains("3 0 obj\n<</Creator (Chromium)\n/Producer (webpage_scraper)\n/Subject (Snapshot \\(2020\\))\n>>"));
+        assert!(text.contains("<</Size 4 /Root 2 0 R /Info 3 0 R /Prev 101 /ID [<AB> <AB>]>>"));
+
+        let xref = text.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse::<usize>().unwrap();
+        assert!(updated[xref..].starts_with(b"xref\n3 1\n"));
+        let object = String::from_utf8_lossy(&updated[xref + 9..xref + 19]).parse::<usize>().unwrap();
+        assert!(updated[object..].starts_with(b"3 0 obj"));
+
+        assert!(matches!(set_info(b"%PDF-1.7\n", &[]), Err(PdfError::NoTrailer)));
+    }
+
+    #[test]
+    fn indexes_the_last_definition_of_each_object() {
+        let pdf = b"1 0 obj\n<</Type /Catalog /Outlines 2 0 R>>\nendobj\n\
+            3 0 obj\n<</Length 9>>\nstream\n<<x>>\nendstream\nendobj\n\
+            2 0 obj\n<</Type /Outlines>>\nendobj\n2 0 obj\n<</Type /Outlines /Count 0>>\nendobj\n";
+        let objects = dictionaries(pdf);
+        assert_eq!(objects.keys().collect::<Vec<_>>(), [&(1, 0), &(2, 0)]);
+        assert_eq!(objects[&(2, 0)], b"/Type /Outlines /Count 0");
+        assert_eq!(reference(objects[&(1, 0)], "Outlines"), Some((2, 0)));
+        assert_eq!(reference(objects[&(1, 0)], "Outline"), None);
+    }
+
+    #[test]
+    fn limits_the_outline_depth() {
+        let pdf = b"%PDF-1.4\n1 0 obj\n<</Type /Catalog /Outlines 2 0 R>>\nendobj\n\
+            2 0 obj\n<</Type /Outlines /First 3 0 R /Last 6 0 R /Count 4>>\nendobj\n\
+            3 0 obj\n<</Title (Intro) /Parent 2 0 R /Next 6 0 R /First 4 0 R /Last 4 0 R /Count 2>>\nendobj\n\
+            4 0 obj\n<</Title (Part) /Parent 3 0 R /First 5 0 R /Last 5 0 R /Count 1>>\nendobj\n\
+            5 0 obj\n<</Title (Detail) /Parent 4 0 R>>\nendobj\n\
+            6 0 obj\n<</Title (End) /Parent 2 0 R /Prev 3 0 R>>\nendobj\n\
+            xref\n0 7\ntrailer\n<</Size 7 /Root 1 0 R>>\nstartxref\n400\n%%EOF\n";
+
+        let limited = limit_outline(pdf, 2).unwrap().unwrap();
+        let update = String::from_utf8_lossy(&limited[pdf.len()..]);
+        assert!(update.starts_with("2 0 obj\n<</Type /Outlines /First 3 0 R /Last 6 0 R  /Count 3>>\nendobj\n"));
+        assert!(update.contains("3 0 obj\n<</Title (Intro) /Parent 2 0 R /Next 6 0 R /First 4 0 R /Last 4 0 R  /Count 1>>"));
+        assert!(update.contains("4 0 obj\n<</Title (Part) /Parent 3 0 R   >>"));
+        assert!(update.contains("xref\n2 1\n") && update.contains("<</Size 7 /Root 1 0 R /Prev 400>>"));
+
+        assert_eq!(limit_outline(pdf, 3).unwrap().unwrap(), pdf);
+        let flat = b"%PDF-1.4\n1 0 obj\n<</Type /Catalog>>\nendobj\ntrailer\n<</Size 2 /Root 1 0 R>>\nstartxref\n9\n%%EOF\n";
+        assert_eq!(limit_outline(flat, 2).unwrap(), None);
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+use regex::bytes::Regex;
+use time::OffsetDateTime;
+use crate::hash;
+use crate::pdf::{self, Object, PdfError, Reference, Result, Trailer};
+
+/// PDF/A-2b: part 2, level B (visual appearance preserved)
+const PART: u8 = 2;
+const CONFORMANCE: &str = "B";
+
+const PRODUCER: &str = "webpage_scraper";
+
+/// Output condition of the embedded sRGB profile
+const OUTPUT_CONDITION: &str = "sRGB IEC61966-2.1";
+
+/// Entries of the information dictionary that must agree with the XMP metadata,
+/// replaced by those made along with it
+const STANDARD_INFO: &[&str] = &["Title", "Author", "Subject", "Keywords", "Creator", "Producer", "CreationDate", "ModDate", "Trapped"];
+
+static ANNOTATION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"/Type\s*/Annot\b").unwrap());
+static FLAGS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"/F\s+\d").unwrap());
+static FONT_DESCRIPTOR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"/Type\s*/FontDescriptor\b").unwrap());
+static FONT_FILE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"/FontFile[23]?\b").unwrap());
+static FONT_NAME: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"/FontName\s*/([^\s/\[\]<>()]+)").unwrap());
+static OUTPUT_INTENTS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"/OutputIntents\s*(\[|\d+\s+\d+\s+R)").unwrap());
+static METADATA: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"/Metadata\s+\d+\s+\d+\s+R").unwrap());
+
+/// `value` as an s15Fixed16Number
+fn s15_fixed16(value: f64) -> [u8; 4] {
+    ((value * 65536.0).round() as i32).to_be_bytes()
+}
+
+/// ICC v2 display profile of sRGB: D50 adapted primaries and the sRGB transfer curve
+fn srgb_profile() -> Vec<u8> {
+
+    let xyz = |(x, y, z): (f64, f64, f64)| [&b"XYZ \0\0\0\0"[..], &s15_fixed16(x), &s15_fixed16(y), &s15_fixed16(z)].concat();
+    let text = |signature: &[u8], text: &str| {
+        let mut tag = [signature, b"\0\0\0\0"].concat();
+        if signature == b"desc" {
+            tag.extend((text.len() as u32 + 1).to_be_bytes());
+        }
+        tag.extend(text.as_bytes());
+        tag.push(0);
+        if signature == b"desc" {
+            // no Unicode nor ScriptCode description
+            tag.extend([0; 4 + 4 + 2 + 1 + 67]);
+        }
+        tag
+    };
+    let mut curve = b"curv\0\0\0\0".to_vec();
+    curve.extend(256u32.to_be_bytes());
+    for i in 0..256 {
+        let encoded = i as f64 / 255.0;
+        let linear = match encoded <= 0.04045 {
+            true => encoded / 12.92,
+            false => ((encoded + 0.055) / 1.055).powf(2.4),
+        };
+        curve.extend(((linear * 65535.0).round() as u16).to_be_bytes());
+    }
+
+    let elements: Vec<(&[u8; 4], Vec<u8>)> = vec![
+        (b"desc", text(b"desc", OUTPUT_CONDITION)),
+        (b"cprt", text(b"text", "No copyright, use freely")),
+        (b"wtpt", xyz((0.9642, 1.0, 0.8249))),
+        (b"rXYZ", xyz((0.4361, 0.2225, 0.0139))),
+        (b"gXYZ", xyz((0.3851, 0.7169, 0.0971))),
+        (b"bXYZ", xyz((0.1431, 0.0606, 0.7141))),
+        (b"rTRC", curve),
+    ];
+    // the three channels share the curve
+    let tags = elements.len() + 2;
+    let mut data = Vec::new();
+    let mut table = (tags as u32).to_be_bytes().to_vec();
+    let data_start = 128 + 4 + 12 * tags;
+    for (signature, element) in &elements {
+        let offset = (data_start + data.len()) as u32;
+        let entries: &[&[u8; 4]] = if *signature == b"rTRC" { &[b"rTRC", b"gTRC", b"bTRC"] } else { &[signature] };
+        for signature in entries {
+            table.extend(signature.as_slice());
+            table.extend(offset.to_be_bytes());
+            table.extend((element.len() as u32).to_be_bytes());
+        }
+        data.extend(element);
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+
+    let size = (data_start + data.len()) as u32;
+    let mut profile = Vec::with_capacity(size as usize);
+    profile.extend(size.to_be_bytes());
+    profile.extend([0; 4]);
+    profile.extend(0x0210_0000u32.to_be_bytes());
+    profile.extend(b"mntrRGB XYZ ");
+    for part in [2024u16, 1, 1, 0, 0, 0] {
+        profile.extend(part.to_be_bytes());
+    }
+    profile.extend(b"acsp");
+    profile.extend([0; 64 - 40]);
+    // perceptual rendering intent, D50 illuminant
+    profile.extend([0; 4]);
+    profile.extend([s15_fixed16(0.9642), s15_fixed16(1.0), s15_fixed16(0.8249)].concat());
+    profile.resize(128, 0);
+    profile.extend(table);
+    profile.extend(data);
+    profile
+}
+
+/// Text escaped for XML
+fn xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// XMP packet declaring PDF/A-2b conformance, with the title, URL and dates of the capture
+fn xmp(title: &str, url: &str, date: &str) -> String {
+    format!(concat!(
+        "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n",
+        "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n",
+        "<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n",
+        "<rdf:Description rdf:about=\"\" xmlns:pdfaid=\"http://www.aiim.org/pdfa/ns/id/\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\" ",
+        "xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\" xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\">\n",
+        "<pdfaid:part>{part}</pdfaid:part>\n",
+        "<pdfaid:conformance>{conformance}</pdfaid:conformance>\n",
+        "<dc:format>application/pdf</dc:format>\n",
+        "<dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">{title}</rdf:li></rdf:Alt></dc:title>\n",
+        "<dc:source>{url}</dc:source>\n",
+        "<xmp:CreateDate>{date}</xmp:CreateDate>\n",
+        "<xmp:ModifyDate>{date}</xmp:ModifyDate>\n",
+        "<xmp:MetadataDate>{date}</xmp:MetadataDate>\n",
+        "<pdf:Producer>{producer}</pdf:Producer>\n",
+        "</rdf:Description>\n",
+        "</rdf:RDF>\n",
+        "</x:xmpmeta>\n",
+        "<?xpacket end=\"w\"?>",
+    ), part = PART, conformance = CONFORMANCE, title = xml(title), url = xml(url), date = date, producer = PRODUCER)
+}
+
+/// Annotations without flags among the `objects` of the PDF, which PDF/A wants printed
+fn unflagged_annotations(objects: &BTreeMap<Reference, &[u8]>) -> Vec<(Reference, Vec<u8>)> {
+    objects.iter()
+        .filter(|(_, dictionary)| ANNOTATION.is_match(dictionary) && !FLAGS.is_match(dictionary))
+        .map(|(reference, dictionary)| (*reference, dictionary.to_vec()))
+        .collect()
+}
+
+/// Name of the first font among the `objects` of the PDF whose descriptor has no font file,
+/// which PDF/A forbids
+fn unembedded_font(objects: &BTreeMap<Reference, &[u8]>) -> Option<String> {
+    objects.iter()
+        .find(|(_, dictionary)| FONT_DESCRIPTOR.is_match(dictionary) && !FONT_FILE.is_match(dictionary))
+        .map(|(reference, dictionary)| match FONT_NAME.captures(dictionary) {
+            Some(captures) => String::from_utf8_lossy(&captures[1]).into_owned(),
+            None => format!("{} {} R", reference.0, reference.1),
+        })
+}
+
+/// End of the array starting at `start`, the nested arrays and the brackets of strings skipped
+fn array_end(bytes: &[u8], start: usize) -> usize {
+    let (mut depth, mut string, mut escaped) = (0, 0, false);
+    for (i, byte) in bytes.iter().enumerate().skip(start) {
+        match (string > 0, byte) {
+            (true, _) if escaped => escaped = false,
+            (true, b'\\') => escaped = true,
+            (_, b'(') => string += 1,
+            (true, b')') => string -= 1,
+            (true, _) => {}
+            (false, b'[') => depth += 1,
+            (false, b']') => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    bytes.len()
+}
+
+/// `dictionary` without its `/OutputIntents` entry, be it an array or a reference
+fn without_output_intents(dictionary: &[u8]) -> Vec<u8> {
+    let Some(captures) = OUTPUT_INTENTS.captures(dictionary) else { return dictionary.to_vec() };
+    let value = captures.get(1).unwrap();
+    let end = match value.as_bytes() == b"[" {
+        true => array_end(dictionary, value.start()),
+        false => value.end(),
+    };
+    [&dictionary[..captures.get(0).unwrap().start()], &dictionary[end..]].concat()
+}
+
+/// `pdf` made a PDF/A-2b document by an incremental update: XMP metadata with the title, URL
+/// and capture `date`, the sRGB output intent, an information dictionary agreeing with the
+/// metadata, a document ID and printable annotations. The fonts must already be embedded,
+/// as Chrome does, the conversion fails on a font descriptor without a font file otherwise
+pub fn convert(pdf: &[u8], title: &str, url: &str, date: OffsetDateTime) -> Result<Vec<u8>> {
+
+    let trailer = Trailer::read(pdf)?;
+    let dictionaries = pdf::dictionaries(pdf);
+    if let Some(font) = unembedded_font(&dictionaries) {
+        return Err(PdfError::UnembeddedFont(font));
+    }
+    let date = date.to_offset(time::UtcOffset::UTC);
+    let xmp_date = format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", date.year(), u8::from(date.month()), date.day(), date.hour(), date.minute(), date.second());
+    let pdf_date = format!("D:{:04}{:02}{:02}{:02}{:02}{:02}Z", date.year(), u8::from(date.month()), date.day(), date.hour(), date.minute(), date.second());
+
+    let metadata = (trailer.size, 0);
+    let profile = (trailer.size + 1, 0);
+    let info = (trailer.size + 2, 0);
+
+    let catalog = dictionaries.get(&trailer.root).ok_or(PdfError::NoCatalog)?;
+    let mut catalog = without_output_intents(&METADATA.replace_all(catalog, &b""[..]));
+    catalog.extend(format!(
+        " /Metadata {} 0 R /OutputIntents [<</Type /OutputIntent /S /GTS_PDFA1 /OutputConditionIdentifier ({OUTPUT_CONDITION}) /Info ({OUTPUT_CONDITION}) /DestOutputProfile {} 0 R>>]",
+        metadata.0, profile.0,
+    ).as_bytes());
+
+    let mut info_dictionary = trailer.info.and_then(|info| dictionaries.get(&info)).map(|info| info.to_vec()).unwrap_or_default();
+    for key in STANDARD_INFO {
+        let entry = Regex::new(&format!(r"/{key}\s*(?:\((?:\\.|[^\\)])*\)|<[^>]*>|/\w+)")).unwrap();
+        info_dictionary = entry.replace_all(&info_dictionary, &b""[..]).to_vec();
+    }
+    info_dictionary.extend(format!(
+        "/Title {}\n/Producer ({PRODUCER})\n/CreationDate ({pdf_date})\n/ModDate ({pdf_date})\n", pdf::text_string(title),
+    ).as_bytes());
+
+    let mut objects = vec![
+        Object::new(trailer.root, catalog),
+        Object::stream(metadata, "/Type /Metadata /Subtype /XML", xmp(title, url, &xmp_date).into_bytes()),
+        Object::stream(profile, "/N 3", srgb_profile()),
+        Object::new(info, info_dictionary),
+    ];
+    for (reference, mut dictionary) in unflagged_annotations(&dictionaries) {
+        dictionary.extend(b" /F 4");
+        objects.push(Object::new(reference, dictionary));
+    }
+
+    let id = trailer.id.clone().unwrap_or_else(|| {
+        let id = &hash::sha256_hex(pdf)[..32];
+        format!("[<{id}> <{id}>]").into_bytes()
+    });
+    let trailer = Trailer { size: trailer.size + 3, info: Some(info), id: Some(id), ..trailer };
+    Ok(pdf::append_objects(pdf, &trailer, &objects))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn makes_a_valid_srgb_profile() {
+        let profile = srgb_profile();
+        assert_eq!(u32::from_be_bytes(profile[..4].try_into().unwrap()) as usize, profile.len());
+        assert_eq!(&profile[36..40], b"acsp");
+        assert_eq!(u32::from_be_bytes(profile[128..132].try_into().unwrap()), 9);
+        // every tag is within the profile, 4-byte aligned
+        for tag in profile[132..132 + 9 * 12].chunks(12) {
+            let offset = u32::from_be_bytes(tag[4..8].try_into().unwrap()) as usize;
+            let size = u32::from_be_bytes(tag[8..12].try_into().unwrap()) as usize;
+            assert!(offset.is_multiple_of(4) && offset + size <= profile.len());
+        }
+    }
+
+    /// Only checks the added objects as text, validating the result takes a validator such as veraPDF
+    #[test]
+    fn declares_pdfa_conformance() {
+        let pdf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n1 0 obj\n<</Creator (Chromium)\n/Producer (Skia/PDF)\n/Title (Old \\) title)>>\nendobj\n\
+            2 0 obj\n<</Type /Catalog /Pages 3 0 R>>\nendobj\n\
+            4 0 obj\n<</Type /Annot /Subtype /Link /Rect [0 0 10 10]>>\nendobj\n\
+            5 0 obj\n<</Type /Annot /Subtype /Link /F 4>>\nendobj\n\
+            xref\n0 6\ntrailer\n<</Size 6\n/Root 2 0 R\n/Info 1 0 R>>\nstartxref\n300\n%%EOF";
+        let date = OffsetDateTime::from_unix_timestamp(1_714_557_600).unwrap();
+
+        let converted = convert(pdf, "Café & co", "https://example.com/?a=1&b=2", date).unwrap();
+        let update = String::from_utf8_lossy(&converted[pdf.len()..]);
+        assert!(update.trim_start().starts_with("2 0 obj\n<</Type /Catalog /Pages 3 0 R /Metadata 6 0 R /OutputIntents [<</Type /OutputIntent /S /GTS_PDFA1"));
+        assert!(update.contains("<pdfaid:part>2</pdfaid:part>\n<pdfaid:conformance>B</pdfaid:conformance>"));
+        assert!(update.contains("<rdf:li xml:lang=\"x-default\">Café &amp; co</rdf:li>"));
+        assert!(update.contains("<dc:source>https://example.com/?a=1&amp;b=2</dc:source>"));
+        assert!(update.contains("<xmp:CreateDate>2024-05-01T10:00:00Z</xmp:CreateDate>"));
+        assert!(update.contains("/Title <FEFF00430061006600E90020002600200063006F>\n/Producer (webpage_scraper)\n/CreationDate (D:20240501100000Z)"));
+        assert!(update.contains("4 0 obj\n<</Type /Annot /Subtype /Link /Rect [0 0 10 10] /F 4>>") && !update.contains("5 0 obj"));
+        assert!(update.contains("<</Size 9 /Root 2 0 R /Info 8 0 R /Prev 300 /ID [<"));
+    }
+
+    #[test]
+    fn replaces_nested_output_intents() {
+        let catalog = b"/Type /Catalog /OutputIntents [<</S /GTS_PDFA1 /Info (a ] b) /Extra [[1 2] 3]>>] /Pages 3 0 R";
+        assert_eq!(without_output_intents(catalog), b"/Type /Catalog  /Pages 3 0 R");
+        assert_eq!(without_output_intents(b"/OutputIntents 7 0 R /Pages 3 0 R"), b" /Pages 3 0 R");
+    }
+
+    #[test]
+    fn refuses_unembedded_fonts() {
+        let pdf = b"%PDF-1.4\n1 0 obj\n<</Type /Catalog /Pages 3 0 R>>\nendobj\n\
+            5 0 obj\n<</Type /FontDescriptor /FontName /ABCDEF+Roboto /FontFile2 6 0 R>>\nendobj\n\
+            7 0 obj\n<</Type /FontDescriptor /FontName /Helvetica /Flags 32>>\nendobj\n\
+            xref\n0 8\ntrailer\n<</Size 8\n/Root 1 0 R>>\nstartxref\n200\n%%EOF";
+        let date = OffsetDateTime::from_unix_timestamp(1_714_557_600).unwrap();
+        assert!(matches!(convert(pdf, "", "", date), Err(PdfError::UnembeddedFont(font)) if font == "Helvetica"));
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Toneless pinyin of the most frequent hanzi (simplified and common traditional forms),
+/// polyphonic characters use their most common reading
+const TABLE: &str = "\
+的de 一yi 是shi 不bu 了le 在zai 人ren 有you 我wo 他ta 这zhe 个ge 们men 中zhong 来lai 上shang 大da 为wei 和he 国guo \
+地di 到dao 以yi 说shuo 时shi 要yao 就jiu 出chu 会hui 可ke 也ye 你ni 对dui 生sheng 能neng 而er 子zi 那na 得de 于yu \
+着zhe 下xia 自zi 之zhi 年nian 过guo 发fa 后hou 作zuo 里li 用yong 道dao 行xing 所suo 然ran 家jia 种zhong 事shi 成cheng 方fang \
+多duo 经jing 么me 去qu 法fa 学xue 如ru 都dou 同tong 现xian 当dang 没mei 动dong 面mian 起qi 看kan 定ding 天tian 分fen 还hai \
+进jin 好hao 小xiao 部bu 其qi 些xie 主zhu 样yang 理li 心xin 她ta 本ben 前qian 开kai 但dan 因yin 只zhi 从cong 想xiang 实shi \
+日ri 军jun 者zhe 意yi 无wu 力li 它ta 与yu 长chang 把ba 机ji 十shi 民min 第di 公gong 此ci 已yi 工gong 使shi 情qing \
+明ming 性xing 知zhi 全quan 三san 又you 关guan 点dian 正zheng 业ye 外wai 将jiang 两liang 高gao 间jian 由you 问wen 很hen 最zui 重zhong \
+并bing 物wu 手shou 应ying 战zhan 向xiang 头tou 文wen 体ti 政zheng 美mei 相xiang 见jian 被bei 利li 什shen 二er 等deng 产chan 或huo \
+新xin 己ji 制zhi 身shen 果guo 加jia 西xi 斯si 月yue 话hua 合he 回hui 特te 代dai 内nei 信xin 表biao 化hua 老lao 给gei \
+世shi 位wei 次ci 度du 门men 任ren 常chang 先xian 海hai 通tong 教jiao 儿er 原yuan 东dong 声sheng 提ti 立li 及ji 比bi 员yuan \
+解jie 水shui 名ming 真zhen 论lun 处chu 走zou 义yi 各ge 入ru 几ji 口kou 认ren 条tiao 平ping 系xi 气qi 题ti 活huo 尔er \
+更geng 别bie 打da 女nv 变bian 四si 神shen 总zong 何he 电dian 数shu 安an 少shao 报bao 才cai 结jie 反fan 受shou 目mu 太tai \
+量liang 再zai 感gan 建jian 务wu 做zuo 接jie 必bi 场chang 件jian 计ji 管guan 期qi 市shi 直zhi 德de 资zi 命ming 山shan 金jin \
+指zhi 克ke 许xu 统tong 区qu 保bao 至zhi 队dui 形xing 社she 便bian 空kong 决jue 治zhi 展zhan 马ma 科ke 司si 五wu 基ji \
+眼yan 书shu 非fei 则ze 听ting 白bai 却que 界jie 达da 光guang 放fang 强qiang 即ji 像xiang 难nan 且qie 权quan 思si 王wang 象xiang \
+完wan 设she 式shi 色se 路lu 记ji 南nan 品pin 住zhu 告gao 类lei 求qiu 据ju 程cheng 北bei 边bian 死si 张zhang 该gai 交jiao \
+规gui 万wan 取qu 拉la 格ge 望wang 觉jue 术shu 领ling 共gong 确que 传chuan 师shi 观guan 清qing 今jin 切qie 院yuan 让rang 识shi \
+候hou 带dai 导dao 争zheng 运yun 笑xiao 飞fei 风feng 步bu 改gai 收shou 根gen 干gan 造zao 言yan 联lian 持chi 组zu 每mei 济ji \
+车che 亲qin 极ji 林lin 服fu 快kuai 办ban 议yi 往wang 元yuan 英ying 士shi 证zheng 近jin 失shi 转zhuan 夫fu 令ling 准zhun 布bu \
+始shi 怎zen 呢ne 存cun 未wei 远yuan 叫jiao 台tai 单dan 影ying 具ju 罗luo 字zi 爱ai 击ji 流liu 备bei 兵bing 连lian 调diao \
+深shen 商shang 算suan 质zhi 团tuan 集ji 百bai 需xu 价jia 花hua 党dang 华hua 城cheng 石shi 级ji 整zheng 府fu 离li 况kuang 亚ya \
+请qing 技ji 际ji 约yue 示shi 复fu 病bing 息xi 究jiu 线xian 似si 官guan 火huo 断duan 精jing 满man 支zhi 视shi 消xiao 越yue \
+器qi 容rong 照zhao 须xu 九jiu 增zeng 研yan 写xie 称cheng 企qi 八ba 功gong 吗ma 包bao 片pian 史shi 委wei 乎hu 查cha 轻qing \
+易yi 早zao 曾ceng 除chu 农nong 找zhao 装zhuang 广guang 显xian 吧ba 阿a 李li 标biao 谈tan 吃chi 图tu 念nian 六liu 引yin 历li \
+首shou 医yi 局ju 突tu 专zhuan 费fei 号hao 尽jin 另ling 周zhou 较jiao 注zhu 语yu 仅jin 考kao 落luo 青qing 随sui 选xuan 列lie \
+武wu 红hong 响xiang 虽sui 推tui 势shi 参can 希xi 古gu 众zhong 构gou 房fang 半ban 节jie 土tu 投tou 某mou 案an 黑hei 维wei \
+革ge 划hua 敌di 致zhi 陈chen 律lv 足zu 态tai 护hu 七qi 兴xing 派pai 孩hai 验yan 责ze 营ying 星xing 够gou 章zhang 音yin \
+跟gen 志zhi 底di 站zhan 严yan 巴ba 例li 防fang 族zu 供gong 效xiao 续xu 施shi 留liu 讲jiang 型xing 料liao 终zhong 答da 紧jin \
+黄huang 绝jue 奇qi 察cha 母mu 京jing 段duan 依yi 批pi 群qun 项xiang 故gu 按an 河he 米mi 围wei 江jiang 织zhi 害hai 斗dou \
+双shuang 境jing 客ke 纪ji 采cai 举ju 杀sha 攻gong 父fu 苏su 密mi 低di 朝chao 友you 诉su 止zhi 细xi 愿yuan 千qian 值zhi \
+仍reng 男nan 钱qian 破po 网wang 热re 助zhu 倒dao 育yu 属shu 坐zuo 帝di 限xian 船chuan 脸lian 职zhi 速su 刻ke 乐le 否fou \
+刚gang 威wei 毛mao 状zhuang 率lv 甚shen 独du 球qiu 般ban 普pu 怕pa 弹dan 校xiao 苦ku 创chuang 假jia 久jiu 错cuo 承cheng 印yin \
+晚wan 兰lan 试shi 股gu 拿na 脑nao 预yu 谁shei 益yi 阳yang 若ruo 哪na 微wei 尼ni 继ji 送song 急ji 血xue 惊jing 伤shang \
+素su 药yao 适shi 波bo 夜ye 省sheng 初chu 喜xi 卫wei 源yuan 食shi 险xian 待dai 述shu 陆lu 习xi 置zhi 居ju 劳lao 财cai \
+环huan 排pai 福fu 纳na 欢huan 雷lei 警jing 获huo 模mo 充chong 负fu 云yun 停ting 木mu 游you 龙long 树shu 疑yi 层ceng 冷leng \
+洲zhou 冲chong 射she 略lue 范fan 竟jing 句ju 室shi 异yi 激ji 汉han 村cun 哈ha 策ce 演yan 简jian 卡ka 罪zui 判pan 担dan \
+州zhou 静jing 退tui 既ji 衣yi 您nin 宗zong 积ji 余yu 痛tong 检jian 差cha 富fu 灵ling 协xie 角jiao 占zhan 配pei 征zheng 修xiu \
+皮pi 挥hui 胜sheng 降jiang 阶jie 审shen 沉chen 坚jian 善shan 妈ma 刘liu 读du 啊a 超chao 免mian 压ya 银yin 买mai 皇huang 养yang \
+伊yi 怀huai 执zhi 副fu 乱luan 抗kang 犯fan 追zhui 帮bang 宣xuan 佛fo 岁sui 航hang 优you 怪guai 香xiang 著zhu 田tian 铁tie 控kong \
+税shui 左zuo 右you 份fen 穿chuan 艺yi 背bei 阵zhen 草cao 脚jiao 概gai 恶e 块kuai 顿dun 敢gan 守shou 酒jiu 岛dao 托tuo 央yang \
+户hu 烈lie 洋yang 哥ge 索suo 胡hu 款kuan 靠kao 评ping 版ban 宝bao 座zuo 释shi 景jing 顾gu 弟di 登deng 货huo 互hu 付fu \
+伯bo 慢man 欧ou 换huan 闻wen 危wei 忙mang 核he 暗an 姐jie 介jie 坏huai 讨tao 丽li 良liang 序xu 升sheng 监jian 临lin 亮liang \
+露lu 永yong 呼hu 味wei 野ye 架jia 域yu 沙sha 掉diao 括kuo 舰jian 鱼yu 杂za 误wu 湾wan 吉ji 减jian 编bian 楚chu 肯ken \
+测ce 败bai 屋wu 跑pao 梦meng 散san 温wen 困kun 剑jian 渐jian 封feng 救jiu 贵gui 枪qiang 缺que 楼lou 县xian 尚shang 毫hao 移yi \
+娘niang 朋peng 画hua 班ban 智zhi 亦yi 耳er 恩en 短duan 掌zhang 恐kong 遗yi 固gu 席xi 松song 秘mi 谢xie 鲁lu 遇yu 康kang \
+虑lv 幸xing 均jun 销xiao 钟zhong 诗shi 藏cang 赶gan 剧ju 票piao 损sun 忽hu 巨ju 炮pao 旧jiu 端duan 探tan 湖hu 录lu 叶ye \
+春chun 乡xiang 附fu 吸xi 予yu 礼li 港gang 雨yu 呀ya 板ban 庭ting 妇fu 归gui 睛jing 饭fan 额e 含han 顺shun 输shu 摇yao \
+招zhao 婚hun 脱tuo 补bu 谓wei 督du 毒du 油you 疗liao 旅lv 泽ze 材cai 灭mie 逐zhu 莫mo 笔bi 亡wang 鲜xian 词ci 圣sheng \
+择ze 寻xun 厂chang 睡shui 博bo 勒le 烟yan 授shou 诺nuo 伦lun 岸an 奥ao 唐tang 卖mai 俄e 炸zha 载zai 洛luo 健jian 堂tang \
+旁pang 宫gong 喝he 借jie 君jun 禁jin 阴yin 园yuan 谋mou 宋song 避bi 抓zhua 荣rong 姑gu 孙sun 逃tao 牙ya 束shu 跳tiao 顶ding \
+玉yu 镇zhen 雪xue 午wu 练lian 迫po 爷ye 篇pian 肉rou 嘴zui 馆guan 遍bian 凡fan 础chu 洞dong 卷juan 坦tan 牛niu 宁ning 纸zhi \
+诸zhu 训xun 私si 庄zhuang 祖zu 丝si 翻fan 暴bao 森sen 塔ta 默mo 握wo 戏xi 隐yin 熟shu 骨gu 访fang 弱ruo 蒙meng 歌ge \
+店dian 鬼gui 软ruan 典dian 欲yu 萨sa 伙huo 遭zao 盘pan 爸ba 扩kuo 盖gai 弄nong 雄xiong 稳wen 忘wang 亿yi 刺ci 拥yong 徒tu \
+姆mu 杨yang 齐qi 赛sai 趣qu 曲qu 刀dao 床chuang 迎ying 冰bing 虚xu 玩wan 析xi 窗chuang 醒xing 妻qi 透tou 购gou 替ti 塞sai \
+努nu 休xiu 虎hu 扬yang 途tu 侵qin 刑xing 绿lv 兄xiong 迅xun 套tao 贸mao 毕bi 唯wei 谷gu 轮lun 库ku 迹ji 尤you 竞jing \
+街jie 促cu 延yan 震zhen 弃qi 甲jia 伟wei 麻ma 川chuan 申shen 缓huan 潜qian 闪shan 售shou 灯deng 针zhen 哲zhe 络luo 抵di 朱zhu \
+埃ai 抱bao 鼓gu 植zhi 纯chun 夏xia 忍ren 页ye 杰jie 筑zhu 折zhe 郑zheng 贝bei 尊zun 吴wu 秀xiu 混hun 臣chen 雅ya 振zhen \
+染ran 盛sheng 怒nu 舞wu 圆yuan 搞gao 狂kuang 措cuo 姓xing 残can 秋qiu 培pei 迷mi 诚cheng 宽kuan 宇yu 猛meng 摆bai 梅mei 毁hui \
+伸shen 摩mo 盟meng 末mo 乃nai 悲bei 拍pai 丁ding 赵zhao 频pin 零ling 册ce 搜sou 档dang 绍shao 享xiang 赞zan 荐jian 榜bang 娱yu \
+汽qi 邮you 箱xiang 码ma 坛tan 狐hu 浪lang 苹ping 腾teng 讯xun 淘tao 豆dou 瓣ban 圳zhen 览lan 摄she 帖tie 登deng 注zhu 账zhang \
+號hao 國guo 學xue 會hui 網wang 頁ye 電dian 語yu 說shuo 時shi 們men 這zhe 個ge 來lai 為wei 對dui 過guo 發fa 後hou 麼me \
+見jian 開kai 關guan 點dian 業ye 間jian 問wen 長chang 機ji 實shi 從cong 動dong 經jing 現xian 無wu 與yu 書shu 東dong 臺tai 灣wan \
+華hua 體ti 樣yang 圖tu 區qu 報bao 園yuan 處chu 戰zhan 產chan 萬wan 車che 歷li 藝yi 術shu 設she 計ji 館guan 聞wen 買mai \
+賣mai 價jia 節jie 環huan 當dang 總zong 視shi 頻pin 論lun 檔dang 聯lian 製zhi 應ying 該gai 讀du 寫xie 錄lu 載zai 歡huan 門men \
+愛ai 風feng 飛fei 雲yun 龍long 鳥niao 魚yu 馬ma 氣qi 話hua 員yuan 題ti 類lei 資zi 訊xun 詞ci 變bian 亂luan 傳chuan 專zhuan";
+
+fn table() -> &'static HashMap<char, &'static str> {
+    static TABLE_MAP: OnceLock<HashMap<char, &'static str>> = OnceLock::new();
+    TABLE_MAP.get_or_init(|| {
+        TABLE
+            .split_whitespace()
+            .filter_map(|entry| {
+                let c = entry.chars().next()?;
+                Some((c, &entry[c.len_utf8()..]))
+            })
+            .collect()
+    })
+}
+
+fn is_ideograph(c: char) -> bool {
+    matches!(c, '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '\u{F900}'..='\u{FAFF}')
+}
+
+/// Replace CJK ideographs by space separated pinyin syllables,
+/// ideographs missing from the table become their code point (`u4e2d`)
+pub(crate) fn romanize(text: &str) -> String {
+
+    let mut romanized = String::with_capacity(text.len());
+    let mut after_syllable = false;
+
+    for c in text.chars() {
+        if is_ideograph(c) {
+            let needs_space = romanized
+                .ends_with(|p: char| p.is_alphanumeric() || (!p.is_ascii() && !p.is_whitespace()));
+            if needs_space {
+                romanized.push(' ');
+            }
+            match table().get(&c) {
+                Some(syllable) => romanized.push_str(syllable),
+                None => romanized.push_str(&format!("u{:x}", c as u32)),
+            }
+            after_syllable = true;
+        } else {
+            if after_syllable && c.is_alphanumeric() {
+                romanized.push(' ');
+            }
+            romanized.push(c);
+            after_syllable = false;
+        }
+    }
+
+    romanized
+}
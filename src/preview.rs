@@ -0,0 +1,116 @@
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use crate::dimensions;
+use crate::http::HttpClient;
+
+/// Image saved at the root of the capture, referenced in `informations.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreviewFile {
+    /// Path relative to the capture directory
+    pub path: String,
+    pub url: String,
+}
+
+/// Favicon and OpenGraph/Twitter preview image of a page, for link-preview style indexes
+#[derive(Debug, Clone, Default)]
+pub struct PreviewImages {
+    pub favicon: Option<(PreviewFile, Vec<u8>)>,
+    pub preview: Option<(PreviewFile, Vec<u8>)>,
+}
+
+impl PreviewImages {
+
+    /// Download the first working favicon among the `<link rel="icon">`, `<link rel="apple-touch-icon">`
+    /// and `/favicon.ico`, and the first working `og:image` or `twitter:image`
+    pub async fn fetch(html: &str, base_url: &str, client: &HttpClient) -> Self {
+
+        let Ok(base_url) = Url::parse(base_url) else {
+            return Self::default();
+        };
+        let (icons, previews) = Self::candidates(html, &base_url);
+        let (favicon, preview) = futures::join!(
+            Self::first_image(icons, "favicon", client),
+            Self::first_image(previews, "preview", client),
+        );
+        Self { favicon, preview }
+    }
+
+    /// Favicon and preview image URLs, in order of preference
+    fn candidates(html: &str, base_url: &Url) -> (Vec<Url>, Vec<Url>) {
+
+        let document = Html::parse_document(html);
+        let join = |href: Option<&str>| href.and_then(|href| base_url.join(href.trim()).ok());
+
+        let icon_selector = Selector::parse("link[rel~=icon][href]").unwrap();
+        let touch_icon_selector = Selector::parse("link[rel~=apple-touch-icon][href]").unwrap();
+        let mut icons = document
+            .select(&icon_selector)
+            .chain(document.select(&touch_icon_selector))
+            .filter_map(|link| join(link.attr("href")))
+            .collect::<Vec<_>>();
+        icons.extend(base_url.join("/favicon.ico"));
+
+        let preview_selector = Selector::parse(
+            r#"meta[property="og:image"], meta[property="og:image:url"], meta[property="og:image:secure_url"], meta[name="twitter:image"], meta[name="twitter:image:src"], meta[property="twitter:image"]"#
+        ).unwrap();
+        let previews = document.select(&preview_selector).filter_map(|meta| join(meta.attr("content"))).collect();
+
+        (icons, previews)
+    }
+
+    /// First of `urls` answering with an image, saved as `name` with the extension of its format
+    async fn first_image(mut urls: Vec<Url>, name: &str, client: &HttpClient) -> Option<(PreviewFile, Vec<u8>)> {
+        urls.dedup();
+        for url in urls {
+            let Ok(response) = client.get(&url).await else { continue };
+            let Some(extension) = dimensions::extension(&response.body) else { continue };
+            let file = PreviewFile { path: format!("{name}.{extension}"), url: url.to_string() };
+            return Some((file, response.body));
+        }
+        None
+    }
+
+    /// Write the downloaded images in the capture `directory`
+    pub fn write_to_disk(&self, directory: &std::path::Path) -> std::io::Result<()> {
+        for (file, bytes) in self.favicon.iter().chain(&self.preview) {
+            std::fs::write(directory.join(&file.path), bytes)?;
+        }
+        Ok(())
+    }
+
+    pub fn favicon_file(&self) -> Option<PreviewFile> {
+        self.favicon.as_ref().map(|(file, _)| file.clone())
+    }
+
+    pub fn preview_file(&self) -> Option<PreviewFile> {
+        self.preview.as_ref().map(|(file, _)| file.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::NetworkMode;
+    use crate::testing::{FixtureServer, BLUE_PNG, RED_PNG};
+
+    #[tokio::test]
+    async fn fetches_favicon_and_preview_image() {
+        let server = FixtureServer::new().unwrap();
+        server.route("/icons/broken.png", "image/png", "not an image");
+        server.route("/favicon.ico", "image/png", RED_PNG);
+        server.route("/share.png", "image/png", BLUE_PNG);
+        let html = r#"<html><head>
+            <link rel="shortcut icon" href="/icons/broken.png">
+            <link rel="icon" href="/icons/missing.svg">
+            <meta property="og:image" content="/share.png">
+        </head></html>"#;
+
+        let client = HttpClient::new(&NetworkMode::Live).unwrap();
+        let images = PreviewImages::fetch(html, &server.url("/post.html"), &client).await;
+
+        let favicon = images.favicon_file().unwrap();
+        assert_eq!((favicon.path.as_str(), favicon.url), ("favicon.png", server.url("/favicon.ico")));
+        assert_eq!(images.preview.unwrap().1, BLUE_PNG);
+    }
+}
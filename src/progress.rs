@@ -0,0 +1,86 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+/// Step of an image download, `src` is the URL or `srcset` of the image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadEvent {
+    Started { src: String },
+    Finished { src: String, bytes: usize },
+    Failed { src: String, error: String },
+    /// Left out by the size filter or the image budget
+    Skipped { src: String, reason: String },
+}
+
+impl DownloadEvent {
+    pub fn src(&self) -> &str {
+        match self {
+            Self::Started { src } | Self::Finished { src, .. } | Self::Failed { src, .. } | Self::Skipped { src, .. } => src,
+        }
+    }
+}
+
+/// Step of the scrape of a page, once it is loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// The page is being extracted and converted, its images downloaded meanwhile
+    Converting,
+    /// `count` images of the page are to be downloaded
+    ImagesFound { count: usize },
+    Download(DownloadEvent),
+    /// `write_outputs` started writing into `directory`
+    Writing { directory: PathBuf },
+    /// Every output of the page is in `directory`
+    Written { directory: PathBuf },
+}
+
+impl From<DownloadEvent> for ProgressEvent {
+    fn from(event: DownloadEvent) -> Self {
+        Self::Download(event)
+    }
+}
+
+/// Receiver of the progress events of a scrape, cheap to clone and shared between concurrent tasks
+#[derive(Clone)]
+pub struct Progress(Arc<dyn Fn(ProgressEvent) + Send + Sync>);
+
+impl Progress {
+
+    /// Progress calling `callback` on the download events, the other steps left out
+    pub fn new(callback: impl Fn(DownloadEvent) + Send + Sync + 'static) -> Self {
+        Self::with_events(move |event| {
+            if let ProgressEvent::Download(event) = event {
+                callback(event);
+            }
+        })
+    }
+
+    /// Progress calling `callback` on every step of the scrape
+    pub fn with_events(callback: impl Fn(ProgressEvent) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    /// Progress sending the download events to the returned receiver
+    pub fn channel() -> (Self, UnboundedReceiver<DownloadEvent>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        // the receiver may be gone, nobody is listening then
+        (Self::new(move |event| { let _ = sender.send(event); }), receiver)
+    }
+
+    /// Progress sending every step of the scrape to the returned receiver
+    pub fn event_channel() -> (Self, UnboundedReceiver<ProgressEvent>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (Self::with_events(move |event| { let _ = sender.send(event); }), receiver)
+    }
+
+    pub fn emit(&self, event: impl Into<ProgressEvent>) {
+        (self.0)(event.into())
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Progress")
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// What a request is for, the page loads and the downloads of a host being spaced out apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RequestKind {
+    Page,
+    /// Images, stylesheets, media and other files of the pages
+    Download,
+}
+
+/// Spaces out requests of a kind made to the same host by the delay each reservation asks for,
+/// and requests of any kind to any host by the interval of `with_max_rps`
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    interval: Duration,
+    next_slots: Mutex<Slots>,
+}
+
+#[derive(Debug, Default)]
+struct Slots {
+    /// Next free slot of each host, per kind of request
+    hosts: HashMap<(RequestKind, String), Instant>,
+    /// Slots reserved over all hosts, at least `interval` apart
+    taken: BTreeSet<Instant>,
+}
+
+impl RateLimiter {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send at most `max_rps` requests per second, whatever their host. Without a limit
+    /// when `max_rps` isn't valid, see `interval`
+    pub fn with_max_rps(mut self, max_rps: f64) -> Self {
+        self.interval = Self::interval(max_rps).unwrap_or_default();
+        self
+    }
+
+    /// Time between two requests sent at `max_rps` requests per second, `None` unless
+    /// `max_rps` is positive, finite and the time fits in a `Duration`
+    pub fn interval(max_rps: f64) -> Option<Duration> {
+        (max_rps > 0.0 && max_rps.is_finite()).then(|| Duration::try_from_secs_f64(1.0 / max_rps).ok()).flatten()
+    }
+
+    /// Reserve the next free slot of `kind` for `host`, keeping the one after it `delay` plus
+    /// a random `jitter` later, and return how long to wait before using it
+    pub fn reserve(&self, kind: RequestKind, host: &str, delay: Duration, jitter: Duration) -> Duration {
+
+        let now = Instant::now();
+        let mut next_slots = self.next_slots.lock().unwrap();
+
+        let key = (kind, host.to_string());
+        let mut slot = next_slots.hosts.get(&key).copied().filter(|slot| *slot > now).unwrap_or(now);
+        if delay.is_zero() && jitter.is_zero() && self.interval.is_zero() {
+            return slot - now;
+        }
+        if !self.interval.is_zero() {
+            let interval = self.interval;
+            next_slots.taken.retain(|taken| *taken + interval > now);
+            // the first gap wide enough, a host waiting for its delay doesn't hold up the others
+            for taken in &next_slots.taken {
+                if *taken >= slot + interval {
+                    break;
+                }
+                if *taken + interval > slot {
+                    slot = *taken + interval;
+                }
+            }
+            next_slots.taken.insert(slot);
+        }
+        let jitter = Duration::from_millis(fastrand::u64(..=jitter.as_millis() as u64));
+        next_slots.hosts.insert(key, slot + delay + jitter);
+
+        slot - now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELAY: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn spaces_out_same_host_only() {
+        let limiter = RateLimiter::new();
+        assert_eq!(limiter.reserve(RequestKind::Page, "example.com", DELAY, Duration::ZERO), Duration::ZERO);
+        assert!(limiter.reserve(RequestKind::Page, "example.com", DELAY, Duration::ZERO) > Duration::from_secs(9));
+        assert_eq!(limiter.reserve(RequestKind::Page, "example.org", DELAY, Duration::ZERO), Duration::ZERO);
+        // a request without any delay of its own still waits for the slot of its host
+        assert!(limiter.reserve(RequestKind::Page, "example.com", Duration::ZERO, Duration::ZERO) > Duration::from_secs(19));
+        // the downloads of the host aren't held up by the delay between its pages
+        assert_eq!(limiter.reserve(RequestKind::Download, "example.com", Duration::ZERO, Duration::ZERO), Duration::ZERO);
+    }
+
+    #[test]
+    fn spaces_out_every_host_to_max_rps() {
+        let limiter = RateLimiter::new().with_max_rps(2.0);
+        assert_eq!(limiter.reserve(RequestKind::Page, "example.com", DELAY, Duration::ZERO), Duration::ZERO);
+        assert!(limiter.reserve(RequestKind::Page, "example.com", DELAY, Duration::ZERO) > Duration::from_secs(9));
+        // in the gap before the second request to example.com
+        let other_host = limiter.reserve(RequestKind::Page, "example.org", Duration::ZERO, Duration::ZERO);
+        assert!(other_host > Duration::from_millis(450) && other_host <= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn rejects_rates_without_an_interval() {
+        assert_eq!(RateLimiter::interval(4.0), Some(Duration::from_millis(250)));
+        for max_rps in [0.0, -1.0, 1e-20, f64::NAN, f64::INFINITY] {
+            assert_eq!(RateLimiter::interval(max_rps), None, "{max_rps}");
+        }
+        assert_eq!(RateLimiter::new().with_max_rps(1e-20).interval, Duration::ZERO);
+    }
+}
@@ -0,0 +1,165 @@
+use std::path::Path;
+use std::sync::Arc;
+use base64::Engine;
+use futures::future::{BoxFuture, FutureExt};
+use serde::{Serialize, Deserialize};
+use url::Url;
+use crate::browser::{BrowserError, Result};
+use crate::http::{HttpError, Response};
+use crate::options::ScrapeOptions;
+use crate::pdf::PrintOptions;
+use crate::renderer::{PageRenderer, RenderedPage};
+
+/// Everything a scrape received from the network: the rendered page and every
+/// response fetched afterwards. Replaying it re-runs the extraction and conversion
+/// pipeline without any network, for reproducible regression tests of converters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub url: String,
+    pub title: String,
+    pub html: String,
+    /// Headers of the page response
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    pub responses: Vec<RecordedResponse>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    /// Requested URL
+    pub url: String,
+    /// URL after redirections
+    pub final_url: String,
+    pub status: u16,
+    pub content_type: Option<String>,
+    /// Base64 encoded body
+    pub body: String,
+}
+
+impl RecordedResponse {
+
+    pub(crate) fn new(url: &str, response: &Response) -> Self {
+        Self {
+            url: url.to_string(),
+            final_url: response.url.to_string(),
+            status: response.status,
+            content_type: response.content_type.clone(),
+            body: base64::engine::general_purpose::STANDARD.encode(&response.body),
+        }
+    }
+
+    /// The response as it was received, `HttpError::InvalidRecording` when the recording was
+    /// edited into something no response gives
+    pub(crate) fn to_response(&self) -> std::result::Result<Response, HttpError> {
+        let invalid = |reason: String| HttpError::InvalidRecording { url: self.url.clone(), reason };
+        Ok(Response {
+            url: Url::parse(&self.final_url).or_else(|_| Url::parse(&self.url)).map_err(|e| invalid(e.to_string()))?,
+            status: self.status,
+            content_type: self.content_type.clone(),
+            body: base64::engine::general_purpose::STANDARD.decode(&self.body).map_err(|e| invalid(e.to_string()))?,
+        })
+    }
+}
+
+impl Recording {
+
+    pub const FILENAME: &str = "recording.json";
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// `PageRenderer` serving the page of a `Recording` instead of loading it
+pub struct ReplayRenderer {
+    recording: Arc<Recording>,
+}
+
+impl ReplayRenderer {
+
+    pub fn new(recording: Arc<Recording>) -> Self {
+        Self { recording }
+    }
+}
+
+impl PageRenderer for ReplayRenderer {
+
+    fn render<'a>(&'a self, url: &'a str, _options: &'a ScrapeOptions) -> BoxFuture<'a, Result<Arc<dyn RenderedPage>>> {
+        async move {
+            if url != self.recording.url {
+                return Err(BrowserError::NotRecorded(url.to_string()));
+            }
+            let page: Arc<dyn RenderedPage> = self.recording.clone();
+            Ok(page)
+        }.boxed()
+    }
+}
+
+impl RenderedPage for Recording {
+
+    fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    fn title(&self) -> anyhow::Result<String> {
+        Ok(self.title.clone())
+    }
+
+    fn html(&self) -> anyhow::Result<String> {
+        Ok(self.html.clone())
+    }
+
+    fn supports_pdf(&self) -> bool {
+        false
+    }
+
+    fn print_to_pdf(&self, _options: &PrintOptions) -> anyhow::Result<Vec<u8>> {
+        anyhow::bail!("recordings don't keep the rendered page, it can't be printed")
+    }
+
+    fn response_headers(&self) -> Vec<(String, String)> {
+        self.headers.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{HttpClient, NetworkMode};
+    use crate::images::Images;
+    use crate::warnings::Warnings;
+    use crate::testing::{FixtureServer, ARTICLE_HTML};
+
+    #[tokio::test]
+    async fn replays_recorded_images_without_network() {
+        let server = FixtureServer::with_article().unwrap();
+        let url = server.url("/article.html");
+
+        let client = HttpClient::new(&NetworkMode::Record).unwrap();
+        let images = Images::from_with_client(ARTICLE_HTML, &url, &client, &ScrapeOptions::default(), &Warnings::default()).await.unwrap();
+        let recording = Recording {
+            url: url.clone(), title: "Fixture article".to_string(), html: ARTICLE_HTML.to_string(), headers: Vec::new(), responses: client.recorded(),
+        };
+        drop(server);
+
+        let recording = Arc::new(recording);
+        let page = ReplayRenderer::new(recording.clone()).render(&url, &ScrapeOptions::default()).await.unwrap();
+        let client = HttpClient::new(&NetworkMode::Replay(recording)).unwrap();
+        let replayed = Images::from_with_client(&page.html().unwrap(), &url, &client, &ScrapeOptions::default(), &Warnings::default()).await.unwrap();
+
+        assert_eq!(replayed.len(), images.len());
+    }
+
+    #[test]
+    fn fails_on_edited_responses() {
+        let response = RecordedResponse {
+            url: "https://example.com/a.png".to_string(), final_url: String::new(), status: 200, content_type: None, body: "cmVk".to_string(),
+        };
+        assert_eq!(response.to_response().unwrap().body, b"red");
+        let broken_url = RecordedResponse { url: "example.com/a.png".to_string(), ..response.clone() };
+        let broken_body = RecordedResponse { body: "not base64!".to_string(), ..response };
+        for response in [broken_url, broken_body] {
+            assert!(matches!(response.to_response(), Err(HttpError::InvalidRecording { .. })));
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+use futures::future::BoxFuture;
+use crate::browser::Result;
+use crate::warnings::Warning;
+use crate::http::{Cookie, Response};
+use crate::options::ScrapeOptions;
+use crate::pdf::PrintOptions;
+use crate::timings::Timings;
+
+/// A page loaded by a `PageRenderer`, ready to be turned into a `WebPage`
+pub trait RenderedPage: Send + Sync {
+    /// URL of the page after redirections
+    fn url(&self) -> String;
+    /// URL asked for, before redirections, `None` when the backend doesn't know it
+    fn requested_url(&self) -> Option<String> {
+        None
+    }
+    /// HTTP status of the page response, `None` when the backend can't see it
+    fn status(&self) -> Option<u16> {
+        None
+    }
+    fn title(&self) -> anyhow::Result<String>;
+    /// Serialized DOM of the rendered page
+    fn html(&self) -> anyhow::Result<String>;
+    /// Whether `print_to_pdf` can be called, pages fetched without a browser can't be printed
+    fn supports_pdf(&self) -> bool {
+        true
+    }
+    /// PDF of the page laid out according to `options`, when the backend prints on demand
+    fn print_to_pdf(&self, options: &PrintOptions) -> anyhow::Result<Vec<u8>>;
+    /// Single-file MHTML snapshot of the page, resources included, `None` when the backend can't take one
+    fn capture_mhtml(&self) -> anyhow::Result<Option<String>> {
+        Ok(None)
+    }
+    /// PNG of the browser viewport, `None` when the backend can't take one
+    fn capture_screenshot(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+    /// JPEG of the top of the page, as much as the viewport shows, scaled down to `width` pixels.
+    /// `None` when the backend can't take one
+    fn capture_thumbnail(&self, _width: u32) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+    /// Load timings, requests and bytes of the page as the backend saw them,
+    /// the scrape adds its own downloads and duration
+    fn timings(&self) -> Timings {
+        Timings::default()
+    }
+    /// Degradations noticed while loading the page
+    fn warnings(&self) -> Vec<Warning> {
+        Vec::new()
+    }
+    /// Headers of the page response, empty when the backend can't see them
+    fn response_headers(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+    /// Image responses received while loading the page, empty unless asked for and supported by the backend
+    fn image_responses(&self) -> Vec<Response> {
+        Vec::new()
+    }
+    /// Cookies of the browsing session, sent along with the asset downloads
+    fn cookies(&self) -> Vec<Cookie> {
+        Vec::new()
+    }
+    /// Content of a `blob:` URL of the page as a `data:` URL, `None` when the backend
+    /// can't read it: blobs only exist within the page that created them
+    fn read_blob(&self, _url: &str) -> Option<String> {
+        None
+    }
+}
+
+/// A browser backend able to load and render a URL
+pub trait PageRenderer: Send + Sync {
+    /// Load `url` as the `options` of its site say: selector and time waited for, credentials,
+    /// delay between its pages, ... The rate limit stays the renderer's
+    fn render<'a>(&'a self, url: &'a str, options: &'a ScrapeOptions) -> BoxFuture<'a, Result<Arc<dyn RenderedPage>>>;
+}
+
+/// Browser used to render the page
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Backend {
+    /// Headless Chrome, launched by the scraper
+    #[default]
+    Chrome,
+    /// Firefox through an already running geckodriver (or any W3C WebDriver)
+    Webdriver,
+}
@@ -0,0 +1,214 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::Serialize;
+use crate::bundle;
+use crate::crawl::{Node, NodeState};
+use crate::warnings::Warning;
+use crate::webpage::WebPage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureStatus {
+    Captured,
+    /// Left out on purpose: already archived, disallowed by robots.txt, ...
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PageCounts {
+    /// Words of the markdown
+    pub words: usize,
+    pub images: usize,
+    pub image_failures: usize,
+    /// Images left out once the image budget was exhausted
+    pub skipped_images: usize,
+}
+
+impl PageCounts {
+
+    pub fn of(webpage: &WebPage) -> Self {
+        let info = webpage.info();
+        Self {
+            words: info.nb_md_words,
+            images: info.nb_images,
+            image_failures: webpage.image_failures().len(),
+            skipped_images: webpage.nb_skipped_images(),
+        }
+    }
+}
+
+/// What became of a URL
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PageReport {
+    pub url: String,
+    pub status: CaptureStatus,
+    /// After redirections
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub final_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Capture directory, bundle or note the page was saved to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    /// Files of the capture, under `path`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<PathBuf>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub counts: Option<PageCounts>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+    /// Why the page was skipped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Time taken by the page, from loading to writing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elapsed_ms: Option<u64>,
+}
+
+impl PageReport {
+
+    fn new(url: &str, status: CaptureStatus) -> Self {
+        Self {
+            url: url.to_string(),
+            status,
+            final_url: None,
+            title: None,
+            path: None,
+            files: Vec::new(),
+            counts: None,
+            warnings: Vec::new(),
+            reason: None,
+            error: None,
+            elapsed_ms: None,
+        }
+    }
+
+    /// `url` captured as `webpage` in `path`, a capture directory whose files are listed or a single file
+    pub fn captured(url: &str, webpage: &WebPage, path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            final_url: Some(webpage.url.clone()),
+            title: Some(webpage.title().to_string()),
+            path: Some(path.to_path_buf()),
+            files: files(path)?,
+            counts: Some(PageCounts::of(webpage)),
+            warnings: webpage.warnings(),
+            ..Self::new(url, CaptureStatus::Captured)
+        })
+    }
+
+    pub fn skipped(url: &str, reason: impl Into<String>) -> Self {
+        Self { reason: Some(reason.into()), ..Self::new(url, CaptureStatus::Skipped) }
+    }
+
+    pub fn failed(url: &str, error: impl Into<String>) -> Self {
+        Self { error: Some(error.into()), ..Self::new(url, CaptureStatus::Failed) }
+    }
+
+    /// Page of a crawl into `crawl_directory`, `None` for the pages only linked to
+    pub fn crawled(node: &Node, crawl_directory: &Path) -> io::Result<Option<Self>> {
+        let report = match (node.state, &node.directory) {
+            (NodeState::Seen, _) => return Ok(None),
+            (NodeState::Captured, Some(directory)) => {
+                let path = crawl_directory.join(directory);
+                Self { files: files(&path)?, path: Some(path), ..Self::new(&node.url, CaptureStatus::Captured) }
+            }
+            (NodeState::Captured, None) => Self::new(&node.url, CaptureStatus::Captured),
+            (NodeState::Disallowed, _) => Self::skipped(&node.url, "disallowed by robots.txt"),
+            (NodeState::Failed, _) => Self::failed(&node.url, node.error.clone().unwrap_or_default()),
+        };
+        Ok(Some(Self {
+            title: node.title.clone(),
+            counts: node.counts.clone(),
+            warnings: node.warnings.clone(),
+            elapsed_ms: node.elapsed_ms,
+            ..report
+        }))
+    }
+
+    pub fn with_elapsed(self, elapsed: Duration) -> Self {
+        Self { elapsed_ms: Some(elapsed.as_millis() as u64), ..self }
+    }
+}
+
+/// Files of the directory at `path`, or `path` itself when it's a file
+fn files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    match path.is_dir() {
+        true => Ok(bundle::paths(path)?.into_iter().map(|file| path.join(file)).collect()),
+        false => Ok(vec![path.to_path_buf()]),
+    }
+}
+
+/// Result of a run of the scraper, printed as JSON with `--json`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunReport {
+    pub captured: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub elapsed_ms: u64,
+    /// In the order they were done
+    pub pages: Vec<PageReport>,
+}
+
+impl RunReport {
+
+    pub fn new(pages: Vec<PageReport>, elapsed: Duration) -> Self {
+        let count = |status| pages.iter().filter(|page| page.status == status).count();
+        Self {
+            captured: count(CaptureStatus::Captured),
+            skipped: count(CaptureStatus::Skipped),
+            failed: count(CaptureStatus::Failed),
+            elapsed_ms: elapsed.as_millis() as u64,
+            pages,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_the_pages_and_lists_the_files_written() {
+        let directory = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(directory.path().join("page-one/images")).unwrap();
+        std::fs::write(directory.path().join("page-one/page-one.md"), "# Page one").unwrap();
+        std::fs::write(directory.path().join("page-one/images/red.png"), [0u8; 4]).unwrap();
+        let linked = Node {
+            url: "https://example.com/c".to_string(), title: None, depth: None, state: NodeState::Seen, directory: None, error: None,
+            elapsed_ms: None, counts: None, warnings: Vec::new(),
+        };
+        let node = Node {
+            url: "https://example.com/a".to_string(), title: Some("Page one".to_string()), depth: Some(0), state: NodeState::Captured,
+            directory: Some("page-one".to_string()), elapsed_ms: Some(1200), counts: Some(PageCounts { words: 2, images: 1, ..Default::default() }),
+            warnings: vec![Warning::new(crate::warnings::WarningKind::GuessedEncoding, "no charset declared")],
+            ..linked.clone()
+        };
+        let disallowed = Node { url: "https://example.com/b".to_string(), depth: Some(1), state: NodeState::Disallowed, ..linked.clone() };
+        assert_eq!(PageReport::crawled(&linked, directory.path()).unwrap(), None);
+
+        let report = RunReport::new(vec![
+            PageReport::crawled(&node, directory.path()).unwrap().unwrap(),
+            PageReport::crawled(&disallowed, directory.path()).unwrap().unwrap(),
+            PageReport::failed("https://example.com/d", "Can't render webpage: timeout"),
+        ], Duration::from_secs(2));
+
+        let root = directory.path().join("page-one");
+        assert_eq!(serde_json::to_value(&report).unwrap(), serde_json::json!({
+            "captured": 1, "skipped": 1, "failed": 1, "elapsed_ms": 2000,
+            "pages": [
+                {
+                    "url": "https://example.com/a", "status": "captured", "title": "Page one", "path": root,
+                    "files": [root.join("images/red.png"), root.join("page-one.md")],
+                    "words": 2, "images": 1, "image_failures": 0, "skipped_images": 0,
+                    "warnings": [{ "kind": "guessed_encoding", "message": "no charset declared" }], "elapsed_ms": 1200,
+                },
+                { "url": "https://example.com/b", "status": "skipped", "reason": "disallowed by robots.txt" },
+                { "url": "https://example.com/d", "status": "failed", "error": "Can't render webpage: timeout" },
+            ],
+        }));
+    }
+}
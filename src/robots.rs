@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use scraper::{Html, Selector};
+use url::Url;
+use crate::http::{HttpClient, HttpError, NetworkMode};
+use crate::options::SiteAuth;
+use crate::ratelimit::RateLimiter;
+use crate::recording::RecordedResponse;
+
+/// Name the scraper goes by in robots.txt `User-agent` lines
+pub const USER_AGENT_TOKEN: &str = "webpage_scraper";
+
+/// Indexing and archiving directives of a page, from `<meta name="robots">`
+/// and `X-Robots-Tag` response headers
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RobotsDirectives(Vec<String>);
+
+impl RobotsDirectives {
+
+    /// Directives of `html` and of its response `headers`, lowercased and without duplicates.
+    /// Header directives scoped to another crawler (`googlebot: noindex`) are ignored
+    pub fn from_page(html: &str, headers: &[(String, String)]) -> Self {
+
+        let document = Html::parse_document(html);
+        let selector = Selector::parse("meta[name][content]").unwrap();
+
+        let meta = document
+            .select(&selector)
+            .filter(|meta| meta.attr("name").is_some_and(|name| name.eq_ignore_ascii_case("robots")))
+            .filter_map(|meta| meta.attr("content"))
+            .map(str::to_string);
+
+        let header = headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("x-robots-tag"))
+            .map(|(_, value)| value.clone())
+            .filter(|value| match value.split_once(':') {
+                // `unavailable_after: <date>` is a directive, `otherbot: noindex` isn't for us
+                Some((scope, _)) => scope.trim().eq_ignore_ascii_case("unavailable_after"),
+                None => true,
+            });
+
+        let mut directives: Vec<String> = Vec::new();
+        for directive in meta.chain(header).flat_map(|value| {
+            value.split(',').map(|d| d.trim().to_ascii_lowercase()).collect::<Vec<_>>()
+        }) {
+            let expanded: &[&str] = match directive.as_str() {
+                "" => &[],
+                "none" => &["noindex", "nofollow"],
+                other => &[other],
+            };
+            for directive in expanded {
+                if !directives.iter().any(|d| d == directive) {
+                    directives.push(directive.to_string());
+                }
+            }
+        }
+
+        Self(directives)
+    }
+
+    pub fn contains(&self, directive: &str) -> bool {
+        self.0.iter().any(|d| d == directive)
+    }
+
+    /// The page asks not to be archived
+    pub fn noarchive(&self) -> bool {
+        self.contains("noarchive")
+    }
+
+    pub fn noindex(&self) -> bool {
+        self.contains("noindex")
+    }
+
+    pub fn directives(&self) -> &[String] {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Rules of a robots.txt file (RFC 9309) for one crawler
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RobotsTxt {
+    /// Path patterns, `*` matching anything and a final `$` the end of the path, and whether they allow
+    rules: Vec<(String, bool)>,
+    /// Seconds to wait between two pages, a non-standard but common extension
+    crawl_delay: Option<f64>,
+}
+
+impl RobotsTxt {
+
+    /// Everything allowed, as when a site has no robots.txt
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// Everything disallowed, as when the robots.txt of a site can't be reached
+    pub fn disallow_all() -> Self {
+        Self { rules: vec![("/".to_string(), false)], crawl_delay: None }
+    }
+
+    /// Rules of the groups of `text` for the crawler named `agent`, or else those for `*`
+    pub fn parse(text: &str, agent: &str) -> Self {
+
+        let (mut own, mut any) = (Self::default(), Self::default());
+        let (mut for_own, mut for_any) = (false, false);
+        let (mut found_own, mut found_any) = (false, false);
+        // consecutive user-agent lines share the group following them
+        let mut in_agents = false;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let Some((key, value)) = line.split_once(':') else { continue };
+            let (key, value) = (key.trim().to_ascii_lowercase(), value.trim());
+            if key == "user-agent" {
+                if !in_agents {
+                    (for_own, for_any) = (false, false);
+                }
+                in_agents = true;
+                for_own |= value.eq_ignore_ascii_case(agent);
+                for_any |= value == "*";
+                found_own |= for_own;
+                found_any |= for_any;
+                continue;
+            }
+            in_agents = false;
+            for (group, applies) in [(&mut own, for_own), (&mut any, for_any)] {
+                if !applies {
+                    continue;
+                }
+                match key.as_str() {
+                    "allow" | "disallow" if !value.is_empty() => group.rules.push((value.to_string(), key == "allow")),
+                    "crawl-delay" => group.crawl_delay = value.parse().ok().filter(|delay: &f64| delay.is_finite() && *delay >= 0.0),
+                    _ => {}
+                }
+            }
+        }
+        match (found_own, found_any) {
+            (true, _) => own,
+            (false, true) => any,
+            (false, false) => Self::default(),
+        }
+    }
+
+    /// Whether `pattern` matches the start of `path`
+    fn matches(pattern: &str, path: &str) -> bool {
+        let (pattern, anchored) = match pattern.strip_suffix('$') {
+            Some(pattern) => (pattern, true),
+            None => (pattern, false),
+        };
+        let mut parts = pattern.split('*');
+        let Some(rest) = path.strip_prefix(parts.next().unwrap_or_default()) else {
+            return false;
+        };
+        // each part as early as possible leaves the most room for the next ones
+        let mut rest = rest;
+        let parts = parts.collect::<Vec<_>>();
+        for (i, part) in parts.iter().enumerate() {
+            if anchored && i == parts.len() - 1 {
+                return rest.ends_with(part);
+            }
+            match rest.find(part) {
+                Some(start) => rest = &rest[start + part.len()..],
+                None => return false,
+            }
+        }
+        !anchored || rest.is_empty()
+    }
+
+    /// Whether the crawler may fetch `url`: the longest matching rule decides, allow winning ties
+    pub fn allows(&self, url: &Url) -> bool {
+        let mut path = url.path().to_string();
+        if let Some(query) = url.query() {
+            path.push('?');
+            path.push_str(query);
+        }
+        if path == "/robots.txt" {
+            return true;
+        }
+        self.rules.iter()
+            .filter(|(pattern, _)| Self::matches(pattern, &path))
+            .max_by_key(|(pattern, allow)| (pattern.len(), *allow))
+            .is_none_or(|(_, allow)| *allow)
+    }
+
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay.map(Duration::from_secs_f64)
+    }
+}
+
+/// robots.txt of the sites visited, fetched once per origin, and when their next page may be fetched
+pub struct Robots {
+    client: HttpClient,
+    /// Credentials of the sites, the most specific domain applying
+    auth: Vec<SiteAuth>,
+    /// Per origin, set once its robots.txt is fetched, the sites waiting for their own meanwhile
+    files: Mutex<HashMap<String, Arc<tokio::sync::OnceCell<RobotsTxt>>>>,
+    next_visits: Mutex<HashMap<String, Instant>>,
+}
+
+impl std::fmt::Debug for Robots {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Robots").finish_non_exhaustive()
+    }
+}
+
+impl Robots {
+
+    /// Fetching robots.txt files live, recording them or from a recording, as `network` says
+    pub fn new(network: &NetworkMode) -> Result<Self, HttpError> {
+        // robots.txt files are small, a retry is enough
+        let client = HttpClient::new(network)?.with_retry(crate::http::RetryPolicy { max_retries: 1, ..Default::default() });
+        Ok(Self { client, auth: Vec::new(), files: Default::default(), next_visits: Default::default() })
+    }
+
+    /// Space out the robots.txt requests along with the downloads of the pages, see `HttpClient::with_rate_limit`
+    pub fn with_rate_limit(mut self, rate_limit: Arc<RateLimiter>, delay: Duration) -> Self {
+        self.client = self.client.with_rate_limit(rate_limit, delay, Duration::ZERO);
+        self
+    }
+
+    /// Send the credentials of their site along with the robots.txt requests
+    pub fn with_auth(mut self, auth: Vec<SiteAuth>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Recorded robots.txt of the site of `url`, empty unless recording
+    pub fn recorded(&self, url: &Url) -> Vec<RecordedResponse> {
+        let robots_url = format!("{}/robots.txt", url.origin().ascii_serialization());
+        self.client.recorded().into_iter().filter(|response| response.url == robots_url).collect()
+    }
+
+    /// `Crawl-delay` of the site of `url`, `None` until its robots.txt is fetched
+    pub fn crawl_delay(&self, url: &Url) -> Option<Duration> {
+        let file = self.files.lock().unwrap().get(&url.origin().ascii_serialization()).cloned()?;
+        file.get().and_then(RobotsTxt::crawl_delay)
+    }
+
+    /// Rules of the site of `url`. A missing robots.txt allows everything, one failing
+    /// with a server error or unreachable disallows everything, as RFC 9309 says
+    pub async fn rules(&self, url: &Url) -> RobotsTxt {
+
+        let origin = url.origin().ascii_serialization();
+        let file = self.files.lock().unwrap().entry(origin.clone()).or_default().clone();
+        file.get_or_init(|| self.fetch(origin)).await.clone()
+    }
+
+    async fn fetch(&self, origin: String) -> RobotsTxt {
+        match Url::parse(&format!("{origin}/robots.txt")) {
+            Ok(robots_url) => {
+                let auth = self.auth.iter().filter(|auth| auth.applies_to(&robots_url)).max_by_key(|auth| auth.domain.trim_start_matches("*.").len());
+                let client = self.client.clone().with_auth(auth.cloned());
+                // boxed, the client asks `Robots` before its own requests
+                match Box::pin(client.get(&robots_url)).await {
+                    Ok(response) => RobotsTxt::parse(&String::from_utf8_lossy(&response.body), USER_AGENT_TOKEN),
+                    Err(HttpError::StatusError { status, .. }) if status < 500 => RobotsTxt::allow_all(),
+                    // replaying a capture made without robots.txt, nothing is fetched anyway
+                    Err(HttpError::NotRecorded(_)) => RobotsTxt::allow_all(),
+                    Err(_) => RobotsTxt::disallow_all(),
+                }
+            }
+            // `data:`, `file:`, ... URLs have no robots.txt
+            Err(_) => RobotsTxt::allow_all(),
+        }
+    }
+
+    /// Whether the site of `url` lets the scraper fetch it
+    pub async fn allows(&self, url: &Url) -> bool {
+        !matches!(url.scheme(), "http" | "https") || self.rules(url).await.allows(url)
+    }
+
+    /// Whether the page `url` may be fetched, after waiting for the `Crawl-delay` of its site
+    /// since its previous page when allowed
+    pub async fn admit(&self, url: &Url) -> bool {
+
+        if !matches!(url.scheme(), "http" | "https") {
+            return true;
+        }
+        let rules = self.rules(url).await;
+        if !rules.allows(url) {
+            return false;
+        }
+        if let Some(delay) = rules.crawl_delay() {
+            let wait = {
+                let now = Instant::now();
+                let mut next_visits = self.next_visits.lock().unwrap();
+                let slot = next_visits.get(&url.origin().ascii_serialization()).copied().filter(|slot| *slot > now).unwrap_or(now);
+                next_visits.insert(url.origin().ascii_serialization(), slot + delay);
+                slot - now
+            };
+            tokio::time::sleep(wait).await;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_meta_and_header_directives() {
+        let html = r#"<head><meta name="ROBOTS" content="noindex, NOARCHIVE"><meta name="description" content="none"></head>"#;
+        let headers = vec![
+            ("X-Robots-Tag".to_string(), "none".to_string()),
+            ("x-robots-tag".to_string(), "otherbot: nosnippet".to_string()),
+        ];
+        let robots = RobotsDirectives::from_page(html, &headers);
+        assert_eq!(robots.directives(), ["noindex", "noarchive", "nofollow"]);
+        assert!(robots.noarchive());
+        assert!(RobotsDirectives::from_page("<p>hi</p>", &[]).is_empty());
+    }
+
+    #[test]
+    fn picks_the_group_of_the_scraper() {
+        let text = "User-agent: *\nDisallow: /\n\n# ours\nUser-agent: Googlebot\nUser-agent: WEBPAGE_SCRAPER\n\
+            Disallow: /private\nAllow: /private/*.html$\nDisallow: /*?session=\nCrawl-delay: 2.5\n";
+        let robots = RobotsTxt::parse(text, USER_AGENT_TOKEN);
+        let allows = |path: &str| robots.allows(&Url::parse(&format!("https://example.com{path}")).unwrap());
+        assert!(allows("/") && allows("/public/page") && allows("/robots.txt"));
+        assert!(!allows("/private") && !allows("/private/notes.txt") && !allows("/search?session=1"));
+        assert!(allows("/private/page.html") && !allows("/private/page.html?print=1"));
+        assert_eq!(robots.crawl_delay(), Some(Duration::from_millis(2500)));
+
+        let others = RobotsTxt::parse(text, "otherbot");
+        assert!(!others.allows(&Url::parse("https://example.com/public").unwrap()));
+        assert!(RobotsTxt::parse("User-agent: otherbot\nDisallow: /\n", USER_AGENT_TOKEN).allows(&Url::parse("https://example.com/").unwrap()));
+    }
+
+    #[tokio::test]
+    async fn fetches_robots_txt_once_per_site() {
+        let server = crate::testing::FixtureServer::new().unwrap();
+        server.route("/robots.txt", "text/plain", "User-agent: *\nDisallow: /drafts/\n");
+        let robots = Robots::new(&NetworkMode::Live).unwrap();
+        assert!(robots.admit(&Url::parse(&server.url("/article.html")).unwrap()).await);
+        assert!(!robots.admit(&Url::parse(&server.url("/drafts/next.html")).unwrap()).await);
+        assert!(!robots.allows(&Url::parse(&server.url("/drafts/red.png")).unwrap()).await);
+        assert_eq!(server.requests().iter().filter(|request| request.contains("/robots.txt")).count(), 1);
+    }
+
+    #[tokio::test]
+    async fn records_and_replays_robots_txt_with_credentials() {
+        let server = crate::testing::FixtureServer::new().unwrap();
+        server.route("/robots.txt", "text/plain", "User-agent: *\nDisallow: /drafts/\nCrawl-delay: 3\n");
+        let credentials = crate::options::Credentials { username: Some("reader".to_string()), ..Default::default() };
+        let robots = Robots::new(&NetworkMode::Record).unwrap()
+            .with_rate_limit(Arc::new(RateLimiter::new()), Duration::ZERO)
+            .with_auth(vec![SiteAuth { domain: "127.0.0.1".to_string(), credentials }]);
+        let page = Url::parse(&server.url("/article.html")).unwrap();
+        assert_eq!(robots.crawl_delay(&page), None);
+        assert!(robots.allows(&page).await);
+        assert_eq!(robots.crawl_delay(&page), Some(Duration::from_secs(3)));
+        assert!(server.request_header("/robots.txt", "authorization")[0].as_deref().is_some_and(|value| value.starts_with("Basic ")));
+
+        let recording = crate::recording::Recording { url: page.to_string(), title: String::new(), html: String::new(), headers: Vec::new(), responses: robots.recorded(&page) };
+        assert_eq!(recording.responses.len(), 1);
+        let replayed = Robots::new(&NetworkMode::Replay(Arc::new(recording))).unwrap();
+        assert!(!replayed.allows(&Url::parse(&server.url("/drafts/next.html")).unwrap()).await);
+        // not in the recording
+        assert!(replayed.allows(&Url::parse("http://127.0.0.2:1/drafts/next.html").unwrap()).await);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn does_not_wait_for_the_robots_txt_of_other_sites() {
+        // never answers
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let hanging = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let server = crate::testing::FixtureServer::new().unwrap();
+        server.route("/robots.txt", "text/plain", "User-agent: *\nDisallow: /drafts/\n");
+        let robots = Robots::new(&NetworkMode::Live).unwrap();
+
+        let other = Url::parse(&server.url("/drafts/next.html")).unwrap();
+        let rules = tokio::time::timeout(Duration::from_secs(5), async {
+            tokio::select! {
+                _ = robots.rules(&hanging) => panic!("the robots.txt of {hanging} came"),
+                rules = robots.rules(&other) => rules,
+            }
+        });
+        assert!(!rules.await.expect("waited for the robots.txt of another site").allows(&other));
+    }
+}
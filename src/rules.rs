@@ -0,0 +1,173 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::Deserialize;
+use thiserror::Error;
+use crate::config::matches_domain;
+use crate::extract::Fields;
+use crate::options::ScrapeOptions;
+use crate::toml;
+
+#[derive(Error, Debug)]
+pub enum RulesError {
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("invalid rule file {file}: {message}")]
+    InvalidRule { file: PathBuf, message: String },
+}
+
+pub type Result<T> = std::result::Result<T, RulesError>;
+
+/// Extraction rule of a site, a `<domain>.toml` file of the rules directory
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// Domains the rule applies to, subdomains included. The name of the file without `.toml` unless given
+    #[serde(default)]
+    pub domains: Vec<String>,
+    /// CSS selector of the content, like `--select`
+    pub content: Option<String>,
+    /// CSS selectors of the elements removed before conversion, like `--exclude`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Attributes holding the URLs of lazy-loaded images, on top of the usual ones
+    #[serde(default)]
+    pub lazy_attributes: Vec<String>,
+    /// CSS selector waited for before capturing the page
+    pub wait_for: Option<String>,
+    /// Extra time given to the page once loaded, in milliseconds
+    pub wait_ms: Option<u64>,
+    /// Fields saved to `extracted.json`, like those of `--extract`
+    #[serde(default)]
+    pub fields: Fields,
+}
+
+/// Site-specific extraction rules loaded from a directory, so quirks of a site are fixed
+/// by dropping a file in it
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+
+    /// Rules of the `.toml` files of `directory`
+    pub fn load(directory: &Path) -> Result<Self> {
+
+        let mut files = std::fs::read_dir(directory)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        files.retain(|path| path.is_file() && path.extension().is_some_and(|extension| extension == "toml"));
+        files.sort();
+
+        let rules = files.iter().map(|file| {
+            let invalid = |message: String| RulesError::InvalidRule { file: file.clone(), message };
+            let value = toml::parse(&std::fs::read_to_string(file)?).map_err(|e| invalid(e.to_string()))?;
+            let mut rule = serde_json::from_value::<Rule>(value).map_err(|e| invalid(e.to_string()))?;
+            if rule.domains.is_empty() {
+                let stem = file.file_stem().unwrap_or_default().to_string_lossy().to_string();
+                rule.domains.push(stem);
+            }
+            Ok(rule)
+        }).collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Rules matching `host` with the length of their matching domain, least specific first
+    fn rules_for<'a>(&'a self, host: &'a str) -> impl Iterator<Item = &'a Rule> {
+        let mut matching = self.rules
+            .iter()
+            .filter_map(|rule| {
+                let specificity = rule.domains.iter()
+                    .filter(|domain| matches_domain(host, domain))
+                    .map(|domain| domain.trim_start_matches("*.").trim_start_matches('.').len())
+                    .max()?;
+                Some((specificity, rule))
+            })
+            .collect::<Vec<_>>();
+        matching.sort_by_key(|(specificity, _)| *specificity);
+        matching.into_iter().map(|(_, rule)| rule)
+    }
+
+    /// `options` with the rules matching the host of `url`, more specific domains winning over
+    /// their parents. A content selector, fields and waits given on the command line or in the
+    /// configuration win over those of the rules
+    pub fn options_for(&self, url: &str, options: &ScrapeOptions) -> ScrapeOptions {
+
+        let mut options = options.clone();
+        let Some(host) = url::Url::parse(url).ok().and_then(|url| url.host_str().map(str::to_string)) else {
+            return options;
+        };
+
+        let given_content = options.select.take();
+        let given_fields = std::mem::take(&mut options.extract);
+        let given_wait_for = options.wait_for.take();
+        let given_wait = (options.wait_after_load != ScrapeOptions::default().wait_after_load).then_some(options.wait_after_load);
+        for rule in self.rules_for(&host) {
+            if let Some(selector) = &rule.content {
+                options.select = Some(selector.clone());
+            }
+            options.remove_selectors.extend(rule.exclude.iter().cloned());
+            for attribute in &rule.lazy_attributes {
+                if !options.lazy_attributes.0.contains(attribute) {
+                    options.lazy_attributes.0.push(attribute.clone());
+                }
+            }
+            if let Some(selector) = &rule.wait_for {
+                options.wait_for = Some(selector.clone());
+            }
+            if let Some(ms) = rule.wait_ms {
+                options.wait_after_load = Duration::from_millis(ms);
+            }
+            options.extract.extend(&rule.fields);
+        }
+        options.select = given_content.or(options.select);
+        options.extract.extend(&given_fields);
+        options.wait_for = given_wait_for.or(options.wait_for);
+        options.wait_after_load = given_wait.unwrap_or(options.wait_after_load);
+
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_the_rules_of_the_domain() {
+        let directory = tempfile::tempdir().unwrap();
+        std::fs::write(directory.path().join("example.com.toml"), r#"
+            content = "main"
+            exclude = [".share-buttons"]
+            wait_ms = 500
+        "#).unwrap();
+        std::fs::write(directory.path().join("news.toml"), r##"
+            domains = ["news.example.com"]
+            content = "article.story"
+            lazy_attributes = ["data-hi-res"]
+            wait_for = "#story"
+
+            [fields]
+            byline = ".byline"
+        "##).unwrap();
+        std::fs::write(directory.path().join("notes.txt"), "not a rule").unwrap();
+        let rules = Rules::load(directory.path()).unwrap();
+
+        let options = rules.options_for("https://news.example.com/today", &ScrapeOptions::default());
+        assert_eq!(options.select.as_deref(), Some("article.story"));
+        assert_eq!(options.remove_selectors, [".share-buttons"]);
+        assert!(options.lazy_attributes.0.contains(&"data-hi-res".to_string()));
+        assert_eq!((options.wait_for.as_deref(), options.wait_after_load), (Some("#story"), Duration::from_millis(500)));
+        assert!(options.extract.0.contains_key("byline"));
+
+        let given = ScrapeOptions { select: Some("#main".to_string()), ..Default::default() };
+        assert_eq!(rules.options_for("https://example.com/", &given).select.as_deref(), Some("#main"));
+        let given = ScrapeOptions { wait_for: Some("#app".to_string()), wait_after_load: Duration::from_millis(2000), ..Default::default() };
+        let options = rules.options_for("https://news.example.com/", &given);
+        assert_eq!((options.wait_for.as_deref(), options.wait_after_load), (Some("#app"), Duration::from_millis(2000)));
+        assert_eq!(rules.options_for("https://other.org/", &ScrapeOptions::default()).select, None);
+
+        std::fs::write(directory.path().join("broken.toml"), "contnet = \"main\"").unwrap();
+        assert!(matches!(Rules::load(directory.path()), Err(RulesError::InvalidRule { .. })));
+    }
+}
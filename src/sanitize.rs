@@ -0,0 +1,181 @@
+use scraper::{ElementRef, Html, Node};
+use url::Url;
+
+/// Elements running code or loading third-party documents, removed with their content
+const ACTIVE_ELEMENTS: &[&str] = &["script", "iframe", "frame", "frameset", "object", "embed", "applet", "portal"];
+
+/// `<link rel>` values making the browser fetch or run something ahead of time
+const PRELOADING_RELS: &[&str] = &["preload", "modulepreload", "prefetch", "prerender", "preconnect", "dns-prefetch", "import", "serviceworker"];
+
+/// Attributes holding URLs, removed when they are `javascript:` ones or alike, see `is_script_url`
+const URL_ATTRIBUTES: &[&str] = &["href", "src", "action", "formaction", "xlink:href", "data", "poster", "background"];
+
+/// Hosts of analytics, ads and tracking pixels
+const TRACKERS: &[&str] = &[
+    "google-analytics.com", "googletagmanager.com", "googlesyndication.com", "doubleclick.net", "googleadservices.com",
+    "connect.facebook.net", "facebook.com/tr", "scorecardresearch.com", "quantserve.com", "hotjar.com", "segment.io",
+    "segment.com", "mixpanel.com", "amplitude.com", "clarity.ms", "bat.bing.com", "ads.linkedin.com", "px.ads.linkedin.com",
+    "analytics.twitter.com", "t.co/i/adsct", "pixel.wp.com", "stats.wp.com", "matomo.cloud", "chartbeat.com", "newrelic.com", "nr-data.net",
+];
+
+/// What `html` removed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Removed {
+    /// Scripts, frames, plugins and preloads
+    pub elements: usize,
+    /// Event handlers and `javascript:` URLs
+    pub attributes: usize,
+    /// Tracking pixels and tracker scripts or images
+    pub trackers: usize,
+}
+
+fn is_tracker(url: &str, base_url: Option<&Url>) -> bool {
+    let Some(url) = base_url.and_then(|base_url| base_url.join(url.trim()).ok()).or_else(|| Url::parse(url.trim()).ok()) else {
+        return false;
+    };
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    TRACKERS.iter().any(|tracker| {
+        let (domain, path) = tracker.split_once('/').map_or((*tracker, ""), |(domain, path)| (domain, path));
+        let host_matches = host == domain || host.ends_with(&format!(".{domain}"));
+        host_matches && (path.is_empty() || url.path() == format!("/{path}") || url.path().starts_with(&format!("/{path}/")))
+    })
+}
+
+/// Images of at most 1×1 pixel, the usual tracking pixels
+fn is_pixel(element: &ElementRef) -> bool {
+    let tiny = |name: &str| element.attr(name).and_then(|value| value.trim().trim_end_matches("px").parse::<u32>().ok()).is_some_and(|size| size <= 1);
+    tiny("width") && tiny("height")
+}
+
+fn is_preload(element: &ElementRef) -> bool {
+    element.value().name() == "link"
+        && element.attr("rel").is_some_and(|rel| rel.split_whitespace().any(|rel| PRELOADING_RELS.contains(&rel.to_ascii_lowercase().as_str())))
+}
+
+/// URLs running code when followed: `javascript:`, `vbscript:` and HTML `data:` ones, read as
+/// browsers do, leading controls and spaces and any tab or newline left out
+fn is_script_url(value: &str) -> bool {
+    let url = value.trim_start_matches(|c: char| c.is_ascii_control() || c == ' ')
+        .chars()
+        .filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+        .take(14)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    ["javascript:", "vbscript:", "data:text/html"].iter().any(|scheme| url.starts_with(scheme))
+}
+
+/// SVG `<animate>` or `<set>` changing a link of its parent, a way around the `href` check
+fn is_link_animation(element: &ElementRef) -> bool {
+    matches!(element.value().name(), "animate" | "set")
+        && element.attr("attributeName").or_else(|| element.attr("attributename"))
+            .is_some_and(|name| name.trim().eq_ignore_ascii_case("href") || name.trim().eq_ignore_ascii_case("xlink:href"))
+}
+
+/// `<base href>`, sending the relative links of the saved page elsewhere
+fn is_base(element: &ElementRef) -> bool {
+    element.value().name() == "base" && element.attr("href").is_some()
+}
+
+/// `<meta http-equiv="refresh">`, navigating away from the saved page
+fn is_refresh(element: &ElementRef) -> bool {
+    element.value().name() == "meta" && element.attr("http-equiv").is_some_and(|equiv| equiv.eq_ignore_ascii_case("refresh"))
+}
+
+/// `html` safe to open locally: without scripts, frames, plugins, preloads, `<base>`, SVG link
+/// animations, event handlers, `javascript:` URLs and alike, `ping` attributes, nor the tracking pixels and tracker resources
+/// of known analytics and ad hosts. Relative URLs are resolved against `base_url` to spot trackers
+pub fn html(html: &str, base_url: &str) -> (String, Removed) {
+
+    let base_url = Url::parse(base_url).ok();
+    let mut document = Html::parse_document(html);
+    let mut removed = Removed::default();
+
+    let mut detached = Vec::new();
+    for node in document.root_element().descendants() {
+        let Some(element) = ElementRef::wrap(node) else { continue };
+        let name = element.value().name();
+        let source = element.attr("src").or_else(|| (name == "link").then(|| element.attr("href")).flatten());
+        if ACTIVE_ELEMENTS.contains(&name) {
+            match source.is_some_and(|source| is_tracker(source, base_url.as_ref())) {
+                true => removed.trackers += 1,
+                false => removed.elements += 1,
+            }
+            detached.push(node.id());
+        } else if name == "img" && (is_pixel(&element) || source.is_some_and(|source| is_tracker(source, base_url.as_ref()))) {
+            removed.trackers += 1;
+            detached.push(node.id());
+        } else if is_preload(&element) || is_refresh(&element) || is_base(&element) || is_link_animation(&element) {
+            removed.elements += 1;
+            detached.push(node.id());
+        }
+    }
+    for id in detached {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+
+    let ids = document.root_element().descendants().filter(|node| node.value().is_element()).map(|node| node.id()).collect::<Vec<_>>();
+    for id in ids {
+        let Some(mut node) = document.tree.get_mut(id) else { continue };
+        let Node::Element(element) = node.value() else { continue };
+        let before = element.attrs.len();
+        element.attrs.retain(|(name, value)| {
+            let name = name.local.to_ascii_lowercase().to_string();
+            let javascript = URL_ATTRIBUTES.contains(&name.as_str()) && is_script_url(value);
+            !(name.starts_with("on") || name == "ping" || name == "srcdoc" || javascript)
+        });
+        removed.attributes += before - element.attrs.len();
+    }
+
+    (document.html(), removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_scripts_handlers_and_trackers() {
+        let page = r#"<html><head>
+            <script src="/app.js"></script>
+            <script async src="https://www.googletagmanager.com/gtag/js?id=G-1"></script>
+            <link rel="preload" href="/app.js" as="script">
+            <link rel="stylesheet" href="/style.css">
+        </head><body onload="init()">
+            <p onclick="track()">Text <a href="javascript:void(0)" ping="/ping">link</a> <a href="/next">next</a></p>
+            <img src="/images/photo.jpg" alt="Photo">
+            <img src="/pixel.gif" width="1" height="1">
+            <img src="https://www.facebook.com/tr?id=1&ev=PageView">
+            <iframe src="https://ads.example.com/frame"></iframe>
+        </body></html>"#;
+
+        let (sanitized, removed) = html(page, "https://example.com/post");
+        assert!(!sanitized.contains("<script") && !sanitized.contains("<iframe") && !sanitized.contains("preload"));
+        assert!(!sanitized.contains("onload") && !sanitized.contains("onclick") && !sanitized.contains("javascript:") && !sanitized.contains("ping="));
+        assert!(!sanitized.contains("pixel.gif") && !sanitized.contains("facebook.com"));
+        assert!(sanitized.contains("/style.css") && sanitized.contains("/images/photo.jpg"));
+        assert!(sanitized.contains(r#"<a href="/next">next</a>"#));
+        assert_eq!(removed, Removed { elements: 3, attributes: 4, trackers: 3 });
+    }
+
+    #[test]
+    fn catches_disguised_script_urls() {
+        for url in ["java\tscript:alert(1)", " \x01JavaScript:alert(1)", "vbscript:msgbox", "DATA:text/html,<script>", "jav\r\nascript:x"] {
+            assert!(is_script_url(url), "{url:?}");
+        }
+        assert!(!is_script_url("data:image/png;base64,AAAA") && !is_script_url("/javascript:notes"));
+
+        let page = r#"<html><head><base href="https://evil.example/"></head><body>
+            <a href="java&#10;script:alert(1)">entity</a>
+            <svg><a href="/ok"><animate attributeName="href" to="javascript:alert(1)"/><set attributeName="xlink:href" to="javascript:x"/><text>svg</text></a></svg>
+            <svg><circle r="1"><animate attributeName="r" values="1;2"/></circle></svg>
+        </body></html>"#;
+        let (sanitized, removed) = html(page, "https://example.com/");
+        assert!(!sanitized.contains("javascript") && !sanitized.contains("<base") && !sanitized.contains("<set"));
+        assert!(sanitized.contains(r#"attributename="r""#) || sanitized.contains(r#"attributeName="r""#));
+        assert_eq!(removed, Removed { elements: 3, attributes: 1, trackers: 0 });
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use base64::Engine;
+use scraper::{Html, Node, Selector};
+use url::Url;
+use crate::css;
+use crate::dom;
+use crate::filename;
+
+/// Suffix of the single-file copy of the page, next to `<file stem>.html`
+pub const EXTENSION: &str = "single.html";
+
+/// `@import`s followed within stylesheets, deeper ones stay as they are
+const MAX_IMPORT_DEPTH: usize = 4;
+
+/// Files of a capture by their path relative to the capture directory, percent-encoded as the HTML references them
+pub type Files<'a> = HashMap<String, &'a [u8]>;
+
+/// MIME type of a saved file, after its extension
+fn mime(path: &str) -> &'static str {
+    match filename::split_extension(path).1.to_ascii_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "bmp" => "image/bmp",
+        "tif" | "tiff" => "image/tiff",
+        "jxl" => "image/jxl",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "woff2" => "font/woff2",
+        "woff" => "font/woff",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "eot" => "application/vnd.ms-fontobject",
+        _ => "application/octet-stream",
+    }
+}
+
+fn data_url(path: &str, bytes: &[u8]) -> String {
+    format!("data:{};base64,{}", mime(path), base64::engine::general_purpose::STANDARD.encode(bytes))
+}
+
+/// Capture directory as a URL, for the relative paths of the HTML and stylesheets to resolve against
+fn root() -> Url {
+    Url::parse("file:///capture/").unwrap()
+}
+
+/// Path relative to the capture directory of `src`, referenced from the file at `from`
+fn resolve(src: &str, from: &Url) -> Option<String> {
+    if src.starts_with("data:") || src.starts_with('#') {
+        return None;
+    }
+    let url = from.join(src.trim()).ok()?;
+    (url.scheme() == "file").then(|| url.path().strip_prefix(root().path()).map(str::to_string)).flatten()
+}
+
+/// `css` of the file at `from` with its imports, fonts and images turned into `data:` URLs
+fn inline_css(css: &str, from: &Url, files: &Files, depth: usize) -> String {
+    let local = |src: &str| -> Option<String> {
+        let path = resolve(src, from)?;
+        let bytes = files.get(&path)?;
+        if mime(&path) != "text/css" {
+            return Some(data_url(&path, bytes));
+        }
+        if depth >= MAX_IMPORT_DEPTH {
+            return None;
+        }
+        let imported = inline_css(&String::from_utf8_lossy(bytes), &root().join(&path).ok()?, files, depth + 1);
+        Some(data_url(&path, imported.as_bytes()))
+    };
+    css::replace_urls(&css::replace_imports(css, local), local)
+}
+
+/// One HTML file holding all of `html`: the stylesheets and scripts among `files` are put inline,
+/// and the images and fonts become `data:` URLs. References to anything else are left as they are
+pub fn inline(html: &str, files: &Files) -> String {
+
+    let mut document = Html::parse_document(html);
+    let stylesheet_selector = Selector::parse("link[rel~=stylesheet][href]").unwrap();
+    let script_selector = Selector::parse("script[src]").unwrap();
+    let style_selector = Selector::parse("style").unwrap();
+    let element_selector = Selector::parse("[src], [srcset], [poster], [style], link[rel~=icon][href]").unwrap();
+    let root = root();
+    let local = |src: Option<&str>| resolve(src?, &root).filter(|path| files.contains_key(path));
+
+    let mut replacements = Vec::new();
+    for link in document.select(&stylesheet_selector) {
+        if let Some(path) = local(link.attr("href")) {
+            let css = inline_css(&String::from_utf8_lossy(files[&path]), &root.join(&path).unwrap_or(root.clone()), files, 0);
+            let media = link.attr("media").map(|media| format!(r#" media="{}""#, media.replace('"', "&quot;"))).unwrap_or_default();
+            // the text of <style> isn't escaped, only its end tag would end it early
+            replacements.push((link.id(), format!("<style{media}>{}</style>", css.replace("</style", r"<\/style"))));
+        }
+    }
+    for script in document.select(&script_selector) {
+        if let Some(path) = local(script.attr("src")) {
+            let source = String::from_utf8_lossy(files[&path]).replace("</script", r"<\/script");
+            let kind = script.attr("type").map(|kind| format!(r#" type="{}""#, kind.replace('"', "&quot;"))).unwrap_or_default();
+            replacements.push((script.id(), format!("<script{kind}>{source}</script>")));
+        }
+    }
+
+    let mut attributes = Vec::new();
+    for element in document.select(&element_selector) {
+        // scripts are put inline whole, and the only links inlined are icons
+        let names = match element.value().name() {
+            "script" => &[][..],
+            "link" => &["href"][..],
+            _ => &["src", "srcset", "poster"][..],
+        };
+        for &name in names {
+            if let Some(path) = local(element.attr(name)) {
+                attributes.push((element.id(), name, data_url(&path, files[&path])));
+            }
+        }
+        if let Some(style) = element.attr("style") {
+            let inlined = inline_css(style, &root, files, 0);
+            if inlined != style {
+                attributes.push((element.id(), "style", inlined));
+            }
+        }
+    }
+    let styles = document
+        .select(&style_selector)
+        .flat_map(|style| style.children())
+        .filter_map(|child| Some((child.id(), inline_css(child.value().as_text()?, &root, files, 0))))
+        .collect::<Vec<_>>();
+
+    for (id, name, value) in attributes {
+        if let Some(mut node) = document.tree.get_mut(id) && let Node::Element(element) = node.value() {
+            dom::set_attribute(&mut element.attrs, name, &value);
+        }
+    }
+    for (id, css) in styles {
+        if let Some(mut node) = document.tree.get_mut(id) && let Node::Text(text) = node.value() {
+            text.text = css.as_str().into();
+        }
+    }
+    for (id, replacement) in replacements {
+        dom::replace_with_html(&mut document, id, &replacement);
+    }
+    document.html()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inlines_local_files() {
+        let files = Files::from([
+            ("images/red%20dot.png".to_string(), &b"PNG"[..]),
+            ("assets/css/site.css".to_string(), &b"@import \"print.css\"; body { background: url(../../images/red%20dot.png) }"[..]),
+            ("assets/css/print.css".to_string(), &b"@font-face { src: url(../fonts/a.woff2) }"[..]),
+            ("assets/fonts/a.woff2".to_string(), &b"WOFF"[..]),
+            ("assets/js/app.js".to_string(), &b"if (a </script> b) run()"[..]),
+        ]);
+        let html = r#"<html><head><link rel="stylesheet" href="assets/css/site.css"><script src="assets/js/app.js"></script></head>
+            <body><img src="images/red%20dot.png"><img src="https://example.com/remote.png"></body></html>"#;
+        let single = inline(html, &files);
+        let png = data_url("x.png", b"PNG");
+
+        assert!(single.contains(&format!(r#"<img src="{png}">"#)));
+        assert!(single.contains(r#"<img src="https://example.com/remote.png">"#));
+        assert!(single.contains(&format!(r#"background: url("{png}")"#)));
+        let print = data_url("x.css", format!(r#"@font-face {{ src: url("{}") }}"#, data_url("a.woff2", b"WOFF")).as_bytes());
+        assert!(single.contains(&format!(r#"<style>@import "{print}";"#)));
+        assert!(single.contains(r"<script>if (a <\/script> b) run()</script>"));
+        assert!(!single.contains("assets/"));
+    }
+}
@@ -0,0 +1,72 @@
+use crate::filename::{self, Transliteration};
+
+/// Longest file stem, in bytes, leaving room for extensions such as `.single.html` and
+/// collision suffixes under the 255 bytes most file systems allow
+const MAX_STEM_LEN: usize = 100;
+
+/// Emoji and the joiners, selectors and modifiers composing them
+fn is_emoji(c: char) -> bool {
+    matches!(c,
+        '\u{1F000}'..='\u{1FAFF}'
+        | '\u{2600}'..='\u{27BF}'
+        | '\u{2B00}'..='\u{2BFF}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{200D}'
+        | '\u{20E3}'
+        | '\u{E0020}'..='\u{E007F}'
+    )
+}
+
+/// File stem of the outputs of a page titled `title`: transliterated according to `profile`,
+/// without path separators, characters illegal on Windows or emoji, and at most
+/// `MAX_STEM_LEN` bytes long. Empty when nothing is left of the title
+pub fn file_stem(title: &str, profile: Transliteration) -> String {
+
+    let transliterated = filename::transliterate(title, profile);
+    let cleaned = transliterated
+        .chars()
+        .filter(|c| !is_emoji(*c))
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => ' ',
+            c if c.is_control() || c.is_whitespace() => ' ',
+            c => c,
+        })
+        .collect::<String>();
+    // replaced characters may leave runs of spaces behind
+    let mut stem = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if stem.len() > MAX_STEM_LEN {
+        let mut end = MAX_STEM_LEN;
+        while !stem.is_char_boundary(end) {
+            end -= 1;
+        }
+        // cut between words when there is one not too far back
+        if let Some(space) = stem[..end].rfind(' ').filter(|space| *space > MAX_STEM_LEN / 2) {
+            end = space;
+        }
+        stem.truncate(end);
+    }
+
+    let stem = stem.trim_matches(|c: char| c == '.' || c.is_whitespace());
+    match filename::RESERVED_NAMES.iter().any(|reserved| stem.eq_ignore_ascii_case(reserved)) {
+        true => format!("_{stem}"),
+        false => stem.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn makes_titles_safe_file_stems() {
+        assert_eq!(file_stem("AC/DC: Back in Black | Wiki", Transliteration::Unicode), "AC DC Back in Black Wiki");
+        assert_eq!(file_stem("🚀 Launch day 👩‍💻!", Transliteration::Unicode), "Launch day !");
+        assert_eq!(file_stem("C# in 100% of cases?", Transliteration::Unicode), "C# in 100% of cases");
+        assert_eq!(file_stem("CON", Transliteration::Unicode), "_CON");
+        assert_eq!(file_stem("🎉 ...", Transliteration::Unicode), "");
+
+        let long = file_stem(&"Très longue phrase ".repeat(20), Transliteration::Unicode);
+        assert!(long.len() <= MAX_STEM_LEN && long.ends_with("phrase"));
+    }
+}
@@ -0,0 +1,142 @@
+use std::sync::OnceLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use time::{Date, Month, PrimitiveDateTime, Time};
+use crate::pdf;
+
+/// Where a capture was taken from: the live web, or an archive serving a past version of the page
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureSource {
+    #[default]
+    Live,
+    /// web.archive.org
+    Wayback,
+    /// archive.today and its mirrors (archive.ph, archive.is...)
+    ArchiveToday,
+    /// Any other web archive, announcing its snapshots with the `Memento-Datetime` header
+    Memento,
+}
+
+impl CaptureSource {
+    pub fn is_live(self) -> bool {
+        self == Self::Live
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Live => "live",
+            Self::Wayback => "wayback",
+            Self::ArchiveToday => "archive_today",
+            Self::Memento => "memento",
+        }
+    }
+}
+
+const ARCHIVE_TODAY_HOSTS: &[&str] = &[
+    "archive.today", "archive.ph", "archive.is", "archive.li", "archive.vn", "archive.fo", "archive.md",
+];
+
+/// Provenance of a capture: for historical ones, when the archive took its snapshot
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    pub source: CaptureSource,
+    /// `2020-01-31T12:34:56Z`, when the archived page was originally captured
+    pub original_capture_date: Option<String>,
+}
+
+impl Snapshot {
+
+    /// Snapshot served at `url` with the response `headers`. The `Memento-Datetime` header
+    /// (RFC 7089) wins over the timestamp of archive URLs
+    pub fn detect(url: &str, headers: &[(String, String)]) -> Self {
+
+        static WAYBACK: OnceLock<Regex> = OnceLock::new();
+        static ARCHIVE_TODAY: OnceLock<Regex> = OnceLock::new();
+        let wayback = WAYBACK.get_or_init(|| Regex::new(r"^https?://web\.archive\.org/web/(\d{14})(?:[a-z]{2}_)?/").unwrap());
+        let archive_today = ARCHIVE_TODAY.get_or_init(|| {
+            Regex::new(r"^https?://(?:www\.)?([a-z.]+)/(?:(\d{4})\.(\d{2})\.(\d{2})-(\d{6})|(\d{14}))/").unwrap()
+        });
+
+        let memento_date = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("memento-datetime"))
+            .and_then(|(_, value)| http_date(value));
+
+        let host = url::Url::parse(url).ok().and_then(|url| url.host_str().map(|host| host.trim_start_matches("www.").to_string()));
+        let (source, url_date) = match host.as_deref() {
+            Some("web.archive.org") => (CaptureSource::Wayback, wayback.captures(url).and_then(|captures| timestamp(&captures[1]))),
+            Some(host) if ARCHIVE_TODAY_HOSTS.contains(&host) => {
+                let date = archive_today.captures(url).and_then(|captures| match captures.get(6) {
+                    Some(digits) => timestamp(digits.as_str()),
+                    None => timestamp(&format!("{}{}{}{}", &captures[2], &captures[3], &captures[4], &captures[5])),
+                });
+                (CaptureSource::ArchiveToday, date)
+            }
+            _ if memento_date.is_some() => (CaptureSource::Memento, None),
+            _ => (CaptureSource::Live, None),
+        };
+
+        Self { source, original_capture_date: memento_date.or(url_date).map(iso8601) }
+    }
+
+    /// Record the provenance of a historical capture in the document information of `pdf`.
+    /// Live captures, and PDFs whose structure isn't understood, are returned as they are
+    pub fn tag_pdf(&self, pdf: Vec<u8>) -> Vec<u8> {
+        if self.source.is_live() {
+            return pdf;
+        }
+        let mut entries = vec![("CaptureSource", self.source.name())];
+        if let Some(date) = &self.original_capture_date {
+            entries.push(("OriginalCaptureDate", date));
+        }
+        pdf::set_info(&pdf, &entries).unwrap_or(pdf)
+    }
+}
+
+/// `20200131123456`
+fn timestamp(digits: &str) -> Option<PrimitiveDateTime> {
+    let field = |range: std::ops::Range<usize>| digits.get(range)?.parse::<u32>().ok();
+    let date = Date::from_calendar_date(field(0..4)? as i32, Month::try_from(field(4..6)? as u8).ok()?, field(6..8)? as u8).ok()?;
+    let time = Time::from_hms(field(8..10)? as u8, field(10..12)? as u8, field(12..14)? as u8).ok()?;
+    Some(PrimitiveDateTime::new(date, time))
+}
+
+/// `Fri, 31 Jan 2020 12:34:56 GMT`
+fn http_date(value: &str) -> Option<PrimitiveDateTime> {
+    let [_, day, month, year, time, _] = value.split_whitespace().collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let month = MONTHS.iter().position(|name| name.eq_ignore_ascii_case(month))? + 1;
+    timestamp(&format!("{year:0>4}{month:02}{day:0>2}{}", time.replace(':', "")))
+}
+
+pub(crate) fn iso8601(date: PrimitiveDateTime) -> String {
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        date.year(), u8::from(date.month()), date.day(), date.hour(), date.minute(), date.second()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_historical_captures() {
+        let wayback = Snapshot::detect("https://web.archive.org/web/20200131123456/https://example.com/", &[]);
+        assert_eq!(wayback, Snapshot { source: CaptureSource::Wayback, original_capture_date: Some("2020-01-31T12:34:56Z".to_string()) });
+
+        let archive_today = Snapshot::detect("https://archive.ph/2019.12.01-080910/https://example.com/", &[]);
+        assert_eq!(archive_today.original_capture_date.as_deref(), Some("2019-12-01T08:09:10Z"));
+
+        let headers = [("Memento-Datetime".to_string(), "Sun, 05 Mar 2017 01:02:03 GMT".to_string())];
+        let memento = Snapshot::detect("https://arquivo.pt/wayback/20170305010203/https://example.com/", &headers);
+        assert_eq!(memento, Snapshot { source: CaptureSource::Memento, original_capture_date: Some("2017-03-05T01:02:03Z".to_string()) });
+
+        let live = Snapshot::detect("https://example.com/web/20200131123456/", &[]);
+        assert!(live.source.is_live() && live.original_capture_date.is_none());
+        assert_eq!(live.tag_pdf(b"%PDF".to_vec()), b"%PDF");
+    }
+}
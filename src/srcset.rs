@@ -0,0 +1,276 @@
+use std::str::FromStr;
+
+/// Descriptor of a `srcset` candidate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Descriptor {
+    /// `800w`, intrinsic width in pixels
+    Width(u32),
+    /// `2x`, pixel density, `1x` when no descriptor is given
+    Density(f32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub url: String,
+    pub descriptor: Descriptor,
+}
+
+/// Which `srcset` candidate gets downloaded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SrcsetStrategy {
+    /// Widest candidate, or the highest density
+    #[default]
+    Largest,
+    /// Narrowest candidate, or the lowest density
+    Smallest,
+    /// Candidate whose width is closest to this many pixels,
+    /// the `1x` candidate when only densities are given
+    Closest(u32),
+}
+
+impl FromStr for SrcsetStrategy {
+    type Err = String;
+
+    /// `largest`, `smallest` or a target width in pixels
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "largest" => Ok(Self::Largest),
+            "smallest" => Ok(Self::Smallest),
+            width => width
+                .trim_end_matches("px")
+                .parse()
+                .map(Self::Closest)
+                .map_err(|_| format!("expected largest, smallest or a width in pixels, got {s:?}")),
+        }
+    }
+}
+
+/// Parse a `srcset` attribute following the HTML candidate parsing rules,
+/// so URLs containing commas survive. Invalid candidates are dropped
+pub fn parse(srcset: &str) -> Vec<Candidate> {
+
+    let mut candidates = Vec::new();
+    let mut rest = srcset;
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+        if rest.is_empty() {
+            break;
+        }
+
+        let url_end = rest.find(|c: char| c.is_ascii_whitespace()).unwrap_or(rest.len());
+        let (url, after) = rest.split_at(url_end);
+
+        // a URL ending with commas has no descriptors
+        let (url, descriptors) = if url.ends_with(',') {
+            rest = after;
+            (url.trim_end_matches(','), "")
+        } else {
+            let end = descriptors_end(after);
+            rest = &after[end..];
+            (url, &after[..end])
+        };
+
+        if url.is_empty() {
+            continue;
+        }
+        if let Some(descriptor) = parse_descriptor(descriptors) {
+            candidates.push(Candidate { url: url.to_string(), descriptor });
+        }
+    }
+
+    candidates
+}
+
+/// End of the descriptors: the first comma outside parentheses
+fn descriptors_end(text: &str) -> usize {
+    let mut depth = 0usize;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => return i,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+fn parse_descriptor(descriptors: &str) -> Option<Descriptor> {
+
+    let mut descriptor = None;
+    for token in descriptors.split_ascii_whitespace() {
+        // a second width or density makes the candidate invalid, heights are ignored
+        let parsed = if let Some(width) = token.strip_suffix('w') {
+            Descriptor::Width(width.parse().ok().filter(|&w| w > 0)?)
+        } else if let Some(density) = token.strip_suffix('x') {
+            Descriptor::Density(density.parse().ok().filter(|&d: &f32| d > 0.0)?)
+        } else if token.ends_with('h') {
+            continue;
+        } else {
+            return None;
+        };
+        if descriptor.replace(parsed).is_some() {
+            return None;
+        }
+    }
+
+    Some(descriptor.unwrap_or(Descriptor::Density(1.0)))
+}
+
+/// Viewport width assumed when evaluating `<source media>` queries,
+/// unless the strategy targets a width
+pub const DEFAULT_VIEWPORT_WIDTH: u32 = 1920;
+
+/// Image types of `<source type>` that get downloaded, sources of other types are skipped
+const SUPPORTED_TYPES: &[&str] = &[
+    "image/avif", "image/webp", "image/jpeg", "image/jpg", "image/png", "image/apng", "image/gif", "image/svg+xml",
+];
+
+pub fn is_supported_type(mime: Option<&str>) -> bool {
+    mime.is_none_or(|mime| SUPPORTED_TYPES.contains(&mime.trim().to_ascii_lowercase().as_str()))
+}
+
+/// Evaluate a `<source media>` query for a screen `viewport_width` pixels wide.
+/// Only width features are understood, `dark` and `print` conditions never match
+/// and any other feature is assumed to match
+pub fn media_matches(media: Option<&str>, viewport_width: u32) -> bool {
+
+    let Some(media) = media else {
+        return true;
+    };
+    let media = media.to_ascii_lowercase();
+
+    // a comma separated list matches when any query does
+    media.split(',').any(|query| {
+        let query = query.trim();
+        if query.starts_with("print") || query.starts_with("not ") {
+            return false;
+        }
+        query
+            .split(" and ")
+            .filter_map(|condition| {
+                let condition = condition.trim().trim_start_matches("only screen").trim_start_matches("screen").trim();
+                let (feature, value) = condition.trim_matches(['(', ')']).split_once(':')?;
+                Some((feature.trim().to_string(), value.trim().to_string()))
+            })
+            .all(|(feature, value)| match feature.as_str() {
+                "min-width" => pixels(&value).is_none_or(|min| viewport_width as f32 >= min),
+                "max-width" => pixels(&value).is_none_or(|max| viewport_width as f32 <= max),
+                "prefers-color-scheme" => value != "dark",
+                _ => true,
+            })
+    })
+}
+
+/// CSS length in pixels, `em` and `rem` counted as 16px
+fn pixels(value: &str) -> Option<f32> {
+    if let Some(px) = value.strip_suffix("px") {
+        px.trim().parse().ok()
+    } else if let Some(em) = value.strip_suffix("rem").or_else(|| value.strip_suffix("em")) {
+        em.trim().parse::<f32>().ok().map(|em| em * 16.0)
+    } else {
+        value.parse().ok()
+    }
+}
+
+/// Pick a candidate according to `strategy`, width descriptors win over densities
+pub fn select(candidates: &[Candidate], strategy: SrcsetStrategy) -> Option<&Candidate> {
+
+    let sized = sized(candidates);
+
+    let by_size = |a: &&(&Candidate, f32), b: &&(&Candidate, f32)| a.1.total_cmp(&b.1);
+
+    let selected = match strategy {
+        SrcsetStrategy::Largest => sized.iter().max_by(by_size),
+        SrcsetStrategy::Smallest => sized.iter().min_by(by_size),
+        SrcsetStrategy::Closest(target) => {
+            let target = if sized.iter().any(|(c, _)| matches!(c.descriptor, Descriptor::Width(_))) {
+                target as f32
+            } else {
+                1.0
+            };
+            sized.iter().min_by(|a, b| (a.1 - target).abs().total_cmp(&(b.1 - target).abs()))
+        }
+    };
+
+    selected.map(|(candidate, _)| *candidate)
+}
+
+/// Candidates sized by width, or by density when no width is given
+fn sized(candidates: &[Candidate]) -> Vec<(&Candidate, f32)> {
+
+    let widths = candidates
+        .iter()
+        .filter_map(|c| match c.descriptor {
+            Descriptor::Width(width) => Some((c, width as f32)),
+            Descriptor::Density(_) => None,
+        })
+        .collect::<Vec<_>>();
+
+    if !widths.is_empty() {
+        return widths;
+    }
+    candidates
+        .iter()
+        .filter_map(|c| match c.descriptor {
+            Descriptor::Density(density) => Some((c, density)),
+            Descriptor::Width(_) => None,
+        })
+        .collect()
+}
+
+/// The candidate `select` picks followed by the smaller ones, largest first,
+/// to step down from when the selected one is too heavy
+pub fn select_with_fallbacks(candidates: &[Candidate], strategy: SrcsetStrategy) -> Vec<&Candidate> {
+
+    let Some(selected) = select(candidates, strategy) else {
+        return Vec::new();
+    };
+    let mut sized = sized(candidates);
+    let limit = sized.iter().find(|(c, _)| std::ptr::eq(*c, selected)).map_or(f32::MAX, |(_, size)| *size);
+    sized.retain(|(_, size)| *size <= limit);
+    sized.sort_by(|a, b| b.1.total_cmp(&a.1));
+    sized.into_iter().map(|(candidate, _)| candidate).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_descriptors_and_commas_in_urls() {
+        let candidates = parse("a.jpg 480w, https://cdn.example.com/w_800,h_600/b.jpg 800w,c.jpg, d.jpg 2x, bad.jpg 1w 2w");
+        let urls = candidates.iter().map(|c| c.url.as_str()).collect::<Vec<_>>();
+        assert_eq!(urls, vec!["a.jpg", "https://cdn.example.com/w_800,h_600/b.jpg", "c.jpg", "d.jpg"]);
+        assert_eq!(candidates[1].descriptor, Descriptor::Width(800));
+        assert_eq!(candidates[2].descriptor, Descriptor::Density(1.0));
+    }
+
+    #[test]
+    fn evaluates_media_queries() {
+        assert!(media_matches(None, 1920));
+        assert!(media_matches(Some("(min-width: 1024px)"), 1920));
+        assert!(!media_matches(Some("(max-width: 600px)"), 1920));
+        assert!(media_matches(Some("screen and (min-width: 40em) and (max-width: 80rem)"), 1024));
+        assert!(!media_matches(Some("(prefers-color-scheme: dark)"), 1920));
+        assert!(media_matches(Some("print, (orientation: landscape)"), 1920));
+        assert!(is_supported_type(Some("image/webp")) && !is_supported_type(Some("image/jxl")));
+    }
+
+    #[test]
+    fn selects_with_strategy() {
+        let candidates = parse("s.jpg 320w, m.jpg 800w, l.jpg 1600w");
+        assert_eq!(select(&candidates, SrcsetStrategy::Largest).unwrap().url, "l.jpg");
+        assert_eq!(select(&candidates, SrcsetStrategy::Smallest).unwrap().url, "s.jpg");
+        assert_eq!(select(&candidates, "700".parse().unwrap()).unwrap().url, "m.jpg");
+
+        let densities = parse("a.png, b.png 2x, c.png 3x");
+        assert_eq!(select(&densities, SrcsetStrategy::Largest).unwrap().url, "c.png");
+        assert_eq!(select(&densities, SrcsetStrategy::Closest(1200)).unwrap().url, "a.png");
+
+        let urls = |strategy| select_with_fallbacks(&candidates, strategy).iter().map(|c| c.url.as_str()).collect::<Vec<_>>();
+        assert_eq!(urls(SrcsetStrategy::Largest), vec!["l.jpg", "m.jpg", "s.jpg"]);
+        assert_eq!(urls(SrcsetStrategy::Closest(700)), vec!["m.jpg", "s.jpg"]);
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::{BTreeMap, HashMap};
+use serde::{Deserialize, Serialize};
+use crate::language;
+use crate::links::Link;
+use crate::toc;
+
+/// File of the capture holding the statistics of the page
+pub const FILENAME: &str = "stats.json";
+
+/// Terms listed unless asked otherwise
+pub const DEFAULT_TERMS: usize = 20;
+
+/// Shorter words are left out of the terms
+const MIN_TERM_LEN: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Term {
+    pub term: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkCounts {
+    pub total: usize,
+    pub internal: usize,
+    pub external: usize,
+    pub nofollow: usize,
+}
+
+/// Words of the markdown under a heading, up to the next one of any level
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionWords {
+    /// `None` for what comes before the first heading
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heading: Option<String>,
+    /// 1 to 6 for `#` to `######`, 0 before the first heading
+    pub level: usize,
+    pub words: usize,
+}
+
+/// Word frequencies, headings, links and section lengths of a page, for content analysis
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stats {
+    /// Words of the markdown
+    pub words: usize,
+    /// Most frequent words of the text, stopwords and words under three letters aside,
+    /// the most frequent first and ties in alphabetical order
+    pub terms: Vec<Term>,
+    /// Headings of the markdown by level, `h1` to `h6`
+    pub headings: BTreeMap<String, usize>,
+    pub links: LinkCounts,
+    pub sections: Vec<SectionWords>,
+}
+
+impl Stats {
+
+    /// Statistics of the page of `markdown`, `text` and `links`, with its `top` terms. Stopwords
+    /// are those of `language`, or of every language the scraper detects when unknown
+    pub fn new(markdown: &str, text: &str, links: &[Link], language: Option<&str>, top: usize) -> Self {
+
+        let stopwords = language::stopwords(language);
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for word in text.split(|c: char| !c.is_alphanumeric() && c != '\'' && c != '-') {
+            let word = word.trim_matches(['\'', '-']).to_lowercase();
+            if word.chars().count() >= MIN_TERM_LEN && !word.chars().all(|c| c.is_numeric()) && !stopwords.contains(&word.as_str()) {
+                *counts.entry(word).or_default() += 1;
+            }
+        }
+        let mut terms = counts.into_iter().map(|(term, count)| Term { term, count }).collect::<Vec<_>>();
+        terms.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.term.cmp(&b.term)));
+        terms.truncate(top);
+
+        let mut headings = BTreeMap::new();
+        for heading in toc::headings(markdown) {
+            *headings.entry(format!("h{}", heading.level)).or_default() += 1;
+        }
+
+        let links = LinkCounts {
+            total: links.len(),
+            internal: links.iter().filter(|link| link.internal).count(),
+            external: links.iter().filter(|link| !link.internal).count(),
+            nofollow: links.iter().filter(|link| link.nofollow).count(),
+        };
+
+        Self { words: markdown.split_whitespace().count(), terms, headings, links, sections: sections(markdown) }
+    }
+}
+
+/// Words under each heading of `markdown`, those of fenced code blocks aside
+fn sections(markdown: &str) -> Vec<SectionWords> {
+
+    let mut headings = toc::headings(markdown).into_iter();
+    let mut sections = vec![SectionWords { heading: None, level: 0, words: 0 }];
+    let mut fence: Option<&str> = None;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|marker| trimmed.starts_with(marker)) {
+            fence = match fence {
+                Some(open) if open == marker => None,
+                None => Some(marker),
+                open => open,
+            };
+        }
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        let is_heading = fence.is_none() && !line.starts_with("    ") && (1..=6).contains(&level)
+            && trimmed[level..].chars().next().is_none_or(|c| c == ' ' || c == '\t');
+        // the same headings as the table of contents, empty ones aside
+        match headings.as_slice().first().filter(|heading| is_heading && heading.level == level) {
+            Some(heading) => {
+                sections.push(SectionWords { heading: Some(heading.text.clone()), level, words: 0 });
+                headings.next();
+            }
+            None => sections.last_mut().unwrap().words += line.split_whitespace().count(),
+        }
+    }
+    if sections[0].words == 0 {
+        sections.remove(0);
+    }
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKDOWN: &str = "Rust Weekly\n\n# Rust ownership\n\nOwnership is Rust's most unique feature, ownership rules.\n\n\
+        ## Borrowing\n\nReferences borrow values.\n\n```rust\n# not a heading\nlet r = &value;\n```\n\n## Lifetimes\n";
+
+    #[test]
+    fn counts_terms_headings_and_sections() {
+        let text = "Rust ownership. Ownership is Rust's most unique feature, ownership rules in Rust. The 2024 edition";
+        let links = [
+            Link { url: "https://example.com/a".to_string(), text: "A".to_string(), internal: true, nofollow: false, rel: Vec::new() },
+            Link { url: "https://other.org/".to_string(), text: "B".to_string(), internal: false, nofollow: true, rel: vec!["nofollow".to_string()] },
+        ];
+        let stats = Stats::new(MARKDOWN, text, &links, Some("en"), 3);
+
+        assert_eq!(stats.terms, [
+            Term { term: "ownership".to_string(), count: 3 },
+            Term { term: "rust".to_string(), count: 2 },
+            Term { term: "edition".to_string(), count: 1 },
+        ]);
+        assert_eq!(stats.headings, BTreeMap::from([("h1".to_string(), 1), ("h2".to_string(), 2)]));
+        assert_eq!(stats.links, LinkCounts { total: 2, internal: 1, external: 1, nofollow: 1 });
+        assert_eq!(stats.sections.iter().map(|section| (section.heading.as_deref(), section.level, section.words)).collect::<Vec<_>>(), [
+            (None, 0, 2),
+            (Some("Rust ownership"), 1, 8),
+            (Some("Borrowing"), 2, 13),
+            (Some("Lifetimes"), 2, 0),
+        ]);
+    }
+}
@@ -0,0 +1,196 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::progress::{DownloadEvent, Progress, ProgressEvent};
+
+/// Download events closer than this to the last drawing don't redraw the board
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Characters of the bar of the pages done
+const BAR_WIDTH: usize = 24;
+
+/// Live status of the pages being scraped, drawn on the standard error: a line per page
+/// in progress and, with several pages, a bar of those done
+pub struct StatusBoard {
+    board: Mutex<Board>,
+}
+
+#[derive(Debug, Default)]
+struct Board {
+    pages: Vec<PageStatus>,
+    done: usize,
+    total: usize,
+    /// Lines drawn last time, erased before drawing again
+    drawn: usize,
+    last_drawn: Option<Instant>,
+    cleared: bool,
+}
+
+#[derive(Debug)]
+struct PageStatus {
+    url: String,
+    stage: Stage,
+    images: usize,
+    images_found: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Loading,
+    Converting,
+    Writing,
+}
+
+impl Board {
+
+    /// Lines of the board, cut to `width` characters so none wraps
+    fn lines(&self, width: usize) -> Vec<String> {
+
+        let mut lines = self.pages.iter().map(|page| {
+            let stage = match page.stage {
+                Stage::Loading => "loading".to_string(),
+                Stage::Converting if page.images_found > 0 => format!("images {}/{}", page.images, page.images_found),
+                Stage::Converting => "converting".to_string(),
+                Stage::Writing => "writing".to_string(),
+            };
+            format!("{stage:>14}  {}", page.url)
+        }).collect::<Vec<_>>();
+        if self.total > 1 {
+            let filled = BAR_WIDTH * self.done / self.total;
+            lines.push(format!("[{}{}] {}/{} pages", "#".repeat(filled), "-".repeat(BAR_WIDTH - filled), self.done, self.total));
+        }
+        lines.into_iter().map(|line| match line.char_indices().nth(width.saturating_sub(1)) {
+            Some((end, _)) if line.chars().count() > width => format!("{}…", &line[..end]),
+            _ => line,
+        }).collect()
+    }
+
+    fn erase(&mut self, terminal: &mut impl Write) {
+        if self.drawn > 0 {
+            let _ = write!(terminal, "\x1b[{}A\x1b[J", self.drawn);
+            self.drawn = 0;
+        }
+    }
+
+    fn draw(&mut self, terminal: &mut impl Write) {
+        if self.cleared {
+            return;
+        }
+        self.erase(terminal);
+        let lines = self.lines(terminal_width());
+        for line in &lines {
+            let _ = writeln!(terminal, "{line}");
+        }
+        let _ = terminal.flush();
+        self.drawn = lines.len();
+        self.last_drawn = Some(Instant::now());
+    }
+}
+
+/// Columns of the terminal on the standard error, else as the shell tells, 80 otherwise
+fn terminal_width() -> usize {
+    terminal_size::terminal_size_of(std::io::stderr()).map(|(width, _)| width.0 as usize)
+        .or_else(|| std::env::var("COLUMNS").ok().and_then(|columns| columns.parse().ok()))
+        .filter(|columns| *columns > 0)
+        .unwrap_or(80)
+}
+
+impl StatusBoard {
+
+    /// Board of `total` pages to scrape
+    pub fn new(total: usize) -> Self {
+        Self { board: Mutex::new(Board { total, ..Default::default() }) }
+    }
+
+    fn update(&self, change: impl FnOnce(&mut Board) -> bool) {
+        let mut board = self.board.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let redraw = change(&mut board);
+        if redraw || board.last_drawn.is_none_or(|drawn| drawn.elapsed() >= REDRAW_INTERVAL) {
+            board.draw(&mut std::io::stderr().lock());
+        }
+    }
+
+    /// Show `url` as loading, its next steps come through the returned `Progress`
+    pub fn start(self: &Arc<Self>, url: &str) -> Progress {
+        self.update(|board| {
+            board.pages.push(PageStatus { url: url.to_string(), stage: Stage::Loading, images: 0, images_found: 0 });
+            true
+        });
+        let (status, url) = (self.clone(), url.to_string());
+        Progress::with_events(move |event| status.record(&url, event))
+    }
+
+    fn record(&self, url: &str, event: ProgressEvent) {
+        self.update(|board| {
+            let Some(page) = board.pages.iter_mut().find(|page| page.url == url) else {
+                return false;
+            };
+            match event {
+                ProgressEvent::Converting => page.stage = Stage::Converting,
+                ProgressEvent::ImagesFound { count } => page.images_found += count,
+                ProgressEvent::Download(DownloadEvent::Started { .. }) => return false,
+                ProgressEvent::Download(_) => {
+                    page.images += 1;
+                    return false;
+                }
+                ProgressEvent::Writing { .. } => page.stage = Stage::Writing,
+                ProgressEvent::Written { .. } => return false,
+            }
+            true
+        });
+    }
+
+    /// Take `url` off the board, counting it as done
+    pub fn finish(&self, url: &str) {
+        self.update(|board| {
+            board.pages.retain(|page| page.url != url);
+            board.done += 1;
+            true
+        });
+    }
+
+    /// Run `print` with the board erased, so what it prints isn't mixed with the board
+    pub fn suspend<T>(&self, print: impl FnOnce() -> T) -> T {
+        let mut board = self.board.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        board.erase(&mut std::io::stderr().lock());
+        let printed = print();
+        board.draw(&mut std::io::stderr().lock());
+        printed
+    }
+
+    /// Erase the board for good
+    pub fn clear(&self) {
+        let mut board = self.board.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        board.erase(&mut std::io::stderr().lock());
+        board.cleared = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_a_line_per_page_and_the_pages_done() {
+        let board = Board {
+            pages: vec![
+                PageStatus { url: "https://example.com/a".to_string(), stage: Stage::Converting, images: 3, images_found: 12 },
+                PageStatus { url: "https://example.com/a-very-long-address".to_string(), stage: Stage::Loading, images: 0, images_found: 0 },
+            ],
+            done: 5,
+            total: 10,
+            ..Default::default()
+        };
+        assert_eq!(board.lines(40), [
+            "   images 3/12  https://example.com/a",
+            "       loading  https://example.com/a-v…",
+            "[############------------] 5/10 pages",
+        ]);
+
+        let mut terminal = Vec::new();
+        let mut board = Board { drawn: 3, ..board };
+        board.erase(&mut terminal);
+        assert_eq!(terminal, b"\x1b[3A\x1b[J");
+        assert_eq!(board.drawn, 0);
+    }
+}
@@ -0,0 +1,202 @@
+use ego_tree::NodeRef;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::{Deserialize, Serialize};
+use crate::tables;
+
+/// File of the capture holding the structure of the page
+pub const FILENAME: &str = "structure.json";
+
+/// Elements whose content isn't part of the document
+const SKIPPED: &[&str] = &["head", "script", "style", "noscript", "template", "svg", "canvas", "iframe", "object", "button", "select", "textarea", "form"];
+
+/// Part of the page under a heading, the root having none
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Section {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heading: Option<String>,
+    /// 1 to 6 for `h1` to `h6`, 0 for the root
+    pub level: u8,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub content: Vec<Block>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sections: Vec<Section>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InlineLink {
+    pub url: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Block {
+    Paragraph {
+        text: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        links: Vec<InlineLink>,
+    },
+    List { ordered: bool, items: Vec<String> },
+    Image {
+        src: String,
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        alt: String,
+    },
+    Code {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        language: Option<String>,
+        text: String,
+    },
+    Quote { text: String },
+    /// Rows of cells, spanning cells repeated
+    Table { rows: Vec<Vec<String>> },
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn text(element: &ElementRef) -> String {
+    collapse(&element.text().collect::<String>())
+}
+
+/// Sections of `html` nested by heading level, holding its paragraphs, lists, images,
+/// code blocks, quotes and tables in document order
+pub fn from_html(html: &str) -> Section {
+    let document = Html::parse_document(html);
+    let mut builder = Builder { stack: vec![Section::default()] };
+    builder.walk(*document.root_element());
+    builder.finish()
+}
+
+struct Builder {
+    /// Open sections, from the root to the current one
+    stack: Vec<Section>,
+}
+
+impl Builder {
+
+    fn push(&mut self, block: Block) {
+        self.stack.last_mut().expect("the root is never closed").content.push(block);
+    }
+
+    /// Close the sections of `level` and deeper
+    fn close(&mut self, level: u8) {
+        while self.stack.len() > 1 && self.stack.last().is_some_and(|section| section.level >= level) {
+            let section = self.stack.pop().unwrap();
+            self.stack.last_mut().unwrap().sections.push(section);
+        }
+    }
+
+    fn finish(mut self) -> Section {
+        self.close(1);
+        self.stack.pop().unwrap()
+    }
+
+    /// Walk the descendants of `node` in document order, with a stack of the nodes left
+    /// rather than recursion so deeply nested pages can't overflow it
+    fn walk(&mut self, node: NodeRef<Node>) {
+        let mut pending = node.children().rev().collect::<Vec<_>>();
+        while let Some(node) = pending.pop() {
+            match node.value() {
+                Node::Text(loose) if !loose.trim().is_empty() => self.push(Block::Paragraph { text: collapse(loose), links: Vec::new() }),
+                Node::Element(_) if self.element(ElementRef::wrap(node).unwrap()) => pending.extend(node.children().rev()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Add the blocks of `element`, returning whether its children are still to be walked
+    fn element(&mut self, element: ElementRef) -> bool {
+        let name = element.value().name();
+        match name {
+            name if SKIPPED.contains(&name) => {}
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse().unwrap();
+                self.close(level);
+                self.stack.push(Section { heading: Some(text(&element)), level, ..Default::default() });
+            }
+            "p" => {
+                let paragraph = text(&element);
+                for image in element.select(&Selector::parse("img[src]").unwrap()) {
+                    self.image(image);
+                }
+                if !paragraph.is_empty() {
+                    let anchors = Selector::parse("a[href]").unwrap();
+                    let links = element.select(&anchors)
+                        .map(|anchor| InlineLink { url: anchor.attr("href").unwrap_or_default().to_string(), text: text(&anchor) })
+                        .collect();
+                    self.push(Block::Paragraph { text: paragraph, links });
+                }
+            }
+            "ul" | "ol" => {
+                let items = element.children().filter_map(ElementRef::wrap)
+                    .filter(|item| item.value().name() == "li")
+                    .map(|item| text(&item))
+                    .filter(|item| !item.is_empty())
+                    .collect::<Vec<_>>();
+                if !items.is_empty() {
+                    self.push(Block::List { ordered: name == "ol", items });
+                }
+            }
+            "img" => self.image(element),
+            "pre" => {
+                let code = element.select(&Selector::parse("code").unwrap()).next();
+                let language = code
+                    .and_then(|code| code.value().classes().find_map(|class| class.strip_prefix("language-").map(str::to_string)));
+                self.push(Block::Code { language, text: element.text().collect::<String>().trim_end().to_string() });
+            }
+            "blockquote" => self.push(Block::Quote { text: text(&element) }),
+            "table" => {
+                let rows = tables::grid(&element);
+                if !rows.is_empty() {
+                    self.push(Block::Table { rows });
+                }
+            }
+            _ => return true,
+        }
+        false
+    }
+
+    fn image(&mut self, image: ElementRef) {
+        if let Some(src) = image.attr("src").filter(|src| !src.is_empty()) {
+            self.push(Block::Image { src: src.to_string(), alt: image.attr("alt").unwrap_or_default().trim().to_string() });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nests_sections_by_heading() {
+        let html = r#"<html><body><article>
+            <h1>Guide</h1><p>Read the <a href="/docs">docs</a> first.</p>
+            <h2>Install</h2><pre><code class="language-sh">cargo install x</code></pre>
+            <h3>From source</h3><ol><li>Clone</li><li>Build</li></ol>
+            <h2>Use</h2><p><img src="images/shot.png" alt="Screenshot"></p>
+            <script>ignored()</script>
+        </article></body></html>"#;
+        let root = from_html(html);
+
+        assert_eq!(root.sections.len(), 1);
+        let guide = &root.sections[0];
+        assert_eq!(guide.heading.as_deref(), Some("Guide"));
+        assert_eq!(guide.content, [Block::Paragraph {
+            text: "Read the docs first.".to_string(),
+            links: vec![InlineLink { url: "/docs".to_string(), text: "docs".to_string() }],
+        }]);
+        assert_eq!(guide.sections.iter().map(|section| section.heading.as_deref().unwrap()).collect::<Vec<_>>(), ["Install", "Use"]);
+        let install = &guide.sections[0];
+        assert_eq!(install.content, [Block::Code { language: Some("sh".to_string()), text: "cargo install x".to_string() }]);
+        assert_eq!(install.sections[0].content, [Block::List { ordered: true, items: vec!["Clone".to_string(), "Build".to_string()] }]);
+        assert_eq!(guide.sections[1].content, [Block::Image { src: "images/shot.png".to_string(), alt: "Screenshot".to_string() }]);
+    }
+
+    #[test]
+    fn walks_deeply_nested_pages() {
+        let html = format!("<html><body>{}<p>Deep</p></body></html>", "<div>".repeat(5_000));
+        assert_eq!(from_html(&html).content, [Block::Paragraph { text: "Deep".to_string(), links: Vec::new() }]);
+    }
+}
@@ -0,0 +1,128 @@
+use base64::Engine;
+use scraper::{ElementRef, Html, Selector};
+use crate::dom;
+
+/// Elements drawing nothing by themselves
+const NON_GRAPHIC: &[&str] = &["defs", "symbol", "style", "title", "desc", "metadata", "use"];
+
+/// Turn the SVGs of `html` into images, so they're saved in `images/` and survive
+/// the markdown conversion: inline `<svg>` elements become `<img>` of `data:` URLs,
+/// and `<object>`/`<embed>` referencing SVG files become `<img>` of those files.
+/// Icons only referencing a sprite of the page (`<use href="#icon">`) are kept inline
+pub fn to_images(html: &str) -> String {
+
+    let mut document = Html::parse_document(html);
+    let svg_selector = Selector::parse("svg").unwrap();
+    let embed_selector = Selector::parse("object[data], embed[src]").unwrap();
+
+    let mut replacements = document
+        .select(&svg_selector)
+        // nested SVGs are part of the outer one
+        .filter(|svg| !svg.ancestors().filter_map(ElementRef::wrap).any(|ancestor| ancestor.value().name() == "svg"))
+        .filter(|svg| draws_by_itself(svg))
+        .map(|svg| {
+            let data = base64::engine::general_purpose::STANDARD.encode(standalone(&svg));
+            (svg.id(), img(&format!("data:image/svg+xml;base64,{data}"), label(&svg).as_deref()))
+        })
+        .collect::<Vec<_>>();
+
+    replacements.extend(document.select(&embed_selector).filter_map(|element| {
+        let src = element.attr("data").or_else(|| element.attr("src"))?;
+        let is_svg = element.attr("type").is_some_and(|kind| kind.eq_ignore_ascii_case("image/svg+xml"))
+            || src.split(['?', '#']).next().is_some_and(|path| path.to_ascii_lowercase().ends_with(".svg"));
+        is_svg.then(|| (element.id(), img(src, element.attr("aria-label").or_else(|| element.attr("title")))))
+    }));
+
+    if replacements.is_empty() {
+        return html.to_string();
+    }
+    for (id, replacement) in replacements {
+        dom::replace_with_html(&mut document, id, &replacement);
+    }
+    document.html()
+}
+
+/// Whether `svg` draws something without the rest of the page: sprite sheets only
+/// hold symbols, and icons `<use>` symbols defined elsewhere in the page
+fn draws_by_itself(svg: &ElementRef) -> bool {
+    let elements = svg.descendants().filter_map(ElementRef::wrap).skip(1).collect::<Vec<_>>();
+    let external_use = elements.iter().any(|element| {
+        element.value().name() == "use"
+            && element.attr("href").or_else(|| element.attr("xlink:href")).is_some_and(|href| href.starts_with('#'))
+            && !elements.iter().any(|target| href_target(element).is_some_and(|id| target.attr("id") == Some(id)))
+    });
+    let draws = elements.iter().any(|element| {
+        !NON_GRAPHIC.contains(&element.value().name())
+            && !element.ancestors().filter_map(ElementRef::wrap).any(|ancestor| matches!(ancestor.value().name(), "defs" | "symbol"))
+    });
+    draws && !external_use
+}
+
+fn href_target<'a>(element: &ElementRef<'a>) -> Option<&'a str> {
+    element.attr("href").or_else(|| element.attr("xlink:href"))?.strip_prefix('#')
+}
+
+/// `<title>` or `aria-label` of `svg`, used as the alt text of its image
+fn label(svg: &ElementRef) -> Option<String> {
+    let title = Selector::parse("title").unwrap();
+    svg.attr("aria-label")
+        .map(str::to_string)
+        .or_else(|| svg.select(&title).next().map(|title| title.text().collect::<String>()))
+        .map(|label| label.trim().to_string())
+        .filter(|label| !label.is_empty())
+}
+
+/// Serialization of `svg` valid as an SVG file
+fn standalone(svg: &ElementRef) -> String {
+    let mut markup = svg.html();
+    // namespaces are implied in HTML, not in XML
+    let start_tag = &markup[..markup.find('>').unwrap_or(markup.len())];
+    let mut declarations = String::new();
+    if !start_tag.contains("xmlns=") {
+        declarations.push_str(r#" xmlns="http://www.w3.org/2000/svg""#);
+    }
+    if markup.contains("xlink:") && !start_tag.contains("xmlns:xlink=") {
+        declarations.push_str(r#" xmlns:xlink="http://www.w3.org/1999/xlink""#);
+    }
+    markup.insert_str("<svg".len(), &declarations);
+    // the only named entity of HTML serialization unknown to XML
+    markup.replace("&nbsp;", "&#160;")
+}
+
+fn img(src: &str, alt: Option<&str>) -> String {
+    let escape = |text: &str| text.replace('&', "&amp;").replace('"', "&quot;").replace('<', "&lt;");
+    match alt {
+        Some(alt) => format!(r#"<img src="{}" alt="{}">"#, escape(src), escape(alt)),
+        None => format!(r#"<img src="{}">"#, escape(src)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turns_svgs_into_images() {
+        let html = r##"<html><body>
+            <svg viewBox="0 0 10 10"><title>Sales chart</title><rect width="5" height="8"/><text>&nbsp;8</text></svg>
+            <svg style="display:none"><symbol id="star"><path d="M0 0"/></symbol></svg>
+            <svg class="icon"><use href="#star"/></svg>
+            <object data="/diagrams/flow.svg" type="image/svg+xml">Flow</object>
+            <embed src="/player.swf">
+        </body></html>"##;
+        let converted = to_images(html);
+        let document = Html::parse_document(&converted);
+
+        let images = document.select(&Selector::parse("img").unwrap()).collect::<Vec<_>>();
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].attr("alt"), Some("Sales chart"));
+        let data = images[0].attr("src").unwrap().strip_prefix("data:image/svg+xml;base64,").unwrap();
+        let svg = String::from_utf8(base64::engine::general_purpose::STANDARD.decode(data).unwrap()).unwrap();
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10">"#));
+        assert!(svg.contains("&#160;8"));
+        assert_eq!(images[1].attr("src"), Some("/diagrams/flow.svg"));
+
+        assert_eq!(document.select(&Selector::parse("svg").unwrap()).count(), 2);
+        assert!(converted.contains(r#"<embed src="/player.swf">"#));
+    }
+}
@@ -0,0 +1,160 @@
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use crate::http::HttpClient;
+use crate::warnings::{Warning, WarningKind, Warnings};
+
+/// Directory of the capture holding the feeds of the page
+pub const DIRECTORY: &str = "feeds";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedKind {
+    Rss,
+    Atom,
+    /// JSON Feed
+    Json,
+}
+
+impl FeedKind {
+
+    /// Kind announced by the `type` of a `<link rel="alternate">`
+    fn from_mime(mime: &str) -> Option<Self> {
+        match mime.split(';').next().unwrap_or_default().trim().to_ascii_lowercase().as_str() {
+            "application/rss+xml" | "application/rdf+xml" => Some(FeedKind::Rss),
+            "application/atom+xml" => Some(FeedKind::Atom),
+            "application/feed+json" => Some(FeedKind::Json),
+            _ => None,
+        }
+    }
+
+    fn file_name(&self, index: usize) -> String {
+        let (name, extension) = match self {
+            FeedKind::Rss => ("rss", "xml"),
+            FeedKind::Atom => ("atom", "xml"),
+            FeedKind::Json => ("feed", "json"),
+        };
+        match index {
+            0 => format!("{name}.{extension}"),
+            index => format!("{name}-{}.{extension}", index + 1),
+        }
+    }
+
+    /// Whether `body` looks like a feed of this kind rather than an error page
+    fn matches(&self, body: &[u8]) -> bool {
+        let start = String::from_utf8_lossy(&body[..body.len().min(1024)]).to_ascii_lowercase();
+        match self {
+            FeedKind::Rss => start.contains("<rss") || start.contains("<rdf:rdf"),
+            FeedKind::Atom => start.contains("<feed"),
+            FeedKind::Json => start.contains("jsonfeed.org/version"),
+        }
+    }
+}
+
+/// Feed the page links to, referenced in `informations.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageFeed {
+    pub url: String,
+    pub kind: FeedKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Path relative to the capture directory, when downloaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+/// Feeds announced by the `<link rel="alternate">` of `html`, in document order
+pub fn discover(html: &str, base_url: &str) -> Vec<PageFeed> {
+
+    let Ok(base_url) = Url::parse(base_url) else {
+        return Vec::new();
+    };
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("link[rel~=alternate][type][href]").unwrap();
+
+    let mut feeds: Vec<PageFeed> = Vec::new();
+    for link in document.select(&selector) {
+        let Some(kind) = link.attr("type").and_then(FeedKind::from_mime) else { continue };
+        let Some(url) = link.attr("href").and_then(|href| base_url.join(href.trim()).ok()) else { continue };
+        if feeds.iter().any(|feed| feed.url == url.as_str()) {
+            continue;
+        }
+        let title = link.attr("title").map(str::trim).filter(|title| !title.is_empty()).map(str::to_string);
+        feeds.push(PageFeed { url: url.to_string(), kind, title, path: None });
+    }
+    feeds
+}
+
+/// Download the feeds of `html`, with their bytes when they could be downloaded.
+/// Those failing or not looking like feeds are kept without a path
+pub async fn capture(html: &str, base_url: &str, client: &HttpClient, warnings: &Warnings) -> Vec<(PageFeed, Option<Vec<u8>>)> {
+
+    let feeds = discover(html, base_url);
+    let responses = futures::future::join_all(feeds.iter().map(|feed| async move {
+        client.get(&Url::parse(&feed.url).ok()?).await.ok()
+    })).await;
+
+    let mut captured: Vec<(PageFeed, Option<Vec<u8>>)> = Vec::new();
+    for (mut feed, response) in feeds.into_iter().zip(responses) {
+        let bytes = match response {
+            Some(response) if feed.kind.matches(&response.body) => {
+                let index = captured.iter().filter(|(other, bytes)| other.kind == feed.kind && bytes.is_some()).count();
+                feed.path = Some(format!("{DIRECTORY}/{}", feed.kind.file_name(index)));
+                Some(response.body)
+            }
+            Some(_) => {
+                warnings.push(Warning::with_url(WarningKind::SkippedFeed, &feed.url, "the feed link doesn't lead to a feed"));
+                None
+            }
+            None => {
+                warnings.push(Warning::with_url(WarningKind::SkippedFeed, &feed.url, "the feed couldn't be downloaded"));
+                None
+            }
+        };
+        captured.push((feed, bytes));
+    }
+    captured
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::NetworkMode;
+    use crate::testing::FixtureServer;
+
+    #[test]
+    fn discovers_alternate_feeds() {
+        let html = r#"<html><head>
+            <link rel="alternate" type="application/rss+xml" title="Posts" href="/feed.xml">
+            <link rel="alternate" type="application/atom+xml" href="https://example.com/atom">
+            <link rel="alternate" hreflang="fr" href="/fr/">
+            <link rel="stylesheet" type="text/css" href="/style.css">
+        </head></html>"#;
+
+        let feeds = discover(html, "https://example.com/blog/post");
+        assert_eq!(feeds, [
+            PageFeed { url: "https://example.com/feed.xml".to_string(), kind: FeedKind::Rss, title: Some("Posts".to_string()), path: None },
+            PageFeed { url: "https://example.com/atom".to_string(), kind: FeedKind::Atom, title: None, path: None },
+        ]);
+    }
+
+    #[tokio::test]
+    async fn downloads_feeds() {
+        let server = FixtureServer::new().unwrap();
+        server.route("/feed.xml", "application/rss+xml", r#"<?xml version="1.0"?><rss version="2.0"><channel></channel></rss>"#);
+        server.route("/atom", "text/html", "<html><body>Not found</body></html>");
+        let html = r#"<head>
+            <link rel="alternate" type="application/rss+xml" href="/feed.xml">
+            <link rel="alternate" type="application/atom+xml" href="/atom">
+        </head>"#;
+
+        let client = HttpClient::new(&NetworkMode::Live).unwrap();
+        let warnings = Warnings::default();
+        let feeds = capture(html, &server.url("/post.html"), &client, &warnings).await;
+
+        assert_eq!(feeds[0].0.path.as_deref(), Some("feeds/rss.xml"));
+        assert!(feeds[0].1.as_ref().is_some_and(|bytes| bytes.starts_with(b"<?xml")));
+        assert_eq!((feeds[1].0.path.as_ref(), feeds[1].1.as_ref()), (None, None));
+        assert_eq!(warnings.to_vec()[0].kind, WarningKind::SkippedFeed);
+    }
+}
@@ -0,0 +1,138 @@
+use scraper::{ElementRef, Html, Selector};
+use crate::dom;
+
+/// Directory of the capture holding the tables of the page as CSV files
+pub const DIRECTORY: &str = "tables";
+
+/// Table of the page, saved as `tables/<filename>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableFile {
+    pub filename: String,
+    pub csv: String,
+}
+
+/// Cells of `table` row by row, those spanning several rows or columns repeated in each of them
+pub(crate) fn grid(table: &ElementRef) -> Vec<Vec<String>> {
+    let row_selector = Selector::parse("tr").unwrap();
+    let cell_selector = Selector::parse("th, td").unwrap();
+
+    let mut grid: Vec<Vec<Option<String>>> = Vec::new();
+    // rows of nested tables are theirs
+    let rows = table.select(&row_selector).filter(|row| row.ancestors().filter_map(ElementRef::wrap).find(|ancestor| ancestor.value().name() == "table").map(|ancestor| ancestor.id()) == Some(table.id())).collect::<Vec<_>>();
+    for (y, row) in rows.iter().enumerate() {
+        if grid.len() <= y {
+            grid.resize(y + 1, Vec::new());
+        }
+        let mut x = 0;
+        for cell in row.children().filter_map(ElementRef::wrap).filter(|cell| cell_selector.matches(cell)) {
+            while grid[y].get(x).is_some_and(Option::is_some) {
+                x += 1;
+            }
+            // the limits browsers apply, rows spanned past the end of the table not being added
+            let span = |name: &str, max: usize| cell.attr(name).and_then(|span| span.trim().parse::<usize>().ok()).unwrap_or(1).clamp(1, max);
+            let text = cell.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" ");
+            for dy in 0..span("rowspan", 65534).min(rows.len() - y) {
+                if grid.len() <= y + dy {
+                    grid.resize(y + dy + 1, Vec::new());
+                }
+                let row = &mut grid[y + dy];
+                for dx in 0..span("colspan", 1000) {
+                    if row.len() <= x + dx {
+                        row.resize(x + dx + 1, None);
+                    }
+                    row[x + dx] = Some(text.clone());
+                }
+            }
+            x += span("colspan", 1000);
+        }
+    }
+    let width = grid.iter().map(Vec::len).max().unwrap_or_default();
+    grid.into_iter()
+        .filter(|row| !row.is_empty())
+        .map(|row| (0..width).map(|x| row.get(x).cloned().flatten().unwrap_or_default()).collect())
+        .collect()
+}
+
+fn csv_field(text: &str) -> String {
+    match text.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text.to_string(),
+    }
+}
+
+fn to_csv(grid: &[Vec<String>]) -> String {
+    grid.iter().map(|row| row.iter().map(|cell| csv_field(cell)).collect::<Vec<_>>().join(",") + "\r\n").collect()
+}
+
+/// Data tables of `html` as CSV files, along with `html` linking to each file right after its table,
+/// for the markdown to reference them. Layout tables (`role="presentation"`, or less than two rows
+/// or columns) are left out, nested tables are part of the table around them
+pub fn extract(html: &str) -> (String, Vec<TableFile>) {
+
+    let mut document = Html::parse_document(html);
+    let selector = Selector::parse("table").unwrap();
+
+    let mut tables = Vec::new();
+    let mut links = Vec::new();
+    for table in document.select(&selector) {
+        let nested = table.ancestors().filter_map(ElementRef::wrap).any(|ancestor| ancestor.value().name() == "table");
+        let layout = table.attr("role").is_some_and(|role| matches!(role, "presentation" | "none"));
+        if nested || layout {
+            continue;
+        }
+        let grid = grid(&table);
+        if grid.len() < 2 || grid[0].len() < 2 {
+            continue;
+        }
+        let filename = format!("table-{}.csv", tables.len() + 1);
+        links.push((table.id(), format!(
+            r#"{}<p><a href="{DIRECTORY}/{filename}">Table {} as CSV</a></p>"#, table.html(), tables.len() + 1
+        )));
+        tables.push(TableFile { filename, csv: to_csv(&grid) });
+    }
+
+    if tables.is_empty() {
+        return (html.to_string(), tables);
+    }
+    for (id, replacement) in links {
+        dom::replace_with_html(&mut document, id, &replacement);
+    }
+    (document.html(), tables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exports_data_tables() {
+        let html = r#"<html><body>
+            <table role="presentation"><tr><td>Layout</td><td>only</td></tr><tr><td>a</td><td>b</td></tr></table>
+            <table>
+                <thead><tr><th>City</th><th colspan="2">Population, "2020"</th></tr></thead>
+                <tbody>
+                    <tr><td rowspan="2">Lyon</td><td>522 250</td><td>city</td></tr>
+                    <tr><td>1.4 M</td><td><table><tr><td>metro</td></tr></table></td></tr>
+                </tbody>
+            </table>
+            <table><tr><td>Single row</td><td>x</td></tr></table>
+        </body></html>"#;
+        let (linked, tables) = extract(html);
+
+        assert_eq!(tables, [TableFile {
+            filename: "table-1.csv".to_string(),
+            csv: "City,\"Population, \"\"2020\"\"\",\"Population, \"\"2020\"\"\"\r\nLyon,522 250,city\r\nLyon,1.4 M,metro\r\n".to_string(),
+        }]);
+        assert!(linked.contains(r#"</table><p><a href="tables/table-1.csv">Table 1 as CSV</a></p>"#));
+    }
+
+    #[test]
+    fn limits_spans() {
+        let html = Html::parse_fragment(r#"<table><tr><td rowspan="4000000000">a</td><td colspan="5000">b</td></tr><tr><td>c</td></tr></table>"#);
+        let table = html.select(&Selector::parse("table").unwrap()).next().unwrap();
+        let grid = grid(&table);
+        assert_eq!(grid.len(), 2);
+        assert!(grid.iter().all(|row| row.len() == 1001));
+        assert_eq!(grid[1][..2], ["a", "c"]);
+    }
+}
@@ -0,0 +1,178 @@
+//! Hermetic test helpers: a local HTTP server serving HTML and image fixtures,
+//! so the scraping and image-downloading paths can be tested without the network.
+//! Available to the crate's own tests and, through the `testing` feature, to dependents.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+pub const ARTICLE_HTML: &str = include_str!("../test/fixtures/article.html");
+pub const STYLE_CSS: &str = include_str!("../test/fixtures/style.css");
+pub const RED_PNG: &[u8] = include_bytes!("../test/fixtures/red.png");
+pub const BLUE_PNG: &[u8] = include_bytes!("../test/fixtures/blue.png");
+
+#[derive(Clone)]
+struct Fixture {
+    status: u16,
+    content_type: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+type Routes = Arc<Mutex<HashMap<String, Fixture>>>;
+
+/// Request line and headers of a request received
+#[derive(Clone)]
+struct Request {
+    line: String,
+    headers: Vec<(String, String)>,
+}
+
+/// HTTP/1.1 server bound to a random local port, stopped when dropped
+pub struct FixtureServer {
+    address: SocketAddr,
+    routes: Routes,
+    requests: Arc<Mutex<Vec<Request>>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl FixtureServer {
+
+    pub fn new() -> std::io::Result<Self> {
+
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?;
+        let routes: Routes = Arc::new(Mutex::new(HashMap::new()));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let (routes, requests, stop) = (routes.clone(), requests.clone(), stop.clone());
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        let (routes, requests) = (routes.clone(), requests.clone());
+                        std::thread::spawn(move || Self::serve(stream, &routes, &requests));
+                    }
+                }
+            })
+        };
+
+        Ok(Self { address, routes, requests, stop, handle: Some(handle) })
+    }
+
+    /// Server with the article page, its stylesheet and its images under `/article.html`, `/css/` and `/images/`
+    pub fn with_article() -> std::io::Result<Self> {
+        let server = Self::new()?;
+        server.route("/article.html", "text/html; charset=utf-8", ARTICLE_HTML);
+        server.route("/css/style.css", "text/css", STYLE_CSS);
+        server.route("/images/red.png", "image/png", RED_PNG);
+        server.route("/images/blue.png", "image/png", BLUE_PNG);
+        Ok(server)
+    }
+
+    /// Serve `body` with status 200 at `path`
+    pub fn route(&self, path: &str, content_type: &str, body: impl Into<Vec<u8>>) -> &Self {
+        self.route_with_status(path, 200, content_type, body)
+    }
+
+    pub fn route_with_status(&self, path: &str, status: u16, content_type: &str, body: impl Into<Vec<u8>>) -> &Self {
+        let fixture = Fixture { status, content_type: content_type.to_string(), headers: Vec::new(), body: body.into() };
+        self.routes.lock().unwrap().insert(path.to_string(), fixture);
+        self
+    }
+
+    /// Add an extra response header to an already registered route
+    pub fn header(&self, path: &str, name: &str, value: &str) -> &Self {
+        if let Some(fixture) = self.routes.lock().unwrap().get_mut(path) {
+            fixture.headers.push((name.to_string(), value.to_string()));
+        }
+        self
+    }
+
+    /// Absolute URL of `path` on this server
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.address, path)
+    }
+
+    /// Request lines (`GET /path HTTP/1.1`) received so far
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().iter().map(|request| request.line.clone()).collect()
+    }
+
+    /// Value of the `name` header of each request received for `path`, `None` for those without it
+    pub fn request_header(&self, path: &str, name: &str) -> Vec<Option<String>> {
+        self.requests.lock().unwrap()
+            .iter()
+            .filter(|request| request.line.split_whitespace().nth(1) == Some(path))
+            .map(|request| request.headers.iter().find(|(header, _)| header.eq_ignore_ascii_case(name)).map(|(_, value)| value.clone()))
+            .collect()
+    }
+
+    fn serve(mut stream: TcpStream, routes: &Routes, requests: &Mutex<Vec<Request>>) {
+
+        let mut reader = BufReader::new(match stream.try_clone() {
+            Ok(stream) => stream,
+            Err(_) => return,
+        });
+
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).is_err() {
+            return;
+        }
+        let mut headers = Vec::new();
+        let mut line = String::new();
+        while reader.read_line(&mut line).is_ok_and(|n| n > 0) && line != "\r\n" {
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+            line.clear();
+        }
+
+        let request_line = request_line.trim().to_string();
+        requests.lock().unwrap().push(Request { line: request_line.clone(), headers });
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default();
+        let path = parts.next().unwrap_or("/");
+
+        let fixture = routes.lock().unwrap().get(path).cloned().unwrap_or(Fixture {
+            status: 404,
+            content_type: "text/plain".to_string(),
+            headers: Vec::new(),
+            body: b"not found".to_vec(),
+        });
+
+        let mut response = format!(
+            "HTTP/1.1 {} Fixture\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            fixture.status, fixture.content_type, fixture.body.len()
+        );
+        for (name, value) in &fixture.headers {
+            response.push_str(&format!("{name}: {value}\r\n"));
+        }
+        response.push_str("\r\n");
+
+        let _ = stream.write_all(response.as_bytes());
+        if method != "HEAD" {
+            let _ = stream.write_all(&fixture.body);
+        }
+    }
+}
+
+impl Drop for FixtureServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // wake up the accept loop so it notices the stop flag
+        let _ = TcpStream::connect(self.address);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
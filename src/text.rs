@@ -0,0 +1,149 @@
+use ego_tree::NodeRef;
+use scraper::{Html, Node};
+
+/// Elements whose content isn't text of the page
+const SKIPPED: &[&str] = &["head", "script", "style", "noscript", "template", "svg", "canvas", "iframe", "object", "button", "select", "textarea"];
+
+/// Elements starting a new paragraph
+const BLOCKS: &[&str] = &[
+    "p", "div", "section", "article", "main", "aside", "header", "footer", "nav", "blockquote", "figure", "figcaption",
+    "h1", "h2", "h3", "h4", "h5", "h6", "ul", "ol", "dl", "dt", "dd", "table", "tr", "form", "fieldset", "details", "summary", "hr", "address",
+];
+
+/// Text of `html` without any markup: one paragraph per block separated by blank lines,
+/// list items and table rows on their own lines, preformatted text kept as it is
+pub fn from_html(html: &str) -> String {
+    let document = Html::parse_document(html);
+    let mut writer = Writer::default();
+    writer.walk(*document.root_element());
+    writer.finish()
+}
+
+#[derive(Default)]
+struct Writer {
+    text: String,
+    /// Line breaks owed before the next text
+    pending: usize,
+}
+
+impl Writer {
+
+    fn walk(&mut self, node: NodeRef<Node>) {
+        match node.value() {
+            Node::Text(text) => self.push_inline(text),
+            Node::Element(element) => {
+                let name = element.name();
+                match name {
+                    name if SKIPPED.contains(&name) => {}
+                    "br" => self.text.push('\n'),
+                    "pre" => {
+                        self.break_lines(2);
+                        let text = node.descendants().filter_map(|node| node.value().as_text()).map(|text| &**text).collect::<String>();
+                        self.push_raw(text.trim_matches('\n'));
+                        self.break_lines(2);
+                    }
+                    "li" | "tr" => {
+                        self.break_lines(1);
+                        if name == "li" {
+                            self.push_raw("• ");
+                        }
+                        self.walk_children(node);
+                        self.break_lines(1);
+                    }
+                    "td" | "th" => {
+                        // cells after the first of their row
+                        if self.pending == 0 && !self.text.is_empty() && !self.text.ends_with('\n') {
+                            self.text.push('\t');
+                        }
+                        self.walk_children(node);
+                    }
+                    "img" => if let Some(alt) = element.attr("alt").map(str::trim).filter(|alt| !alt.is_empty()) {
+                        self.push_inline(alt);
+                    },
+                    name if BLOCKS.contains(&name) => {
+                        self.break_lines(2);
+                        self.walk_children(node);
+                        self.break_lines(2);
+                    }
+                    _ => self.walk_children(node),
+                }
+            }
+            _ => self.walk_children(node),
+        }
+    }
+
+    fn walk_children(&mut self, node: NodeRef<Node>) {
+        for child in node.children() {
+            self.walk(child);
+        }
+    }
+
+    /// Ask for at least `lines` line breaks before the next text
+    fn break_lines(&mut self, lines: usize) {
+        self.pending = self.pending.max(lines);
+    }
+
+    fn flush(&mut self) {
+        if self.pending > 0 {
+            let end = self.text.trim_end_matches([' ', '\t']).len();
+            self.text.truncate(end);
+            let existing = self.text.len() - self.text.trim_end_matches('\n').len();
+            if !self.text.is_empty() {
+                self.text.push_str(&"\n".repeat(self.pending.saturating_sub(existing)));
+            }
+            self.pending = 0;
+        }
+    }
+
+    /// Text of the flow, whitespace collapsed as browsers do
+    fn push_inline(&mut self, text: &str) {
+        let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if collapsed.is_empty() {
+            if text.chars().next().is_some_and(char::is_whitespace) && !self.text.ends_with(char::is_whitespace) && self.pending == 0 {
+                self.text.push(' ');
+            }
+            return;
+        }
+        self.flush();
+        if text.starts_with(char::is_whitespace) && !self.text.is_empty() && !self.text.ends_with(char::is_whitespace) {
+            self.text.push(' ');
+        }
+        self.text.push_str(&collapsed);
+        if text.ends_with(char::is_whitespace) {
+            self.text.push(' ');
+        }
+    }
+
+    fn push_raw(&mut self, text: &str) {
+        self.flush();
+        self.text.push_str(text);
+    }
+
+    fn finish(self) -> String {
+        let lines = self.text.lines().map(str::trim_end).collect::<Vec<_>>();
+        let mut text = lines.join("\n").trim().to_string();
+        text.push('\n');
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_text_without_markup() {
+        let html = r#"<html><head><title>Post</title><style>p { color: red }</style></head><body>
+            <h1>Fixture   <em>article</em></h1>
+            <p>First line<br>second <a href="/x">line</a>.</p>
+            <script>track()</script>
+            <ul><li>One</li><li>Two</li></ul>
+            <table><tr><th>Name</th><th>Size</th></tr><tr><td>a.png</td><td>3 kB</td></tr></table>
+            <pre>fn main() {
+    run();
+}</pre>
+            <p><img src="chart.png" alt="Sales chart"></p>
+        </body></html>"#;
+        assert_eq!(from_html(html), "Fixture article\n\nFirst line\nsecond line.\n\n• One\n• Two\n\nName\tSize\na.png\t3 kB\n\nfn main() {\n    run();\n}\n\nSales chart\n");
+    }
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+/// Expression evaluating to the navigation timings and resources of the page as JSON,
+/// from the Navigation and Resource Timing APIs
+pub const SCRIPT: &str = r#"JSON.stringify((() => {
+    const navigation = performance.getEntriesByType('navigation')[0];
+    const resources = performance.getEntriesByType('resource');
+    const since_start = end => navigation && end > 0 ? end - navigation.startTime : null;
+    return {
+        navigation_ms: since_start(navigation && navigation.responseEnd),
+        dom_content_loaded_ms: since_start(navigation && navigation.domContentLoadedEventEnd),
+        load_ms: since_start(navigation && navigation.loadEventEnd),
+        requests: resources.length + (navigation ? 1 : 0),
+        transferred_bytes: resources.reduce((sum, resource) => sum + (resource.transferSize || 0), navigation ? navigation.transferSize || 0 : 0),
+    };
+})())"#;
+
+/// How long the page took to load and to capture, and what was downloaded for it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Timings {
+    /// From the start of the navigation to the end of the page response, in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub navigation_ms: Option<f64>,
+    /// When the DOM was ready, from the start of the navigation. Browser backends only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dom_content_loaded_ms: Option<f64>,
+    /// When the page and its subresources were loaded, from the start of the navigation. Browser backends only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub load_ms: Option<f64>,
+    /// Network requests of the backend and of the downloads of the scraper
+    #[serde(default)]
+    pub requests: usize,
+    /// Bytes received by those requests, bodies only for the downloads of the scraper
+    #[serde(default)]
+    pub transferred_bytes: u64,
+    /// Time the backend took to load the page, waits included
+    #[serde(default)]
+    pub render_ms: u64,
+    /// Wall-clock time of the whole scrape, rendering included
+    #[serde(default)]
+    pub scrape_ms: u64,
+}
+
+impl Timings {
+
+    /// Timings reported by `SCRIPT`, `None` when its result can't be read
+    pub fn from_script(json: &str) -> Option<Self> {
+        serde_json::from_str(json).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_script_result() {
+        let json = r#"{"navigation_ms":120.5,"dom_content_loaded_ms":300,"load_ms":null,"requests":12,"transferred_bytes":48000}"#;
+        assert_eq!(Timings::from_script(json), Some(Timings {
+            navigation_ms: Some(120.5),
+            dom_content_loaded_ms: Some(300.0),
+            requests: 12,
+            transferred_bytes: 48000,
+            ..Default::default()
+        }));
+        assert_eq!(Timings::from_script("undefined"), None);
+    }
+}
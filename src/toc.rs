@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use regex::Regex;
+
+/// Heading levels listed unless asked otherwise, down to `###`
+pub const DEFAULT_DEPTH: usize = 3;
+
+/// ATX heading of the markdown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    pub level: usize,
+    /// Text without its inline markup
+    pub text: String,
+    /// Anchor GitHub, GitLab and most renderers give the heading
+    pub anchor: String,
+}
+
+/// Text of inline markdown: link and image texts, without emphasis and code marks
+fn plain_text(markdown: &str) -> String {
+    static LINK: OnceLock<Regex> = OnceLock::new();
+    let link = LINK.get_or_init(|| Regex::new(r"!?\[([^\]]*)\]\([^)]*\)").unwrap());
+    link.replace_all(markdown, "$1")
+        .replace(['*', '`'], "")
+        .replace("\\", "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// GitHub anchor of a heading: lowercase, punctuation dropped, spaces turned into `-`,
+/// `-1`, `-2`, ... appended to the repeated ones
+fn anchor(text: &str, seen: &mut HashMap<String, usize>) -> String {
+    let slug = text.to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+        .map(|c| if c == ' ' { '-' } else { c })
+        .collect::<String>();
+    let count = seen.entry(slug.clone()).or_insert(0);
+    let anchor = match *count {
+        0 => slug,
+        n => format!("{slug}-{n}"),
+    };
+    *count += 1;
+    anchor
+}
+
+/// `#` headings of `markdown`, those of fenced code blocks aside
+pub fn headings(markdown: &str) -> Vec<Heading> {
+
+    let mut seen = HashMap::new();
+    let mut fence: Option<&str> = None;
+    let mut headings = Vec::new();
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|marker| trimmed.starts_with(marker)) {
+            fence = match fence {
+                Some(open) if open == marker => None,
+                None => Some(marker),
+                open => open,
+            };
+            continue;
+        }
+        if fence.is_some() || line.starts_with("    ") {
+            continue;
+        }
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        let Some(rest) = trimmed.get(level..).filter(|_| (1..=6).contains(&level)) else { continue };
+        if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+            continue;
+        }
+        // closing sequence of `## Heading ##`
+        let text = plain_text(rest.trim().trim_end_matches('#'));
+        if !text.is_empty() {
+            let anchor = anchor(&text, &mut seen);
+            headings.push(Heading { level, text, anchor });
+        }
+    }
+    headings
+}
+
+/// Linked list of the headings of `markdown` down to `depth`, nested by level. The title,
+/// a single `#` heading starting the document, is left out. `None` below two entries
+pub fn render(markdown: &str, depth: usize) -> Option<String> {
+
+    let mut headings = headings(markdown);
+    let titled = markdown.trim_start().starts_with("# ");
+    if titled && headings.iter().filter(|heading| heading.level == 1).count() == 1 {
+        headings.remove(0);
+    }
+    headings.retain(|heading| heading.level <= depth);
+    if headings.len() < 2 {
+        return None;
+    }
+
+    let top = headings.iter().map(|heading| heading.level).min().unwrap_or(1);
+    let mut toc = String::new();
+    for heading in &headings {
+        let text = heading.text.replace('[', "\\[").replace(']', "\\]");
+        toc.push_str(&format!("{}- [{text}](#{})\n", "  ".repeat(heading.level - top), heading.anchor));
+    }
+    Some(toc)
+}
+
+/// `markdown` with `toc` after its title, or at the top when it has none
+pub fn insert(markdown: &str, toc: &str) -> String {
+    let start = markdown.len() - markdown.trim_start().len();
+    match markdown[start..].starts_with("# ") {
+        true => {
+            let end = markdown[start..].find('\n').map_or(markdown.len(), |end| start + end + 1);
+            format!("{}\n{toc}\n{}", &markdown[..end], markdown[end..].trim_start_matches('\n'))
+        }
+        false => format!("{toc}\n{markdown}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKDOWN: &str = "# Rust *concurrency*\n\nIntro\n\n## Threads & [channels](https://doc.rust-lang.org)\n\n\
+        ```sh\n# not a heading\n```\n\n### Scoped threads\n\n#### Details\n\n## Summary ##\n\n## Summary\n";
+
+    #[test]
+    fn finds_headings_and_anchors() {
+        let headings = headings(MARKDOWN);
+        assert_eq!(headings.iter().map(|heading| (heading.level, heading.text.as_str(), heading.anchor.as_str())).collect::<Vec<_>>(), [
+            (1, "Rust concurrency", "rust-concurrency"),
+            (2, "Threads & channels", "threads--channels"),
+            (3, "Scoped threads", "scoped-threads"),
+            (4, "Details", "details"),
+            (2, "Summary", "summary"),
+            (2, "Summary", "summary-1"),
+        ]);
+    }
+
+    #[test]
+    fn inserts_the_toc_after_the_title() {
+        let toc = render(MARKDOWN, DEFAULT_DEPTH).unwrap();
+        assert_eq!(toc, "- [Threads & channels](#threads--channels)\n  - [Scoped threads](#scoped-threads)\n\
+            - [Summary](#summary)\n- [Summary](#summary-1)\n");
+        assert!(insert(MARKDOWN, &toc).starts_with("# Rust *concurrency*\n\n- [Threads & channels](#threads--channels)\n"));
+        assert!(insert(MARKDOWN, &toc).contains("[Summary](#summary-1)\n\nIntro\n"));
+        assert_eq!(render("# Title\n\n## Only section\n", DEFAULT_DEPTH), None);
+    }
+}
@@ -0,0 +1,431 @@
+//! Minimal TOML reader for the configuration files. Documents are parsed into a
+//! `serde_json::Value` so the configuration structs can keep using serde derives.
+//! Tables, arrays of tables, dotted and quoted keys, strings, integers, floats,
+//! booleans, arrays and inline tables are supported, dates are not
+
+use serde_json::{Map, Number, Value};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[error("TOML error on line {line}: {message}")]
+pub struct TomlError {
+    pub line: usize,
+    pub message: String,
+}
+
+pub type Result<T> = std::result::Result<T, TomlError>;
+
+/// Parse a whole TOML document into a JSON object
+pub fn parse(text: &str) -> Result<Value> {
+    Parser { chars: text.chars().collect(), pos: 0, line: 1 }.document()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T> {
+        Err(TomlError { line: self.line, message: message.into() })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn starts_with(&self, text: &str) -> bool {
+        text.chars().enumerate().all(|(i, c)| self.peek_at(i) == Some(c))
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        match self.bump() {
+            Some(found) if found == c => Ok(()),
+            Some(found) => self.error(format!("expected {c:?}, found {found:?}")),
+            None => self.error(format!("expected {c:?}, found end of file")),
+        }
+    }
+
+    /// Spaces and tabs only
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.bump();
+            }
+        }
+    }
+
+    /// Spaces, newlines and comments, as allowed between array elements
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n' | '\r') => { self.bump(); }
+                _ => break,
+            }
+        }
+    }
+
+    /// Nothing but a comment may follow a key/value pair or a table header
+    fn end_of_line(&mut self) -> Result<()> {
+        self.skip_spaces();
+        self.skip_comment();
+        if self.peek() == Some('\r') {
+            self.bump();
+        }
+        match self.bump() {
+            None | Some('\n') => Ok(()),
+            Some(c) => self.error(format!("unexpected {c:?} at the end of the line")),
+        }
+    }
+
+    fn document(&mut self) -> Result<Value> {
+
+        let mut root = Map::new();
+        let mut current: Vec<String> = Vec::new();
+
+        loop {
+            self.skip_blank();
+            match self.peek() {
+                None => break,
+                Some('[') if self.peek_at(1) == Some('[') => {
+                    self.pos += 2;
+                    let path = self.key()?;
+                    self.expect(']')?;
+                    self.expect(']')?;
+                    self.end_of_line()?;
+                    let (last, parents) = path.split_last().unwrap();
+                    let parent = self.table_at(&mut root, parents)?;
+                    let array = parent.entry(last.clone()).or_insert_with(|| Value::Array(Vec::new()));
+                    match array {
+                        Value::Array(tables) => tables.push(Value::Object(Map::new())),
+                        _ => return self.error(format!("{last} is not an array of tables")),
+                    }
+                    current = path;
+                }
+                Some('[') => {
+                    self.bump();
+                    let path = self.key()?;
+                    self.expect(']')?;
+                    self.end_of_line()?;
+                    self.table_at(&mut root, &path)?;
+                    current = path;
+                }
+                Some(_) => {
+                    let key = self.key()?;
+                    self.expect('=')?;
+                    self.skip_spaces();
+                    let value = self.value()?;
+                    let table = self.table_at(&mut root, &current)?;
+                    self.insert(table, &key, value)?;
+                    self.end_of_line()?;
+                }
+            }
+        }
+
+        Ok(Value::Object(root))
+    }
+
+    /// The table at `path`, created if needed, the last table of arrays of tables
+    fn table_at<'a>(&self, root: &'a mut Map<String, Value>, path: &[String]) -> Result<&'a mut Map<String, Value>> {
+        let mut table = root;
+        for segment in path {
+            let entry = table.entry(segment.clone()).or_insert_with(|| Value::Object(Map::new()));
+            table = match entry {
+                Value::Object(map) => map,
+                Value::Array(items) => match items.last_mut() {
+                    Some(Value::Object(map)) => map,
+                    _ => return self.error(format!("{segment} is not a table")),
+                },
+                _ => return self.error(format!("{segment} is not a table")),
+            };
+        }
+        Ok(table)
+    }
+
+    fn insert(&self, table: &mut Map<String, Value>, key: &[String], value: Value) -> Result<()> {
+        let (last, parents) = key.split_last().unwrap();
+        let table = self.table_at(table, parents)?;
+        if table.contains_key(last) {
+            return self.error(format!("duplicate key {last}"));
+        }
+        table.insert(last.clone(), value);
+        Ok(())
+    }
+
+    /// Dotted key, made of bare and quoted parts
+    fn key(&mut self) -> Result<Vec<String>> {
+        let mut parts = Vec::new();
+        loop {
+            self.skip_spaces();
+            let part = match self.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let start = self.pos;
+                    while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                        self.bump();
+                    }
+                    if start == self.pos {
+                        return self.error("expected a key");
+                    }
+                    self.chars[start..self.pos].iter().collect()
+                }
+            };
+            parts.push(part);
+            self.skip_spaces();
+            if self.peek() != Some('.') {
+                return Ok(parts);
+            }
+            self.bump();
+        }
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        match self.peek() {
+            Some('"') if self.starts_with("\"\"\"") => self.multiline_string('"').map(Value::String),
+            Some('\'') if self.starts_with("'''") => self.multiline_string('\'').map(Value::String),
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            Some(_) if self.starts_with("true") => { self.pos += 4; Ok(Value::Bool(true)) }
+            Some(_) if self.starts_with("false") => { self.pos += 5; Ok(Value::Bool(false)) }
+            Some(_) => self.number(),
+            None => self.error("expected a value"),
+        }
+    }
+
+    fn array(&mut self) -> Result<Value> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        loop {
+            self.skip_blank();
+            if self.peek() == Some(']') {
+                self.bump();
+                return Ok(Value::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_blank();
+            match self.bump() {
+                Some(',') => continue,
+                Some(']') => return Ok(Value::Array(items)),
+                _ => return self.error("expected ',' or ']' in array"),
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Value> {
+        self.expect('{')?;
+        let mut table = Map::new();
+        self.skip_spaces();
+        if self.peek() == Some('}') {
+            self.bump();
+            return Ok(Value::Object(table));
+        }
+        loop {
+            let key = self.key()?;
+            self.expect('=')?;
+            self.skip_spaces();
+            let value = self.value()?;
+            self.insert(&mut table, &key, value)?;
+            self.skip_spaces();
+            match self.bump() {
+                Some(',') => continue,
+                Some('}') => return Ok(Value::Object(table)),
+                _ => return self.error("expected ',' or '}' in inline table"),
+            }
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut string = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(string),
+                Some('\\') => string.push(self.escape()?),
+                Some('\n') | None => return self.error("unterminated string"),
+                Some(c) => string.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String> {
+        self.expect('\'')?;
+        let mut string = String::new();
+        loop {
+            match self.bump() {
+                Some('\'') => return Ok(string),
+                Some('\n') | None => return self.error("unterminated string"),
+                Some(c) => string.push(c),
+            }
+        }
+    }
+
+    fn multiline_string(&mut self, quote: char) -> Result<String> {
+        self.pos += 3;
+        // a newline right after the opening delimiter is trimmed
+        if self.starts_with("\r\n") {
+            self.pos += 1;
+        }
+        if self.peek() == Some('\n') {
+            self.bump();
+        }
+        let delimiter: String = std::iter::repeat_n(quote, 3).collect();
+        let mut string = String::new();
+        loop {
+            if self.starts_with(&delimiter) {
+                self.pos += 3;
+                return Ok(string);
+            }
+            match self.bump() {
+                // line ending backslash: skip the newline and the leading whitespace
+                Some('\\') if quote == '"' && matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) => {
+                    while matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) {
+                        self.bump();
+                    }
+                }
+                Some('\\') if quote == '"' => string.push(self.escape()?),
+                Some(c) => string.push(c),
+                None => return self.error("unterminated multi-line string"),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char> {
+        let c = match self.bump() {
+            Some('b') => '\u{8}',
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('f') => '\u{c}',
+            Some('r') => '\r',
+            Some('e') => '\u{1b}',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some(u @ ('u' | 'U')) => {
+                let len = if u == 'u' { 4 } else { 8 };
+                let hex: String = (0..len).filter_map(|_| self.bump()).collect();
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(c) => c,
+                    None => return self.error(format!("invalid unicode escape \\{u}{hex}")),
+                }
+            }
+            other => return self.error(format!("invalid escape {other:?}")),
+        };
+        Ok(c)
+    }
+
+    fn number(&mut self) -> Result<Value> {
+
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.' | '_' | ':')) {
+            self.bump();
+        }
+        let token: String = self.chars[start..self.pos].iter().collect();
+        let digits = token.replace('_', "");
+
+        let (sign, unsigned) = match digits.strip_prefix('-') {
+            Some(rest) => (-1, rest),
+            None => (1, digits.strip_prefix('+').unwrap_or(&digits)),
+        };
+
+        let radix = match unsigned.get(..2) {
+            Some("0x") => Some(16),
+            Some("0o") => Some(8),
+            Some("0b") => Some(2),
+            _ => None,
+        };
+        if let Some(radix) = radix {
+            return match i64::from_str_radix(&unsigned[2..], radix) {
+                Ok(n) => Ok(Value::Number((sign * n).into())),
+                Err(_) => self.error(format!("invalid integer {token}")),
+            };
+        }
+
+        if let Ok(n) = digits.parse::<i64>() {
+            return Ok(Value::Number(n.into()));
+        }
+        if token.contains([':']) || unsigned.chars().filter(|&c| c == '-').count() >= 2 {
+            return self.error(format!("dates are not supported: {token}"));
+        }
+        match digits.parse::<f64>().ok().and_then(Number::from_f64) {
+            Some(n) if !token.is_empty() => Ok(Value::Number(n)),
+            _ => self.error(format!("invalid value {token:?}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_tables_and_values() {
+        let document = r#"
+            # global settings
+            title = "Captures"   # trailing comment
+            jobs = 4
+            ratio = 1.5e0
+
+            [domains."news.example.com"]
+            remove = [
+                ".cookie-banner",
+                '#paywall',
+            ]
+            auth = { username = "me", password = "sécret" }
+            rate.delay_ms = 1_000
+
+            [[sites]]
+            name = 'first'
+            [[sites]]
+            name = """
+multi"""
+        "#;
+
+        assert_eq!(parse(document).unwrap(), json!({
+            "title": "Captures",
+            "jobs": 4,
+            "ratio": 1.5,
+            "domains": {
+                "news.example.com": {
+                    "remove": [".cookie-banner", "#paywall"],
+                    "auth": { "username": "me", "password": "sécret" },
+                    "rate": { "delay_ms": 1000 }
+                }
+            },
+            "sites": [{ "name": "first" }, { "name": "multi" }]
+        }));
+    }
+
+    #[test]
+    fn reports_errors_with_line() {
+        let error = parse("a = 1\na = 2\n").unwrap_err();
+        assert_eq!((error.line, error.message.as_str()), (2, "duplicate key a"));
+        assert!(parse("date = 1979-05-27\n").is_err());
+        assert!(parse("s = \"unterminated\n").is_err());
+    }
+}
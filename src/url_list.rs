@@ -0,0 +1,40 @@
+use std::io::Read;
+use std::path::Path;
+
+/// URLs of a list, one per line. Blank lines and `#` comments, on their own line or after
+/// a URL and a space, are skipped
+pub fn parse(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| match line.find(" #").or_else(|| line.find("\t#")) {
+            Some(comment) => &line[..comment],
+            None => line,
+        })
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// URLs listed in the file at `path`, or on the standard input when it is `-`
+pub fn read(path: &Path) -> std::io::Result<Vec<String>> {
+    let text = match path.as_os_str() == "-" {
+        true => {
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text)?;
+            text
+        }
+        false => std::fs::read_to_string(path)?,
+    };
+    Ok(parse(&text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_blank_lines_and_comments() {
+        let list = "# reading list\nhttps://example.com/a\n\n  https://example.com/b#section  # the second one\r\n\t\n#https://example.com/c\n";
+        assert_eq!(parse(list), ["https://example.com/a", "https://example.com/b#section"]);
+    }
+}
@@ -0,0 +1,230 @@
+use std::path::{Component, Path, PathBuf};
+use serde::Serialize;
+use thiserror::Error;
+use time::OffsetDateTime;
+use url::Url;
+use crate::feed::{Feed, FeedError};
+use crate::hash;
+use crate::exif;
+use crate::http::{HttpClient, HttpError, NetworkMode};
+use crate::images::{Images, ImagesError, Manifest};
+
+#[derive(Error, Debug)]
+pub enum VerifyError {
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("JSON conversion error: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("HttpError: {0}")]
+    HttpError(#[from] HttpError),
+    #[error("FeedError: {0}")]
+    FeedError(#[from] FeedError),
+    #[error("ImagesError: {0}")]
+    ImagesError(#[from] ImagesError),
+    #[error("the manifest names a file outside of the images directory: {0}")]
+    UnsafeFilename(String),
+}
+
+pub type Result<T> = std::result::Result<T, VerifyError>;
+
+/// State of an archived file compared to the manifest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LocalStatus {
+    Intact,
+    Modified,
+    Missing,
+    /// Left out of the capture on purpose, only its thumbnail was saved
+    Omitted,
+}
+
+/// State of the origin URL of an archived file, when re-fetched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OriginStatus {
+    /// Still serves the archived bytes
+    Unchanged,
+    /// Serves something else now
+    Changed,
+    /// Answers an error or can't be reached
+    Dead,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileCheck {
+    pub filename: String,
+    pub url: Option<String>,
+    pub local: LocalStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<OriginStatus>,
+    /// Missing or modified file downloaded again from an unchanged origin
+    pub restored: bool,
+}
+
+/// Result of `verify_capture`, written to `freshness.json` in the capture directory
+#[derive(Debug, Clone, Serialize)]
+pub struct FreshnessReport {
+    pub capture: PathBuf,
+    pub checked_at: String,
+    /// Whether the capture had an image manifest to check against
+    pub has_manifest: bool,
+    pub images: Vec<FileCheck>,
+}
+
+impl FreshnessReport {
+
+    pub const FILENAME: &str = "freshness.json";
+
+    pub fn count_local(&self, status: LocalStatus) -> usize {
+        self.images.iter().filter(|check| check.local == status).count()
+    }
+
+    pub fn count_origin(&self, status: OriginStatus) -> usize {
+        self.images.iter().filter(|check| check.origin == Some(status)).count()
+    }
+
+    pub fn count_restored(&self) -> usize {
+        self.images.iter().filter(|check| check.restored).count()
+    }
+
+    /// Every archived file matches its manifest, once restorations are done
+    pub fn is_intact(&self) -> bool {
+        self.images.iter().all(|check| matches!(check.local, LocalStatus::Intact | LocalStatus::Omitted) || check.restored)
+    }
+}
+
+/// `filename` of the manifest as a path within the images directory, `None` unless it's
+/// relative and made of normal components only: no `..`, root, drive or backslash
+fn relative_path(filename: &str) -> Option<&Path> {
+    if filename.is_empty() || filename.contains(['\\', ':']) {
+        return None;
+    }
+    let path = Path::new(filename);
+    path.components().all(|component| matches!(component, Component::Normal(_))).then_some(path)
+}
+
+/// Check the images of a capture against `images/manifest.json`. With `refetch`,
+/// origins are downloaded again to tell whether they changed or died, and files
+/// missing or modified locally are restored from origins still serving the same bytes
+pub async fn verify_capture(capture: &Path, refetch: bool, client: &HttpClient) -> Result<FreshnessReport> {
+
+    let images_directory = capture.join("images");
+    let manifest_path = images_directory.join(Images::MANIFEST_FILENAME);
+
+    let manifest = match manifest_path.is_file() {
+        true => Manifest::load(&manifest_path)?,
+        false => Manifest::default(),
+    };
+
+    let mut images = Vec::new();
+    for entry in manifest.images {
+
+        let path = images_directory.join(relative_path(&entry.filename).ok_or_else(|| VerifyError::UnsafeFilename(entry.filename.clone()))?);
+        let local = match std::fs::read(&path) {
+            Ok(bytes) if hash::sha256_hex(&bytes) == entry.sha256 => LocalStatus::Intact,
+            Ok(_) => LocalStatus::Modified,
+            Err(_) if entry.omitted => LocalStatus::Omitted,
+            Err(_) => LocalStatus::Missing,
+        };
+
+        let mut origin = None;
+        let mut restored = false;
+        if let Some(url) = entry.url.as_deref().filter(|_| refetch) {
+            let response = match Url::parse(url) {
+                Ok(url) => client.get(&url).await.ok(),
+                Err(_) => None,
+            };
+            // compared as archived, without the metadata removed at capture time
+            let response = response.map(|mut response| {
+                if entry.metadata_stripped && let Some(stripped) = exif::strip_jpeg(&response.body) {
+                    response.body = stripped;
+                }
+                response
+            });
+            origin = Some(match &response {
+                Some(response) if hash::sha256_hex(&response.body) == entry.sha256 => OriginStatus::Unchanged,
+                Some(_) => OriginStatus::Changed,
+                None => OriginStatus::Dead,
+            });
+            if let Some(response) = response.filter(|_| matches!(local, LocalStatus::Modified | LocalStatus::Missing) && origin == Some(OriginStatus::Unchanged)) {
+                std::fs::write(&path, response.body)?;
+                restored = true;
+            }
+        }
+
+        images.push(FileCheck { filename: entry.filename, url: entry.url, local, origin, restored });
+    }
+
+    Ok(FreshnessReport {
+        capture: capture.to_path_buf(),
+        checked_at: OffsetDateTime::now_utc().date().to_string(),
+        has_manifest: manifest_path.is_file(),
+        images,
+    })
+}
+
+/// Verify every capture found under `root`, writing each `freshness.json`
+pub async fn verify_archive(root: &Path, refetch: bool) -> Result<Vec<FreshnessReport>> {
+
+    let client = HttpClient::new(&NetworkMode::Live)?;
+
+    let mut captures = Vec::new();
+    Feed::find_captures(root, &mut captures)?;
+    captures.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut reports = Vec::new();
+    for (capture, _) in captures {
+        let report = verify_capture(&capture, refetch, &client).await?;
+        std::fs::write(capture.join(FreshnessReport::FILENAME), serde_json::to_string_pretty(&report)?)?;
+        reports.push(report);
+    }
+
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{FixtureServer, RED_PNG, ARTICLE_HTML};
+
+    #[tokio::test]
+    async fn detects_and_restores_damaged_images() {
+
+        let server = FixtureServer::with_article().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        let capture = root.path().join("capture");
+        std::fs::create_dir(&capture).unwrap();
+        let info = r#"{"url":"http://localhost/article.html","title":"Article","date":"2025-01-01","nb_md_words":1,"nb_images":4}"#;
+        std::fs::write(capture.join("informations.json"), info).unwrap();
+
+        let images = Images::from(ARTICLE_HTML, &server.url("/article.html")).await.unwrap();
+        images.write_images_to_disk(&capture).await.unwrap();
+        std::fs::write(capture.join("images/red.png"), b"corrupted").unwrap();
+        // the stylesheet image has changed since the capture
+        server.route("/images/blue.png", "image/png", RED_PNG);
+
+        let report = verify_capture(&capture, false, &HttpClient::new(&NetworkMode::Live).unwrap()).await.unwrap();
+        assert_eq!(report.count_local(LocalStatus::Modified), 1);
+        assert!(!report.is_intact());
+
+        let reports = verify_archive(root.path(), true).await.unwrap();
+        assert_eq!(reports[0].count_restored(), 1);
+        assert_eq!(reports[0].count_origin(OriginStatus::Changed), 1);
+        assert!(reports[0].is_intact());
+        assert_eq!(std::fs::read(capture.join("images/red.png")).unwrap(), RED_PNG);
+        assert!(capture.join(FreshnessReport::FILENAME).is_file());
+    }
+
+    #[tokio::test]
+    async fn rejects_filenames_outside_of_the_images_directory() {
+        let capture = tempfile::tempdir().unwrap();
+        std::fs::create_dir(capture.path().join("images")).unwrap();
+        let client = HttpClient::new(&NetworkMode::Live).unwrap();
+        for filename in ["../informations.json", "/etc/passwd", "a/../../b.png", "C:b.png", "..\\b.png", ""] {
+            let manifest = format!(r#"[{{"filename":{},"url":null,"sha256":"","bytes":0}}]"#, serde_json::to_string(filename).unwrap());
+            std::fs::write(capture.path().join("images").join(Images::MANIFEST_FILENAME), manifest).unwrap();
+            assert!(matches!(verify_capture(capture.path(), false, &client).await, Err(VerifyError::UnsafeFilename(name)) if name == filename), "{filename}");
+        }
+        assert_eq!(relative_path("nested/red.png"), Some(Path::new("nested/red.png")));
+    }
+}
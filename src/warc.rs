@@ -0,0 +1,184 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use time::{OffsetDateTime, PrimitiveDateTime};
+use url::Url;
+use crate::http::Response;
+use crate::snapshot;
+
+/// Extension of the WARC file of a capture, each record compressed on its own
+/// as replay tools (pywb, ReplayWeb.page) expect
+pub const EXTENSION: &str = "warc.gz";
+
+/// Response headers describing the original transfer rather than the stored body
+const TRANSFER_HEADERS: &[&str] = &["content-length", "content-encoding", "transfer-encoding", "connection"];
+
+/// Request made during a scrape and the response it received
+#[derive(Clone)]
+pub struct Exchange {
+    /// Requested URL, the response tells where redirections led
+    pub url: Url,
+    pub request_headers: Vec<(String, String)>,
+    pub response_headers: Vec<(String, String)>,
+    pub response: Response,
+    /// `2024-01-31T12:34:56Z`
+    pub date: String,
+}
+
+impl Exchange {
+
+    pub fn new(url: &Url, request_headers: Vec<(String, String)>, response: &Response) -> Self {
+        let response_headers = response.content_type.iter().map(|kind| ("Content-Type".to_string(), kind.clone())).collect();
+        Self { url: url.clone(), request_headers, response_headers, response: response.clone(), date: now() }
+    }
+
+    /// Exchange of the page itself, whose `headers` are those the renderer received
+    pub fn page(url: &Url, html: &str, headers: &[(String, String)]) -> Self {
+        let mut response_headers = headers.iter()
+            .filter(|(name, _)| !TRANSFER_HEADERS.contains(&name.to_ascii_lowercase().as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !response_headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("content-type")) {
+            response_headers.push(("Content-Type".to_string(), "text/html; charset=utf-8".to_string()));
+        }
+        let response = Response { url: url.clone(), status: 200, content_type: None, body: html.as_bytes().to_vec() };
+        Self { url: url.clone(), request_headers: Vec::new(), response_headers, response, date: now() }
+    }
+}
+
+/// Exchanges of an `HttpClient`, shared by its clones
+#[derive(Clone, Default)]
+pub struct Journal(Arc<Mutex<Vec<Exchange>>>);
+
+impl Journal {
+
+    pub fn push(&self, exchange: Exchange) {
+        self.0.lock().unwrap().push(exchange);
+    }
+
+    pub fn exchanges(&self) -> Vec<Exchange> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+fn now() -> String {
+    let now = OffsetDateTime::now_utc();
+    snapshot::iso8601(PrimitiveDateTime::new(now.date(), now.time()))
+}
+
+/// `<urn:uuid:...>` of a random (version 4) UUID
+fn record_id() -> String {
+    let mut bytes = fastrand::u128(..).to_be_bytes();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = bytes.iter().map(|byte| format!("{byte:02x}")).collect::<String>();
+    format!("<urn:uuid:{}-{}-{}-{}-{}>", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// Gzip member holding one WARC record
+fn record(kind: &str, id: &str, date: &str, fields: &[(&str, String)], content_type: &str, block: &[u8]) -> Vec<u8> {
+    let mut header = format!("WARC/1.1\r\nWARC-Type: {kind}\r\nWARC-Record-ID: {id}\r\nWARC-Date: {date}\r\n");
+    for (name, value) in fields {
+        header.push_str(&format!("{name}: {value}\r\n"));
+    }
+    header.push_str(&format!("Content-Type: {content_type}\r\nContent-Length: {}\r\n\r\n", block.len()));
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    // writing to memory can't fail
+    encoder.write_all(header.as_bytes()).unwrap();
+    encoder.write_all(block).unwrap();
+    encoder.write_all(b"\r\n\r\n").unwrap();
+    encoder.finish().unwrap()
+}
+
+fn http_response(status: u16, headers: &[(String, String)], body: &[u8]) -> Vec<u8> {
+    let reason = reqwest::StatusCode::from_u16(status).ok().and_then(|status| status.canonical_reason()).unwrap_or_default();
+    let mut head = format!("HTTP/1.1 {status} {reason}\r\n");
+    for (name, value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str(&format!("Content-Length: {}\r\n\r\n", body.len()));
+    [head.into_bytes(), body.to_vec()].concat()
+}
+
+/// Request headers carrying credentials, left out of the request records
+const CREDENTIALS: [&str; 3] = ["authorization", "proxy-authorization", "cookie"];
+
+fn http_request(url: &Url, headers: &[(String, String)]) -> Vec<u8> {
+    let target = &url[url::Position::BeforePath..url::Position::AfterQuery];
+    let mut head = format!("GET {target} HTTP/1.1\r\nHost: {}\r\n", &url[url::Position::BeforeHost..url::Position::AfterPort]);
+    for (name, value) in headers.iter().filter(|(name, _)| !CREDENTIALS.iter().any(|credential| name.eq_ignore_ascii_case(credential))) {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    head.into_bytes()
+}
+
+/// Response and request records of `exchange`, a redirection record first
+/// when the response came from another URL than the requested one
+fn exchange_records(exchange: &Exchange) -> Vec<u8> {
+    let mut records = Vec::new();
+    let mut push_pair = |url: &Url, status: u16, headers: &[(String, String)], body: &[u8]| {
+        let id = record_id();
+        let target = ("WARC-Target-URI", url.to_string());
+        records.extend(record("response", &id, &exchange.date, std::slice::from_ref(&target), "application/http; msgtype=response", &http_response(status, headers, body)));
+        records.extend(record("request", &record_id(), &exchange.date, &[target, ("WARC-Concurrent-To", id)], "application/http; msgtype=request", &http_request(url, &exchange.request_headers)));
+    };
+    let response = &exchange.response;
+    if response.url != exchange.url {
+        push_pair(&exchange.url, 302, &[("Location".to_string(), response.url.to_string())], b"");
+    }
+    push_pair(&response.url, response.status, &exchange.response_headers, &response.body);
+    records
+}
+
+/// WARC file of a capture: a `warcinfo` record, then the page and each response received while scraping it
+pub fn write(page: &Exchange, exchanges: &[Exchange], filename: &str) -> Vec<u8> {
+    let info = format!(
+        "software: {}/{}\r\nformat: WARC File Format 1.1\r\nconformsTo: https://iipc.github.io/warc-specifications/specifications/warc-format/warc-1.1/\r\n",
+        env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")
+    );
+    let mut warc = record("warcinfo", &record_id(), &page.date, &[("WARC-Filename", filename.to_string())], "application/warc-fields", info.as_bytes());
+    for exchange in std::iter::once(page).chain(exchanges) {
+        warc.extend(exchange_records(exchange));
+    }
+    warc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use flate2::read::MultiGzDecoder;
+
+    #[test]
+    fn writes_request_and_response_records() {
+        let page_url = Url::parse("https://example.com/post?id=3").unwrap();
+        let headers = [("Content-Type".to_string(), "text/html".to_string()), ("Content-Encoding".to_string(), "br".to_string())];
+        let page = Exchange::page(&page_url, "<p>Hi</p>", &headers);
+        let image = Response { url: Url::parse("https://cdn.example.com/red.png").unwrap(), status: 200, content_type: Some("image/png".to_string()), body: b"PNG".to_vec() };
+        let moved = Exchange::new(&Url::parse("https://example.com/red.png").unwrap(), vec![("Referer".to_string(), page_url.to_string())], &image);
+
+        let mut warc = String::new();
+        MultiGzDecoder::new(&write(&page, &[moved], "post.warc.gz")[..]).read_to_string(&mut warc).unwrap();
+
+        let records = warc.split("WARC/1.1\r\n").skip(1).collect::<Vec<_>>();
+        assert_eq!(records.len(), 7);
+        assert!(records[0].starts_with("WARC-Type: warcinfo\r\n"));
+        assert!(records[1].contains("WARC-Target-URI: https://example.com/post?id=3\r\n"));
+        assert!(records[1].ends_with("HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: 9\r\n\r\n<p>Hi</p>\r\n\r\n"));
+        assert!(records[2].contains("GET /post?id=3 HTTP/1.1\r\nHost: example.com\r\n"));
+        assert!(records[3].contains("HTTP/1.1 302 Found\r\nLocation: https://cdn.example.com/red.png\r\n"));
+        assert!(records[5].contains("WARC-Target-URI: https://cdn.example.com/red.png\r\n"));
+        assert!(records[6].contains("Referer: https://example.com/post?id=3\r\n"));
+    }
+
+    #[test]
+    fn leaves_credentials_out_of_request_records() {
+        let url = Url::parse("https://example.com/private").unwrap();
+        let headers = [("Authorization", "Basic dXNlcjpzZWNyZXQ="), ("cookie", "session=secret"), ("Proxy-Authorization", "Bearer secret"), ("User-Agent", "webpage-scraper")];
+        let request = String::from_utf8(http_request(&url, &headers.map(|(name, value)| (name.to_string(), value.to_string())))).unwrap();
+        assert_eq!(request, "GET /private HTTP/1.1\r\nHost: example.com\r\nUser-Agent: webpage-scraper\r\n\r\n");
+    }
+}
@@ -0,0 +1,108 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use serde::{Serialize, Deserialize};
+
+/// What kind of silent degradation happened during a scrape
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// An image couldn't be downloaded or decoded and is missing from the capture
+    SkippedImage,
+    /// The page had no usable title, files are named after a placeholder
+    FallbackTitle,
+    /// No charset was declared, the page was decoded as UTF-8
+    GuessedEncoding,
+    /// A configured selector matched nothing on the page
+    SelectorNotFound,
+    /// The markdown fell below the `min_words` threshold
+    LowContent,
+    /// The source of an embedded code snippet couldn't be fetched
+    SkippedEmbed,
+    /// A video or audio file is a stream, over the size limits or couldn't be downloaded
+    SkippedMedia,
+    /// A stylesheet, script or font couldn't be downloaded and stays remote
+    SkippedAsset,
+    /// A feed the page links to couldn't be downloaded or isn't one
+    SkippedFeed,
+    /// An output that was asked for can't be produced by the browser backend
+    UnsupportedOutput,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub message: String,
+    /// Resource the warning is about, when there is one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl Warning {
+
+    pub fn new(kind: WarningKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into(), url: None }
+    }
+
+    pub fn with_url(kind: WarningKind, url: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into(), url: Some(url.into()) }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.url {
+            Some(url) => write!(f, "{}: {}", url, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Warnings collected while scraping, cheap to clone and shared between concurrent tasks
+#[derive(Debug, Clone, Default)]
+pub struct Warnings(Arc<Mutex<Vec<Warning>>>);
+
+impl Warnings {
+
+    pub const FILENAME: &str = "warnings.json";
+
+    pub fn push(&self, warning: Warning) {
+        tracing::debug!("{warning}");
+        self.0.lock().unwrap().push(warning);
+    }
+
+    pub fn extend(&self, warnings: impl IntoIterator<Item = Warning>) {
+        for warning in warnings {
+            self.push(warning);
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<Warning> {
+        self.0.lock().unwrap().clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.lock().unwrap().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_kind_in_snake_case() {
+        let warnings = Warnings::default();
+        warnings.clone().push(Warning::with_url(WarningKind::SkippedImage, "https://example.com/a.png", "404 Not Found"));
+        warnings.push(Warning::new(WarningKind::GuessedEncoding, "no charset declared"));
+
+        let json = serde_json::to_string(&warnings.to_vec()).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"kind":"skipped_image","message":"404 Not Found","url":"https://example.com/a.png"},{"kind":"guessed_encoding","message":"no charset declared"}]"#
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use std::sync::Arc;
+use base64::Engine;
+use futures::future::{BoxFuture, FutureExt};
+use reqwest::Method;
+use serde_json::{json, Value};
+use url::Url;
+use crate::browser::{BrowserError, Result};
+use crate::http::Cookie;
+use crate::options::ScrapeOptions;
+use crate::ratelimit::RequestKind;
+use crate::pdf::PrintOptions;
+use crate::renderer::{PageRenderer, RenderedPage};
+use crate::timings::{self, Timings};
+use crate::webpage::WebPage;
+use crate::comments;
+use crate::warnings::{Warning, WarningKind};
+use std::time::{Duration, Instant};
+
+/// `PageRenderer` driving Firefox (or any W3C WebDriver) through a running
+/// geckodriver, for pages that block headless Chrome
+pub struct WebDriver {
+    endpoint: Url,
+    client: reqwest::Client,
+    options: ScrapeOptions,
+}
+
+/// Everything is fetched while the WebDriver session is alive, the session is closed afterwards
+struct WebDriverPage {
+    requested_url: String,
+    url: String,
+    title: String,
+    html: String,
+    pdf: Vec<u8>,
+    warnings: Vec<Warning>,
+    cookies: Vec<Cookie>,
+    timings: Timings,
+}
+
+impl WebDriver {
+
+    pub const DEFAULT_ENDPOINT: &str = "http://localhost:4444";
+    const WAIT_TIMEOUT: Duration = Duration::from_secs(20);
+
+    pub fn new(endpoint: &str) -> Result<Self> {
+        Self::with_options(endpoint, ScrapeOptions::default())
+    }
+
+    pub fn with_options(endpoint: &str, options: ScrapeOptions) -> Result<Self> {
+
+        let mut endpoint = Url::parse(endpoint)?;
+        // commands are joined to the endpoint, which must then look like a directory
+        if !endpoint.path().ends_with('/') {
+            endpoint.set_path(&format!("{}/", endpoint.path()));
+        }
+
+        Ok(Self { endpoint, client: reqwest::Client::new(), options })
+    }
+
+    pub async fn open_tab(&self, url: &str) -> Result<WebPage> {
+        let page = self.render(url, &self.options).await?;
+        Ok(WebPage::from_page(page, &self.options).await?)
+    }
+
+    async fn command(&self, method: Method, path: &str, body: Option<Value>) -> Result<Value> {
+
+        let mut request = self.client
+            .request(method, self.endpoint.join(path)?)
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+        if let Some(body) = body {
+            request = request.body(body.to_string());
+        }
+
+        let response: Value = serde_json::from_str(&request.send().await?.text().await?)?;
+        let value = response.get("value").cloned().unwrap_or(Value::Null);
+
+        if let Some(error) = value.get("error").and_then(Value::as_str) {
+            let message = value.get("message").and_then(Value::as_str).unwrap_or_default();
+            return Err(BrowserError::WebDriverError(format!("{error}: {message}")));
+        }
+
+        Ok(value)
+    }
+
+    async fn new_session(&self) -> Result<String> {
+
+        let capabilities = json!({
+            "capabilities": {
+                "alwaysMatch": {
+                    "browserName": "firefox",
+                    "moz:firefoxOptions": { "args": ["-headless"] }
+                }
+            }
+        });
+
+        let session = self.command(Method::POST, "session", Some(capabilities)).await?;
+
+        session
+            .get("sessionId")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| BrowserError::WebDriverError("no sessionId in new session response".to_string()))
+    }
+
+    /// Poll for `selector` until it shows up or `WAIT_TIMEOUT` runs out
+    async fn wait_for_element(&self, session: &str, selector: &str) -> bool {
+        let start = Instant::now();
+        let query = json!({ "using": "css selector", "value": selector });
+        while start.elapsed() < Self::WAIT_TIMEOUT {
+            if self.command(Method::POST, &format!("session/{session}/element"), Some(query.clone())).await.is_ok() {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+        false
+    }
+
+    /// Cookies can only be set once on the site, the page is loaded again afterwards.
+    /// Basic authentication isn't supported by WebDriver and is left out
+    async fn set_cookies(&self, session: &str, url: &str, options: &ScrapeOptions) -> Result<()> {
+
+        let Some(auth) = options.auth.as_ref().filter(|auth| Url::parse(url).is_ok_and(|url| auth.applies_to(&url))) else {
+            return Ok(());
+        };
+        let cookies = auth.cookies();
+        if cookies.is_empty() {
+            return Ok(());
+        }
+
+        self.command(Method::POST, &format!("session/{session}/url"), Some(json!({ "url": url }))).await?;
+        for (name, value) in cookies {
+            let cookie = json!({ "cookie": { "name": name, "value": value, "path": "/" } });
+            self.command(Method::POST, &format!("session/{session}/cookie"), Some(cookie)).await?;
+        }
+        Ok(())
+    }
+
+    /// Cookies of the session once the page is loaded, none when they can't be read
+    async fn cookies(&self, session: &str, url: &str) -> Vec<Cookie> {
+        let host = Url::parse(url).ok().and_then(|url| url.host_str().map(str::to_string)).unwrap_or_default();
+        let Ok(Value::Array(cookies)) = self.command(Method::GET, &format!("session/{session}/cookie"), None).await else {
+            return Vec::new();
+        };
+        cookies
+            .iter()
+            .filter_map(|cookie| Some(Cookie {
+                name: cookie["name"].as_str()?.to_string(),
+                value: cookie["value"].as_str()?.to_string(),
+                domain: cookie["domain"].as_str().map(|domain| domain.trim_start_matches('.')).unwrap_or(&host).to_string(),
+                host_only: !cookie["domain"].as_str().is_some_and(|domain| domain.starts_with('.')),
+                path: cookie["path"].as_str().unwrap_or("/").to_string(),
+                secure: cookie["secure"].as_bool().unwrap_or_default(),
+            }))
+            .collect()
+    }
+
+    async fn capture(&self, session: &str, url: &str, options: &ScrapeOptions) -> Result<WebDriverPage> {
+
+        let requested_url = url;
+        let started = Instant::now();
+        self.set_cookies(session, url, options).await?;
+        self.command(Method::POST, &format!("session/{session}/url"), Some(json!({ "url": url }))).await?;
+
+        let mut warnings = Vec::new();
+        if let Some(selector) = &options.wait_for
+            && !self.wait_for_element(session, selector).await {
+            warnings.push(Warning::with_url(WarningKind::SelectorNotFound, url, format!("{selector:?} never appeared, captured anyway")));
+        }
+        tokio::time::sleep(options.wait_after_load).await;
+
+        if options.comments {
+            // the promise returned by the script is awaited by the WebDriver server
+            let script = json!({ "script": format!("return {};", comments::EXPAND_SCRIPT), "args": [] });
+            if self.command(Method::POST, &format!("session/{session}/execute/sync"), Some(script)).await.is_err() {
+                warnings.push(Warning::with_url(WarningKind::SelectorNotFound, url, "comment threads couldn't be expanded"));
+            }
+        }
+
+        let as_string = |value: Value| value.as_str().unwrap_or_default().to_string();
+
+        let url = as_string(self.command(Method::GET, &format!("session/{session}/url"), None).await?);
+        let title = as_string(self.command(Method::GET, &format!("session/{session}/title"), None).await?);
+        let html = as_string(self.command(Method::GET, &format!("session/{session}/source"), None).await?);
+        let pdf = as_string(self.command(Method::POST, &format!("session/{session}/print"), Some(options.pdf.to_webdriver())).await?);
+        let pdf = base64::engine::general_purpose::STANDARD.decode(pdf)?;
+        let cookies = self.cookies(session, &url).await;
+        let script = json!({ "script": format!("return {};", timings::SCRIPT), "args": [] });
+        let timings = self.command(Method::POST, &format!("session/{session}/execute/sync"), Some(script)).await.ok()
+            .and_then(|value| value.as_str().and_then(Timings::from_script))
+            .unwrap_or_default();
+        let timings = Timings { render_ms: started.elapsed().as_millis() as u64, ..timings };
+
+        Ok(WebDriverPage { requested_url: requested_url.to_string(), url, title, html, pdf, warnings, cookies, timings })
+    }
+}
+
+impl PageRenderer for WebDriver {
+
+    fn render<'a>(&'a self, url: &'a str, options: &'a ScrapeOptions) -> BoxFuture<'a, Result<Arc<dyn RenderedPage>>> {
+        async move {
+            let host = Url::parse(url)?.host_str().unwrap_or_default().to_string();
+            tokio::time::sleep(self.options.rate_limit.reserve(RequestKind::Page, &host, options.domain_delay, options.domain_jitter)).await;
+
+            let session = self.new_session().await?;
+            let page = self.capture(&session, url, options).await;
+            // close the session even when the capture failed
+            self.command(Method::DELETE, &format!("session/{session}"), None).await?;
+
+            let page: Arc<dyn RenderedPage> = Arc::new(page?);
+            Ok(page)
+        }.boxed()
+    }
+}
+
+impl RenderedPage for WebDriverPage {
+
+    fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    fn requested_url(&self) -> Option<String> {
+        Some(self.requested_url.clone())
+    }
+
+    fn title(&self) -> anyhow::Result<String> {
+        Ok(self.title.clone())
+    }
+
+    fn html(&self) -> anyhow::Result<String> {
+        Ok(self.html.clone())
+    }
+
+    /// Printed with the options of the `WebDriver` while the session was alive
+    fn print_to_pdf(&self, _options: &PrintOptions) -> anyhow::Result<Vec<u8>> {
+        Ok(self.pdf.clone())
+    }
+
+    fn timings(&self) -> Timings {
+        self.timings.clone()
+    }
+
+    fn warnings(&self) -> Vec<Warning> {
+        self.warnings.clone()
+    }
+
+    fn cookies(&self) -> Vec<Cookie> {
+        self.cookies.clone()
+    }
+}
@@ -2,31 +2,317 @@ use std::{fs, path::{Path, PathBuf}};
 use pandoc;
 use time::OffsetDateTime;
 use thiserror::Error;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use regex::Regex;
 use headless_chrome;
 use anyhow;
 use futures::future;
 use serde_json;
-use serde::Serialize;
-use crate::images::{Images, ImagesError};
+use serde::{Serialize, Deserialize};
+use crate::images::{self, Images, ImagesError, SkippedImage};
+use crate::options::{ScrapeOptions, ContentGuard};
+use crate::progress::{Progress, ProgressEvent};
+use crate::{filename, slug};
+use crate::dom;
+use crate::comments;
+use crate::embeds::{self, CodeFile};
+use crate::svg;
+use crate::figures;
+use crate::text;
+use crate::language;
+use crate::warc::{self, Exchange, Journal};
+use crate::media::{self, MediaFile};
+use crate::assets::{self, Assets};
+use crate::single_file;
+use crate::sanitize;
+use crate::html_report::HtmlReport;
+use crate::snapshot::{CaptureSource, Snapshot};
+use crate::preview::{PreviewFile, PreviewImages};
+use crate::localize;
+use crate::robots::RobotsDirectives;
+use crate::metadata::Metadata;
+use crate::front_matter;
+use crate::citation;
+use crate::extract;
+use crate::diff;
+use crate::toc;
+use crate::stats::{self, Stats};
+use crate::checksums::FileManifest;
+use crate::obsidian::{self, ObsidianError, Vault};
+use crate::links::{self, Link};
+use crate::tables::{self, TableFile};
+use crate::structure::{self, Section};
+use crate::syndication::{self, PageFeed};
+use crate::markdown::{self, PandocOptions};
+use crate::renderer::RenderedPage;
+use crate::timings::Timings;
+use crate::layout::{self, Kind, Layout};
+use crate::pdf::{PdfError, PrintOptions};
+use crate::pdfa;
+use crate::http::{HttpClient, HttpError, NetworkMode};
+use crate::recording::Recording;
+use crate::warnings::{Warning, WarningKind, Warnings};
+
+/// What `write_to_disk_with_policy` does when the output path already exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputPolicy {
+    /// Fail, leaving the existing path untouched
+    #[default]
+    Error,
+    /// Delete the existing path first
+    Overwrite,
+    /// Write into the existing directory, replacing files of the same name
+    Merge,
+    /// Write next to it, in `<path>-<YYYYMMDD-HHMMSS>`
+    AppendTimestamp,
+}
+
+impl OutputPolicy {
+
+    /// Directory to write the capture in, created empty or, when merging, kept as is
+    pub fn prepare(&self, output_path: &Path) -> std::io::Result<PathBuf> {
+
+        let exists = output_path.is_file() || output_path.is_dir();
+        match self {
+            _ if !exists => {}
+            OutputPolicy::Error => return Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, "Output path already exists")),
+            OutputPolicy::Overwrite if output_path.is_dir() => fs::remove_dir_all(output_path)?,
+            OutputPolicy::Overwrite => fs::remove_file(output_path)?,
+            OutputPolicy::Merge if output_path.is_dir() => return Ok(output_path.to_path_buf()),
+            OutputPolicy::Merge => return Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, "Output path is a file, can't merge into it")),
+            OutputPolicy::AppendTimestamp => {
+                let now = OffsetDateTime::now_utc();
+                let mut timestamped = output_path.as_os_str().to_owned();
+                timestamped.push(format!(
+                    "-{:04}{:02}{:02}-{:02}{:02}{:02}",
+                    now.year(), u8::from(now.month()), now.day(), now.hour(), now.minute(), now.second()
+                ));
+                // create_dir fails if a capture of the same second is there
+                fs::create_dir(&timestamped)?;
+                return Ok(PathBuf::from(timestamped));
+            }
+        }
+        fs::create_dir(output_path)?;
+        Ok(output_path.to_path_buf())
+    }
+}
+
+bitflags::bitflags! {
+    /// Outputs written by `write_outputs`, the outputs asked for through `ScrapeOptions`
+    /// (docx, WARC, tables, ...) come on top of them
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Outputs: u8 {
+        /// The HTML and the archives of the page: its assets, WARC, MHTML and single file
+        const HTML = 1;
+        const PDF = 1 << 1;
+        /// The markdown, the comments, code blocks and thumbnail it links to
+        const MARKDOWN = 1 << 2;
+        /// Images, favicon, preview image and media files
+        const IMAGES = 1 << 3;
+        /// `informations.json`, `warnings.json`, `links.json` and the other JSON reports, the tables,
+        /// the recording, `changes.diff`, the citation files and `manifest.json`
+        const INFO = 1 << 4;
+        /// PNG of the browser viewport
+        const SCREENSHOT = 1 << 5;
+        /// Plain text, when `ScrapeOptions::plain_text` is set
+        const TEXT = 1 << 6;
+        /// Word document, when `ScrapeOptions::docx` is set
+        const DOCX = 1 << 7;
+    }
+}
+
+impl Default for Outputs {
+    /// Everything but the screenshot
+    fn default() -> Self {
+        Outputs::all().difference(Outputs::SCREENSHOT)
+    }
+}
+
+impl std::str::FromStr for Outputs {
+    type Err = WebPageError;
+
+    /// Comma-separated names: `html`, `pdf`, `md` or `markdown`, `images`, `info` or `json`, `screenshot`,
+    /// `text` or `txt`, `docx`
+    fn from_str(names: &str) -> Result<Self> {
+        names.split(',').map(str::trim).filter(|name| !name.is_empty()).try_fold(Outputs::empty(), |outputs, name| {
+            let output = match name.to_ascii_lowercase().as_str() {
+                "html" => Outputs::HTML,
+                "pdf" => Outputs::PDF,
+                "md" | "markdown" => Outputs::MARKDOWN,
+                "images" => Outputs::IMAGES,
+                "info" | "json" => Outputs::INFO,
+                "screenshot" => Outputs::SCREENSHOT,
+                "text" | "txt" => Outputs::TEXT,
+                "docx" => Outputs::DOCX,
+                _ => return Err(WebPageError::UnknownOutput(name.to_string())),
+            };
+            Ok(outputs | output)
+        })
+    }
+}
+
+/// What a `WebPage` is made of, for library users keeping the capture in memory
+pub struct WebPageParts {
+    pub url: String,
+    pub title: String,
+    pub file_stem: String,
+    /// HTML of the page, once unwanted elements are removed
+    pub html: String,
+    /// Markdown of the page, without front matter
+    pub markdown: String,
+    pub images: Images,
+    pub links: Vec<Link>,
+    pub info: InfoJson,
+    pub warnings: Vec<Warning>,
+}
 
 pub struct WebPage {
     pub url: String,
     pub title: String,
+    /// Title made safe for use in file and directory names
+    pub file_stem: String,
     html: String,
     images: Images,
     markdown: String,
-    tab: Arc<headless_chrome::Tab>,
+    /// Pandoc format of `markdown`, `gfm` unless asked otherwise
+    markdown_format: String,
+    /// Markdown of the comment thread, when asked for and found
+    comments: Option<String>,
+    /// Sources of the embedded code snippets
+    code: Vec<CodeFile>,
+    /// Anchors of the page as rendered
+    links: Vec<Link>,
+    /// Data tables as CSV, when asked for
+    tables: Vec<TableFile>,
+    /// Video and audio files, when asked for
+    media: Vec<MediaFile>,
+    /// Feeds the page links to, with their bytes when downloaded
+    feeds: Vec<(PageFeed, Option<Vec<u8>>)>,
+    /// Values of the fields asked for, when there are some
+    extracted: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Unified diff against an earlier capture, when it changed since
+    diff: Option<String>,
+    /// Stylesheets, scripts and fonts, when asked for
+    assets: Assets,
+    /// Save a Word document of the markdown along with it
+    docx: bool,
+    /// Sections of the page and their content, when asked for
+    structure: Option<Section>,
+    /// Word frequencies, headings, links and section lengths, when asked for
+    stats: Option<Stats>,
+    /// Where the documents go within the capture directory
+    layout: Layout,
+    /// Page layout of the PDF
+    print_options: PrintOptions,
+    /// Save the text of the page along with it
+    plain_text: bool,
+    /// Start the markdown file with YAML front matter
+    front_matter: bool,
+    /// The HTML with everything it needs inline, when asked for
+    single_file: Option<String>,
+    /// MHTML snapshot taken by the browser, when asked for
+    mhtml: Option<String>,
+    /// Thumbnail of the top of the page, referenced by the markdown, when asked for
+    thumbnail: Option<Vec<u8>>,
+    /// WARC file of the page and its subresources, when asked for
+    warc: Option<Vec<u8>>,
+    /// Parser recoveries of the captured HTML, when asked for
+    html_report: Option<HtmlReport>,
+    snapshot: Snapshot,
+    /// Favicon and preview image, saved at the root of the capture
+    previews: PreviewImages,
+    page: Arc<dyn RenderedPage>,
+    recording: Option<Recording>,
+    warnings: Warnings,
+    /// Told when the outputs are written
+    progress: Option<Progress>,
     info_json: InfoJson
 }
 
-#[derive(Serialize)]
+/// Content of `informations.json`. Fields may be added in any release, `schema_version` only
+/// changes when one is renamed, removed or changes meaning
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct InfoJson {
-    url: String,
-    title: String,
-    date: String,
-    nb_md_words: usize,
-    nb_images: usize,
+    /// `InfoJson::SCHEMA_VERSION` of the scraper that wrote the file, 0 for files older than it
+    #[serde(default)]
+    pub schema_version: u32,
+    /// URL of the page after redirections
+    pub url: String,
+    /// URL asked for, when the backend knows it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requested_url: Option<String>,
+    /// Same as `url`, spelled out for archive tools deduplicating on it
+    #[serde(default)]
+    pub final_url: String,
+    /// `rel="canonical"` link of the page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical_url: Option<String>,
+    /// HTTP status of the page response, when the backend can see it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_status: Option<u16>,
+    /// Title of the page, as the page gives it
+    pub title: String,
+    /// Name of the outputs, derived from the title
+    #[serde(default)]
+    pub file_stem: String,
+    pub date: String,
+    #[serde(default)]
+    pub summary: String,
+    pub nb_md_words: usize,
+    /// Characters of the text of the page, line breaks aside
+    #[serde(default)]
+    pub nb_chars: usize,
+    #[serde(default)]
+    pub nb_chars_no_spaces: usize,
+    /// Estimated from the words, or the characters for Chinese, Japanese and Korean
+    #[serde(default)]
+    pub reading_time_minutes: usize,
+    /// ISO 639-1 code, as declared by the page or else detected from its text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    pub nb_images: usize,
+    /// Images that couldn't be downloaded
+    #[serde(default)]
+    pub nb_failed_images: usize,
+    /// Images left out once the image budget was exhausted
+    #[serde(default)]
+    pub nb_skipped_images: usize,
+    #[serde(default)]
+    pub low_content: bool,
+    /// Description, authors, dates, ... from the meta tags and JSON-LD of the page
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
+    /// Robots directives of the page (`noindex`, `noarchive`, ...)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub robots: Vec<String>,
+    /// `live`, or the web archive the page was captured from
+    #[serde(default)]
+    pub capture_source: CaptureSource,
+    /// When the archive captured the page, for historical captures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_capture_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<PreviewFile>,
+    /// OpenGraph or Twitter card image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview_image: Option<PreviewFile>,
+    /// Load timings of the page, requests and bytes downloaded, duration of the scrape
+    #[serde(default)]
+    pub timings: Timings,
+    /// RSS, Atom and JSON feeds of the site, to follow it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub feeds: Vec<PageFeed>,
+    /// Whether the markdown changed since the earlier capture it was compared with, see `WebPage::diff`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changed: Option<bool>,
+    /// Entries added by tools building on the scraper, kept as is
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl InfoJson {
+    pub const SCHEMA_VERSION: u32 = 1;
 }
 
 #[derive(Error, Debug)]
@@ -41,53 +327,400 @@ pub enum WebPageError {
     TimeError(#[from] time::error::IndeterminateOffset),
     #[error("ImagesError: {0}")]
     ImagesError(#[from] ImagesError),
+    #[error("HttpError: {0}")]
+    HttpError(#[from] HttpError),
     #[error("AnyhowError: {0}")]
     AnyhowError(#[from] anyhow::Error),
     #[error("JSON conversion error: {0}")]
-    JsonConversionError(#[from] serde_json::Error)
+    JsonConversionError(#[from] serde_json::Error),
+    #[error("Insufficient content: {words} markdown words, expected at least {min}")]
+    InsufficientContent { words: usize, min: usize },
+    #[error("{url} asks not to be archived (noarchive)")]
+    NoArchive { url: String },
+    #[error("unknown output {0:?}, expected html, pdf, md, images, info or screenshot")]
+    UnknownOutput(String),
+    #[error("Obsidian error: {0}")]
+    ObsidianError(#[from] ObsidianError),
+    #[error("no markdown or text of an earlier capture in {0}")]
+    NoPreviousCapture(PathBuf),
+    #[error("PDF error: {0}")]
+    PdfError(#[from] PdfError),
 }
 
 pub type Result<T> = std::result::Result<T, WebPageError>;
 
+/// Average silent reading speeds, for `InfoJson::reading_time_minutes`
+const WORDS_PER_MINUTE: usize = 230;
+const CJK_CHARS_PER_MINUTE: usize = 500;
+
 impl WebPage {
 
-    pub async fn from_tab(tab: Arc<headless_chrome::Tab>) -> Result<Self> {
+    pub async fn from_tab(tab: Arc<headless_chrome::Tab>, options: &ScrapeOptions) -> Result<Self> {
+        Self::from_page(tab, options).await
+    }
+
+    pub async fn from_page(page: Arc<dyn RenderedPage>, options: &ScrapeOptions) -> Result<Self> {
 
+        let started = std::time::Instant::now();
         let today = OffsetDateTime::now_local()?.date().to_string();
 
-        let title = tab.get_title()?;
-        let url = tab.get_url();
-        let html = tab.get_content()?;
+        let title = page.title()?;
+        let url = page.url();
+        let rendered_html = page.html()?;
+
+        let metadata = Metadata::from_html(&rendered_html);
+        let declared_language = language::declared(&rendered_html);
+        let canonical_url = links::canonical(&rendered_html, &url);
+        let robots = RobotsDirectives::from_page(&rendered_html, &page.response_headers());
+        if options.honor_noarchive && robots.noarchive() {
+            return Err(WebPageError::NoArchive { url });
+        }
+        tracing::info!(url, title, "scraping");
+        if let Some(progress) = &options.progress {
+            progress.emit(ProgressEvent::Converting);
+        }
+
+        // the page as rendered, the exchanges of the client follow it in the WARC file
+        let page_exchange = options.warc.then(|| url::Url::parse(&url).ok()).flatten()
+            .map(|page_url| Exchange::page(&page_url, &rendered_html, &page.response_headers()));
+        let journal = page_exchange.is_some().then(Journal::default);
+        // before the scrape changes anything in the page
+        let mhtml = match options.mhtml {
+            true => page.capture_mhtml()?,
+            false => None,
+        };
+        let client = HttpClient::new(&options.network)?
+            .with_auth(options.auth.clone())
+            .with_concurrency(options.image_concurrency.unwrap_or(HttpClient::DEFAULT_CONCURRENCY))
+            .with_permits(options.download_permits.clone())
+            .with_retry(options.retry)
+            .with_preloaded(page.image_responses())
+            .with_referer(&url)
+            .with_cookies(page.cookies())
+            .with_journal(journal.clone())
+            .with_robots(options.robots.clone())
+            .with_rate_limit(options.rate_limit.clone(), options.request_delay, std::time::Duration::ZERO);
+        let warnings = Warnings::default();
+        warnings.extend(page.warnings());
+        if options.mhtml && mhtml.is_none() {
+            warnings.push(Warning::with_url(WarningKind::UnsupportedOutput, &url, "the browser backend can't take MHTML snapshots"));
+        }
+        let thumbnail = match options.screenshot_thumbnail.map(|width| page.capture_thumbnail(width)) {
+            Some(Ok(Some(thumbnail))) => Some(thumbnail),
+            Some(Ok(None)) => {
+                warnings.push(Warning::with_url(WarningKind::UnsupportedOutput, &url, "the browser backend can't take screenshots"));
+                None
+            }
+            Some(Err(e)) => {
+                warnings.push(Warning::with_url(WarningKind::UnsupportedOutput, &url, format!("the thumbnail couldn't be taken: {e}")));
+                None
+            }
+            None => None,
+        };
+
+        let snapshot = Snapshot::detect(&url, &page.response_headers());
+        let html_report = options.html_report.then(|| HtmlReport::new(&rendered_html, &page.response_headers()));
+
+        // from the whole page, the fields may be outside of the content
+        let extracted = (!options.extract.is_empty()).then(|| {
+            let (extracted, missing) = options.extract.extract(&rendered_html);
+            for name in missing {
+                warnings.push(Warning::with_url(WarningKind::SelectorNotFound, &url, format!("nothing matches the field {name:?}")));
+            }
+            extracted
+        });
+
+        let (html, unmatched) = dom::remove_elements(&rendered_html, &options.remove_selectors);
+        for selector in unmatched {
+            warnings.push(Warning::with_url(WarningKind::SelectorNotFound, &url, format!("nothing to remove matches {selector:?}")));
+        }
+        let (html, links) = match &options.select {
+            Some(selector) => match dom::select_elements(&html, selector) {
+                Some(selected) => {
+                    let links = links::extract(&selected, &url);
+                    (selected, links)
+                }
+                None => {
+                    warnings.push(Warning::with_url(WarningKind::SelectorNotFound, &url, format!("nothing matches {selector:?}, the whole page is captured")));
+                    (html, links::extract(&rendered_html, &url))
+                }
+            },
+            None => (html, links::extract(&rendered_html, &url)),
+        };
+
+        let (html, code) = if options.code_embeds {
+            embeds::extract(&html, &url, &client, &warnings).await
+        } else {
+            (html, Vec::new())
+        };
 
-        let md = WebPage::html2md(html.clone());
-        let images = Images::from(&html, &url);
+        let (html, media) = match &options.media {
+            Some(limits) => media::capture(&html, &url, &client, limits, &warnings).await,
+            None => (html, Vec::new()),
+        };
 
-        let (md, images) = future::join(md, images).await;
+        // saved as image files, pandoc would drop them from the markdown
+        let html = match options.no_images {
+            true => html,
+            false => images::inline_blobs(&svg::to_images(&html), |url| page.read_blob(url), &warnings),
+        };
+
+        // the built-in converter writes gfm, whatever the pandoc format
+        let pandoc = options.markdown_converter.uses_pandoc().then_some(&options.pandoc);
+        if pandoc.is_none() && !markdown::is_markdown(&options.pandoc.format) {
+            warnings.push(Warning::new(WarningKind::UnsupportedOutput, format!(
+                "pandoc isn't used, the page is converted to markdown instead of {}", options.pandoc.format
+            )));
+        }
+        let markdown_format = pandoc.map_or("gfm", |pandoc| pandoc.format.as_str()).to_string();
+        let (markdown_html, tables) = match options.tables {
+            true => tables::extract(&html),
+            false => (html.clone(), Vec::new()),
+        };
+        // org, AsciiDoc and the other writers have no image syntax `localize` knows,
+        // they are converted once the images are downloaded, from the localized HTML
+        let converts_localized = !markdown::is_markdown(&markdown_format);
+        let md = async {
+            match converts_localized {
+                true => Ok(String::new()),
+                false => WebPage::html2md(figures::for_markdown(&markdown_html), pandoc).await,
+            }
+        };
+        let images = async {
+            match options.no_images {
+                true => Ok(Images::default()),
+                false => Images::from_with_client(&html, &url, &client, options, &warnings).await,
+            }
+        };
+        let previews = async {
+            match options.no_images {
+                true => PreviewImages::default(),
+                false => PreviewImages::fetch(&rendered_html, &url, &client).await,
+            }
+        };
+
+        let feeds = async {
+            match options.no_feeds {
+                true => syndication::discover(&rendered_html, &url).into_iter().map(|feed| (feed, None)).collect(),
+                false => syndication::capture(&rendered_html, &url, &client, &warnings).await,
+            }
+        };
+
+        let (md, images, previews, feeds) = future::join4(md, images, previews, feeds).await;
 
         let md = md?; let images = images?;
 
+        // the saved copy references the downloaded images instead of the network
+        let local_paths = images.local_paths();
+        let md = match converts_localized {
+            true => WebPage::html2md(figures::for_markdown(&localize::html(&markdown_html, &url, &local_paths, &options.lazy_attributes)), pandoc).await?,
+            false => localize::markdown(&md, &url, &local_paths),
+        };
+        let html = localize::html(&html, &url, &local_paths, &options.lazy_attributes);
+        let (captured_html, assets) = match options.assets || options.single_file {
+            true => Assets::capture(&html, &url, &client, &local_paths, &warnings).await,
+            false => (html.clone(), Assets::default()),
+        };
+
+        let single_file = options.single_file.then(|| {
+            let mut files = images.local_files();
+            files.extend(assets.assets().iter().map(|asset| (
+                format!("{}/{}/{}", assets::DIRECTORY, asset.kind.directory(), filename::to_url_path(&asset.filename)),
+                asset.bytes.as_slice(),
+            )));
+            single_file::inline(&captured_html, &files)
+        });
+        // captured for the single file only, the saved HTML keeps the remote ones
+        let (html, assets) = match options.assets {
+            true => (captured_html, assets),
+            false => (html, Assets::default()),
+        };
+        let (html, single_file) = match options.sanitize {
+            true => (sanitize::html(&html, &url).0, single_file.map(|single_file| sanitize::html(&single_file, &url).0)),
+            false => (html, single_file),
+        };
+
+        let comments = match options.comments.then(|| comments::extract(&html)) {
+            Some(Some(thread)) => Some(WebPage::html2md(thread, pandoc).await?),
+            Some(None) => {
+                warnings.push(Warning::with_url(WarningKind::SelectorNotFound, &url, "no comment thread found"));
+                None
+            }
+            None => None,
+        };
+
+        let recording = matches!(options.network, NetworkMode::Record).then(|| {
+            let mut responses = client.recorded();
+            if let (Some(robots), Ok(page_url)) = (&options.robots, url::Url::parse(&url)) {
+                responses.extend(robots.recorded(&page_url));
+            }
+            Recording { url: url.clone(), title: title.clone(), html: rendered_html, headers: page.response_headers(), responses }
+        });
+
         let nb_md_words = md.split_whitespace().count();
         let nb_images = images.len();
+        tracing::debug!(url, words = nb_md_words, images = nb_images, image_failures = images.failures().len(), "converted");
+        let text = text::from_html(&html);
+        let nb_chars = text.chars().filter(|c| *c != '\n').count();
+        let nb_chars_no_spaces = text.chars().filter(|c| !c.is_whitespace()).count();
+        let language = declared_language.or_else(|| language::detect(&text).map(str::to_string));
+        let reading_time_minutes = WebPage::reading_time(nb_md_words, nb_chars_no_spaces, language.as_deref());
+
+        let low_content = options.is_low_content(nb_md_words);
+        if low_content && options.content_guard == ContentGuard::Fail {
+            return Err(WebPageError::InsufficientContent {
+                words: nb_md_words, min: options.min_words.unwrap_or_default()
+            });
+        }
+        if low_content {
+            warnings.push(Warning::with_url(WarningKind::LowContent, &url, format!(
+                "only {} markdown words, less than {}, it may be a consent or login wall",
+                nb_md_words, options.min_words.unwrap_or_default()
+            )));
+        }
        
+        let file_stem = match slug::file_stem(&title, options.transliteration) {
+            // nothing left of the title once made safe for file names
+            stem if stem.is_empty() => {
+                let reason = if title.trim().is_empty() { "the page has no title" } else { "nothing is left of the title once made safe for file names" };
+                warnings.push(Warning::new(WarningKind::FallbackTitle, format!("{reason}, files are named \"webpage\"")));
+                "webpage".to_string()
+            }
+            stem => stem,
+        };
+
+        let (requests, bytes) = client.traffic();
+        let timings = page.timings();
+        let timings = Timings {
+            requests: timings.requests + requests,
+            transferred_bytes: timings.transferred_bytes + bytes,
+            scrape_ms: timings.render_ms + started.elapsed().as_millis() as u64,
+            ..timings
+        };
+
+        let stats = options.stats.map(|top| Stats::new(&md, &text, &links, language.as_deref(), top));
+
         let info_json = InfoJson {
-            url: url.clone(), title: title.clone(), date: today.clone(), nb_md_words: nb_md_words, nb_images: nb_images,
+            schema_version: InfoJson::SCHEMA_VERSION,
+            url: url.clone(), requested_url: page.requested_url(), final_url: url.clone(), canonical_url, http_status: page.status(),
+            title: title.clone(), file_stem: file_stem.clone(), date: today.clone(), summary: WebPage::summary(&md),
+            nb_md_words, nb_chars, nb_chars_no_spaces, reading_time_minutes, language, nb_images, nb_failed_images: images.failures().len(), nb_skipped_images: images.skipped().len(), low_content, metadata, robots: robots.directives().to_vec(),
+            capture_source: snapshot.source, original_capture_date: snapshot.original_capture_date.clone(),
+            favicon: previews.favicon_file(), preview_image: previews.preview_file(),
+            feeds: feeds.iter().map(|(feed, _)| feed.clone()).collect(), timings, changed: None, extra: serde_json::Map::new(),
+        };
+
+        // left out of the word count and summary, it repeats the headings
+        let md = match options.toc {
+            Some(_) if !markdown::is_markdown(&markdown_format) => {
+                warnings.push(Warning::new(WarningKind::UnsupportedOutput, format!("the table of contents is only inserted in markdown, not {markdown_format}")));
+                md
+            }
+            Some(depth) => match toc::render(&md, depth) {
+                Some(contents) => toc::insert(&md, &contents),
+                None => md,
+            },
+            None => md,
         };
+        // above the table of contents, right under the title
+        let thumbnail = thumbnail.filter(|_| markdown::is_markdown(&markdown_format));
+        let md = match &thumbnail {
+            Some(_) => toc::insert(&md, &format!("![Screenshot of the page]({})\n", Self::thumbnail_file(&file_stem).replace(' ', "%20"))),
+            None => md,
+        };
+
+        let warc = page_exchange.zip(journal).map(|(page_exchange, journal)| {
+            warc::write(&page_exchange, &journal.exchanges(), &format!("{file_stem}.{}", warc::EXTENSION))
+        });
 
         Ok( Self {
-            url: url,
-            title: title,
+            url,
+            title,
+            file_stem,
             markdown: md,
-            images: images,
-            html: html,
-            tab: tab,
-            info_json: info_json
+            markdown_format,
+            comments,
+            code,
+            links,
+            tables,
+            media,
+            feeds,
+            extracted,
+            diff: None,
+            assets,
+            docx: options.docx,
+            structure: options.structure.then(|| structure::from_html(&html)),
+            stats,
+            layout: options.layout.clone(),
+            print_options: options.pdf.clone(),
+            plain_text: options.plain_text,
+            front_matter: options.front_matter,
+            mhtml,
+            thumbnail,
+            single_file,
+            warc,
+            html_report,
+            snapshot,
+            previews,
+            images,
+            html,
+            page,
+            recording,
+            warnings,
+            progress: options.progress.clone(),
+            info_json
         })
 
 
     }
 
-    async fn html2md(html: String) -> Result<String> {
-        
+    const SUMMARY_LEN: usize = 280;
+
+    /// First prose paragraph of the markdown, stripped of link and emphasis syntax
+    fn summary(markdown: &str) -> String {
+
+        let paragraph = markdown
+            .split("\n\n")
+            .map(|p| p.trim())
+            .find(|p| {
+                !p.is_empty()
+                    && !p.starts_with(['#', '!', '|', '-', '*', '>', '<', '['])
+                    && p.split_whitespace().count() > 5
+            })
+            .unwrap_or_default();
+
+        static LINK: OnceLock<Regex> = OnceLock::new();
+        let link = LINK.get_or_init(|| Regex::new(r"!?\[([^\]]*)\]\([^)]*\)").unwrap());
+
+        let mut summary = link
+            .replace_all(paragraph, "$1")
+            .replace(['*', '_', '`'], "")
+            .replace('\n', " ");
+
+        if summary.chars().count() > Self::SUMMARY_LEN {
+            summary = summary.chars().take(Self::SUMMARY_LEN).collect::<String>();
+            summary.push('…');
+        }
+        summary
+    }
+
+    /// Minutes it takes to read a page, rounded up
+    fn reading_time(words: usize, chars_no_spaces: usize, language: Option<&str>) -> usize {
+        // languages written without spaces are read by the character
+        match language {
+            Some("zh" | "ja" | "ko") => chars_no_spaces.div_ceil(CJK_CHARS_PER_MINUTE),
+            _ => words.div_ceil(WORDS_PER_MINUTE),
+        }
+    }
+
+    /// Markdown of `html`, or the format of `pandoc` when it does the conversion.
+    /// The built-in converter is used without `pandoc`
+    async fn html2md(html: String, pandoc: Option<&PandocOptions>) -> Result<String> {
+
+        let Some(options) = pandoc else {
+            return Ok(markdown::from_html(&html));
+        };
+
         let mut pandoc = pandoc::Pandoc::new();
 
         pandoc
@@ -96,67 +729,473 @@ impl WebPage {
                 pandoc::InputFormat::Html, 
                 vec![]
             )
-            .set_output(pandoc::OutputKind::Pipe)
-            .set_output_format(
-                pandoc::OutputFormat::Other("gfm-raw_html".to_string()), 
-                vec![]
-            );
+            .set_output(pandoc::OutputKind::Pipe);
+        options.apply(&mut pandoc);
 
         let res = pandoc.execute()?;
 
         match res {
-            pandoc::PandocOutput::ToBuffer(e) => return Ok(e),
-            _ => return Err(WebPageError::MarkdownConversionError(
+            pandoc::PandocOutput::ToBuffer(e) => Ok(e),
+            _ => Err(WebPageError::MarkdownConversionError(
                 pandoc::PandocError::PandocNotFound
             ))
         }
     }
 
+    /// Write the capture in the new directory `output_path`
     pub async fn write_to_disk(&self, output_path: &str) -> Result<()> {
+        self.write_to_disk_with_policy(output_path, OutputPolicy::Error).await?;
+        Ok(())
+    }
 
-        let output_path = PathBuf::from(output_path);
+    /// Write the capture in `output_path`, handling an existing one according to `policy`.
+    /// Returns the directory the capture was written in
+    pub async fn write_to_disk_with_policy(&self, output_path: &str, policy: OutputPolicy) -> Result<PathBuf> {
+        self.write_outputs(output_path, policy, Outputs::default()).await
+    }
 
-        if output_path.is_file() || output_path.is_dir() {
-            return Err(WebPageError::IO(
-                std::io::Error::new(std::io::ErrorKind::InvalidInput, "Output path already exists")
-            ));
-        }
+    /// Write the `outputs` of the capture in `output_path`, handling an existing one according to `policy`.
+    /// Returns the directory the capture was written in
+    pub async fn write_outputs(&self, output_path: &str, policy: OutputPolicy, outputs: Outputs) -> Result<PathBuf> {
 
-        std::fs::create_dir(&output_path)?;
+        let started = std::time::Instant::now();
+        let output_path = policy.prepare(Path::new(output_path))?;
+        if let Some(progress) = &self.progress {
+            progress.emit(ProgressEvent::Writing { directory: output_path.clone() });
+        }
+        // the documents and what they link to, at the root unless the layout says otherwise
+        let documents_path = self.layout_path(&output_path, None);
+        fs::create_dir_all(&documents_path)?;
 
-        let html_res = self.output_html(output_path.as_path());
-        let pdf_res = self.output_pdf(output_path.as_path());
-        let md_res = self.output_markdown(output_path.as_path()); 
-        let images_res = self.images.write_images_to_disk(output_path.as_path());
-        let info_json_res = self.output_info_json(output_path.as_path());
+        let html_res = async { if outputs.contains(Outputs::HTML) { self.output_html(output_path.as_path()).await } else { Ok(()) } };
+        let pdf_res = async { if outputs.contains(Outputs::PDF) { self.output_pdf(output_path.as_path()).await } else { Ok(()) } };
+        let md_res = async { if outputs.contains(Outputs::MARKDOWN) { self.output_markdown(output_path.as_path()).await } else { Ok(()) } };
+        let images_res = async { if outputs.contains(Outputs::IMAGES) { self.images.write_images_to_disk(documents_path.as_path()).await } else { Ok(()) } };
+        let info_json_res = async { if outputs.contains(Outputs::INFO) { self.output_info_json(output_path.as_path()).await } else { Ok(()) } };
+        let recording_res = async { if outputs.contains(Outputs::INFO) { self.output_recording(output_path.as_path()).await } else { Ok(()) } };
+        let warnings_res = async { if outputs.contains(Outputs::INFO) { self.output_warnings(output_path.as_path()).await } else { Ok(()) } };
+        let comments_res = async { if outputs.contains(Outputs::MARKDOWN) { self.output_comments(output_path.as_path()).await } else { Ok(()) } };
+        let url_map_res = async { if outputs.contains(Outputs::INFO) { self.output_url_map(output_path.as_path()).await } else { Ok(()) } };
+        let links_res = async { if outputs.contains(Outputs::INFO) { self.output_links(output_path.as_path()).await } else { Ok(()) } };
+        let code_res = async { if outputs.contains(Outputs::MARKDOWN) { self.output_code(documents_path.as_path()).await } else { Ok(()) } };
+        let media_res = async { if outputs.contains(Outputs::IMAGES) { self.output_media(documents_path.as_path()).await } else { Ok(()) } };
+        let tables_res = async { if outputs.contains(Outputs::INFO) { self.output_tables(documents_path.as_path()).await } else { Ok(()) } };
+        // next to the images its paths point to
+        let structure_res = async { if outputs.contains(Outputs::INFO) { self.output_structure(documents_path.as_path()).await } else { Ok(()) } };
+        let assets_res = async { if outputs.contains(Outputs::HTML) { self.assets.write_to_disk(documents_path.as_path()).await } else { Ok(()) } };
+        let html_report_res = async { if outputs.contains(Outputs::INFO) { self.output_html_report(output_path.as_path()).await } else { Ok(()) } };
+        let previews_res = async { if outputs.contains(Outputs::IMAGES) { self.output_previews(output_path.as_path()).await } else { Ok(()) } };
+        let citation_res = async { if outputs.contains(Outputs::INFO) { self.output_citation(output_path.as_path()).await } else { Ok(()) } };
+        let feeds_res = async { if outputs.contains(Outputs::INFO) { self.output_feeds(output_path.as_path()).await } else { Ok(()) } };
+        let stats_res = async { if outputs.contains(Outputs::INFO) { self.output_stats(output_path.as_path()).await } else { Ok(()) } };
+        let extracted_res = async { if outputs.contains(Outputs::INFO) { self.output_extracted(output_path.as_path()).await } else { Ok(()) } };
+        let diff_res = async { if outputs.contains(Outputs::INFO) { self.output_diff(output_path.as_path()).await } else { Ok(()) } };
+        let screenshot_res = async { if outputs.contains(Outputs::SCREENSHOT) { self.output_screenshot(output_path.as_path()).await } else { Ok(()) } };
+        let warc_res = async { if outputs.contains(Outputs::HTML) { self.output_warc(output_path.as_path()).await } else { Ok(()) } };
+        let mhtml_res = async { if outputs.contains(Outputs::HTML) { self.output_mhtml(output_path.as_path()).await } else { Ok(()) } };
+        let thumbnail_res = async { if outputs.contains(Outputs::MARKDOWN) { self.output_thumbnail(documents_path.as_path()).await } else { Ok(()) } };
+        let single_file_res = async { if outputs.contains(Outputs::HTML) { self.output_single_file(output_path.as_path()).await } else { Ok(()) } };
+        let text_res = async { if self.plain_text && outputs.contains(Outputs::TEXT) { self.output_text(output_path.as_path()).await } else { Ok(()) } };
 
         let (html_res, pdf_res, md_res, images_res, info_json_res) = future::join5(html_res, pdf_res, md_res, images_res, info_json_res).await;
 
-        html_res?; pdf_res?; md_res?; images_res?; info_json_res?;
+        html_res?; pdf_res?; md_res?; images_res?; info_json_res?; recording_res.await?; warnings_res.await?; comments_res.await?; url_map_res.await?; links_res.await?; code_res.await?; media_res.await?; tables_res.await?; structure_res.await?; assets_res.await?; html_report_res.await?; previews_res.await?; feeds_res.await?; stats_res.await?; extracted_res.await?; diff_res.await?; citation_res.await?; text_res.await?; warc_res.await?; mhtml_res.await?; thumbnail_res.await?; single_file_res.await?; screenshot_res.await?;
 
-        Ok(())
+        // made from the markdown and images once they're written
+        if outputs.contains(Outputs::DOCX) {
+            self.output_docx(output_path.as_path(), documents_path.as_path()).await?;
+        }
+        // of everything in the directory, once it's all there
+        if outputs.contains(Outputs::INFO) {
+            FileManifest::write(output_path.as_path())?;
+        }
+        tracing::info!(url = self.url, directory = %output_path.display(), elapsed_ms = started.elapsed().as_millis() as u64, "outputs written");
+        if let Some(progress) = &self.progress {
+            progress.emit(ProgressEvent::Written { directory: output_path.clone() });
+        }
+
+        Ok(output_path)
+    }
+
+    /// Compare the capture with the earlier one of the page in `previous_directory`, its markdown
+    /// or else its text: sets `changed` in `informations.json` and keeps the unified diff for
+    /// `changes.diff`. Returns the diff, `None` when nothing changed
+    pub fn diff(&mut self, previous_directory: &Path) -> Result<Option<&str>> {
+
+        let previous_info = fs::read_to_string(previous_directory.join("informations.json")).ok()
+            .and_then(|info| serde_json::from_str::<InfoJson>(&info).ok());
+        let stem = previous_info.as_ref().map_or(self.file_stem.as_str(), |info| info.file_stem.as_str());
+        let stem = if stem.is_empty() { self.file_stem.as_str() } else { stem };
+        let extension = markdown::file_extension(&self.markdown_format);
+
+        let (previous, current, file_name) = match diff::find(previous_directory, &format!("{stem}.{extension}"))? {
+            Some(path) => {
+                let previous = fs::read_to_string(&path)?;
+                (front_matter::strip(&previous, &self.markdown_format).to_string(), self.markdown.clone(), format!("{}.{extension}", self.file_stem))
+            }
+            None => match diff::find(previous_directory, &format!("{stem}.txt"))? {
+                Some(path) => (fs::read_to_string(&path)?, text::from_html(&self.html), format!("{}.txt", self.file_stem)),
+                None => return Err(WebPageError::NoPreviousCapture(previous_directory.to_path_buf())),
+            },
+        };
+
+        let previous_date = previous_info.map_or_else(|| "previous".to_string(), |info| info.date);
+        let current_date = if previous_date == self.info_json.date { "current" } else { self.info_json.date.as_str() };
+        self.diff = diff::unified(&previous, &current, &format!("{previous_date}/{file_name}"), &format!("{current_date}/{file_name}"));
+        self.info_json.changed = Some(self.diff.is_some());
+        Ok(self.diff.as_deref())
+    }
+
+    /// Write the capture as a note of `vault` instead of a capture directory: front matter
+    /// tagged with the keywords of the page and the tags of the vault, images embedded from
+    /// the attachment folder. An existing note is replaced when `overwrite`, returns the path of the note
+    pub fn export_to_obsidian(&self, vault: &Vault, overwrite: bool) -> Result<PathBuf> {
+
+        let note = vault.note_path(&self.file_stem);
+        if note.exists() && !overwrite {
+            return Err(ObsidianError::NoteExists(note).into());
+        }
+
+        // named after the note, attachment folders are shared by the whole vault
+        let directory = vault.attachment_directory();
+        let mut attachments = std::collections::HashMap::new();
+        for (path, bytes) in self.images.local_files() {
+            let name = path.rsplit('/').next().unwrap_or_default();
+            let name = format!("{} - {}", self.file_stem, percent_encoding::percent_decode_str(name).decode_utf8_lossy());
+            fs::create_dir_all(&directory)?;
+            fs::write(directory.join(&name), bytes)?;
+            attachments.insert(path, name);
+        }
+
+        let mut info = self.info_json.clone();
+        let mut tags = Vec::new();
+        for tag in info.metadata.keywords.iter().chain(vault.tags()).filter_map(|tag| obsidian::tag(tag)) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        info.metadata.keywords = tags;
+
+        fs::create_dir_all(note.parent().expect("notes are within the vault"))?;
+        fs::write(&note, front_matter::render(&info) + &obsidian::embed_images(&self.markdown, &attachments))?;
+        Ok(note)
+    }
+
+    /// Path of a document according to the layout, or with `None` of the directory the documents share
+    fn layout_path(&self, output_path: &Path, document: Option<(Kind, &str)>) -> PathBuf {
+        let domain = url::Url::parse(&self.url).ok()
+            .and_then(|url| url.host_str().map(|host| host.trim_start_matches("www.").to_string()))
+            .unwrap_or_else(|| "local".to_string());
+        let fields = layout::Fields { domain: &domain, date: &self.info_json.date, slug: &self.file_stem };
+        output_path.join(match document {
+            Some((kind, extension)) => self.layout.file(&fields, kind, extension),
+            None => self.layout.directory(&fields),
+        })
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Markdown of the page, without front matter
+    pub fn markdown(&self) -> &str {
+        &self.markdown
+    }
+
+    /// Downloaded images, those that failed or were skipped included
+    pub fn images(&self) -> &Images {
+        &self.images
+    }
+
+    /// What is written to `informations.json`
+    pub fn info(&self) -> &InfoJson {
+        &self.info_json
+    }
+
+    /// Add `key` to the `extra` entries of `informations.json`, replacing any of the same name
+    pub fn set_extra(&mut self, key: impl Into<String>, value: serde_json::Value) {
+        self.info_json.extra.insert(key.into(), value);
+    }
+
+    /// The capture without writing any file, the browser page it was made from is released
+    pub fn into_parts(self) -> WebPageParts {
+        WebPageParts {
+            warnings: self.warnings.to_vec(),
+            url: self.url,
+            title: self.title,
+            file_stem: self.file_stem,
+            html: self.html,
+            markdown: self.markdown,
+            images: self.images,
+            links: self.links,
+            info: self.info_json,
+        }
+    }
+
+    /// HTML of the page, once unwanted elements are removed
+    pub fn html(&self) -> &str {
+        &self.html
+    }
+
+    /// Statistics of the page, also written to `stats.json`, when `ScrapeOptions::stats` is set
+    pub fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+
+    /// Links of the page, also written to `links.json`
+    pub fn links(&self) -> &[Link] {
+        &self.links
+    }
+
+    /// Values of the fields of `ScrapeOptions::extract`, also written to `extracted.json`
+    pub fn extracted(&self) -> Option<&serde_json::Map<String, serde_json::Value>> {
+        self.extracted.as_ref()
+    }
+
+    /// Description, authors, dates, ... the page gives in its meta tags and JSON-LD
+    pub fn metadata(&self) -> &Metadata {
+        &self.info_json.metadata
+    }
+
+    /// Whether the extracted markdown fell below the configured word threshold
+    pub fn is_low_content(&self) -> bool {
+        self.info_json.low_content
+    }
+
+    /// Images of the page that couldn't be downloaded, with the error of each
+    pub fn image_failures(&self) -> &[SkippedImage] {
+        self.images.failures()
+    }
+
+    /// Number of images left out once the image budget was exhausted
+    pub fn nb_skipped_images(&self) -> usize {
+        self.images.skipped().len()
+    }
+
+    /// Everything that silently degraded the capture, also written to `warnings.json`
+    pub fn warnings(&self) -> Vec<Warning> {
+        self.warnings.to_vec()
     }
 
     pub async fn output_pdf(&self, output_path: &Path) -> Result<()> {
-        let output_path = output_path.join(format!("{}.pdf", self.title));
-        let pdf = self.tab.print_to_pdf(None)?;
+        if !self.page.supports_pdf() {
+            return Ok(());
+        }
+        let output_path = self.layout_path(output_path, Some((Kind::Pdf, "pdf")));
+        let pdf = self.print_options.post_process(self.page.print_to_pdf(&self.print_options)?);
+        let mut pdf = self.snapshot.tag_pdf(pdf);
+        if self.print_options.pdfa {
+            pdf = pdfa::convert(&pdf, &self.title, &self.url, OffsetDateTime::now_utc())?;
+        }
         std::fs::write(output_path, pdf)?;
         Ok(())
     }
 
+    /// Write the MHTML snapshot of the page, taken when `ScrapeOptions::mhtml` is set, to `<file stem>.mhtml`
+    pub async fn output_mhtml(&self, output_path: &Path) -> Result<()> {
+        if let Some(mhtml) = &self.mhtml {
+            fs::write(output_path.join(format!("{}.mhtml", self.file_stem)), mhtml)?;
+        }
+        Ok(())
+    }
+
+    /// File name of the thumbnail of the page
+    fn thumbnail_file(file_stem: &str) -> String {
+        format!("{file_stem}.thumbnail.jpg")
+    }
+
+    /// Write the thumbnail of the page, taken when `ScrapeOptions::screenshot_thumbnail` is set,
+    /// next to the markdown referencing it
+    pub async fn output_thumbnail(&self, documents_path: &Path) -> Result<()> {
+        if let Some(thumbnail) = &self.thumbnail {
+            fs::write(documents_path.join(Self::thumbnail_file(&self.file_stem)), thumbnail)?;
+        }
+        Ok(())
+    }
+
+    /// Write the text of the page, without any markup, where the layout puts it, `<file stem>.txt` by default
+    pub async fn output_text(&self, output_path: &Path) -> Result<()> {
+        let output_path = self.layout_path(output_path, Some((Kind::Text, "txt")));
+        fs::write(output_path, text::from_html(&self.html))?;
+        Ok(())
+    }
+
+    async fn output_screenshot(&self, output_path: &Path) -> Result<()> {
+        if let Some(png) = self.page.capture_screenshot()? {
+            fs::write(output_path.join(format!("{}.png", self.file_stem)), png)?;
+        }
+        Ok(())
+    }
+
     async fn output_html(&self, output_path: &Path) -> Result<()> {
-        let html_path = output_path.join(format!("{}.html", self.title));
+        let html_path = self.layout_path(output_path, Some((Kind::Html, "html")));
         fs::write(html_path, &self.html)?;
         Ok(())
     }
 
     async fn output_markdown(&self, output_path: &Path) -> Result<()> {
-        let output_path = output_path.join(format!("{}.md", self.title));
-        fs::write(output_path, &self.markdown)?;
+        let output_path = self.layout_path(output_path, Some((Kind::Markdown, markdown::file_extension(&self.markdown_format))));
+        match self.front_matter {
+            true => fs::write(output_path, front_matter::render_for(&self.info_json, &self.markdown_format) + &self.markdown)?,
+            false => fs::write(output_path, &self.markdown)?,
+        }
         //println!("Saved markdown to {}", path.display());
         Ok(())
     }
      
+    async fn output_docx(&self, output_path: &Path, documents_path: &Path) -> Result<()> {
+        if !self.docx {
+            return Ok(());
+        }
+        let mut pandoc = pandoc::Pandoc::new();
+        pandoc
+            // the markdown without its front matter, unknown to some readers
+            .set_input(pandoc::InputKind::Pipe(self.markdown.clone()))
+            .set_input_format(pandoc::InputFormat::Other(self.markdown_format.clone()), vec![])
+            // images are referenced relatively to the markdown
+            .add_option(pandoc::PandocOption::ResourcePath(vec![documents_path.to_path_buf()]))
+            .set_output(pandoc::OutputKind::File(self.layout_path(output_path, Some((Kind::Docx, "docx")))))
+            .set_output_format(pandoc::OutputFormat::Docx, vec![]);
+        pandoc.execute()?;
+        Ok(())
+    }
+
+    async fn output_single_file(&self, output_path: &Path) -> Result<()> {
+        if let Some(html) = &self.single_file {
+            fs::write(output_path.join(format!("{}.{}", self.file_stem, single_file::EXTENSION)), html)?;
+        }
+        Ok(())
+    }
+
+    async fn output_warc(&self, output_path: &Path) -> Result<()> {
+        if let Some(warc) = &self.warc {
+            fs::write(output_path.join(format!("{}.{}", self.file_stem, warc::EXTENSION)), warc)?;
+        }
+        Ok(())
+    }
+
+    async fn output_comments(&self, output_path: &Path) -> Result<()> {
+        if let Some(comments) = &self.comments {
+            fs::write(output_path.join("comments.md"), comments)?;
+        }
+        Ok(())
+    }
+
+    async fn output_recording(&self, output_path: &Path) -> Result<()> {
+        if let Some(recording) = &self.recording {
+            let json = serde_json::to_string(recording)?;
+            fs::write(output_path.join(Recording::FILENAME), json)?;
+        }
+        Ok(())
+    }
+
+    async fn output_warnings(&self, output_path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.warnings.to_vec())?;
+        fs::write(output_path.join(Warnings::FILENAME), json)?;
+        Ok(())
+    }
+
+    async fn output_code(&self, output_path: &Path) -> Result<()> {
+        if self.code.is_empty() {
+            return Ok(());
+        }
+        let directory = output_path.join(embeds::DIRECTORY);
+        fs::create_dir_all(&directory)?;
+        for file in &self.code {
+            fs::write(directory.join(&file.filename), &file.source)?;
+        }
+        Ok(())
+    }
+
+    async fn output_tables(&self, output_path: &Path) -> Result<()> {
+        if self.tables.is_empty() {
+            return Ok(());
+        }
+        let directory = output_path.join(tables::DIRECTORY);
+        fs::create_dir_all(&directory)?;
+        for table in &self.tables {
+            fs::write(directory.join(&table.filename), &table.csv)?;
+        }
+        Ok(())
+    }
+
+    async fn output_structure(&self, output_path: &Path) -> Result<()> {
+        if let Some(structure) = &self.structure {
+            fs::write(output_path.join(structure::FILENAME), serde_json::to_string_pretty(structure)?)?;
+        }
+        Ok(())
+    }
+
+    async fn output_stats(&self, output_path: &Path) -> Result<()> {
+        if let Some(stats) = &self.stats {
+            fs::write(output_path.join(stats::FILENAME), serde_json::to_string_pretty(stats)?)?;
+        }
+        Ok(())
+    }
+
+    async fn output_media(&self, output_path: &Path) -> Result<()> {
+        if self.media.is_empty() {
+            return Ok(());
+        }
+        let directory = output_path.join(media::DIRECTORY);
+        fs::create_dir_all(&directory)?;
+        for file in &self.media {
+            fs::write(directory.join(&file.filename), &file.bytes)?;
+        }
+        Ok(())
+    }
+
+    async fn output_feeds(&self, output_path: &Path) -> Result<()> {
+        for (feed, bytes) in &self.feeds {
+            if let (Some(path), Some(bytes)) = (&feed.path, bytes) {
+                let path = output_path.join(path);
+                fs::create_dir_all(path.parent().unwrap_or(output_path))?;
+                fs::write(path, bytes)?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn output_diff(&self, output_path: &Path) -> Result<()> {
+        if let Some(diff) = &self.diff {
+            fs::write(output_path.join(diff::FILENAME), diff)?;
+        }
+        Ok(())
+    }
+
+    async fn output_extracted(&self, output_path: &Path) -> Result<()> {
+        if let Some(extracted) = &self.extracted {
+            let json = serde_json::to_string_pretty(extracted)?;
+            fs::write(output_path.join(extract::FILENAME), json)?;
+        }
+        Ok(())
+    }
+
+    async fn output_previews(&self, output_path: &Path) -> Result<()> {
+        self.previews.write_to_disk(output_path)?;
+        Ok(())
+    }
+
+    async fn output_html_report(&self, output_path: &Path) -> Result<()> {
+        if let Some(report) = &self.html_report {
+            let json = serde_json::to_string_pretty(report)?;
+            fs::write(output_path.join(HtmlReport::FILENAME), json)?;
+        }
+        Ok(())
+    }
+
+    async fn output_url_map(&self, output_path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.images.url_map())?;
+        fs::write(output_path.join(Images::URL_MAP_FILENAME), json)?;
+        Ok(())
+    }
+
+    async fn output_links(&self, output_path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.links)?;
+        fs::write(output_path.join(links::FILENAME), json)?;
+        Ok(())
+    }
+
     async fn output_info_json(&self, output_path: &Path) -> Result<()> {
         let output_path = output_path.join("informations.json");
         let json = serde_json::to_string_pretty(&self.info_json)?;
@@ -164,30 +1203,219 @@ impl WebPage {
         Ok(())
     }
 
+    async fn output_citation(&self, output_path: &Path) -> Result<()> {
+        fs::write(output_path.join(citation::BIBTEX_FILENAME), citation::bibtex(&self.info_json))?;
+        let csl = serde_json::to_string_pretty(&citation::csl_json(&self.info_json))?;
+        fs::write(output_path.join(citation::CSL_FILENAME), csl)?;
+        Ok(())
+    }
+
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use crate::testing::ARTICLE_HTML;
+
     #[tokio::test]
-    async fn test_html_epfl() {
+    #[ignore = "needs pandoc"]
+    async fn test_html_article() {
         
-        let html = std::fs::read_to_string("test/htmls/EPFL.html").unwrap();
-        let md = WebPage::html2md(html).await.unwrap();
+        let md = WebPage::html2md(ARTICLE_HTML.to_string(), Some(&PandocOptions::default())).await.unwrap();
         //let md = WebPage::html_to_simple_markdown(&html);
-        std::fs::write("test/test_markdown/markdown_epfl.md", md).unwrap();
+        assert!(md.contains("# Fixture article"));
+        assert!(md.contains("![A red square](/images/red.png)"));
         
     }
 
+    #[test]
+    fn output_policies() {
+        let root = tempfile::tempdir().unwrap();
+        let capture = root.path().join("capture");
+        assert_eq!(OutputPolicy::Error.prepare(&capture).unwrap(), capture);
+        fs::write(capture.join("page.md"), "# Page").unwrap();
+
+        assert_eq!(OutputPolicy::Error.prepare(&capture).unwrap_err().kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(OutputPolicy::Merge.prepare(&capture).unwrap(), capture);
+        assert!(capture.join("page.md").exists());
+
+        let timestamped = OutputPolicy::AppendTimestamp.prepare(&capture).unwrap();
+        assert!(timestamped.file_name().unwrap().to_string_lossy().starts_with("capture-"));
+        assert!(capture.join("page.md").exists());
+
+        assert_eq!(OutputPolicy::Overwrite.prepare(&capture).unwrap(), capture);
+        assert!(!capture.join("page.md").exists());
+    }
+
     #[tokio::test]
-    async fn test_html_ecal() {
-        
-        let html = std::fs::read_to_string("test/htmls/100 BESTE PLAKATE 24, 17.12.2025–15.01.2026, Galerie l'elac, ECAL - ECAL.html").unwrap();
-        let md = WebPage::html2md(html).await.unwrap();
-        //let md = WebPage::html_to_simple_markdown(&html);
-        std::fs::write("test/test_markdown/markdown_ecal.md", md).unwrap();
-        
+    async fn parts_of_a_capture() {
+        let server = crate::testing::FixtureServer::with_article().unwrap();
+        let options = ScrapeOptions { markdown_converter: crate::markdown::MarkdownConverter::Builtin, ..Default::default() };
+        let fetcher = crate::fetch::StaticFetcher::with_options(options.clone()).unwrap();
+        let page = crate::renderer::PageRenderer::render(&fetcher, &server.url("/article.html"), &options).await.unwrap();
+        let webpage = WebPage::from_page(page, &options).await.unwrap();
+
+        assert_eq!(webpage.title(), "Fixture article");
+        assert!(webpage.markdown().contains("# Fixture article"));
+        assert_eq!(webpage.info().title, "Fixture article");
+        assert_eq!(webpage.info().language.as_deref(), Some("en"));
+        assert_eq!(webpage.info().http_status, Some(200));
+        assert_eq!(webpage.info().requested_url.as_deref(), Some(webpage.info().final_url.as_str()));
+        assert!(webpage.info().nb_chars > webpage.info().nb_chars_no_spaces);
+        // the page, its images and the favicon fallback
+        let timings = &webpage.info().timings;
+        assert!(timings.requests > 1 + webpage.info().nb_images && timings.transferred_bytes > 0);
+        assert!(timings.navigation_ms.is_some() && timings.scrape_ms >= timings.render_ms);
+        let parts = webpage.into_parts();
+        assert_eq!(parts.images.len(), parts.info.nb_images);
+    }
+
+    #[tokio::test]
+    async fn exports_to_an_obsidian_vault() {
+        let server = crate::testing::FixtureServer::with_article().unwrap();
+        let options = ScrapeOptions { markdown_converter: crate::markdown::MarkdownConverter::Builtin, ..Default::default() };
+        let fetcher = crate::fetch::StaticFetcher::with_options(options.clone()).unwrap();
+        let page = crate::renderer::PageRenderer::render(&fetcher, &server.url("/article.html"), &options).await.unwrap();
+        let webpage = WebPage::from_page(page, &options).await.unwrap();
+
+        let root = tempfile::tempdir().unwrap();
+        let vault = Vault::open(root.path()).unwrap().with_folder("Clippings").with_tags(vec!["to read".to_string()]);
+        let note = webpage.export_to_obsidian(&vault, false).unwrap();
+
+        assert_eq!(note, root.path().join("Clippings/Fixture article.md"));
+        let markdown = fs::read_to_string(&note).unwrap();
+        assert!(markdown.starts_with("---
+title: \"Fixture article\"\n"));
+        assert!(markdown.contains("tags: [\"to-read\"]\n"));
+        assert!(markdown.contains("![[Fixture article - red.png|A red square]]"));
+        assert!(root.path().join("Fixture article - red.png").is_file());
+        assert!(matches!(webpage.export_to_obsidian(&vault, false), Err(WebPageError::ObsidianError(ObsidianError::NoteExists(_)))));
+    }
+
+    #[tokio::test]
+    async fn compares_with_an_earlier_capture() {
+        let server = crate::testing::FixtureServer::with_article().unwrap();
+        let options = ScrapeOptions { markdown_converter: crate::markdown::MarkdownConverter::Builtin, front_matter: true, ..Default::default() };
+        let fetcher = crate::fetch::StaticFetcher::with_options(options.clone()).unwrap();
+        let page = crate::renderer::PageRenderer::render(&fetcher, &server.url("/article.html"), &options).await.unwrap();
+        let mut webpage = WebPage::from_page(page, &options).await.unwrap();
+
+        let previous = tempfile::tempdir().unwrap();
+        let previous = webpage.write_to_disk_with_policy(&previous.path().to_string_lossy(), OutputPolicy::Overwrite).await.unwrap();
+        assert_eq!(webpage.diff(&previous).unwrap(), None);
+        assert_eq!(webpage.info().changed, Some(false));
+
+        let markdown = previous.join("Fixture article.md");
+        fs::write(&markdown, fs::read_to_string(&markdown).unwrap() + "\nA paragraph since removed.\n").unwrap();
+        let date = webpage.info().date.clone();
+        let diff = webpage.diff(&previous).unwrap().unwrap();
+        assert!(diff.starts_with(&format!("--- {date}/Fixture article.md\n+++ current/Fixture article.md\n")));
+        assert!(diff.contains("\n-A paragraph since removed.\n"));
+        assert_eq!(webpage.info().changed, Some(true));
+
+        assert!(matches!(webpage.diff(Path::new("/nonexistent")), Err(WebPageError::IO(_))));
+    }
+
+    #[tokio::test]
+    async fn writes_only_the_outputs_selected() {
+        let server = crate::testing::FixtureServer::with_article().unwrap();
+        let options = ScrapeOptions { markdown_converter: crate::markdown::MarkdownConverter::Builtin, plain_text: true, ..Default::default() };
+        let fetcher = crate::fetch::StaticFetcher::with_options(options.clone()).unwrap();
+        let page = crate::renderer::PageRenderer::render(&fetcher, &server.url("/article.html"), &options).await.unwrap();
+        let webpage = WebPage::from_page(page, &options).await.unwrap();
+
+        let output = tempfile::tempdir().unwrap();
+        let output = webpage.write_outputs(&output.path().to_string_lossy(), OutputPolicy::Overwrite, Outputs::MARKDOWN).await.unwrap();
+        let files = fs::read_dir(&output).unwrap().map(|entry| entry.unwrap().file_name()).collect::<Vec<_>>();
+        assert_eq!(files, ["Fixture article.md"]);
+        assert_eq!("txt, docx".parse::<Outputs>().unwrap(), Outputs::TEXT | Outputs::DOCX);
+    }
+
+    #[tokio::test]
+    async fn writes_the_text_where_the_layout_says() {
+        let server = crate::testing::FixtureServer::with_article().unwrap();
+        let layout = crate::layout::Layout::new("{slug}/{kind}").unwrap();
+        let options = ScrapeOptions { markdown_converter: crate::markdown::MarkdownConverter::Builtin, plain_text: true, layout, ..Default::default() };
+        let fetcher = crate::fetch::StaticFetcher::with_options(options.clone()).unwrap();
+        let page = crate::renderer::PageRenderer::render(&fetcher, &server.url("/article.html"), &options).await.unwrap();
+        let webpage = WebPage::from_page(page, &options).await.unwrap();
+
+        let output = tempfile::tempdir().unwrap();
+        let output = webpage.write_to_disk_with_policy(&output.path().to_string_lossy(), OutputPolicy::Overwrite).await.unwrap();
+        assert!(output.join("Fixture article/text.txt").is_file());
+        assert!(!output.join("Fixture article.txt").exists());
+    }
+
+    /// Page of the static fetcher, able to take thumbnails, or failing to when `.1` is set
+    struct ThumbnailPage(Arc<dyn RenderedPage>, bool);
+
+    impl RenderedPage for ThumbnailPage {
+        fn url(&self) -> String { self.0.url() }
+        fn title(&self) -> anyhow::Result<String> { self.0.title() }
+        fn html(&self) -> anyhow::Result<String> { self.0.html() }
+        fn supports_pdf(&self) -> bool { self.0.supports_pdf() }
+        fn print_to_pdf(&self, options: &PrintOptions) -> anyhow::Result<Vec<u8>> { self.0.print_to_pdf(options) }
+        fn capture_thumbnail(&self, _width: u32) -> anyhow::Result<Option<Vec<u8>>> {
+            match self.1 {
+                true => anyhow::bail!("the tab crashed"),
+                false => Ok(Some(b"jpeg".to_vec())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn references_the_thumbnail_at_the_top() {
+        let server = crate::testing::FixtureServer::with_article().unwrap();
+        let options = ScrapeOptions { markdown_converter: crate::markdown::MarkdownConverter::Builtin, screenshot_thumbnail: Some(320), ..Default::default() };
+        let fetcher = crate::fetch::StaticFetcher::with_options(options.clone()).unwrap();
+        let page = crate::renderer::PageRenderer::render(&fetcher, &server.url("/article.html"), &options).await.unwrap();
+
+        let without = WebPage::from_page(page.clone(), &options).await.unwrap();
+        assert!(!without.markdown.contains("Screenshot"));
+        assert!(without.warnings.to_vec().iter().any(|warning| warning.kind == WarningKind::UnsupportedOutput));
+
+        let failed = WebPage::from_page(Arc::new(ThumbnailPage(page.clone(), true)), &options).await.unwrap();
+        assert!(!failed.markdown.contains("Screenshot"));
+        assert!(failed.warnings.to_vec().iter().any(|warning| warning.message.contains("the tab crashed")));
+
+        let webpage = WebPage::from_page(Arc::new(ThumbnailPage(page, false)), &options).await.unwrap();
+        assert!(webpage.markdown.starts_with("![Screenshot of the page](Fixture%20article.thumbnail.jpg)\n\n"));
+        let output = tempfile::tempdir().unwrap();
+        let output = webpage.write_to_disk_with_policy(&output.path().to_string_lossy(), OutputPolicy::Overwrite).await.unwrap();
+        assert_eq!(fs::read(output.join("Fixture article.thumbnail.jpg")).unwrap(), b"jpeg");
+    }
+
+    #[test]
+    fn versions_the_info_json() {
+        let old: InfoJson = serde_json::from_str(r#"{"url": "https://example.com/", "title": "Page", "date": "2024-05-01", "nb_md_words": 12, "nb_images": 0}"#).unwrap();
+        assert_eq!((old.schema_version, old.extra.len()), (0, 0));
+
+        let mut info = InfoJson { schema_version: InfoJson::SCHEMA_VERSION, ..old };
+        info.extra.insert("reviewed_by".to_string(), serde_json::json!("archivist"));
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!((&json["schema_version"], &json["extra"]["reviewed_by"]), (&serde_json::json!(1), &serde_json::json!("archivist")));
+        assert_eq!(serde_json::from_value::<InfoJson>(json).unwrap().extra, info.extra);
+    }
+
+    #[test]
+    fn estimates_reading_time() {
+        assert_eq!(WebPage::reading_time(0, 0, None), 0);
+        assert_eq!(WebPage::reading_time(231, 1200, Some("en")), 2);
+        assert_eq!(WebPage::reading_time(3, 1000, Some("ja")), 2);
+    }
+
+    #[test]
+    fn parses_outputs() {
+        assert_eq!("md, PDF".parse::<Outputs>().unwrap(), Outputs::MARKDOWN | Outputs::PDF);
+        assert!(!Outputs::default().contains(Outputs::SCREENSHOT));
+        assert!(matches!("md,epub".parse::<Outputs>(), Err(WebPageError::UnknownOutput(name)) if name == "epub"));
+    }
+
+    #[test]
+    fn summary_skips_headings_and_links() {
+        let md = "# Title\n\n![logo](logo.png)\n\nA [first](https://example.com) paragraph with *enough* words in it.";
+        assert_eq!(WebPage::summary(md), "A first paragraph with enough words in it.");
     }
 
 
@@ -0,0 +1,670 @@
+//! Evaluator of the XPath 1.0 subset extraction fields are written in: absolute and relative
+//! location paths over the child, descendant, parent, ancestor, sibling, self and attribute axes,
+//! `*`, `text()` and `node()` tests, predicates with positions, `and`, `or`, `=` and `!=`, unions,
+//! and the `contains`, `starts-with`, `normalize-space`, `string`, `concat`, `substring-before`,
+//! `substring-after`, `not`, `count`, `position` and `last` functions. Element and attribute
+//! names are matched without regard to case, as in HTML
+
+use std::collections::HashMap;
+use ego_tree::{NodeId, NodeRef};
+use scraper::{Html, Node};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[error("invalid XPath {expression:?}: {message}")]
+pub struct XPathError {
+    pub expression: String,
+    pub message: String,
+}
+
+pub type Result<T> = std::result::Result<T, XPathError>;
+
+const FUNCTIONS: &[&str] = &[
+    "contains", "starts-with", "normalize-space", "string", "concat", "substring-before", "substring-after",
+    "not", "count", "position", "last",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Slash,
+    DoubleSlash,
+    OpenBracket,
+    CloseBracket,
+    OpenParen,
+    CloseParen,
+    At,
+    Comma,
+    Pipe,
+    Equal,
+    NotEqual,
+    Dot,
+    DoubleDot,
+    Star,
+    DoubleColon,
+    Name(String),
+    Literal(String),
+    Number(f64),
+}
+
+fn tokenize(expression: &str) -> std::result::Result<Vec<Token>, String> {
+
+    let chars = expression.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while let Some(&c) = chars.get(i) {
+        let next = chars.get(i + 1).copied();
+        let (token, length) = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '/' if next == Some('/') => (Token::DoubleSlash, 2),
+            '/' => (Token::Slash, 1),
+            '[' => (Token::OpenBracket, 1),
+            ']' => (Token::CloseBracket, 1),
+            '(' => (Token::OpenParen, 1),
+            ')' => (Token::CloseParen, 1),
+            '@' => (Token::At, 1),
+            ',' => (Token::Comma, 1),
+            '|' => (Token::Pipe, 1),
+            '=' => (Token::Equal, 1),
+            '!' if next == Some('=') => (Token::NotEqual, 2),
+            '.' if next == Some('.') => (Token::DoubleDot, 2),
+            '.' if !next.is_some_and(|next| next.is_ascii_digit()) => (Token::Dot, 1),
+            '*' => (Token::Star, 1),
+            ':' if next == Some(':') => (Token::DoubleColon, 2),
+            '"' | '\'' => {
+                let end = chars[i + 1..].iter().position(|&other| other == c).ok_or("unterminated string")?;
+                (Token::Literal(chars[i + 1..i + 1 + end].iter().collect()), end + 2)
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let length = chars[i..].iter().take_while(|c| c.is_ascii_digit() || **c == '.').count();
+                let number = chars[i..i + length].iter().collect::<String>();
+                (Token::Number(number.parse().map_err(|_| format!("invalid number {number}"))?), length)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let length = chars[i..].iter().take_while(|c| c.is_alphanumeric() || matches!(c, '_' | '-')).count();
+                (Token::Name(chars[i..i + length].iter().collect()), length)
+            }
+            c => return Err(format!("unexpected {c:?}")),
+        };
+        tokens.push(token);
+        i += length;
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    Child,
+    Descendant,
+    DescendantOrSelf,
+    Parent,
+    Ancestor,
+    SelfNode,
+    Attribute,
+    FollowingSibling,
+    PrecedingSibling,
+}
+
+impl Axis {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "child" => Axis::Child,
+            "descendant" => Axis::Descendant,
+            "descendant-or-self" => Axis::DescendantOrSelf,
+            "parent" => Axis::Parent,
+            "ancestor" => Axis::Ancestor,
+            "self" => Axis::SelfNode,
+            "attribute" => Axis::Attribute,
+            "following-sibling" => Axis::FollowingSibling,
+            "preceding-sibling" => Axis::PrecedingSibling,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum NodeTest {
+    Name(String),
+    /// `*`, any element or attribute
+    Any,
+    Text,
+    Node,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Step {
+    axis: Axis,
+    test: NodeTest,
+    predicates: Vec<Expr>,
+}
+
+impl Step {
+    fn new(axis: Axis, test: NodeTest) -> Self {
+        Self { axis, test, predicates: Vec::new() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Path { absolute: bool, steps: Vec<Step> },
+    Union(Vec<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Equal { left: Box<Expr>, right: Box<Expr>, negated: bool },
+    Function(String, Vec<Expr>),
+    Literal(String),
+    Number(f64),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset)
+    }
+
+    fn bump(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> std::result::Result<(), String> {
+        match self.bump() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("expected {expected:?}, found {token:?}")),
+            None => Err(format!("expected {expected:?}, found the end")),
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Name(name)) if name == keyword)
+    }
+
+    fn or(&mut self) -> std::result::Result<Expr, String> {
+        let mut left = self.and()?;
+        while self.is_keyword("or") {
+            self.bump();
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> std::result::Result<Expr, String> {
+        let mut left = self.equality()?;
+        while self.is_keyword("and") {
+            self.bump();
+            left = Expr::And(Box::new(left), Box::new(self.equality()?));
+        }
+        Ok(left)
+    }
+
+    fn equality(&mut self) -> std::result::Result<Expr, String> {
+        let mut left = self.union()?;
+        while let Some(negated) = match self.peek() {
+            Some(Token::Equal) => Some(false),
+            Some(Token::NotEqual) => Some(true),
+            _ => None,
+        } {
+            self.bump();
+            left = Expr::Equal { left: Box::new(left), right: Box::new(self.union()?), negated };
+        }
+        Ok(left)
+    }
+
+    fn union(&mut self) -> std::result::Result<Expr, String> {
+        let mut paths = vec![self.primary()?];
+        while self.peek() == Some(&Token::Pipe) {
+            self.bump();
+            paths.push(self.primary()?);
+        }
+        Ok(match paths.len() {
+            1 => paths.remove(0),
+            _ => Expr::Union(paths),
+        })
+    }
+
+    fn primary(&mut self) -> std::result::Result<Expr, String> {
+        match self.peek().cloned() {
+            Some(Token::Literal(text)) => {
+                self.bump();
+                Ok(Expr::Literal(text))
+            }
+            Some(Token::Number(number)) => {
+                self.bump();
+                Ok(Expr::Number(number))
+            }
+            Some(Token::OpenParen) => {
+                self.bump();
+                let expr = self.or()?;
+                self.expect(Token::CloseParen)?;
+                Ok(expr)
+            }
+            Some(Token::Name(name)) if self.peek_at(1) == Some(&Token::OpenParen) && name != "text" && name != "node" => {
+                if !FUNCTIONS.contains(&name.as_str()) {
+                    return Err(format!("unsupported function {name}()"));
+                }
+                self.bump();
+                self.bump();
+                let mut arguments = Vec::new();
+                if self.peek() != Some(&Token::CloseParen) {
+                    arguments.push(self.or()?);
+                    while self.peek() == Some(&Token::Comma) {
+                        self.bump();
+                        arguments.push(self.or()?);
+                    }
+                }
+                self.expect(Token::CloseParen)?;
+                Ok(Expr::Function(name, arguments))
+            }
+            _ => self.path(),
+        }
+    }
+
+    fn path(&mut self) -> std::result::Result<Expr, String> {
+        let mut steps = Vec::new();
+        let absolute = match self.peek() {
+            Some(Token::Slash) => {
+                self.bump();
+                // `/` alone is the document
+                if !self.starts_step() {
+                    return Ok(Expr::Path { absolute: true, steps });
+                }
+                true
+            }
+            Some(Token::DoubleSlash) => {
+                self.bump();
+                steps.push(Step::new(Axis::DescendantOrSelf, NodeTest::Node));
+                true
+            }
+            _ => false,
+        };
+        steps.push(self.step()?);
+        loop {
+            match self.peek() {
+                Some(Token::Slash) => {
+                    self.bump();
+                }
+                Some(Token::DoubleSlash) => {
+                    self.bump();
+                    steps.push(Step::new(Axis::DescendantOrSelf, NodeTest::Node));
+                }
+                _ => break,
+            }
+            steps.push(self.step()?);
+        }
+        Ok(Expr::Path { absolute, steps })
+    }
+
+    fn starts_step(&self) -> bool {
+        matches!(self.peek(), Some(Token::Name(_) | Token::At | Token::Star | Token::Dot | Token::DoubleDot))
+    }
+
+    fn step(&mut self) -> std::result::Result<Step, String> {
+        let axis = match (self.peek().cloned(), self.peek_at(1)) {
+            (Some(Token::Dot), _) => {
+                self.bump();
+                return Ok(Step::new(Axis::SelfNode, NodeTest::Node));
+            }
+            (Some(Token::DoubleDot), _) => {
+                self.bump();
+                return Ok(Step::new(Axis::Parent, NodeTest::Node));
+            }
+            (Some(Token::At), _) => {
+                self.bump();
+                Axis::Attribute
+            }
+            (Some(Token::Name(name)), Some(Token::DoubleColon)) => {
+                let axis = Axis::from_name(&name).ok_or(format!("unsupported axis {name}"))?;
+                self.bump();
+                self.bump();
+                axis
+            }
+            _ => Axis::Child,
+        };
+        let test = match self.bump() {
+            Some(Token::Star) => NodeTest::Any,
+            Some(Token::Name(name)) if self.peek() == Some(&Token::OpenParen) => {
+                self.bump();
+                self.expect(Token::CloseParen)?;
+                match name.as_str() {
+                    "text" => NodeTest::Text,
+                    "node" => NodeTest::Node,
+                    _ => return Err(format!("unsupported node test {name}()")),
+                }
+            }
+            Some(Token::Name(name)) => NodeTest::Name(name),
+            Some(token) => return Err(format!("expected a step, found {token:?}")),
+            None => return Err("expected a step, found the end".to_string()),
+        };
+        let mut step = Step::new(axis, test);
+        while self.peek() == Some(&Token::OpenBracket) {
+            self.bump();
+            step.predicates.push(self.or()?);
+            self.expect(Token::CloseBracket)?;
+        }
+        Ok(step)
+    }
+}
+
+/// Node of the document, or attribute of an element
+#[derive(Debug, Clone, Copy)]
+pub enum Item<'a> {
+    Node(NodeRef<'a, Node>),
+    Attribute { element: NodeRef<'a, Node>, name: &'a str, value: &'a str },
+}
+
+impl<'a> Item<'a> {
+
+    /// Text of the node and its descendants, value of the attribute
+    pub fn string_value(&self) -> String {
+        match self {
+            Item::Attribute { value, .. } => value.to_string(),
+            Item::Node(node) => match node.value() {
+                Node::Text(text) => text.to_string(),
+                Node::Comment(comment) => comment.to_string(),
+                Node::Element(_) | Node::Document | Node::Fragment => node.descendants()
+                    .filter_map(|node| node.value().as_text().map(|text| text.to_string()))
+                    .collect(),
+                _ => String::new(),
+            },
+        }
+    }
+
+    fn node(&self) -> NodeRef<'a, Node> {
+        match self {
+            Item::Node(node) => *node,
+            Item::Attribute { element, .. } => *element,
+        }
+    }
+}
+
+/// Result of an expression
+#[derive(Debug, Clone)]
+pub enum XPathValue<'a> {
+    Items(Vec<Item<'a>>),
+    Text(String),
+    Number(f64),
+    Boolean(bool),
+}
+
+impl XPathValue<'_> {
+
+    fn boolean(&self) -> bool {
+        match self {
+            XPathValue::Items(items) => !items.is_empty(),
+            XPathValue::Text(text) => !text.is_empty(),
+            XPathValue::Number(number) => *number != 0.0 && !number.is_nan(),
+            XPathValue::Boolean(boolean) => *boolean,
+        }
+    }
+
+    fn string(&self) -> String {
+        match self {
+            XPathValue::Items(items) => items.first().map(Item::string_value).unwrap_or_default(),
+            XPathValue::Text(text) => text.clone(),
+            XPathValue::Number(number) if number.fract() == 0.0 => format!("{number:.0}"),
+            XPathValue::Number(number) => number.to_string(),
+            XPathValue::Boolean(boolean) => boolean.to_string(),
+        }
+    }
+}
+
+/// `=` between two values: true when any pair of their strings, or numbers, is equal
+fn equal(left: &XPathValue, right: &XPathValue) -> bool {
+    let strings = |value: &XPathValue| match value {
+        XPathValue::Items(items) => items.iter().map(Item::string_value).collect(),
+        other => vec![other.string()],
+    };
+    match (left, right) {
+        (XPathValue::Boolean(_), _) | (_, XPathValue::Boolean(_)) => left.boolean() == right.boolean(),
+        (XPathValue::Number(number), other) | (other, XPathValue::Number(number)) => strings(other)
+            .iter()
+            .any(|text| text.trim().parse::<f64>().is_ok_and(|other| other == *number)),
+        _ => {
+            let right = strings(right);
+            strings(left).iter().any(|left| right.contains(left))
+        }
+    }
+}
+
+/// Whitespace collapsed to single spaces, trimmed
+pub(crate) fn normalize_space(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Compiled XPath expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct XPath {
+    expr: Expr,
+}
+
+impl XPath {
+
+    pub fn parse(expression: &str) -> Result<Self> {
+        let error = |message: String| XPathError { expression: expression.to_string(), message };
+        let mut parser = Parser { tokens: tokenize(expression).map_err(error)?, pos: 0 };
+        let expr = parser.or().map_err(error)?;
+        match parser.peek() {
+            Some(token) => Err(error(format!("unexpected {token:?}"))),
+            None => Ok(Self { expr }),
+        }
+    }
+
+    /// Value of the expression for `document`, relative paths starting from its root
+    pub fn evaluate<'a>(&self, document: &'a Html) -> XPathValue<'a> {
+        let order = document.tree.root().descendants().enumerate().map(|(i, node)| (node.id(), i)).collect();
+        let evaluator = Evaluator { root: document.tree.root(), order };
+        evaluator.evaluate(&self.expr, &Context { item: Item::Node(document.tree.root()), position: 1, size: 1 })
+    }
+}
+
+struct Context<'a> {
+    item: Item<'a>,
+    position: usize,
+    size: usize,
+}
+
+struct Evaluator<'a> {
+    root: NodeRef<'a, Node>,
+    /// Position of the nodes in document order
+    order: HashMap<NodeId, usize>,
+}
+
+impl<'a> Evaluator<'a> {
+
+    fn evaluate(&self, expr: &Expr, context: &Context<'a>) -> XPathValue<'a> {
+        match expr {
+            Expr::Literal(text) => XPathValue::Text(text.clone()),
+            Expr::Number(number) => XPathValue::Number(*number),
+            Expr::Or(left, right) => XPathValue::Boolean(self.evaluate(left, context).boolean() || self.evaluate(right, context).boolean()),
+            Expr::And(left, right) => XPathValue::Boolean(self.evaluate(left, context).boolean() && self.evaluate(right, context).boolean()),
+            Expr::Equal { left, right, negated } => {
+                let equal = equal(&self.evaluate(left, context), &self.evaluate(right, context));
+                XPathValue::Boolean(equal != *negated)
+            }
+            Expr::Union(exprs) => {
+                let items = exprs.iter()
+                    .flat_map(|expr| match self.evaluate(expr, context) {
+                        XPathValue::Items(items) => items,
+                        _ => Vec::new(),
+                    })
+                    .collect();
+                XPathValue::Items(self.in_document_order(items))
+            }
+            Expr::Path { absolute, steps } => {
+                let start = match absolute {
+                    true => Item::Node(self.root),
+                    false => context.item,
+                };
+                let items = steps.iter().fold(vec![start], |items, step| self.step(step, &items));
+                XPathValue::Items(items)
+            }
+            Expr::Function(name, arguments) => self.function(name, arguments, context),
+        }
+    }
+
+    fn function(&self, name: &str, arguments: &[Expr], context: &Context<'a>) -> XPathValue<'a> {
+        let string = |i: usize| match arguments.get(i) {
+            Some(argument) => self.evaluate(argument, context).string(),
+            None => context.item.string_value(),
+        };
+        match name {
+            "position" => XPathValue::Number(context.position as f64),
+            "last" => XPathValue::Number(context.size as f64),
+            "count" => match arguments.first().map(|argument| self.evaluate(argument, context)) {
+                Some(XPathValue::Items(items)) => XPathValue::Number(items.len() as f64),
+                _ => XPathValue::Number(0.0),
+            },
+            "not" => XPathValue::Boolean(!arguments.first().is_some_and(|argument| self.evaluate(argument, context).boolean())),
+            "contains" => XPathValue::Boolean(string(0).contains(&string(1))),
+            "starts-with" => XPathValue::Boolean(string(0).starts_with(&string(1))),
+            "normalize-space" => XPathValue::Text(normalize_space(&string(0))),
+            "string" => XPathValue::Text(string(0)),
+            "concat" => XPathValue::Text((0..arguments.len()).map(string).collect()),
+            "substring-before" => XPathValue::Text(string(0).split_once(&string(1)).map(|(before, _)| before.to_string()).unwrap_or_default()),
+            "substring-after" => XPathValue::Text(string(0).split_once(&string(1)).map(|(_, after)| after.to_string()).unwrap_or_default()),
+            _ => unreachable!("functions are checked when parsing"),
+        }
+    }
+
+    /// Items of `step` from each of `items`, in document order
+    fn step(&self, step: &Step, items: &[Item<'a>]) -> Vec<Item<'a>> {
+        let mut selected = Vec::new();
+        for item in items {
+            let mut candidates = self.axis(step.axis, item)
+                .into_iter()
+                .filter(|candidate| Self::matches(&step.test, step.axis, candidate))
+                .collect::<Vec<_>>();
+            for predicate in &step.predicates {
+                let size = candidates.len();
+                candidates = candidates.into_iter()
+                    .enumerate()
+                    .filter(|(i, candidate)| {
+                        let context = Context { item: *candidate, position: i + 1, size };
+                        match self.evaluate(predicate, &context) {
+                            XPathValue::Number(position) => position == (i + 1) as f64,
+                            value => value.boolean(),
+                        }
+                    })
+                    .map(|(_, candidate)| candidate)
+                    .collect();
+            }
+            selected.extend(candidates);
+        }
+        self.in_document_order(selected)
+    }
+
+    /// Items along `axis`, nearest first for the reverse axes
+    fn axis(&self, axis: Axis, item: &Item<'a>) -> Vec<Item<'a>> {
+        let node = match item {
+            Item::Node(node) => *node,
+            Item::Attribute { element, .. } => return match axis {
+                Axis::SelfNode => vec![*item],
+                Axis::Parent => vec![Item::Node(*element)],
+                Axis::Ancestor => std::iter::once(*element).chain(element.ancestors()).map(Item::Node).collect(),
+                _ => Vec::new(),
+            },
+        };
+        match axis {
+            Axis::Child => node.children().map(Item::Node).collect(),
+            Axis::Descendant => node.descendants().skip(1).map(Item::Node).collect(),
+            Axis::DescendantOrSelf => node.descendants().map(Item::Node).collect(),
+            Axis::Parent => node.parent().map(Item::Node).into_iter().collect(),
+            Axis::Ancestor => node.ancestors().map(Item::Node).collect(),
+            Axis::SelfNode => vec![Item::Node(node)],
+            Axis::FollowingSibling => node.next_siblings().map(Item::Node).collect(),
+            Axis::PrecedingSibling => node.prev_siblings().map(Item::Node).collect(),
+            Axis::Attribute => match node.value() {
+                Node::Element(element) => element.attrs().map(|(name, value)| Item::Attribute { element: node, name, value }).collect(),
+                _ => Vec::new(),
+            },
+        }
+    }
+
+    fn matches(test: &NodeTest, axis: Axis, item: &Item) -> bool {
+        match (item, test) {
+            (Item::Attribute { name, .. }, NodeTest::Name(expected)) => name.eq_ignore_ascii_case(expected),
+            (Item::Attribute { .. }, test) => axis == Axis::Attribute && matches!(test, NodeTest::Any | NodeTest::Node),
+            (Item::Node(_), _) if axis == Axis::Attribute => false,
+            (Item::Node(node), NodeTest::Name(expected)) => node.value().as_element().is_some_and(|element| element.name().eq_ignore_ascii_case(expected)),
+            (Item::Node(node), NodeTest::Any) => node.value().is_element(),
+            (Item::Node(node), NodeTest::Text) => node.value().is_text(),
+            (Item::Node(_), NodeTest::Node) => true,
+        }
+    }
+
+    /// `items` sorted in document order, attributes after their element, without duplicates
+    fn in_document_order(&self, mut items: Vec<Item<'a>>) -> Vec<Item<'a>> {
+        let key = |item: &Item| {
+            let position = self.order.get(&item.node().id()).copied().unwrap_or_default();
+            match item {
+                Item::Node(_) => (position, None),
+                Item::Attribute { name, .. } => (position, Some(name.to_string())),
+            }
+        };
+        // stable, the attributes of an element keep their order
+        items.sort_by_key(|item| key(item).0);
+        let mut seen = std::collections::HashSet::new();
+        items.retain(|item| seen.insert(key(item)));
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(expression: &str, html: &str) -> Vec<String> {
+        let document = Html::parse_document(html);
+        match XPath::parse(expression).unwrap().evaluate(&document) {
+            XPathValue::Items(items) => items.iter().map(|item| normalize_space(&item.string_value())).collect(),
+            value => vec![value.string()],
+        }
+    }
+
+    const PAGE: &str = r#"<html><head><meta name="author" content="Ada Lovelace"></head><body>
+        <div class="product"><h2>Lamp</h2><span class="price">$ 12.99</span>
+            <ul class="tags"><li>home</li><li>light</li><li>sale</li></ul></div>
+        <table><tr><th>Weight</th><td>2 kg</td></tr><tr><th>Color</th><td>Red</td></tr></table>
+    </body></html>"#;
+
+    #[test]
+    fn evaluates_location_paths() {
+        assert_eq!(strings("//meta[@name='author']/@content", PAGE), ["Ada Lovelace"]);
+        assert_eq!(strings("//span[contains(@class, 'price')]", PAGE), ["$ 12.99"]);
+        assert_eq!(strings("//ul[@class='tags']/li", PAGE), ["home", "light", "sale"]);
+        assert_eq!(strings("//ul/li[2]", PAGE), ["light"]);
+        assert_eq!(strings("//ul/li[last()]/text()", PAGE), ["sale"]);
+        assert_eq!(strings("//th[text()='Color']/following-sibling::td", PAGE), ["Red"]);
+        assert_eq!(strings("//li[.='sale']/ancestor::div/h2", PAGE), ["Lamp"]);
+        assert_eq!(strings("//h2 | //th", PAGE), ["Lamp", "Weight", "Color"]);
+        assert_eq!(strings("//DIV[not(@id)]/*[1]", PAGE), ["Lamp"]);
+    }
+
+    #[test]
+    fn evaluates_functions() {
+        assert_eq!(strings("substring-after(//span[@class='price'], '$ ')", PAGE), ["12.99"]);
+        assert_eq!(strings("count(//li)", PAGE), ["3"]);
+        assert_eq!(strings("normalize-space(concat(' a ', //h2))", PAGE), ["a Lamp"]);
+        assert_eq!(strings("count(//li[position() = 1 or . = 'sale'])", PAGE), ["2"]);
+    }
+
+    #[test]
+    fn rejects_unsupported_expressions() {
+        assert!(XPath::parse("//div[").is_err());
+        assert!(XPath::parse("//div[@id='a]").is_err());
+        assert!(XPath::parse("//a/following::p").is_err());
+        assert!(XPath::parse("lower-case(//h1)").is_err());
+    }
+}